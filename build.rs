@@ -0,0 +1,10 @@
+// Compiles the C side of the FFI tests, tests/ffi.c, into a static
+// library; only the test calling into it pulls it into a binary.
+fn main() {
+    println!("cargo:rerun-if-changed=tests/ffi.c");
+    println!("cargo:rerun-if-changed=include/svg_kleinian.h");
+    if std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("wasm32") {
+        return;
+    }
+    cc::Build::new().file("tests/ffi.c").include("include").compile("kleinian_ffi_test");
+}
@@ -0,0 +1,54 @@
+/* The C interface to svg_kleinian; see src/ffi.rs. Link against the
+ * cdylib that `cargo build --release` leaves in target/release. */
+
+#ifndef SVG_KLEINIAN_H
+#define SVG_KLEINIAN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define KLEINIAN_PARAMS_VERSION 1
+
+#define KLEINIAN_OK 0
+#define KLEINIAN_ERR_NULL (-1)
+#define KLEINIAN_ERR_VERSION (-2)
+#define KLEINIAN_ERR_BUFFER_TOO_SMALL (-3)
+#define KLEINIAN_ERR_TRACE (-4)
+#define KLEINIAN_ERR_WORD (-5)
+
+typedef struct KleinianParams {
+    uint32_t version; /* KLEINIAN_PARAMS_VERSION */
+    uint32_t max_depth;
+    double ta_re;
+    double ta_im;
+    double tb_re;
+    double tb_im;
+    double epsilon;
+} KleinianParams;
+
+/* Call with out_buf NULL to get the size in *out_len, then again with a
+ * buffer of that many bytes; the SVG comes back NUL-terminated. */
+int32_t kleinian_render_svg(const KleinianParams *params, uint8_t *out_buf, size_t *out_len);
+
+/* The same, filling x and y interleaved, with *out_len counting doubles. */
+int32_t kleinian_limit_points(const KleinianParams *params, double *out, size_t *out_len);
+
+/* The traces of a, b and ab as re, im pairs: 6 doubles. */
+int32_t kleinian_traces(const KleinianParams *params, double *out);
+
+/* The matrix of a word in a, b, A = a^-1 and B = b^-1, entries a, b, c, d
+ * as re, im pairs: 8 doubles. */
+int32_t kleinian_word(const KleinianParams *params, const char *word, double *out);
+
+/* What the last failing call on this thread went wrong with. */
+const char *kleinian_last_error_message(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,49 @@
+"""Tests of the Python module; build it into the current environment with
+`maturin develop` at the top of the repository, then run `pytest python`."""
+
+import cmath
+
+import pytest
+
+import svg_kleinian
+from svg_kleinian import Kleinian
+
+
+def test_from_mu_gives_the_maskit_traces():
+    ta, tb, tab = Kleinian.from_mu(2j).traces()
+    assert ta == pytest.approx(2)
+    assert tb == pytest.approx(2)
+    assert tab == pytest.approx(2 - 2j)
+
+
+def test_traces_can_be_given_as_strings():
+    ta, tb, _ = Kleinian("1.91+0.05j", 2).traces()
+    assert ta == pytest.approx(1.91 + 0.05j)
+    assert tb == pytest.approx(2)
+
+
+def test_limit_points_are_finite_complex_numbers():
+    points = Kleinian.from_mu(2j).limit_points(epsilon=0.01, max_depth=40)
+    assert len(points) > 1
+    for z in points:
+        assert cmath.isfinite(complex(z)), z
+
+
+def test_render_svg_takes_the_style_as_keywords():
+    svg = Kleinian.from_mu(2j).render_svg(epsilon=0.01, max_depth=40, stroke="navy", view_box=(-2, -2, 4, 4))
+    assert svg.startswith('<svg viewBox="-2 -2 4 4"'), svg[:60]
+    assert 'stroke="navy"' in svg
+    with pytest.raises(TypeError):
+        Kleinian.from_mu(2j).render_svg(colour="red")
+
+
+def test_a_bad_word_raises_a_word_error():
+    with pytest.raises(svg_kleinian.WordError, match="`x`"):
+        Kleinian.from_mu(2j).word("abx")
+
+
+def test_errors_keep_the_tracer_message():
+    with pytest.raises(svg_kleinian.DegenerateError, match="degenerate"):
+        Kleinian(2, 0)
+    with pytest.raises(svg_kleinian.KleinianError, match="branches"):
+        Kleinian(1.91 + 0.05j, 2).limit_points(max_depth=200)
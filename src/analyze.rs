@@ -0,0 +1,42 @@
+use std::io::{self, Write};
+
+use crate::{limitset_with, Kleinian, RenderOptions};
+
+/// One row of `analyze_depths`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthRow {
+    pub depth: i64,
+    pub points: usize,
+    pub bounding_box: (f64, f64, f64, f64),
+    /// The largest move of a side of the bounding box since the previous
+    /// row; `None` on the first.
+    pub change: Option<f64>,
+}
+
+/// Traces at depths 5, 10, … up to `max_depth` and records how the point
+/// count and bounding box settle, to show where a render converges.
+pub fn analyze_depths(g: &mut Kleinian, max_depth: i64, opts: &RenderOptions) -> Vec<DepthRow> {
+    let mut rows: Vec<DepthRow> = Vec::new();
+    for depth in (5..=max_depth).step_by(5) {
+        limitset_with(depth, g, opts);
+        let b = g.bounding_box();
+        let change = rows.last().map(|prev| {
+            let p = prev.bounding_box;
+            [b.0 - p.0, b.1 - p.1, b.2 - p.2, b.3 - p.3].iter().fold(0.0, |m: f64, d| m.max(d.abs()))
+        });
+        rows.push(DepthRow { depth, points: g.points().len(), bounding_box: b, change });
+    }
+    rows
+}
+
+/// Writes `rows` as a plain-text table.
+pub fn write_analysis<W: Write>(rows: &[DepthRow], w: &mut W) -> io::Result<()> {
+    writeln!(w, "{:>5}  {:>10}  {:>40}  {:>10}", "depth", "points", "bounding box", "change")?;
+    for row in rows {
+        let (x0, y0, x1, y1) = row.bounding_box;
+        let bbox = format!("({:.4}, {:.4}) – ({:.4}, {:.4})", x0, y0, x1, y1);
+        let change = row.change.map_or("-".to_string(), |c| format!("{:.2e}", c));
+        writeln!(w, "{:>5}  {:>10}  {:>40}  {:>10}", row.depth, row.points, bbox, change)?;
+    }
+    Ok(())
+}
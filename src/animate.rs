@@ -0,0 +1,483 @@
+//! A parameter sweep as one animated SVG: every frame's curve is in the
+//! file, and SMIL timing shows them in turn, so it plays in a browser with
+//! no script.
+
+use num::complex::Complex;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use svg::Document;
+use svg::node::element::{Animate, Circle, Group, Path};
+use svg::node::element::path::Data;
+
+use crate::grid::trace_group;
+use crate::overlay::circle_through;
+use crate::{background, fmt_complex, COMPLEX_PRECISION, grandma, grandma_with_root, markov_discriminant, Kleinian, drawn_runs, polyline_data, stroke_path, write_svg, RenderOptions, DEFAULT_COMPRESSION};
+
+/// How many points along the sweep `render_frames` traces, coarsely,
+/// beforehand to fix the viewBox all its frames share.
+pub const PREPASS_SAMPLES: usize = 9;
+
+/// How the traces move from one end of the sweep to the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// In a straight line.
+    Linear,
+    /// Along the complex translation length 2 acosh(t/2), which moves the
+    /// group at an even pace where a straight line in the trace would rush
+    /// through the parts near ±2.
+    Geodesic,
+    /// Modulus and argument each in a straight line, so a sweep between
+    /// traces of the same size turns about 0, as a bend does.
+    Polar,
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Interpolation::Linear),
+            "geodesic" => Ok(Interpolation::Geodesic),
+            "polar" => Ok(Interpolation::Polar),
+            _ => Err(format!("unknown interpolation `{}`, expected linear, geodesic or polar", s)),
+        }
+    }
+}
+
+impl Interpolation {
+    /// The trace a fraction `s` of the way from `t0` to `t1`.
+    pub fn at(self, t0: Complex<f64>, t1: Complex<f64>, s: f64) -> Complex<f64> {
+        match self {
+            Interpolation::Linear => t0 + (t1 - t0) * s,
+            Interpolation::Geodesic => {
+                let length = |t: Complex<f64>| (t / 2.0).acosh() * 2.0;
+                let (l0, l1) = (length(t0), length(t1));
+                ((l0 + (l1 - l0) * s) / 2.0).cosh() * 2.0
+            }
+            Interpolation::Polar => {
+                let (r0, arg0) = t0.to_polar();
+                let (r1, arg1) = t1.to_polar();
+                Complex::from_polar(&(r0 + (r1 - r0) * s), &(arg0 + (arg1 - arg0) * s))
+            }
+        }
+    }
+}
+
+/// How `animate` lays out the sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimateOptions {
+    pub frames: usize,
+    /// Depth each frame is traced to.
+    pub level: i64,
+    pub interpolation: Interpolation,
+    /// Frames with more traced points than this are thinned evenly down
+    /// to it, to keep the file small enough to open.
+    pub max_points: usize,
+    /// Seconds each frame is shown for, to the millisecond.
+    pub frame_duration: f64,
+    /// Traces of a and b the sweep passes through on its way from start
+    /// to end, in order; the frames are shared evenly between the legs.
+    pub via: Vec<(Complex<f64>, Complex<f64>)>,
+    /// When the first frame is Fuchsian, draw the circle its limit set
+    /// lies on faintly behind every frame, to show how far the later
+    /// ones have moved from it.
+    pub reference_circle: bool,
+}
+
+impl Default for AnimateOptions {
+    fn default() -> Self {
+        AnimateOptions {
+            frames: 24,
+            level: 30,
+            interpolation: Interpolation::Linear,
+            max_points: 20_000,
+            frame_duration: 0.1,
+            via: Vec::new(),
+            reference_circle: false,
+        }
+    }
+}
+
+// every step-th point and the last, so that at most `max` are left
+pub(crate) fn thin(points: Vec<Complex<f64>>, max: usize) -> Vec<Complex<f64>> {
+    if points.len() <= max || max < 2 {
+        return points;
+    }
+    let step = (points.len() - 1).div_ceil(max - 1);
+    let mut thinned: Vec<_> = points.iter().step_by(step).copied().collect();
+    if !(points.len() - 1).is_multiple_of(step) {
+        thinned.push(points[points.len() - 1]);
+    }
+    thinned
+}
+
+impl AnimateOptions {
+    /// The traces of frame `k`.
+    pub fn traces(&self, ta: (Complex<f64>, Complex<f64>), tb: (Complex<f64>, Complex<f64>), k: usize) -> (Complex<f64>, Complex<f64>) {
+        let s = if self.frames > 1 { k as f64 / (self.frames - 1) as f64 } else { 0.0 };
+        self.traces_at(ta, tb, s)
+    }
+
+    // the traces a fraction `s` of the way along the sweep
+    fn traces_at(&self, ta: (Complex<f64>, Complex<f64>), tb: (Complex<f64>, Complex<f64>), s: f64) -> (Complex<f64>, Complex<f64>) {
+        let mut stops = vec![(ta.0, tb.0)];
+        stops.extend_from_slice(&self.via);
+        stops.push((ta.1, tb.1));
+        // the leg frame k falls on, and how far along it
+        let legs = stops.len() - 1;
+        let leg = ((s * legs as f64) as usize).min(legs - 1);
+        let s = s * legs as f64 - leg as f64;
+        let ((ta0, tb0), (ta1, tb1)) = (stops[leg], stops[leg + 1]);
+        (self.interpolation.at(ta0, ta1, s), self.interpolation.at(tb0, tb1, s))
+    }
+
+    /// For each frame, the square root of `markov_discriminant` its group
+    /// is built from: the principal one at the first frame, then whichever
+    /// of the two stays nearest, followed through `ROOT_SUBSTEPS` steps
+    /// between frames so that a quick turn isn't taken for a flip.
+    pub fn frame_roots(&self, ta: (Complex<f64>, Complex<f64>), tb: (Complex<f64>, Complex<f64>)) -> Vec<Complex<f64>> {
+        let (ta0, tb0) = self.traces_at(ta, tb, 0.0);
+        let mut root = markov_discriminant(ta0, tb0).sqrt();
+        let mut roots = vec![root];
+        let steps = self.frames.saturating_sub(1) * ROOT_SUBSTEPS;
+        for j in 1..=steps {
+            let (ta, tb) = self.traces_at(ta, tb, j as f64 / steps as f64);
+            let next = markov_discriminant(ta, tb).sqrt();
+            root = if (next - root).norm() <= (next + root).norm() { next } else { -next };
+            if j % ROOT_SUBSTEPS == 0 {
+                roots.push(root);
+            }
+        }
+        roots
+    }
+}
+
+/// Steps per frame at which `AnimateOptions::frame_roots` checks the
+/// square root it is following.
+pub const ROOT_SUBSTEPS: usize = 16;
+
+// the group of a frame, as `grandma` builds it when the root is its own,
+// which keeps the gasket exact
+pub(crate) fn frame_group(ta: Complex<f64>, tb: Complex<f64>, root: Complex<f64>) -> Kleinian {
+    if root == markov_discriminant(ta, tb).sqrt() {
+        grandma(ta, tb)
+    } else {
+        grandma_with_root(ta, tb, root)
+    }
+}
+
+/// The circle the first frame's limit set lies on, as a center and
+/// radius, when `reference_circle` is set and that frame is Fuchsian.
+pub fn reference_circle(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    anim: &AnimateOptions,
+    opts: &RenderOptions,
+) -> Option<(Complex<f64>, f64)> {
+    if !anim.reference_circle {
+        return None;
+    }
+    let (ta, tb) = anim.traces(ta, tb, 0);
+    let g = grandma(ta, tb);
+    if !g.is_fuchsian() {
+        return None;
+    }
+    let coarse = RenderOptions { epsilon: opts.epsilon.max(0.01), ..opts.clone() };
+    let points: Vec<_> = trace_group(g, anim.level.min(20), &coarse)?
+        .into_iter()
+        .filter(|z| z.is_finite())
+        .collect();
+    let n = points.len();
+    if n < 3 {
+        return None;
+    }
+    circle_through(points[0], points[n / 3], points[2 * n / 3])
+}
+
+// the faint reference circle
+fn reference_element((center, radius): (Complex<f64>, f64), opts: &RenderOptions) -> Circle {
+    Circle::new()
+        .set("class", "reference")
+        .set("cx", center.re)
+        .set("cy", center.im)
+        .set("r", radius)
+        .set("fill", "none")
+        .set("stroke", opts.stroke.as_str())
+        .set("stroke-opacity", 0.25)
+        .set("stroke-width", opts.stroke_width)
+}
+
+// a viewBox around the finite points, with a margin, or `fallback` when
+// there are none
+fn framing<'a>(points: impl Iterator<Item = &'a Complex<f64>>, fallback: (f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
+    let inf = f64::INFINITY;
+    let (x0, y0, x1, y1) = points
+        .filter(|z| z.is_finite())
+        .fold((inf, inf, -inf, -inf), |(x0, y0, x1, y1), z| {
+            (x0.min(z.re), y0.min(z.im), x1.max(z.re), y1.max(z.im))
+        });
+    if x0 <= x1 {
+        let pad = 0.05 * (x1 - x0).max(y1 - y0);
+        (x0 - pad, y0 - pad, x1 - x0 + 2.0 * pad, y1 - y0 + 2.0 * pad)
+    } else {
+        fallback
+    }
+}
+
+/// Traces `anim.frames` limit sets with traces running from `ta.0` to
+/// `ta.1` and `tb.0` to `tb.1`, and draws them into one looping SVG
+/// animation. Each frame is a group of class "frame", hidden until its
+/// `<animate>` begins, `frame_duration` after the one before; the groups
+/// share the stroke set on their parent and a viewBox covering them all.
+/// Frames where the recipe breaks down or the trace runs out of budget
+/// are left empty.
+pub fn animate(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    anim: &AnimateOptions,
+    opts: &RenderOptions,
+) -> Document {
+    let n = anim.frames;
+    let roots = anim.frame_roots(ta, tb);
+    let reference = reference_circle(ta, tb, anim, opts);
+    let frames: Vec<_> = (0..n)
+        .map(|k| {
+            let (ta, tb) = anim.traces(ta, tb, k);
+            let points = trace_group(frame_group(ta, tb, roots[k]), anim.level, opts).unwrap_or_default();
+            (ta, tb, thin(points, anim.max_points))
+        })
+        .collect();
+
+    let view_box = framing(frames.iter().flat_map(|(_, _, points)| points), opts.view_box);
+    let opts = RenderOptions { view_box, ..opts.clone() };
+
+    // in whole milliseconds, which keeps the begin times readable
+    let ms = |frames: usize| (frames as f64 * anim.frame_duration * 1000.0).round();
+    let mut group = Group::new()
+        .set("class", "frames")
+        .set("fill", "none")
+        .set("stroke", opts.stroke.as_str())
+        .set("stroke-width", opts.stroke_width);
+    for (k, (ta, tb, points)) in frames.into_iter().enumerate() {
+        let mut data = Data::new();
+        for run in drawn_runs(&points, &opts) {
+            data = polyline_data(data, &run);
+        }
+        // shown for the first 1/n of each cycle, which starts k frames in
+        let timing = Animate::new()
+            .set("attributeName", "visibility")
+            .set("values", "visible;hidden")
+            .set("keyTimes", format!("0;{}", 1.0 / n as f64))
+            .set("calcMode", "discrete")
+            .set("dur", format!("{}ms", ms(n)))
+            .set("begin", format!("{}ms", ms(k)))
+            .set("repeatCount", "indefinite");
+        group = group.add(Group::new()
+            .set("class", "frame")
+            .set("visibility", "hidden")
+            .set("data-ta", fmt_complex(ta, COMPLEX_PRECISION))
+            .set("data-tb", fmt_complex(tb, COMPLEX_PRECISION))
+            .add(Path::new().set("d", data))
+            .add(timing));
+    }
+
+    let mut document = Document::new().set("viewBox", view_box);
+    if let Some(ref color) = opts.background {
+        document = document.add(background(view_box, color));
+    }
+    if let Some(circle) = reference {
+        document = document.add(reference_element(circle, &opts));
+    }
+    document.add(group)
+}
+
+/// The file name for frame `k`: `pattern` with its `%d`, or `%0Nd` for
+/// zero-padding to N digits, replaced by `k`. `None` when there is no such
+/// placeholder.
+pub fn frame_path(pattern: &str, k: usize) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d')?;
+    let spec = &rest[..end];
+    if !spec.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let width = spec.parse().unwrap_or(0);
+    Some(format!("{}{:0width$}{}", &pattern[..start], k, &rest[end + 1..], width = width))
+}
+
+// `opts` with a viewBox fitted to a coarse trace of `PREPASS_SAMPLES`
+// frames spread along the sweep
+pub(crate) fn shared_framing(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    anim: &AnimateOptions,
+    opts: &RenderOptions,
+) -> RenderOptions {
+    let n = anim.frames;
+    let coarse = RenderOptions { epsilon: opts.epsilon.max(0.01), ..opts.clone() };
+    let samples = PREPASS_SAMPLES.min(n);
+    let roots = anim.frame_roots(ta, tb);
+    let sketch: Vec<_> = (0..samples)
+        .map(|j| if samples > 1 { j * (n - 1) / (samples - 1) } else { 0 })
+        .filter_map(|k| {
+            let (ta, tb) = anim.traces(ta, tb, k);
+            trace_group(frame_group(ta, tb, roots[k]), anim.level.min(20), &coarse)
+        })
+        .flatten()
+        .collect();
+    RenderOptions { view_box: framing(sketch.iter(), opts.view_box), ..opts.clone() }
+}
+
+// the document for one frame, or a crossed-out one when it failed
+pub(crate) fn frame_document(
+    k: usize,
+    ta: Complex<f64>,
+    tb: Complex<f64>,
+    points: Option<&[Complex<f64>]>,
+    reference: Option<(Complex<f64>, f64)>,
+    opts: &RenderOptions,
+) -> Document {
+    let (x, y, w, h) = opts.view_box;
+    let mut document = Document::new()
+        .set("viewBox", opts.view_box)
+        .set("data-frame", k)
+        .set("data-ta", fmt_complex(ta, COMPLEX_PRECISION))
+        .set("data-tb", fmt_complex(tb, COMPLEX_PRECISION));
+    if let Some(ref color) = opts.background {
+        document = document.add(background(opts.view_box, color));
+    }
+    if let Some(circle) = reference {
+        document = document.add(reference_element(circle, opts));
+    }
+    match points {
+        Some(points) => document.add(stroke_path(points, &opts.stroke, opts)),
+        None => {
+            let cross = Data::new()
+                .move_to((x, y))
+                .line_to((x + w, y + h))
+                .move_to((x + w, y))
+                .line_to((x, y + h));
+            document.set("class", "failed").add(Path::new()
+                .set("fill", "none")
+                .set("stroke", "#d62728")
+                .set("stroke-width", opts.stroke_width)
+                .set("d", cross))
+        }
+    }
+}
+
+/// Writes the frames of the sweep `animate` would draw as separate SVG
+/// files, named by `frame_path(pattern, k)`, for rasterizing and putting
+/// together as video. All frames share one viewBox, fitted to a coarse
+/// trace of `PREPASS_SAMPLES` points along the sweep so that it does not
+/// jump about, and carry their number and traces as `data-frame`,
+/// `data-ta` and `data-tb`. Frames are traced on as many threads as there
+/// are cores. A frame that fails is drawn crossed out instead, and with
+/// `resume` frames whose files exist already are skipped. Returns a
+/// warning for each frame that failed or could not be written.
+pub fn render_frames(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    anim: &AnimateOptions,
+    opts: &RenderOptions,
+    pattern: &str,
+    resume: bool,
+) -> Vec<String> {
+    let n = anim.frames;
+    let opts = shared_framing(ta, tb, anim, opts);
+    let roots = anim.frame_roots(ta, tb);
+    let reference = reference_circle(ta, tb, anim, &opts);
+
+    let warnings = Mutex::new(Vec::new());
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(n);
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let k = next.fetch_add(1, Ordering::Relaxed);
+                if k >= n {
+                    break;
+                }
+                let path = frame_path(pattern, k).unwrap_or_else(|| format!("{}{}", pattern, k));
+                if resume && std::path::Path::new(&path).exists() {
+                    continue;
+                }
+                let (ta, tb) = anim.traces(ta, tb, k);
+                let points = trace_group(frame_group(ta, tb, roots[k]), anim.level, &opts)
+                    .map(|points| thin(points, anim.max_points));
+                if points.is_none() {
+                    warnings.lock().unwrap().push(format!("frame {} (ta = {}, tb = {}) failed to trace", k, fmt_complex(ta, COMPLEX_PRECISION), fmt_complex(tb, COMPLEX_PRECISION)));
+                }
+                let document = frame_document(k, ta, tb, points.as_deref(), reference, &opts);
+                let level = if path.ends_with(".svgz") { Some(DEFAULT_COMPRESSION) } else { None };
+                if let Err(e) = write_svg(&path, &document, level) {
+                    warnings.lock().unwrap().push(format!("{}: {}", path, e));
+                }
+            });
+        }
+    });
+    let mut warnings = warnings.into_inner().unwrap();
+    warnings.sort();
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_paths_fill_in_the_number() {
+        assert_eq!(frame_path("frame-%04d.svg", 7).as_deref(), Some("frame-0007.svg"));
+        assert_eq!(frame_path("out/%d.svgz", 12).as_deref(), Some("out/12.svgz"));
+        assert_eq!(frame_path("f%02d", 123).as_deref(), Some("f123"));
+        assert_eq!(frame_path("frame.svg", 1), None);
+        assert_eq!(frame_path("frame-%s.svg", 1), None);
+    }
+
+    #[test]
+    fn every_frame_shares_one_view_box() {
+        let dir = std::env::temp_dir().join(format!("svg_kleinian-frames-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pattern = dir.join("frame-%02d.svg");
+        let anim = AnimateOptions { frames: 3, level: 8, ..AnimateOptions::default() };
+        let opts = RenderOptions { epsilon: 0.01, ..RenderOptions::default() };
+        let (start, end) = (Complex::new(2.0, 0.0), Complex::new(1.91, 0.05));
+        let warnings = render_frames((start, end), (start, end), &anim, &opts, pattern.to_str().unwrap(), false);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let view_boxes: Vec<_> = (0..3)
+            .map(|k| {
+                let svg = std::fs::read_to_string(frame_path(pattern.to_str().unwrap(), k).unwrap()).unwrap();
+                let start = svg.find("viewBox=\"").expect("a viewBox") + 9;
+                svg[start..start + svg[start..].find('"').unwrap()].to_string()
+            })
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(view_boxes.iter().all(|v| *v == view_boxes[0]), "{:?}", view_boxes);
+        let (x, y, w, h) = shared_framing((start, end), (start, end), &anim, &opts).view_box;
+        assert_eq!(view_boxes[0], format!("{} {} {} {}", x, y, w, h));
+    }
+
+    #[test]
+    fn a_bend_leaves_the_fuchsian_start() {
+        // as `animate --bend` sets it up: ta turns at a fixed modulus, tb
+        // stays where ta starts
+        let t = Complex::new(3.0, 0.0);
+        let (ta, tb) = ((t, Complex::from_polar(&3.0, &0.4)), (t, t));
+        let anim = AnimateOptions { frames: 5, level: 8, interpolation: Interpolation::Polar, reference_circle: true, ..AnimateOptions::default() };
+        let roots = anim.frame_roots(ta, tb);
+        let groups: Vec<_> = (0..5)
+            .map(|k| {
+                let (ta, tb) = anim.traces(ta, tb, k);
+                assert!((ta.norm() - 3.0).abs() < 1e-12 && tb == t, "frame {}: ta = {}, tb = {}", k, ta, tb);
+                frame_group(ta, tb, roots[k])
+            })
+            .collect();
+        assert!(groups[0].is_fuchsian());
+        assert!(!groups[4].is_fuchsian());
+        assert!(reference_circle(ta, tb, &anim, &RenderOptions::default()).is_some());
+    }
+}
@@ -0,0 +1,230 @@
+//! A sweep rasterized straight into an animated GIF or APNG, behind the
+//! `animation` feature, with no SVG frames on disk in between.
+
+use apng::{BitDepth, ColorType};
+use color_quant::NeuQuant;
+use gif::{Frame, Repeat};
+use num::complex::Complex;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::animate::{frame_document, frame_group, reference_circle, shared_framing, thin};
+use crate::grid::trace_group;
+use crate::output::create_output;
+use crate::raster::rasterize;
+use crate::{fmt_complex, AnimateOptions, Error, PngOptions, RenderOptions, COMPLEX_PRECISION};
+
+/// Which kind of animated image `encode_animation` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// 256 colours, chosen from the first frame and shared by all of them.
+    Gif,
+    /// Full colour, but not every viewer plays it.
+    Apng,
+}
+
+impl FromStr for AnimationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gif" => Ok(AnimationFormat::Gif),
+            "apng" => Ok(AnimationFormat::Apng),
+            _ => Err(format!("unknown animation format `{}`, expected gif or apng", s)),
+        }
+    }
+}
+
+/// How `encode_animation` draws and packs the frames; how long each shows
+/// is `AnimateOptions::frame_duration`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodeOptions {
+    pub format: AnimationFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Times to play through, or `None` to loop forever.
+    pub plays: Option<u16>,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            format: AnimationFormat::Gif,
+            width: 800,
+            height: 800,
+            plays: None,
+        }
+    }
+}
+
+fn gif_error(e: gif::EncodingError) -> Error {
+    Error::Raster(e.to_string())
+}
+
+fn apng_error(e: apng::EncodingError) -> Error {
+    Error::Raster(e.to_string())
+}
+
+// the packer behind `encode_animation`, taking one frame at a time
+enum Sink<W: Write> {
+    Gif {
+        out: Option<W>,
+        encoder: Option<(gif::Encoder<W>, NeuQuant)>,
+        delay: u16,
+        plays: Option<u16>,
+    },
+    Apng {
+        writer: apng::Writer<W>,
+        delay: u16,
+    },
+}
+
+impl<W: Write> Sink<W> {
+    fn new(w: W, frames: usize, anim: &AnimateOptions, enc: &EncodeOptions) -> Result<Self, Error> {
+        match enc.format {
+            AnimationFormat::Gif => {
+                // in hundredths of a second
+                let delay = (anim.frame_duration * 100.0).round().max(1.0) as u16;
+                Ok(Sink::Gif { out: Some(w), encoder: None, delay, plays: enc.plays })
+            }
+            AnimationFormat::Apng => {
+                let mut encoder = apng::Encoder::new(w, enc.width, enc.height);
+                encoder.set_color(ColorType::Rgba);
+                encoder.set_depth(BitDepth::Eight);
+                encoder.set_animated(frames as u32, u32::from(enc.plays.unwrap_or(0))).map_err(apng_error)?;
+                let writer = encoder.write_header().map_err(apng_error)?;
+                // in thousandths of a second
+                let delay = (anim.frame_duration * 1000.0).round().max(1.0) as u16;
+                Ok(Sink::Apng { writer, delay })
+            }
+        }
+    }
+
+    fn push(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), Error> {
+        match self {
+            Sink::Gif { out, encoder, delay, plays } => {
+                // the palette comes from the first frame, so the encoder,
+                // which writes it in the header, waits for it
+                if encoder.is_none() {
+                    let quant = NeuQuant::new(10, 256, rgba);
+                    let w = out.take().unwrap();
+                    let mut gif = gif::Encoder::new(w, width as u16, height as u16, &quant.color_map_rgb()).map_err(gif_error)?;
+                    gif.set_repeat(match *plays {
+                        Some(n) => Repeat::Finite(n.saturating_sub(1)),
+                        None => Repeat::Infinite,
+                    }).map_err(gif_error)?;
+                    *encoder = Some((gif, quant));
+                }
+                let (encoder, quant) = encoder.as_mut().unwrap();
+                let indices: Vec<u8> = rgba.chunks_exact(4).map(|p| quant.index_of(p) as u8).collect();
+                let frame = Frame {
+                    width: width as u16,
+                    height: height as u16,
+                    buffer: indices.into(),
+                    delay: *delay,
+                    ..Frame::default()
+                };
+                encoder.write_frame(&frame).map_err(gif_error)
+            }
+            Sink::Apng { writer, delay } => {
+                writer.set_frame_delay(*delay, 1000).map_err(apng_error)?;
+                writer.write_image_data(rgba).map_err(apng_error)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self {
+            Sink::Gif { encoder: Some((encoder, _)), .. } => Ok(encoder.into_inner().map(drop)?),
+            Sink::Gif { encoder: None, .. } => Ok(()),
+            Sink::Apng { writer, .. } => writer.finish().map_err(apng_error),
+        }
+    }
+}
+
+/// Traces the sweep `animate` would draw and writes it to `path` as an
+/// animated GIF or APNG, rasterizing and packing each frame before tracing
+/// the next, so only one is ever held in memory. The frames share a
+/// viewBox fitted as `render_frames` does, strokes about a pixel wide, and
+/// a white background unless `opts` has another. A frame that fails to
+/// trace is drawn crossed out. `progress` is called with the number of
+/// frames done and the total after each one. Returns a warning for each
+/// frame that failed.
+pub fn encode_animation(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    anim: &AnimateOptions,
+    opts: &RenderOptions,
+    enc: &EncodeOptions,
+    path: &str,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<String>, Error> {
+    if enc.format == AnimationFormat::Gif && (enc.width > u32::from(u16::MAX) || enc.height > u32::from(u16::MAX)) {
+        return Err(Error::Raster(format!("GIF frames can't be {}x{}", enc.width, enc.height)));
+    }
+    let n = anim.frames;
+    let mut opts = shared_framing(ta, tb, anim, opts);
+    // widened to the frame's shape, as the margins `rasterize` would
+    // leave are transparent
+    let (x, y, w, h) = opts.view_box;
+    let aspect = enc.width as f64 / enc.height as f64;
+    opts.view_box = if w < h * aspect {
+        (x - (h * aspect - w) / 2.0, y, h * aspect, h)
+    } else {
+        (x, y - (w / aspect - h) / 2.0, w, w / aspect)
+    };
+    opts.stroke_width = opts.view_box.2 / enc.width as f64;
+    opts.background = Some(opts.background.take().unwrap_or_else(|| "white".to_string()));
+    let png = PngOptions { width: enc.width, height: enc.height, supersample: 1 };
+    let roots = anim.frame_roots(ta, tb);
+    let reference = reference_circle(ta, tb, anim, &opts);
+
+    let mut warnings = Vec::new();
+    let mut out = create_output(path)?;
+    let mut sink = Sink::new(&mut out, n, anim, enc)?;
+    for (k, &root) in roots.iter().enumerate() {
+        let (ta, tb) = anim.traces(ta, tb, k);
+        let points = trace_group(frame_group(ta, tb, root), anim.level, &opts)
+            .map(|points| thin(points, anim.max_points));
+        if points.is_none() {
+            warnings.push(format!("frame {} (ta = {}, tb = {}) failed to trace", k, fmt_complex(ta, COMPLEX_PRECISION), fmt_complex(tb, COMPLEX_PRECISION)));
+        }
+        // the background makes every pixel opaque, so the premultiplied
+        // data is plain RGBA
+        let pixels = rasterize(&frame_document(k, ta, tb, points.as_deref(), reference, &opts), &png)?;
+        sink.push(pixels.data(), pixels.width(), pixels.height())?;
+        progress(k + 1, n);
+    }
+    sink.finish()?;
+    out.flush()?;
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_gif_decodes_to_a_frame_per_step() {
+        let path = std::env::temp_dir().join(format!("svg_kleinian-animation-{}.gif", std::process::id()));
+        let anim = AnimateOptions { frames: 4, level: 6, ..AnimateOptions::default() };
+        let opts = RenderOptions { epsilon: 0.01, ..RenderOptions::default() };
+        let enc = EncodeOptions { width: 60, height: 40, ..EncodeOptions::default() };
+        let (start, end) = (Complex::new(2.0, 0.0), Complex::new(1.91, 0.05));
+        let mut done = Vec::new();
+        let warnings = encode_animation((start, end), (start, end), &anim, &opts, &enc, path.to_str().unwrap(), |k, n| done.push((k, n))).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(done, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (60, 40));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((frame.width, frame.height, frame.delay), (60, 40, 10));
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+    }
+}
@@ -0,0 +1,346 @@
+//! One call for programs that want a picture or the points of a limit set
+//! without going through `grandma`, `add_end`, `limitset_with` and
+//! `document` in the right order themselves. Building and rendering fail
+//! with the crate's `Error`:
+//!
+//! ```rust
+//! use num::complex::Complex;
+//! use svg_kleinian::{Error, KleinianRenderer};
+//!
+//! let output = KleinianRenderer::builder()
+//!     .traces(Complex::new(1.91, 0.05), Complex::new(2.0, 0.0))
+//!     .epsilon(1e-2)
+//!     .max_depth(400)
+//!     .max_nodes(2_000_000)
+//!     .with_document()
+//!     .render()?;
+//! assert_eq!(output.points.len(), output.stats.points);
+//! let path = std::env::temp_dir().join("figure.svg");
+//! svg::save(&path, output.document.as_ref().unwrap())?;
+//! # Ok::<(), Error>(())
+//! ```
+
+use num::complex::Complex;
+use std::time::{Duration, Instant};
+use svg::Document;
+
+use crate::{check_trace_size, limitset_with, maskit_traces, try_grandma, Error, Kleinian, Mat, RenderOptions, A, AI, B, BI, EPSILON, MAX_ESTIMATED_NODES};
+
+/// Receives each traced point, in the order of the curve.
+pub trait PointSink {
+    fn point(&mut self, x: f64, y: f64);
+}
+
+impl<F: FnMut(f64, f64)> PointSink for F {
+    fn point(&mut self, x: f64, y: f64) {
+        self(x, y)
+    }
+}
+
+/// How the curve is drawn; the defaults are those of `RenderOptions`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderStyle {
+    pub stroke: String,
+    pub stroke_width: f64,
+    pub linejoin: String,
+    pub linecap: String,
+    pub background: Option<String>,
+    pub view_box: (f64, f64, f64, f64),
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        let opts = RenderOptions::default();
+        RenderStyle {
+            stroke: opts.stroke,
+            stroke_width: opts.stroke_width,
+            linejoin: opts.linejoin,
+            linecap: opts.linecap,
+            background: opts.background,
+            view_box: opts.view_box,
+        }
+    }
+}
+
+/// What a render did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    pub points: usize,
+    /// Branches the traversal visited.
+    pub nodes: usize,
+    /// Whether `max_nodes` cut the traversal short.
+    pub truncated: bool,
+    pub elapsed: Duration,
+}
+
+/// The result of `KleinianRenderer::render`.
+#[derive(Debug)]
+pub struct RenderOutput {
+    pub points: Vec<(f64, f64)>,
+    pub stats: RenderStats,
+    /// The drawing, when `with_document` asked for it.
+    pub document: Option<Document>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Traces(Complex<f64>, Complex<f64>),
+    Maskit(Complex<f64>),
+    Matrices(Mat, Mat),
+}
+
+impl Source {
+    fn name(&self) -> &'static str {
+        match self {
+            Source::Traces(..) => "traces",
+            Source::Maskit(_) => "maskit",
+            Source::Matrices(..) => "matrices",
+        }
+    }
+}
+
+/// Collects the settings of a `KleinianRenderer`. The group is given by
+/// exactly one of `traces`, `maskit` and `matrices`, and is the
+/// Apollonian gasket if none is; everything else has a default.
+pub struct RendererBuilder {
+    sources: Vec<Source>,
+    epsilon: f64,
+    max_depth: i64,
+    max_nodes: Option<usize>,
+    node_ceiling: usize,
+    style: RenderStyle,
+    sink: Option<Box<dyn PointSink>>,
+    document: bool,
+}
+
+impl RendererBuilder {
+    /// Grandma's group with these traces of a and b.
+    pub fn traces(mut self, ta: Complex<f64>, tb: Complex<f64>) -> Self {
+        self.sources.push(Source::Traces(ta, tb));
+        self
+    }
+
+    /// The group of the Maskit slice at `mu`, as `Recipe::Maskit` makes it.
+    pub fn maskit(mut self, mu: Complex<f64>) -> Self {
+        self.sources.push(Source::Maskit(mu));
+        self
+    }
+
+    /// The group generated by these matrices.
+    pub fn matrices(mut self, a: Mat, b: Mat) -> Self {
+        self.sources.push(Source::Matrices(a, b));
+        self
+    }
+
+    /// How close consecutive points must be before a branch stops;
+    /// `EPSILON` by default.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// The longest word traced; 50 by default.
+    pub fn max_depth(mut self, depth: i64) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// The most branches a render may visit, unlimited by default.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Without `max_nodes`, the most branches the render may be expected
+    /// to visit before `build` refuses it; `MAX_ESTIMATED_NODES` by
+    /// default.
+    pub fn node_ceiling(mut self, nodes: usize) -> Self {
+        self.node_ceiling = nodes;
+        self
+    }
+
+    pub fn style(mut self, style: RenderStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Hands each point to `sink` as well as returning it.
+    pub fn sink<S: PointSink + 'static>(mut self, sink: S) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Draws the SVG document as well as tracing the points.
+    pub fn with_document(mut self) -> Self {
+        self.document = true;
+        self
+    }
+
+    /// Checks the settings and builds the group, saying which settings
+    /// clash or are out of range, or which traces the recipe can't use.
+    pub fn build(self) -> Result<KleinianRenderer, Error> {
+        if self.sources.len() > 1 {
+            let names: Vec<_> = self.sources.iter().map(Source::name).collect();
+            return Err(Error::Config(format!("the group is given more than once, by {}; give just one", names.join(" and "))));
+        }
+        if !(self.epsilon > 0.0 && self.epsilon.is_finite()) {
+            return Err(Error::Config(format!("epsilon must be positive, got {}", self.epsilon)));
+        }
+        if self.max_depth < 1 {
+            return Err(Error::Config(format!("max_depth must be at least 1, got {}", self.max_depth)));
+        }
+        let two = Complex::new(2.0, 0.0);
+        let mut group = match self.sources.into_iter().next().unwrap_or(Source::Traces(two, two)) {
+            Source::Traces(ta, tb) => try_grandma(ta, tb)?,
+            Source::Maskit(mu) => {
+                let (ta, tb) = maskit_traces(mu);
+                try_grandma(ta, tb)?
+            }
+            Source::Matrices(a, b) => Kleinian::new(a, b),
+        };
+        check_trace_size(self.max_depth, self.epsilon, group.is_known_discrete(), self.max_nodes, self.node_ceiling)?;
+        for &l in &[A, B, AI, BI] {
+            group.add_end(vec![l]);
+        }
+        let style = self.style;
+        let opts = RenderOptions {
+            stroke: style.stroke,
+            stroke_width: style.stroke_width,
+            linejoin: style.linejoin,
+            linecap: style.linecap,
+            background: style.background,
+            view_box: style.view_box,
+            epsilon: self.epsilon,
+            max_nodes: self.max_nodes,
+            ..RenderOptions::default()
+        };
+        Ok(KleinianRenderer { group, opts, level: self.max_depth, sink: self.sink, document: self.document })
+    }
+
+    /// `build`, then `KleinianRenderer::render`.
+    pub fn render(self) -> Result<RenderOutput, Error> {
+        self.build()?.render()
+    }
+}
+
+/// A group ready to trace and draw, made by `KleinianRenderer::builder`.
+pub struct KleinianRenderer {
+    group: Kleinian,
+    opts: RenderOptions,
+    level: i64,
+    sink: Option<Box<dyn PointSink>>,
+    document: bool,
+}
+
+impl KleinianRenderer {
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder {
+            sources: Vec::new(),
+            epsilon: EPSILON,
+            max_depth: 50,
+            max_nodes: None,
+            node_ceiling: MAX_ESTIMATED_NODES,
+            style: RenderStyle::default(),
+            sink: None,
+            document: false,
+        }
+    }
+
+    /// Traces the limit set afresh, feeding the sink and drawing the
+    /// document if asked to; a trace that finds no curve, just its starting
+    /// point, is an error.
+    pub fn render(&mut self) -> Result<RenderOutput, Error> {
+        let start = Instant::now();
+        limitset_with(self.level, &mut self.group, &self.opts);
+        let points: Vec<(f64, f64)> = self.group.points().iter().map(|z| (z.re, z.im)).collect();
+        if points.len() < 2 {
+            return Err(Error::NoPoints { level: self.level, epsilon: self.opts.epsilon });
+        }
+        if let Some(ref mut sink) = self.sink {
+            for &(x, y) in &points {
+                sink.point(x, y);
+            }
+        }
+        let document = if self.document { Some(self.group.document(&self.opts)) } else { None };
+        let stats = RenderStats {
+            points: points.len(),
+            nodes: self.group.nodes(),
+            truncated: !self.group.pending().is_empty(),
+            elapsed: start.elapsed(),
+        };
+        Ok(RenderOutput { points, stats, document })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn config_error(builder: RendererBuilder) -> String {
+        match builder.build() {
+            Err(Error::Config(message)) => message,
+            Err(e) => panic!("expected a configuration error, got {}", e),
+            Ok(_) => panic!("expected a configuration error"),
+        }
+    }
+
+    #[test]
+    fn defaults_are_the_gasket_and_render_options() {
+        let renderer = KleinianRenderer::builder().build().unwrap();
+        let opts = RenderOptions::default();
+        assert_eq!(renderer.level, 50);
+        assert_eq!(renderer.opts.epsilon, EPSILON);
+        assert_eq!(renderer.opts.max_nodes, None);
+        assert_eq!(renderer.opts.stroke, opts.stroke);
+        assert_eq!(renderer.opts.view_box, opts.view_box);
+        assert!(!renderer.document);
+        let gasket = try_grandma(Complex::new(2.0, 0.0), Complex::new(2.0, 0.0)).unwrap();
+        for &l in &[A, B] {
+            let (m, n) = (renderer.group.mat(l), gasket.mat(l));
+            assert_eq!((m.a, m.b, m.c, m.d), (n.a, n.b, n.c, n.d));
+        }
+    }
+
+    #[test]
+    fn a_group_given_twice_is_refused() {
+        let two = Complex::new(2.0, 0.0);
+        let message = config_error(KleinianRenderer::builder().traces(two, two).maskit(Complex::new(0.0, 2.0)));
+        assert!(message.contains("traces and maskit"), "{}", message);
+        let message = config_error(KleinianRenderer::builder().maskit(Complex::new(0.0, 2.0)).maskit(Complex::new(0.0, 1.9)));
+        assert!(message.contains("maskit and maskit"), "{}", message);
+    }
+
+    #[test]
+    fn settings_out_of_range_are_refused() {
+        assert!(config_error(KleinianRenderer::builder().epsilon(0.0)).contains("epsilon"));
+        assert!(config_error(KleinianRenderer::builder().epsilon(f64::NAN)).contains("epsilon"));
+        assert!(config_error(KleinianRenderer::builder().max_depth(0)).contains("max_depth"));
+        match KleinianRenderer::builder().epsilon(1e-9).node_ceiling(1000).build() {
+            Err(Error::TooLarge { ceiling, .. }) => assert_eq!(ceiling, 1000),
+            other => panic!("expected TooLarge, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn the_sink_sees_the_points_returned() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let into = seen.clone();
+        let output = KleinianRenderer::builder()
+            .epsilon(0.02)
+            .sink(move |x, y| into.lock().unwrap().push((x, y)))
+            .render()
+            .unwrap();
+        assert!(!output.points.is_empty());
+        assert!(output.document.is_none());
+        assert!(!output.stats.truncated);
+        assert_eq!(*seen.lock().unwrap(), output.points);
+    }
+
+    #[test]
+    fn max_nodes_truncates() {
+        let output = KleinianRenderer::builder().epsilon(0.001).max_nodes(100).render().unwrap();
+        assert!(output.stats.truncated);
+        assert!(output.stats.nodes < KleinianRenderer::builder().epsilon(0.001).render().unwrap().stats.nodes);
+    }
+}
@@ -0,0 +1,296 @@
+//! Saving a trace cut short by its node budget, with the branches it had
+//! still to visit, so a render that takes minutes can be carried on later,
+//! in another process, instead of started again.
+
+use num::complex::Complex;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use crate::{add_trace_ends, run_frame, Error, Kleinian, Letter, Mat, Word, A, AI, B, BI};
+
+/// The first line of a checkpoint file, bumped whenever the format changes.
+pub const CHECKPOINT_VERSION: &str = "svg_kleinian checkpoint 1";
+
+/// A branch of the traversal not yet traced: the word `path` followed by
+/// `letter`, whose transform up to `path` is `t`, with `level` steps left
+/// to go and `letter` already repeated `run` times at the end of the word.
+/// `start`, when given, is drawn before the branch, as the first point of
+/// a `RenderOptions::prefixes` branch is.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub level: i64,
+    pub letter: Letter,
+    pub run: usize,
+    pub t: Mat,
+    pub path: Vec<Letter>,
+    pub start: Option<Complex<f64>>,
+}
+
+fn invalid(n: usize, line: &str, expected: &str) -> Error {
+    let msg = format!("line {}: expected {}, got `{}`", n + 1, expected, line);
+    io::Error::new(io::ErrorKind::InvalidData, msg).into()
+}
+
+fn floats(fields: &[&str]) -> Option<Vec<f64>> {
+    fields.iter().map(|f| f.parse().ok()).collect()
+}
+
+fn write_mat<W: Write>(w: &mut W, m: &Mat) -> io::Result<()> {
+    for z in &[m.a, m.b, m.c, m.d] {
+        write!(w, " {} {}", z.re, z.im)?;
+    }
+    Ok(())
+}
+
+fn read_mat(x: &[f64]) -> Mat {
+    let z = |k: usize| Complex::new(x[k], x[k + 1]);
+    Mat::new(z(0), z(2), z(4), z(6))
+}
+
+// a word, or `-` for the empty one, which would leave a blank field
+fn write_word<W: Write>(w: &mut W, word: &[Letter]) -> io::Result<()> {
+    if word.is_empty() {
+        write!(w, " -")
+    } else {
+        write!(w, " {}", Word(word.to_vec()))
+    }
+}
+
+fn read_word(field: &str) -> Option<Vec<Letter>> {
+    if field == "-" {
+        Some(Vec::new())
+    } else {
+        field.parse::<Word>().ok().map(|w| w.0)
+    }
+}
+
+impl Kleinian {
+    /// The branches the last traversal's node budget cut off, which
+    /// `trace_frames` carries on with.
+    pub fn pending(&self) -> &[Frame] {
+        &self.pending
+    }
+
+    /// Writes the generators, the ends, the traversal settings, the points
+    /// traced so far and the pending branches to `path`, one record to a
+    /// line, for `resume` to read back. Words recorded by
+    /// `set_record_words`, the view, and the extent of each top-level
+    /// branch are not kept.
+    pub fn checkpoint(&self, path: &str) -> Result<(), Error> {
+        let mut w = BufWriter::new(File::create(path)?);
+        writeln!(w, "{}", CHECKPOINT_VERSION)?;
+        for &l in &[A, B] {
+            write!(w, "gen {}", l)?;
+            write_mat(&mut w, self.mat(l))?;
+            writeln!(w)?;
+        }
+        for &l in &[A, B, AI, BI] {
+            for z in self.ends.at(l) {
+                writeln!(w, "end {} {} {}", l, z.re, z.im)?;
+            }
+        }
+        for r in &self.relators {
+            write!(w, "relator")?;
+            write_word(&mut w, r)?;
+            writeln!(w)?;
+        }
+        writeln!(w, "reversed {}", self.reversed)?;
+        writeln!(w, "epsilon {}", self.epsilon)?;
+        if let Some(decay) = self.epsilon_decay {
+            writeln!(w, "decay {}", decay)?;
+        }
+        writeln!(w, "last {} {}", self.last.re, self.last.im)?;
+        for z in &self.points {
+            writeln!(w, "point {} {}", z.re, z.im)?;
+        }
+        for f in &self.pending {
+            write!(w, "frame {} {} {}", f.level, f.letter, f.run)?;
+            write_mat(&mut w, &f.t)?;
+            write_word(&mut w, &f.path)?;
+            match f.start {
+                Some(z) => writeln!(w, " {} {}", z.re, z.im)?,
+                None => writeln!(w, " -")?,
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Reads a file written by `checkpoint`, returning the group with the
+    /// points traced so far and the branches still to trace, to be passed
+    /// to `trace_frames`.
+    pub fn resume(path: &str) -> Result<(Kleinian, Vec<Frame>), Error> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        match lines.next() {
+            Some(Ok(ref line)) if line == CHECKPOINT_VERSION => {}
+            Some(Ok(line)) => return Err(invalid(0, &line, CHECKPOINT_VERSION)),
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(invalid(0, "", CHECKPOINT_VERSION)),
+        }
+        let mut gens = Vec::new();
+        let mut ends = Vec::new();
+        let mut relators = Vec::new();
+        let mut reversed = false;
+        let mut epsilon = crate::EPSILON;
+        let mut epsilon_decay = None;
+        let mut last = Complex::new(1.0, 0.0);
+        let mut points = Vec::new();
+        let mut frames = Vec::new();
+        for (n, line) in lines.enumerate() {
+            let line = line?;
+            let n = n + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["gen", _, rest @ ..] => match floats(rest) {
+                    Some(ref x) if x.len() == 8 => gens.push(read_mat(x)),
+                    _ => return Err(invalid(n, &line, "`gen L` and eight numbers")),
+                },
+                ["end", l, x, y] => match (read_word(l).as_deref(), x.parse(), y.parse()) {
+                    (Some(&[l]), Ok(x), Ok(y)) => ends.push((l, Complex::new(x, y))),
+                    _ => return Err(invalid(n, &line, "`end L x y`")),
+                },
+                ["relator", r] => match read_word(r) {
+                    Some(r) => relators.push(r),
+                    None => return Err(invalid(n, &line, "`relator WORD`")),
+                },
+                ["reversed", r] => match r.parse() {
+                    Ok(r) => reversed = r,
+                    Err(_) => return Err(invalid(n, &line, "`reversed true|false`")),
+                },
+                ["epsilon", e] => match e.parse() {
+                    Ok(e) => epsilon = e,
+                    Err(_) => return Err(invalid(n, &line, "`epsilon X`")),
+                },
+                ["decay", d] => match d.parse() {
+                    Ok(d) => epsilon_decay = Some(d),
+                    Err(_) => return Err(invalid(n, &line, "`decay X`")),
+                },
+                ["last", x, y] | ["point", x, y] => match (x.parse(), y.parse()) {
+                    (Ok(x), Ok(y)) if fields[0] == "last" => last = Complex::new(x, y),
+                    (Ok(x), Ok(y)) => points.push(Complex::new(x, y)),
+                    _ => return Err(invalid(n, &line, "`point x y`")),
+                },
+                ["frame", level, l, run, rest @ ..] if rest.len() >= 10 => {
+                    let t = floats(&rest[..8]);
+                    let path = read_word(rest[8]);
+                    let start = match &rest[9..] {
+                        ["-"] => Some(None),
+                        [x, y] => x.parse().ok().zip(y.parse().ok()).map(|(x, y)| Some(Complex::new(x, y))),
+                        _ => None,
+                    };
+                    match (level.parse(), read_word(l).as_deref(), run.parse(), t, path, start) {
+                        (Ok(level), Some(&[letter]), Ok(run), Some(t), Some(path), Some(start)) => {
+                            frames.push(Frame { level, letter, run, t: read_mat(&t), path, start });
+                        }
+                        _ => return Err(invalid(n, &line, "`frame LEVEL L RUN`, eight numbers, a word and a start")),
+                    }
+                }
+                _ => return Err(invalid(n, &line, "a checkpoint record")),
+            }
+        }
+        let (a, b) = match gens.as_slice() {
+            [a, b] => (a.clone(), b.clone()),
+            _ => return Err(Error::Parameters(format!("{} has {} generators, expected 2", path, gens.len()))),
+        };
+        let mut g = Kleinian::new(a, b);
+        for (l, z) in ends {
+            g.ends.at_mut(l).push(z);
+        }
+        g.relators = relators;
+        g.reversed = reversed;
+        g.epsilon = epsilon;
+        g.epsilon_decay = epsilon_decay;
+        g.last = last;
+        g.points = points;
+        Ok((g, frames))
+    }
+
+    /// Carries on a trace from `frames`, the pending branches of an earlier
+    /// one, adding their points after those already traced, under a fresh
+    /// budget of `max_nodes`. Branches that budget cuts off are pending
+    /// again, so a trace can be carried on in as many pieces as it takes;
+    /// the points come out the same as from one uninterrupted trace.
+    pub fn trace_frames(&mut self, frames: Vec<Frame>, max_nodes: Option<usize>) {
+        self.nodes = 0;
+        self.max_nodes = max_nodes;
+        self.pending.clear();
+        let ends = add_trace_ends(self);
+        for frame in frames {
+            run_frame(self, frame);
+        }
+        self.ends = ends;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with, RenderOptions};
+
+    fn group() -> Kleinian {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g
+    }
+
+    // a file of this test's own in the temporary directory
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("svg_kleinian-{}-{}.checkpoint", name, std::process::id()));
+        path.to_str().expect("a UTF-8 temporary directory").to_string()
+    }
+
+    fn whole_trace(g: &mut Kleinian, opts: &RenderOptions) -> Vec<Complex<f64>> {
+        limitset_with(12, g, opts);
+        assert!(g.pending().is_empty());
+        g.points().to_vec()
+    }
+
+    #[test]
+    fn resuming_gives_the_uninterrupted_points() {
+        let opts = RenderOptions { epsilon: 0.02, ..RenderOptions::default() };
+        let whole = whole_trace(&mut group(), &opts);
+        let mut g = group();
+        limitset_with(12, &mut g, &RenderOptions { max_nodes: Some(500), ..opts });
+        assert!(!g.pending().is_empty());
+        assert!(g.points().len() < whole.len());
+        let path = temp_path("resume");
+        g.checkpoint(&path).unwrap();
+        let (mut g, frames) = Kleinian::resume(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        g.trace_frames(frames, None);
+        assert!(g.pending().is_empty());
+        assert_eq!(g.points(), &whole[..]);
+    }
+
+    #[test]
+    fn resuming_in_pieces_keeps_the_settings() {
+        let opts = RenderOptions { epsilon: 0.02, epsilon_decay: Some(0.97), ..RenderOptions::default() };
+        let relator: crate::Word = "aaa".parse().unwrap();
+        let whole = whole_trace(&mut group().with_relator(relator.clone()), &opts);
+        let mut g = group().with_relator(relator);
+        limitset_with(12, &mut g, &RenderOptions { max_nodes: Some(300), ..opts });
+        let path = temp_path("pieces");
+        let mut pieces = 1;
+        while !g.pending().is_empty() {
+            g.checkpoint(&path).unwrap();
+            let (resumed, frames) = Kleinian::resume(&path).unwrap();
+            g = resumed;
+            g.trace_frames(frames, Some(300));
+            pieces += 1;
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(pieces > 2, "{} pieces", pieces);
+        assert_eq!(g.points(), &whole[..]);
+    }
+
+    #[test]
+    fn another_version_is_refused() {
+        let path = temp_path("version");
+        std::fs::write(&path, "svg_kleinian checkpoint 0\n").unwrap();
+        let resumed = Kleinian::resume(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(resumed.is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use num::complex::Complex;
+
+use crate::sphere::to_sphere;
+use crate::{limitset, traces, Kleinian, Mat, Word, COMMUTATORS, PRESETS};
+
+/// How close the normalized trace must be to ±2, or to the real line, to
+/// count as exactly there.
+pub const TRACE_TOLERANCE: f64 = 1e-6;
+
+/// The largest order `elliptic_order` looks for. A rotation by `2π/n` with
+/// `n` beyond this is treated as infinite order.
+pub const MAX_ELLIPTIC_ORDER: usize = 24;
+
+/// How close `n` times the rotation angle, in turns, must be to a whole
+/// number for the rotation to count as having order `n`.
+pub const ORDER_TOLERANCE: f64 = 1e-9;
+
+/// The largest step, as chordal distance on the unit sphere, between
+/// consecutive traced points for `limit_set_topology` to count the trace as
+/// connected. A connected trace steps by about `epsilon`, except near
+/// parabolic points, where the depth runs out first and steps shrink only
+/// like 1/level; a trace of dust jumps across the gaps between its
+/// pieces, which are much wider.
+pub const CONNECTED_GAP: f64 = 0.25;
+
+/// Farey words up to this denominator are checked for accidental
+/// parabolics by `limit_set_topology`.
+pub const PINCH_DENOMINATOR: i64 = 3;
+
+/// The shape of a limit set, as `Kleinian::limit_set_topology` judges it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitSetKind {
+    /// A simple closed curve, a circle or quasicircle, which can be drawn
+    /// closed or filled.
+    JordanCurve,
+    /// A curve pinched together where some word besides the commutator is
+    /// parabolic, as in circle packings and the Apollonian gasket; the
+    /// pinches leave it nowhere to be filled.
+    Gasket,
+    /// Dust: the commutator is not parabolic, so the pieces of the trace
+    /// never meet, and it is best drawn as points.
+    Cantor,
+}
+
+/// The conjugacy type of a Möbius transformation, read off its trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    Identity,
+    /// One fixed point: trace ±2.
+    Parabolic,
+    /// A rotation about two fixed points: real trace in (-2, 2).
+    Elliptic,
+    /// Pure translation along its axis: real trace outside [-2, 2].
+    Hyperbolic,
+    /// Translation with a twist: trace off the real line.
+    Loxodromic,
+}
+
+impl Mat {
+    /// The type of the transformation, judged from the trace scaled to
+    /// determinant 1 to within `TRACE_TOLERANCE`.
+    pub fn classify(&self) -> Classification {
+        let det = self.a * self.d - self.b * self.c;
+        let t = self.trace() / det.sqrt();
+        // ±t are the same transformation
+        let t = if t.re < 0.0 { -t } else { t };
+        let two = Complex::new(2.0, 0.0);
+        if (t - two).norm() < TRACE_TOLERANCE {
+            let scale = det.sqrt();
+            if (self.b / scale).norm() < TRACE_TOLERANCE && (self.c / scale).norm() < TRACE_TOLERANCE {
+                Classification::Identity
+            } else {
+                Classification::Parabolic
+            }
+        } else if t.im.abs() >= TRACE_TOLERANCE {
+            Classification::Loxodromic
+        } else if t.re < 2.0 {
+            Classification::Elliptic
+        } else {
+            Classification::Hyperbolic
+        }
+    }
+
+    /// The order `n` of an elliptic transformation, the least `n` with
+    /// `self^n = ±id`, if it is at most `MAX_ELLIPTIC_ORDER`. The rotation
+    /// angle is `2 acos(t/2)` for the normalized trace `t`, and `n` must
+    /// bring it within `ORDER_TOLERANCE` of a whole turn.
+    pub fn elliptic_order(&self) -> Option<usize> {
+        if self.classify() != Classification::Elliptic {
+            return None;
+        }
+        let det = self.a * self.d - self.b * self.c;
+        let t = (self.trace() / det.sqrt()).re;
+        let turns = (t / 2.0).acos() / std::f64::consts::PI;
+        (2..=MAX_ELLIPTIC_ORDER).find(|&n| {
+            let x = n as f64 * turns;
+            (x - x.round()).abs() < ORDER_TOLERANCE
+        })
+    }
+
+    pub fn is_parabolic(&self) -> bool {
+        self.classify() == Classification::Parabolic
+    }
+
+    pub fn is_elliptic(&self) -> bool {
+        self.classify() == Classification::Elliptic
+    }
+
+    pub fn is_hyperbolic(&self) -> bool {
+        self.classify() == Classification::Hyperbolic
+    }
+
+    pub fn is_loxodromic(&self) -> bool {
+        self.classify() == Classification::Loxodromic
+    }
+}
+
+impl Kleinian {
+    /// Whether a, b and ab all have real traces, to within
+    /// `TRACE_TOLERANCE` of their size, so the group is conjugate into
+    /// SL(2, R); a discrete one then has a round limit set.
+    pub fn is_fuchsian(&self) -> bool {
+        let (ta, tb, tab) = traces(self);
+        [ta, tb, tab].iter().all(|t| t.im.abs() < TRACE_TOLERANCE * t.norm().max(1.0))
+    }
+
+    /// Whether the group is known to be discrete without tracing it: the
+    /// Apollonian gasket, or a Fuchsian group whose traces ta, tb and tab
+    /// are all at least 2 in size, which by Fricke's theorem is a
+    /// punctured torus group, as the commutator is parabolic; or the group
+    /// of one of the curated `PRESETS`. Other groups may well be discrete
+    /// too, but nothing cheap tells.
+    pub fn is_known_discrete(&self) -> bool {
+        let (ta, tb, tab) = traces(self);
+        let near = |u: Complex<f64>, v: Complex<f64>| (u - v).norm() < TRACE_TOLERANCE * v.norm().max(1.0);
+        self.is_gasket()
+            || (self.is_fuchsian() && [ta, tb, tab].iter().all(|t| t.re.abs() >= 2.0 - TRACE_TOLERANCE * t.norm().max(1.0)))
+            || PRESETS.iter().any(|p| near(ta, p.ta) && near(tb, p.tb))
+    }
+
+    /// Traces the limit set to `level`, as `limitset` does, and sorts it
+    /// into a `LimitSetKind`. It is a Cantor set when the commutator is
+    /// not parabolic, or the trace is not connected to within
+    /// `CONNECTED_GAP`; otherwise a gasket when a Farey word up to
+    /// `PINCH_DENOMINATOR` is parabolic too, and otherwise a Jordan curve.
+    pub fn limit_set_topology(&mut self, level: i64) -> LimitSetKind {
+        limitset(level, self);
+        let gap = self.points.windows(2)
+            .map(|p| {
+                let ([x0, y0, z0], [x1, y1, z1]) = (to_sphere(p[0]), to_sphere(p[1]));
+                ((x1 - x0).powi(2) + (y1 - y0).powi(2) + (z1 - z0).powi(2)).sqrt()
+            })
+            .fold(0.0, f64::max);
+        let commutator = self.word(&Word(COMMUTATORS[1].to_vec()));
+        if !commutator.is_parabolic() || gap > CONNECTED_GAP.max(4.0 * self.epsilon) {
+            return LimitSetKind::Cantor;
+        }
+        let mut fractions = vec![(1, 0)];
+        for q in 1..=PINCH_DENOMINATOR {
+            fractions.extend((-q..=q).map(|p| (p, q)));
+        }
+        let pinched = fractions.into_iter()
+            .filter_map(|(p, q)| Word::farey(p, q))
+            .any(|w| self.word(&w).is_parabolic());
+        if pinched { LimitSetKind::Gasket } else { LimitSetKind::JordanCurve }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grandma;
+
+    fn c(re: f64, im: f64) -> Complex<f64> {
+        Complex::new(re, im)
+    }
+
+    #[test]
+    fn each_predicate_picks_out_its_type() {
+        let (zero, one) = (c(0.0, 0.0), c(1.0, 0.0));
+        let parabolic = Mat::new(one, c(1.0, 1.0), zero, one);
+        let elliptic = Mat::new(c(0.0, 1.0), zero, zero, c(0.0, -1.0));
+        let hyperbolic = Mat::new(c(2.0, 0.0), zero, zero, c(0.5, 0.0));
+        let loxodromic = Mat::new(c(1.0, 1.0), zero, zero, c(0.5, -0.5));
+        let all = [parabolic, elliptic, hyperbolic, loxodromic, Mat::id()];
+        let predicates: [fn(&Mat) -> bool; 4] =
+            [Mat::is_parabolic, Mat::is_elliptic, Mat::is_hyperbolic, Mat::is_loxodromic];
+        for (i, m) in all.iter().enumerate() {
+            for (j, p) in predicates.iter().enumerate() {
+                assert_eq!(p(m), i == j, "predicate {} on matrix {}", j, i);
+            }
+        }
+        // a scalar multiple is the same transformation
+        let scaled = Mat::new(c(3.0, 0.0), c(3.0, 3.0), zero, c(3.0, 0.0));
+        assert!(scaled.is_parabolic());
+    }
+
+    #[test]
+    fn each_constructor_gives_its_type() {
+        use std::f64::consts::PI;
+        assert_eq!(Mat::id().classify(), Classification::Identity);
+        assert_eq!(Mat::rotation(0.7).classify(), Classification::Elliptic);
+        assert_eq!(Mat::rotation(2.0 * PI).classify(), Classification::Identity);
+        assert_eq!(Mat::translation(c(1.0, 1.0)).classify(), Classification::Parabolic);
+        assert_eq!(Mat::translation(c(0.0, 0.0)).classify(), Classification::Identity);
+        assert_eq!(Mat::scaling(c(4.0, 0.0)).classify(), Classification::Hyperbolic);
+        assert_eq!(Mat::scaling(Complex::from_polar(&1.0, &0.5)).classify(), Classification::Elliptic);
+        assert_eq!(Mat::scaling(c(2.0, 1.0)).classify(), Classification::Loxodromic);
+    }
+
+    #[test]
+    fn a_rotation_by_a_whole_fraction_has_its_order() {
+        use std::f64::consts::PI;
+        for n in 2..=7 {
+            assert_eq!(Mat::rotation(2.0 * PI / n as f64).elliptic_order(), Some(n));
+        }
+        // two thirds of a turn is also of order 3, and a multiple is the same map
+        let m = Mat::rotation(4.0 * PI / 3.0);
+        assert_eq!(m.elliptic_order(), Some(3));
+        let three = c(3.0, 0.0);
+        assert_eq!(Mat::new(m.a * three, m.b * three, m.c * three, m.d * three).elliptic_order(), Some(3));
+        assert_eq!(Mat::rotation(1.0).elliptic_order(), None);
+        assert_eq!(Mat::translation(c(1.0, 0.0)).elliptic_order(), None);
+    }
+
+    #[test]
+    fn each_preset_has_its_topology() {
+        use crate::{preset_by_name, A, AI, B, BI};
+        let expected = [
+            ("apollonian-gasket", LimitSetKind::Gasket),
+            ("fuchsian", LimitSetKind::JordanCurve),
+            ("spirals", LimitSetKind::JordanCurve),
+            ("sqrt3-plus-i", LimitSetKind::Gasket),
+        ];
+        for (name, kind) in expected {
+            let mut g = preset_by_name(name).unwrap();
+            for &l in &[A, B, AI, BI] {
+                g.add_end(vec![l]);
+            }
+            assert_eq!(g.limit_set_topology(30), kind, "{}", name);
+        }
+    }
+
+    #[test]
+    fn only_groups_with_a_cheap_proof_are_known_discrete() {
+        assert!(grandma(c(2.0, 0.0), c(2.0, 0.0)).is_known_discrete());
+        assert!(grandma(c(3.0, 0.0), c(3.0, 0.0)).is_known_discrete());
+        assert!(PRESETS.iter().all(|p| grandma(p.ta, p.tb).is_known_discrete()));
+        assert!(!grandma(c(1.91, 0.05), c(1.91, 0.05)).is_known_discrete());
+        assert!(!grandma(c(1.5, 0.0), c(3.0, 0.0)).is_known_discrete());
+    }
+}
@@ -0,0 +1,751 @@
+//! The command line, as clap sees it: each subcommand's options, with the
+//! groups several share (the parameters of a group, the style of its
+//! stroke, where the output goes) built once and added to each. `main`
+//! looks the values up and does the work.
+
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use num::complex::Complex;
+use svg_kleinian::{check_branch_order, parse_complex, preset, Colormap, Curve, Interpolation, Mat, Mirror, Model, Overlay, Preset, Recipe, RenderConfig, RenderOptions, Word, PRESETS};
+
+/// The subcommands, in the order `--help` lists them.
+pub const SUBCOMMANDS: [&str; 10] = ["render", "info", "cusp", "animate", "grid", "tiles", "batch", "serve", "repl", "golden"];
+
+/// The formats `render --format` writes.
+pub const FORMATS: [&str; 11] = ["svg", "png", "pdf", "eps", "tikz", "heatmap", "csv", "json", "npy", "sphere-obj", "sphere-json"];
+
+// the options of `render` that only matter to a drawing, which the modes
+// that print something else instead refuse
+const DRAWING: [&str; 8] = ["format", "overlay", "circles", "curve", "mirror", "gasket", "color-letters", "html"];
+
+const RENDER_HELP: &str = "\
+Traces are complex numbers written x, yi, x+yi or x-yi (j for i will do),
+(x, y), or r@θdeg or r@θ in radians. With no options the render is
+grandma's recipe with ta = tb = 2, the Apollonian gasket.
+The picture is the square -1.2..1.2 each way unless --center or --zoom
+moves it; y grows downwards, as in SVG.
+A --config file ending in .toml is a scene file, whose keys stand for these
+options (see scene.rs); options on the command line override it.
+Diagnostics go to stderr: warnings by default, none with -q, and with -v
+the chosen settings and each phase, -vv the traversal's own details and
+-vvv the generators' matrices. Errors are always shown.";
+
+/// The whole command line.
+pub fn command() -> Command {
+    Command::new("svg_kleinian")
+        .about("Draws the limit sets of two-generator Kleinian groups")
+        .subcommand_required(true)
+        .arg(Arg::new("quiet").short('q').long("quiet").global(true).action(ArgAction::SetTrue)
+            .help("show no warnings"))
+        .arg(Arg::new("verbose").short('v').long("verbose").global(true).action(ArgAction::Count)
+            .help("say more about what is going on; -vv and -vvv say more still"))
+        .arg(Arg::new("config").long("config").global(true).value_name("FILE|-").action(ArgAction::Append)
+            .help("read options from FILE, whitespace-separated as here, or a scene file ending in .toml"))
+        .arg(Arg::new("from-svg").long("from-svg").global(true).value_name("FILE").action(ArgAction::Append)
+            .help("take the recipe, traces, level and epsilon from an SVG rendered before, gzipped or not"))
+        .subcommands([render(), info(), cusp(), animate(), grid(), tiles(), batch(), serve(), repl(), golden()].map(hyphen_values))
+}
+
+// a subcommand in which an option given twice takes its last value, so
+// the command line overrides a --config file
+fn subcommand(name: &'static str) -> Command {
+    Command::new(name).args_override_self(true)
+}
+
+// `command` with each option's value allowed to start with a minus sign,
+// as traces often do
+fn hyphen_values(command: Command) -> Command {
+    let ids: Vec<_> = command.get_arguments()
+        .filter(|a| !a.is_positional() && a.get_action().takes_values())
+        .map(|a| a.get_id().clone())
+        .collect();
+    ids.into_iter().fold(command, |command, id| command.mut_arg(id, |a| a.allow_hyphen_values(true)))
+}
+
+fn render() -> Command {
+    subcommand("render")
+        .about("trace a group and draw its limit set (the default)")
+        .after_help(RENDER_HELP)
+        .args(parameter_args())
+        .args(traversal_args())
+        .args(style_args())
+        .arg(output_arg())
+        .args([
+            flag("color-letters", "color each branch by its first letter"),
+            Arg::new("overlay").long("overlay").action(ArgAction::Append).value_parser(parse::<Overlay>)
+                .value_name("OVERLAY")
+                .help("draw something more over the limit set: isometric-circles, axes=WORD,..., cusps[=WORD,...], fundamental-domain, tessellation=N, cayley-tree=N, horoballs=N, geodesics=P/Q,..., geodesics=farey:N or convex-hull"),
+            Arg::new("circles").long("circles").value_name("N").action(ArgAction::Append).value_parser(value_parser!(usize))
+                .help("draw the Schottky circles to depth N"),
+            Arg::new("marker-size").long("marker-size").value_name("N").value_parser(value_parser!(f64)),
+            Arg::new("marker-color").long("marker-color").value_name("COLOR"),
+            flag("chords", "join the ends of each branch by a chord"),
+            Arg::new("domain-opacity").long("domain-opacity").value_name("X").value_parser(value_parser!(f64)),
+            flag("smooth", "draw the points as a smooth curve"),
+            Arg::new("tree-taper").long("tree-taper").value_name("X").value_parser(value_parser!(f64)),
+            Arg::new("horoball-height").long("horoball-height").value_name("X").value_parser(value_parser!(f64)),
+            Arg::new("html").long("html").value_name("FILE").help("also write a page showing the picture"),
+            flag("gasket", "draw the Apollonian gasket's circles, when the group has it as limit set"),
+            flag("dump-generators", "print the generators instead of tracing"),
+            flag("stream-points", "print the points as lines `x y` as they are traced, instead of drawing")
+                .conflicts_with_all(DRAWING).conflicts_with("analyze"),
+            flag("analyze", "print how the trace grows with depth instead of drawing").conflicts_with_all(DRAWING),
+            Arg::new("curve").long("curve").value_name("TA,TB,COLOR").action(ArgAction::Append).value_parser(parse_curve)
+                .help("draw the limit set of another group as well, in COLOR"),
+            Arg::new("mirror").long("mirror").value_name("conj|unit-circle").value_parser(parse::<Mirror>)
+                .help("draw the mirror image too"),
+            Arg::new("mirror-stroke").long("mirror-stroke").value_name("COLOR"),
+            Arg::new("post-transform").long("post-transform").value_name("A,B,C,D").value_parser(parse_mat)
+                .help("draw each point z at (az + b)/(cz + d)"),
+            Arg::new("camera").long("camera").value_name("Z1,Z2,Z3:W1,W2,W3").value_parser(parse_camera)
+                .help("move the picture by the map sending each z to its w, after any --post-transform"),
+            Arg::new("model").long("model").value_name("disk|halfplane").value_parser(parse::<Model>)
+                .help("conjugate a Fuchsian group into this model of the hyperbolic plane"),
+            Arg::new("center").long("center").value_name("Z").value_parser(parse_trace),
+            Arg::new("zoom").long("zoom").value_name("K").value_parser(positive),
+            Arg::new("clip").long("clip").value_name("X,Y,W,H").value_parser(parse_window),
+            Arg::new("precision").long("precision").value_name("DIGITS").value_parser(value_parser!(usize)),
+            Arg::new("outline").long("outline").value_name("WIDTH").value_parser(positive),
+            Arg::new("ribbon").long("ribbon").value_name("WIDTH").value_parser(positive),
+            Arg::new("gradient").long("gradient").value_name("COLOR,COLOR,...")
+                .help("color the curve from the first color to the last along its length"),
+            Arg::new("format").long("format").value_parser(PossibleValuesParser::new(FORMATS)),
+            Arg::new("size").long("size").value_name("WxH").value_parser(parse_size)
+                .help("pixels for images, points for PDF and EPS"),
+            Arg::new("stroke-px").long("stroke-px").value_name("X").value_parser(positive),
+            Arg::new("supersample").long("supersample").value_name("K").value_parser(value_parser!(u32).range(1..)),
+            Arg::new("gamma").long("gamma").value_name("X").value_parser(positive),
+            flag("log", "tone the heatmap logarithmically"),
+            Arg::new("colormap").long("colormap").value_name("gray|palette=N").value_parser(parse::<Colormap>),
+            Arg::new("tikz-width").long("tikz-width").value_name("CM").value_parser(positive),
+            flag("standalone", "write a whole LaTeX document rather than a picture to include"),
+            flag("compress", "gzip the SVG"),
+            Arg::new("compression-level").long("compression-level").value_name("0-9").value_parser(value_parser!(u32).range(0..=9)),
+            flag("ndjson", "write JSON one point per line"),
+            flag("words", "write each point's word with it"),
+            flag("with-depth", "write each point's depth with it"),
+            flag("verify", "check the points are invariant under the generators before writing"),
+            Arg::new("verify-tolerance").long("verify-tolerance").value_name("X").value_parser(positive),
+            flag("watch", "render again whenever the --config file is saved, until stopped"),
+            flag("dump-config", "print the options in effect as a scene file instead"),
+            Arg::new("stats-json").long("stats-json").value_name("FILE").help("write what the render did as JSON"),
+        ])
+}
+
+fn info() -> Command {
+    subcommand("info")
+        .about("describe a group without drawing it")
+        .args(parameter_args())
+}
+
+fn repl() -> Command {
+    subcommand("repl")
+        .about("read commands such as `ta 1.91+0.05i` and `render out.svg` from stdin; see repl.rs")
+        .args(parameter_args())
+}
+
+fn cusp() -> Command {
+    subcommand("cusp")
+        .about("find μ for the Maskit cusp group of P/Q, and draw it with --render")
+        .allow_negative_numbers(true)
+        .arg(Arg::new("p").value_name("P").required(true).value_parser(value_parser!(i64)))
+        .arg(Arg::new("q").value_name("Q").required(true).value_parser(value_parser!(i64).range(1..)))
+        .arg(flag("render", "draw the group as well"))
+        .args([level_arg(), epsilon_arg()])
+        .args(style_args())
+        .arg(output_arg().help("where to draw the group, implying --render (default cusp-P-Q.svg)"))
+}
+
+fn animate() -> Command {
+    let range = |name: &'static str| Arg::new(name).long(name).value_name("Z..Z").value_parser(parse_range);
+    subcommand("animate")
+        .about("draw a group as its traces move, as an animated SVG, numbered frames, or a GIF or APNG")
+        .after_help("PATTERN numbers frames with %d or %0Nd, as in frames/%04d.svg")
+        .args([
+            range("ta").help("the path of ta"),
+            range("tb").required_unless_present_any(["mu", "farey", "bend"]).help("the path of tb"),
+            range("mu").conflicts_with_all(["ta", "tb", "farey", "bend"]).help("the path of μ in the Maskit slice"),
+            Arg::new("farey").long("farey").value_name("P/Q,P/Q,...").value_parser(parse_fractions)
+                .conflicts_with_all(["ta", "tb", "bend"]).help("through the Maskit cusps of these fractions"),
+            Arg::new("cusp-offset").long("cusp-offset").value_name("X").value_parser(non_negative),
+            range("bend").conflicts_with("ta").help("bend a Fuchsian group out of the plane, moving ta"),
+            flag("reference-circle", "draw the circle the bend starts from"),
+            Arg::new("frames").long("frames").value_name("N").value_parser(at_least(1)),
+            Arg::new("depth").long("depth").value_name("N").value_parser(value_parser!(i64)),
+            Arg::new("interpolate").long("interpolate").value_name("linear|geodesic|polar").value_parser(parse::<Interpolation>),
+            Arg::new("max-points").long("max-points").value_name("N").value_parser(at_least(2)),
+            Arg::new("frame-duration").long("frame-duration").value_name("SECONDS").value_parser(positive).conflicts_with("fps"),
+            Arg::new("fps").long("fps").value_name("N").value_parser(positive),
+            Arg::new("out").long("out").value_name("PATTERN").value_parser(parse_frame_pattern)
+                .help("write each frame to a file of its own"),
+            flag("resume", "keep the frames --out already wrote"),
+            Arg::new("format").long("format").num_args(2).value_names(["gif|apng", "FILE"])
+                .conflicts_with("out").help("write an animated image"),
+            Arg::new("size").long("size").value_name("WxH").value_parser(parse_size),
+            Arg::new("loop").long("loop").value_name("N").value_parser(value_parser!(u16))
+                .help("how many times to play, or 0 for forever"),
+        ])
+        .group(ArgGroup::new("path").args(["ta", "mu", "farey", "bend"]).required(true))
+        .args(style_args())
+        .arg(output_arg().help("the animated SVG (default animation.svg)"))
+}
+
+fn grid() -> Command {
+    let range = |name: &'static str| Arg::new(name).long(name).value_name("Z..Z").value_parser(parse_range);
+    subcommand("grid")
+        .about("draw a grid of limit sets over ranges of traces, or of μ in the Maskit slice")
+        .args([
+            range("ta").required_unless_present("mu").help("ta across the columns"),
+            range("tb").required_unless_present("mu").help("tb down the rows"),
+            range("mu").conflicts_with_all(["ta", "tb"])
+                .help("the rectangle of μ with these corners, Re μ across the columns and Im μ down the rows"),
+            Arg::new("size").long("size").value_name("COLSxROWS").value_parser(parse_grid),
+            Arg::new("depth").long("depth").value_name("N").value_parser(value_parser!(i64)),
+            epsilon_arg(),
+            Arg::new("max-nodes").long("max-nodes").value_name("N").value_parser(value_parser!(usize)),
+        ])
+        .args(style_args())
+        .arg(output_arg().help("default grid.svg"))
+}
+
+fn tiles() -> Command {
+    subcommand("tiles")
+        .about("draw a window of the limit set as a grid of tiles")
+        .args([
+            Arg::new("window").long("window").value_name("X,Y,W,H").required(true).value_parser(parse_window),
+            Arg::new("grid").long("grid").value_name("COLSxROWS").value_parser(parse_grid),
+            preset_arg(),
+            Arg::new("depth").long("depth").value_name("N").value_parser(value_parser!(i64)),
+        ])
+        .args(style_args())
+        .arg(Arg::new("output").short('o').value_name("PATTERN")
+            .help("where each tile goes, with {row} and {col} (default tile-{row}-{col}.svg)"))
+}
+
+fn batch() -> Command {
+    subcommand("batch")
+        .about("render the jobs of a file, each begun by [[job]]; see scene.rs")
+        .arg(Arg::new("jobs-file").value_name("JOBS.toml").required(true))
+        .arg(Arg::new("jobs").long("jobs").value_name("N").value_parser(at_least(1)))
+        .arg(flag("fail-fast", "start no more jobs once one fails"))
+}
+
+fn serve() -> Command {
+    subcommand("serve")
+        .about("render over HTTP on this machine")
+        .args([
+            Arg::new("port").long("port").value_name("N").value_parser(value_parser!(u16)),
+            Arg::new("workers").long("workers").value_name("N").value_parser(at_least(1)),
+            Arg::new("max-nodes").long("max-nodes").value_name("N").value_parser(value_parser!(usize)),
+            Arg::new("timeout").long("timeout").value_name("SECONDS").value_parser(positive),
+            Arg::new("cache").long("cache").value_name("N").value_parser(value_parser!(usize)),
+        ])
+}
+
+fn golden() -> Command {
+    subcommand("golden")
+        .about("check the trace against the saved one in golden/")
+        .arg(flag("update", "save the trace as it is now instead"))
+}
+
+fn flag(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).action(ArgAction::SetTrue).help(help)
+}
+
+fn preset_arg() -> Arg {
+    Arg::new("preset").long("preset").value_name("NAME").value_parser(parse_preset)
+        .help("a named pair of traces; see presets.rs")
+}
+
+fn level_arg() -> Arg {
+    Arg::new("level").long("level").value_name("N").value_parser(value_parser!(i64).range(1..))
+        .help("the longest word traced, at least 1 (default 50)")
+}
+
+fn epsilon_arg() -> Arg {
+    Arg::new("epsilon").long("epsilon").value_name("X").value_parser(positive)
+        .help("how far apart, in the plane's own units, consecutive points may be before a branch stops; positive (default 0.001)")
+}
+
+fn output_arg() -> Arg {
+    Arg::new("output").short('o').long("output").value_name("FILE|-")
+        .help("where to write; - is standard output (default image.svg, or the like for other formats)")
+}
+
+/// The options of `RenderConfig`, which every subcommand that draws one
+/// group shares.
+fn parameter_args() -> [Arg; 8] {
+    [
+        Arg::new("recipe").long("recipe").value_name("grandma|maskit").value_parser(parse::<Recipe>)
+            .help("grandma (default), or maskit, the Maskit slice, where --ta is μ, as in Indra's Pearls, and --tb is unused"),
+        Arg::new("ta").long("ta").value_name("Z").value_parser(parse_trace).help("the trace of a (default 2)"),
+        Arg::new("tb").long("tb").value_name("Z").value_parser(parse_trace).help("the trace of b (default 2)"),
+        preset_arg(),
+        level_arg(),
+        epsilon_arg(),
+        Arg::new("max-nodes").long("max-nodes").value_name("N").value_parser(value_parser!(usize))
+            .help("stop tracing after this many branches (default: no limit)"),
+        Arg::new("node-ceiling").long("node-ceiling").value_name("N").value_parser(value_parser!(usize))
+            .help("without --max-nodes, refuse a trace expected to visit more branches than this (default 1000000000)"),
+    ]
+}
+
+/// The options that change how the tree of words is walked.
+fn traversal_args() -> [Arg; 5] {
+    [
+        Arg::new("relator").long("relator").value_name("WORD").action(ArgAction::Append)
+            .value_parser(|s: &str| parse_reduced(s, "relator")).help("a word equal to the identity"),
+        Arg::new("prefix").long("prefix").value_name("WORD").action(ArgAction::Append)
+            .value_parser(|s: &str| parse_reduced(s, "prefix")).help("trace only the words beginning with WORD"),
+        Arg::new("seed-word").long("seed-word").value_name("WORD").value_parser(parse_word)
+            .help("trace the image of the limit set under WORD"),
+        Arg::new("branch-order").long("branch-order").value_name("LETTERS").value_parser(parse_branch_order)
+            .help("the order of the four top-level branches, each of a, b, A and B once"),
+        Arg::new("epsilon-decay").long("epsilon-decay").value_name("FACTOR").value_parser(positive)
+            .help("multiply epsilon by FACTOR at each level"),
+    ]
+}
+
+/// The options of the stroke, which every subcommand that draws shares.
+fn style_args() -> [Arg; 4] {
+    [
+        Arg::new("stroke").long("stroke").value_name("COLOR"),
+        Arg::new("background").long("background").value_name("COLOR"),
+        Arg::new("linejoin").long("linejoin").value_parser(["miter", "round", "bevel"]),
+        Arg::new("linecap").long("linecap").value_parser(["butt", "round", "square"]),
+    ]
+}
+
+// the value of `id` in `m`, or None if it wasn't given or `m` has no such
+// option, so one function can read the options of several subcommands
+fn value<T: Clone + Send + Sync + 'static>(m: &ArgMatches, id: &str) -> Option<T> {
+    m.try_get_one::<T>(id).ok().flatten().cloned()
+}
+
+/// `config` with the parameter options of `m` applied. Of `--preset` and
+/// `--ta` or `--tb`, whichever comes later wins.
+pub fn render_config(m: &ArgMatches, mut config: RenderConfig) -> RenderConfig {
+    let preset_at = m.try_contains_id("preset").ok().filter(|&given| given).and_then(|_| m.index_of("preset"));
+    let later = |id: &str| m.try_contains_id(id).unwrap_or(false) && m.index_of(id) > preset_at;
+    if let Some(p) = value::<&Preset>(m, "preset") {
+        config.ta = p.ta;
+        config.tb = p.tb;
+    }
+    if let Some(ta) = value(m, "ta").filter(|_| later("ta")) {
+        config.ta = ta;
+    }
+    if let Some(tb) = value(m, "tb").filter(|_| later("tb")) {
+        config.tb = tb;
+    }
+    if let Some(recipe) = value(m, "recipe") {
+        config.recipe = recipe;
+    }
+    if let Some(level) = value(m, "level") {
+        config.level = level;
+    }
+    if let Some(epsilon) = value(m, "epsilon") {
+        config.epsilon = epsilon;
+    }
+    if let Some(n) = value(m, "max-nodes") {
+        config.max_nodes = Some(n);
+    }
+    if let Some(n) = value(m, "node-ceiling") {
+        config.node_ceiling = n;
+    }
+    if let Some(path) = value(m, "output") {
+        config.output = Some(path);
+    }
+    config
+}
+
+/// `opts` with the style options of `m` applied.
+pub fn style_options(m: &ArgMatches, opts: &mut RenderOptions) {
+    if let Some(color) = value(m, "stroke") {
+        opts.stroke = color;
+    }
+    if let Some(color) = value(m, "background") {
+        opts.background = Some(color);
+    }
+    if let Some(join) = value(m, "linejoin") {
+        opts.linejoin = join;
+    }
+    if let Some(cap) = value(m, "linecap") {
+        opts.linecap = cap;
+    }
+}
+
+/// The values of `id`, each with where it came on the command line, to be
+/// merged with those of another option in order.
+pub fn indexed<T: Clone + Send + Sync + 'static>(m: &ArgMatches, id: &str) -> Vec<(usize, T)> {
+    match (m.indices_of(id), m.get_many::<T>(id)) {
+        (Some(indices), Some(values)) => indices.zip(values.cloned()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The arguments with `render` put in front of options given without a
+/// subcommand, as they were before there were subcommands, and whether
+/// that was done. The global options may still come first.
+pub fn with_subcommand(mut args: Vec<String>) -> (Vec<String>, bool) {
+    let mut k = 0;
+    while let Some(arg) = args.get(k) {
+        match arg.as_str() {
+            "-q" | "--quiet" | "-v" | "-vv" | "-vvv" | "--verbose" => k += 1,
+            "--config" => k += 2,
+            _ => break,
+        }
+    }
+    match args.get(k).map(String::as_str) {
+        Some(arg) if SUBCOMMANDS.contains(&arg) || arg == "-h" || arg == "--help" || arg == "help" => (args, false),
+        given => {
+            let deprecated = given.is_some();
+            args.insert(k.min(args.len()), "render".to_string());
+            (args, deprecated)
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr<Err = String>>(s: &str) -> Result<T, String> {
+    s.parse()
+}
+
+fn parse_trace(s: &str) -> Result<Complex<f64>, String> {
+    parse_complex(s).map_err(|e| e.to_string())
+}
+
+fn positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(x) if x > 0.0 && x.is_finite() => Ok(x),
+        _ => Err(format!("expected a positive number, got `{}`", s)),
+    }
+}
+
+fn non_negative(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(x) if x >= 0.0 && x.is_finite() => Ok(x),
+        _ => Err(format!("expected a number at least 0, got `{}`", s)),
+    }
+}
+
+fn parse_preset(name: &str) -> Result<&'static Preset, String> {
+    preset(name).ok_or_else(|| {
+        let names: Vec<_> = PRESETS.iter().map(|p| p.name).collect();
+        format!("unknown preset `{}`, expected one of {}", name, names.join(", "))
+    })
+}
+
+// a count of `min` or more
+fn at_least(min: u64) -> impl TypedValueParser<Value = usize> {
+    value_parser!(u64).range(min..).map(|n| n as usize)
+}
+
+fn parse_word(s: &str) -> Result<Word, String> {
+    s.parse().map_err(|e: svg_kleinian::Error| e.to_string())
+}
+
+fn parse_reduced(s: &str, what: &str) -> Result<Word, String> {
+    let w = parse_word(s)?;
+    if w.0.is_empty() || !w.is_reduced() {
+        return Err(format!("{} `{}` must be a nonempty reduced word", what, w));
+    }
+    Ok(w)
+}
+
+fn parse_branch_order(s: &str) -> Result<[svg_kleinian::Letter; 4], String> {
+    let Word(w) = parse_word(s)?;
+    match w[..] {
+        [l1, l2, l3, l4] => {
+            check_branch_order(&[l1, l2, l3, l4])?;
+            Ok([l1, l2, l3, l4])
+        }
+        _ => Err("a branch order is four letters, each of a, b, A and B once".to_string()),
+    }
+}
+
+/// Parses `Z` or `Z..Z`, a range of traces.
+pub fn parse_range(s: &str) -> Result<(Complex<f64>, Complex<f64>), String> {
+    match s.find("..") {
+        Some(n) => Ok((parse_trace(&s[..n])?, parse_trace(&s[n + 2..])?)),
+        None => parse_trace(s).map(|z| (z, z)),
+    }
+}
+
+// `s` split at the commas outside parentheses, so `(1, 2),3` is two items
+fn split_list(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (n, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..n]);
+                start = n + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items
+}
+
+// exactly `count` complex numbers separated by commas
+fn parse_list(s: &str, count: usize) -> Result<Vec<Complex<f64>>, String> {
+    let items = split_list(s);
+    if items.len() != count {
+        return Err(format!("expected {} complex numbers separated by commas, got {} in `{}`", count, items.len(), s));
+    }
+    items.into_iter().map(parse_trace).collect()
+}
+
+/// Parses `a,b,c,d` as the matrix of z ↦ (az + b)/(cz + d).
+fn parse_mat(s: &str) -> Result<Mat, String> {
+    let entries = parse_list(s, 4)?;
+    Ok(Mat::new(entries[0], entries[1], entries[2], entries[3]))
+}
+
+/// Parses `z1,z2,z3:w1,w2,w3` into the map sending each z to its w.
+fn parse_camera(s: &str) -> Result<Mat, String> {
+    let (src, dst) = s.split_once(':').ok_or_else(|| format!("expected `z1,z2,z3:w1,w2,w3`, got `{}`", s))?;
+    let points = |s: &str| -> Result<[Complex<f64>; 3], String> {
+        let z = parse_list(s, 3)?;
+        Ok([z[0], z[1], z[2]])
+    };
+    Ok(Mat::from_three_points(points(src)?, points(dst)?))
+}
+
+/// Parses `ta,tb,color`.
+fn parse_curve(s: &str) -> Result<Curve, String> {
+    match split_list(s)[..] {
+        [ta, tb, ref stroke @ ..] if !stroke.is_empty() => Ok(Curve {
+            ta: parse_trace(ta)?,
+            tb: parse_trace(tb)?,
+            stroke: stroke.join(",").trim().to_string(),
+        }),
+        _ => Err(format!("expected `ta,tb,color`, got `{}`", s)),
+    }
+}
+
+/// Parses `p/q,p/q,...`, at least two of them.
+fn parse_fractions(s: &str) -> Result<Vec<(i64, i64)>, String> {
+    let fraction = |s: &str| -> Option<(i64, i64)> {
+        let (p, q) = s.split_once('/')?;
+        Some((p.trim().parse().ok()?, q.trim().parse().ok()?))
+    };
+    match s.split(',').map(fraction).collect::<Option<Vec<_>>>() {
+        Some(fractions) if fractions.len() >= 2 => Ok(fractions),
+        _ => Err(format!("expected two or more fractions `p/q` separated by commas, got `{}`", s)),
+    }
+}
+
+fn parse_frame_pattern(s: &str) -> Result<String, String> {
+    match svg_kleinian::frame_path(s, 0) {
+        Some(_) => Ok(s.to_string()),
+        None => Err(format!("`{}` needs a %d or %0Nd for the frame number", s)),
+    }
+}
+
+// `WxH` with both positive
+fn parse_dimensions<T: std::str::FromStr + PartialOrd + Default>(s: &str) -> Option<(T, T)> {
+    let (w, h) = s.split_once('x')?;
+    let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+    if w > T::default() && h > T::default() { Some((w, h)) } else { None }
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    parse_dimensions(s).ok_or_else(|| format!("expected WxH with both positive, got `{}`", s))
+}
+
+fn parse_grid(s: &str) -> Result<(usize, usize), String> {
+    parse_dimensions(s).ok_or_else(|| format!("expected COLSxROWS with both positive, got `{}`", s))
+}
+
+/// Parses `x,y,w,h` with `w` and `h` positive.
+fn parse_window(s: &str) -> Result<(f64, f64, f64, f64), String> {
+    match s.split(',').map(|x| x.trim().parse()).collect::<Result<Vec<f64>, _>>() {
+        Ok(ref v) if v.len() == 4 && v[2] > 0.0 && v[3] > 0.0 => Ok((v[0], v[1], v[2], v[3])),
+        _ => Err(format!("expected X,Y,W,H with W and H positive, got `{}`", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+    use svg_kleinian::{A, B, BI};
+
+    fn matches(args: &[&str]) -> ArgMatches {
+        command().try_get_matches_from(args).unwrap_or_else(|e| panic!("{:?}: {}", args, e))
+    }
+
+    fn error(args: &[&str]) -> ErrorKind {
+        match command().try_get_matches_from(args) {
+            Ok(_) => panic!("{:?} parsed", args),
+            Err(e) => e.kind(),
+        }
+    }
+
+    fn config(args: &[&str]) -> RenderConfig {
+        let m = matches(args);
+        let (_, m) = m.subcommand().expect("a subcommand");
+        render_config(m, RenderConfig::default())
+    }
+
+    #[test]
+    fn the_command_is_well_formed() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn defaults_are_the_gasket() {
+        assert_eq!(config(&["svg_kleinian", "render"]), RenderConfig::default());
+    }
+
+    #[test]
+    fn parameters_reach_the_config() {
+        let config = config(&["svg_kleinian", "render", "--recipe", "maskit", "--ta", "1.91+0.05i", "--tb", "(2, 1)",
+            "--level", "30", "--epsilon", "0.01", "--max-nodes", "1000", "-o", "out.svg"]);
+        assert_eq!(config, RenderConfig {
+            recipe: Recipe::Maskit,
+            ta: Complex::new(1.91, 0.05),
+            tb: Complex::new(2.0, 1.0),
+            level: 30,
+            epsilon: 0.01,
+            output: Some("out.svg".to_string()),
+            max_nodes: Some(1000),
+            ..RenderConfig::default()
+        });
+    }
+
+    #[test]
+    fn the_later_of_preset_and_trace_wins() {
+        let p = &PRESETS[0];
+        let before = config(&["svg_kleinian", "info", "--ta", "3", "--preset", p.name]);
+        assert_eq!((before.ta, before.tb), (p.ta, p.tb));
+        let after = config(&["svg_kleinian", "info", "--preset", p.name, "--ta", "3"]);
+        assert_eq!((after.ta, after.tb), (Complex::new(3.0, 0.0), p.tb));
+    }
+
+    #[test]
+    fn analyze_refuses_drawing_options() {
+        matches(&["svg_kleinian", "render", "--analyze", "-o", "depths.txt"]);
+        for option in [&["--format", "png"][..], &["--overlay", "cusps"], &["--html", "page.html"], &["--gasket"]] {
+            let args: Vec<&str> = ["svg_kleinian", "render", "--analyze"].iter().chain(option).copied().collect();
+            assert_eq!(error(&args), ErrorKind::ArgumentConflict, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn stream_points_refuses_drawing_options() {
+        matches(&["svg_kleinian", "render", "--stream-points", "-o", "points.txt"]);
+        for option in [&["--format", "csv"][..], &["--overlay", "cusps"], &["--curve", "2,2,red"], &["--analyze"]] {
+            let args: Vec<&str> = ["svg_kleinian", "render", "--stream-points"].iter().chain(option).copied().collect();
+            assert_eq!(error(&args), ErrorKind::ArgumentConflict, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn traces_may_be_negative() {
+        let config = config(&["svg_kleinian", "render", "--ta", "-2", "--tb", "-1.5-0.2i"]);
+        assert_eq!((config.ta, config.tb), (Complex::new(-2.0, 0.0), Complex::new(-1.5, -0.2)));
+        let m = matches(&["svg_kleinian", "grid", "--ta", "-2..2", "--tb", "2"]);
+        let (_, m) = m.subcommand().unwrap();
+        assert_eq!(m.get_one("ta"), Some(&(Complex::new(-2.0, 0.0), Complex::new(2.0, 0.0))));
+    }
+
+    #[test]
+    fn a_repeated_option_takes_its_last_value() {
+        assert_eq!(config(&["svg_kleinian", "render", "--level", "10", "--level", "20"]).level, 20);
+    }
+
+    #[test]
+    fn bad_values_are_refused() {
+        for args in [
+            &["svg_kleinian", "render", "--epsilon", "0"][..],
+            &["svg_kleinian", "render", "--epsilon", "-0.1"],
+            &["svg_kleinian", "render", "--epsilon", "nan"],
+            &["svg_kleinian", "render", "--level", "0"],
+            &["svg_kleinian", "render", "--ta", "two"],
+            &["svg_kleinian", "render", "--recipe", "auntie"],
+            &["svg_kleinian", "render", "--preset", "nothing-by-this-name"],
+            &["svg_kleinian", "render", "--relator", "aA"],
+            &["svg_kleinian", "render", "--branch-order", "abAb"],
+            &["svg_kleinian", "render", "--format", "bmp"],
+            &["svg_kleinian", "render", "--size", "0x10"],
+            &["svg_kleinian", "cusp", "1", "0"],
+        ] {
+            // InvalidValue is clap's own, for a value not among the possible ones
+            assert!(matches!(error(args), ErrorKind::ValueValidation | ErrorKind::InvalidValue), "{:?}", args);
+        }
+        assert_eq!(error(&["svg_kleinian", "render", "--frames", "3"]), ErrorKind::UnknownArgument);
+        assert_eq!(error(&["svg_kleinian", "grid", "--ta", "2"]), ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn traversal_options_parse() {
+        let m = matches(&["svg_kleinian", "render", "--relator", "aaa", "--prefix", "aB", "--prefix", "b",
+            "--branch-order", "bBAa"]);
+        let (_, m) = m.subcommand().unwrap();
+        let prefixes: Vec<String> = m.get_many::<Word>("prefix").unwrap().map(|w| w.to_string()).collect();
+        assert_eq!(prefixes, ["aB", "b"]);
+        assert_eq!(m.get_one::<[svg_kleinian::Letter; 4]>("branch-order"), Some(&[B, BI, svg_kleinian::AI, A]));
+        assert_eq!(m.get_many::<Word>("relator").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn overlays_keep_their_order() {
+        let m = matches(&["svg_kleinian", "render", "--overlay", "convex-hull", "--circles", "2", "--overlay", "axes=a"]);
+        let (_, m) = m.subcommand().unwrap();
+        let mut at: Vec<usize> = indexed::<Overlay>(m, "overlay").into_iter().map(|(k, _)| k).collect();
+        at.extend(indexed::<usize>(m, "circles").into_iter().map(|(k, _)| k));
+        let mut sorted = at.clone();
+        sorted.sort();
+        assert_eq!(sorted, [at[0], at[2], at[1]]);
+    }
+
+    #[test]
+    fn each_subcommand_parses_its_options() {
+        for (args, name) in [
+            (&["svg_kleinian", "render", "--color-letters", "--overlay", "cusps", "--format", "csv"][..], "render"),
+            (&["svg_kleinian", "info", "--preset", PRESETS[0].name, "--level", "5"], "info"),
+            (&["svg_kleinian", "cusp", "-1", "3", "--render", "--level", "40", "--stroke", "red"], "cusp"),
+            (&["svg_kleinian", "animate", "--mu", "2i..1.9i", "--frames", "4", "--format", "gif", "out.gif"], "animate"),
+            (&["svg_kleinian", "animate", "--farey", "0/1,1/2", "--out", "f/%04d.svg", "--resume"], "animate"),
+            (&["svg_kleinian", "grid", "--ta", "2..3", "--tb", "2", "--size", "3x2", "-o", "g.svg"], "grid"),
+            (&["svg_kleinian", "tiles", "--window", "0,0,1,1", "--grid", "2x2", "-o", "t-{row}-{col}.svg"], "tiles"),
+            (&["svg_kleinian", "batch", "jobs.toml", "--jobs", "4", "--fail-fast"], "batch"),
+            (&["svg_kleinian", "serve", "--port", "8080", "--workers", "2", "--timeout", "1.5"], "serve"),
+            (&["svg_kleinian", "repl", "--recipe", "maskit"], "repl"),
+            (&["svg_kleinian", "golden", "--update"], "golden"),
+            (&["svg_kleinian", "-q", "render", "-vv"], "render"),
+        ] {
+            assert_eq!(matches(args).subcommand_name(), Some(name), "{:?}", args);
+        }
+        let m = matches(&["svg_kleinian", "cusp", "-1", "3"]);
+        let (_, m) = m.subcommand().unwrap();
+        assert_eq!((m.get_one::<i64>("p"), m.get_one::<i64>("q")), (Some(&-1), Some(&3)));
+        assert_eq!(error(&["svg_kleinian", "animate", "--mu", "2i", "--ta", "2"]), ErrorKind::ArgumentConflict);
+        assert_eq!(error(&["svg_kleinian", "grid", "--mu", "2i..1+2i", "--tb", "2"]), ErrorKind::ArgumentConflict);
+        assert_eq!(error(&["svg_kleinian", "animate", "--frames", "3"]), ErrorKind::MissingRequiredArgument);
+        assert_eq!(error(&["svg_kleinian", "animate", "--ta", "2..1.9"]), ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn verbosity_counts() {
+        let m = matches(&["svg_kleinian", "render", "-v", "-vv"]);
+        assert_eq!(m.subcommand().unwrap().1.get_count("verbose"), 3);
+        let m = matches(&["svg_kleinian", "-vv", "render"]);
+        assert_eq!(m.subcommand().unwrap().1.get_count("verbose"), 2);
+    }
+
+    #[test]
+    fn bare_options_mean_render() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(with_subcommand(args(&["--ta", "2"])), (args(&["render", "--ta", "2"]), true));
+        assert_eq!(with_subcommand(args(&["-v", "--level", "3"])), (args(&["-v", "render", "--level", "3"]), true));
+        assert_eq!(with_subcommand(args(&[])), (args(&["render"]), false));
+        assert_eq!(with_subcommand(args(&["-q"])), (args(&["-q", "render"]), false));
+        assert_eq!(with_subcommand(args(&["grid", "--ta", "2"])), (args(&["grid", "--ta", "2"]), false));
+        assert_eq!(with_subcommand(args(&["--help"])), (args(&["--help"]), false));
+    }
+}
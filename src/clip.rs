@@ -0,0 +1,143 @@
+//! Clipping traced polylines to a window, so zoomed renders don't carry
+//! the invisible rest of the curve.
+
+use num::complex::Complex;
+
+/// The part of the segment pq inside the window (x, y, width, height), by
+/// Liang–Barsky. Endpoints cut by an edge are put exactly on it. The window
+/// is half-open, so that tiles sharing an edge agree on who draws what: a
+/// segment running along the right or bottom edge, or only touching the
+/// window at a point, is left to the neighbour.
+pub(crate) fn clip_segment(
+    p: Complex<f64>,
+    q: Complex<f64>,
+    (x, y, w, h): (f64, f64, f64, f64),
+) -> Option<(Complex<f64>, Complex<f64>)> {
+    let d = q - p;
+    let (mut t0, mut t1) = (0.0, 1.0);
+    // the edge each end was cut by: 0..4 for left, right, top, bottom
+    let (mut e0, mut e1) = (None, None);
+    let edges = [(-d.re, p.re - x), (d.re, x + w - p.re), (-d.im, p.im - y), (d.im, y + h - p.im)];
+    for (k, &(num, dist)) in edges.iter().enumerate() {
+        if num == 0.0 {
+            if dist < 0.0 || (dist == 0.0 && k % 2 == 1) {
+                return None;
+            }
+            continue;
+        }
+        let t = dist / num;
+        if num < 0.0 {
+            if t > t1 {
+                return None;
+            }
+            if t > t0 {
+                t0 = t;
+                e0 = Some(k);
+            }
+        } else {
+            if t < t0 {
+                return None;
+            }
+            if t < t1 {
+                t1 = t;
+                e1 = Some(k);
+            }
+        }
+    }
+    if t0 >= t1 && d != Complex::new(0.0, 0.0) {
+        return None;
+    }
+    let snap = |z: Complex<f64>, edge: Option<usize>| match edge {
+        Some(0) => Complex::new(x, z.im),
+        Some(1) => Complex::new(x + w, z.im),
+        Some(2) => Complex::new(z.re, y),
+        Some(3) => Complex::new(z.re, y + h),
+        _ => z,
+    };
+    Some((snap(p + d * t0, e0), snap(p + d * t1, e1)))
+}
+
+/// Splits the polyline into the pieces inside the window, clipping the
+/// segments that cross its edge.
+pub(crate) fn clip_polyline(points: &[Complex<f64>], window: (f64, f64, f64, f64)) -> Vec<Vec<Complex<f64>>> {
+    let mut pieces: Vec<Vec<Complex<f64>>> = Vec::new();
+    let mut current: Vec<Complex<f64>> = Vec::new();
+    for pair in points.windows(2) {
+        match clip_segment(pair[0], pair[1], window) {
+            Some((a, b)) => {
+                if current.last() != Some(&a) {
+                    if !current.is_empty() {
+                        pieces.push(std::mem::take(&mut current));
+                    }
+                    current.push(a);
+                }
+                current.push(b);
+            }
+            None => {
+                if !current.is_empty() {
+                    pieces.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(re: f64, im: f64) -> Complex<f64> {
+        Complex::new(re, im)
+    }
+
+    const WINDOW: (f64, f64, f64, f64) = (0.1, -0.3, 0.7, 0.9);
+
+    #[test]
+    fn segments_are_cut_exactly_at_the_edge() {
+        // inside is untouched, outside is gone
+        assert_eq!(clip_segment(c(0.2, 0.0), c(0.5, 0.4), WINDOW), Some((c(0.2, 0.0), c(0.5, 0.4))));
+        assert_eq!(clip_segment(c(-1.0, 0.0), c(-0.5, 0.4), WINDOW), None);
+        assert_eq!(clip_segment(c(-1.0, -1.0), c(0.0, 2.0), WINDOW), None);
+        // out through the right edge
+        let (p, q) = clip_segment(c(0.3, 0.1), c(1.3, 0.4), WINDOW).unwrap();
+        assert_eq!(p, c(0.3, 0.1));
+        assert_eq!(q.re, 0.1 + 0.7);
+        assert!((q.im - (0.1 + 0.3 * (0.8 - 0.3))).abs() < 1e-12);
+        // across the whole window, in at the top and out at the bottom
+        let (p, q) = clip_segment(c(0.4, -1.0), c(0.5, 1.0), WINDOW).unwrap();
+        assert_eq!((p.im, q.im), (-0.3, -0.3 + 0.9));
+        assert!(0.4 < p.re && p.re < q.re && q.re < 0.5);
+        // the window is half-open: the left edge is in, the right one out
+        assert!(clip_segment(c(0.1, 0.0), c(0.1, 0.5), WINDOW).is_some());
+        assert_eq!(clip_segment(c(0.8, 0.0), c(0.8, 0.5), WINDOW), None);
+    }
+
+    #[test]
+    fn a_polyline_leaving_the_window_breaks_into_runs() {
+        // in, out through the right edge, back in, and out through the top
+        let path = [c(0.2, 0.0), c(0.4, 0.2), c(1.2, 0.2), c(1.2, 0.4), c(0.5, 0.4), c(0.3, 0.5), c(0.3, -1.0)];
+        let runs = clip_polyline(&path, WINDOW);
+        let (x, y, w, h) = WINDOW;
+        for z in runs.iter().flatten() {
+            assert!(x <= z.re && z.re <= x + w && y <= z.im && z.im <= y + h, "{} is outside", z);
+        }
+        // each step of a run lies along one segment of the path
+        let on = |z: Complex<f64>, p: Complex<f64>, q: Complex<f64>| {
+            let t = ((z - p) * (q - p).conj()).re / (q - p).norm_sqr();
+            (0.0..=1.0).contains(&t) && (p + (q - p) * t - z).norm() < 1e-12
+        };
+        for run in &runs {
+            for step in run.windows(2) {
+                assert!(path.windows(2).any(|s| on(step[0], s[0], s[1]) && on(step[1], s[0], s[1])), "{:?}", step);
+            }
+        }
+        assert_eq!(runs, vec![
+            vec![c(0.2, 0.0), c(0.4, 0.2), c(x + w, 0.2)],
+            vec![c(x + w, 0.4), c(0.5, 0.4), c(0.3, 0.5), c(0.3, y)],
+        ]);
+    }
+}
@@ -0,0 +1,161 @@
+//! The core parameters of a render, the ones the command line reads from
+//! `--recipe`, `--ta`, `--tb`, `--level`, `--epsilon` and `--output`, in
+//! one struct, so a program can set them up the same way.
+
+use num::complex::Complex;
+use std::io::Write;
+use std::str::FromStr;
+use svg::Document;
+
+use crate::{check_trace_size, dump_generators, fmt_complex, limitset_with, markov_discriminant, maskit_traces, traces, try_grandma, Error, Kleinian, RenderOptions, Word, A, AI, B, BI, COMPLEX_PRECISION, EPSILON, MAX_ESTIMATED_NODES};
+
+/// How the group is built from the traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipe {
+    /// `grandma(ta, tb)`: the usual normalization, with abAB parabolic.
+    Grandma,
+    /// The Maskit slice, with `ta` read as μ and `tb` unused: grandma's
+    /// group for the traces -iμ and 2, which is Maskit's moved so that ∞
+    /// is off the limit set and the trace can reach all of it.
+    Maskit,
+}
+
+impl Recipe {
+    /// The name `--recipe` takes for this recipe.
+    pub fn name(self) -> &'static str {
+        match self {
+            Recipe::Grandma => "grandma",
+            Recipe::Maskit => "maskit",
+        }
+    }
+}
+
+impl FromStr for Recipe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grandma" => Ok(Recipe::Grandma),
+            "maskit" => Ok(Recipe::Maskit),
+            _ => Err(format!("unknown recipe `{}`, expected grandma or maskit", s)),
+        }
+    }
+}
+
+/// What to trace and where to put it. The defaults are what the command
+/// line does with no options: grandma's recipe with ta = tb = 2, the
+/// Apollonian gasket, to depth 50 and `EPSILON`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    pub recipe: Recipe,
+    pub ta: Complex<f64>,
+    pub tb: Complex<f64>,
+    /// The deepest word the trace follows; at least 1.
+    pub level: i64,
+    /// How close consecutive points must be before a branch stops, in the
+    /// units of the plane the limit set is drawn in; positive.
+    pub epsilon: f64,
+    /// Where the output goes, or `None` for the default of its format.
+    pub output: Option<String>,
+    /// Stop tracing after visiting this many branches.
+    pub max_nodes: Option<usize>,
+    /// Without `max_nodes`, the most branches the trace may be expected
+    /// to visit; see `check_trace_size`.
+    pub node_ceiling: usize,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            recipe: Recipe::Grandma,
+            ta: Complex::new(2.0, 0.0),
+            tb: Complex::new(2.0, 0.0),
+            level: 50,
+            epsilon: EPSILON,
+            output: None,
+            max_nodes: None,
+            node_ceiling: MAX_ESTIMATED_NODES,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Checks the values are ones a render can use, saying which is not,
+    /// or why the recipe fails at the traces.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(self.epsilon > 0.0 && self.epsilon.is_finite()) {
+            return Err(Error::Config(format!("epsilon must be positive, got {}", self.epsilon)));
+        }
+        if self.level < 1 {
+            return Err(Error::Config(format!("level must be at least 1, got {}", self.level)));
+        }
+        let discrete = self.group()?.is_known_discrete();
+        check_trace_size(self.level, self.epsilon, discrete, self.max_nodes, self.node_ceiling)?;
+        Ok(())
+    }
+
+    /// The traces of a and b in the group the recipe makes.
+    pub fn traces(&self) -> (Complex<f64>, Complex<f64>) {
+        match self.recipe {
+            Recipe::Grandma => (self.ta, self.tb),
+            Recipe::Maskit => maskit_traces(self.ta),
+        }
+    }
+
+    /// The group the recipe makes, without ends, or why the recipe fails
+    /// at these traces.
+    pub fn group(&self) -> Result<Kleinian, Error> {
+        let (ta, tb) = self.traces();
+        try_grandma(ta, tb)
+    }
+
+    /// Traces the group with the ends the command line adds, to `level`
+    /// and `epsilon`, taking everything else from `opts`, and draws it.
+    pub fn render(&self, opts: &RenderOptions) -> Result<Document, Error> {
+        self.validate()?;
+        let mut g = self.group()?;
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let opts = RenderOptions { epsilon: self.epsilon, max_nodes: self.max_nodes, ..opts.clone() };
+        limitset_with(self.level, &mut g, &opts);
+        if g.points().len() < 2 {
+            return Err(Error::NoPoints { level: self.level, epsilon: self.epsilon });
+        }
+        Ok(g.document(&opts))
+    }
+
+    /// Writes what the `info` subcommand prints about the group: the
+    /// traces, the generators with their kinds and fixed points, the
+    /// commutator, and the shape of the limit set.
+    pub fn write_info<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+        let mut g = self.group()?;
+        let (ta, tb, tab) = traces(&g);
+        let z = |z: Complex<f64>| fmt_complex(z, COMPLEX_PRECISION);
+        writeln!(w, "ta = {}, tb = {}, tab = {}", z(ta), z(tb), z(tab))?;
+        writeln!(w, "markov discriminant = {}", z(markov_discriminant(ta, tb)))?;
+        dump_generators(&g, w)?;
+        for &l in &[A, B, AI, BI] {
+            let m = g.mat(l);
+            let order = m.elliptic_order().map_or(String::new(), |n| format!(" of order {}", n));
+            writeln!(w, "{}: {:?}{}, attracting fixed point {}", l, m.classify(), order, z(m.fix()))?;
+        }
+        let commutator = g.word(&Word(vec![A, B, AI, BI]));
+        writeln!(w, "abAB: {:?}, fixed point {}", commutator.classify(), z(commutator.fix()))?;
+        writeln!(w, "fuchsian: {}", g.is_fuchsian())?;
+        writeln!(w, "limit set: {:?}", g.limit_set_topology(self.level.min(30)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipe_names_parse_back() {
+        for &recipe in &[Recipe::Grandma, Recipe::Maskit] {
+            assert_eq!(recipe.name().parse(), Ok(recipe));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Several limit sets superimposed, to show how one deforms as the traces
+//! move.
+
+use num::complex::Complex;
+use svg::Document;
+use svg::node::element::Group;
+
+use crate::{background, fmt_complex, grandma, limitset_with, stroke_path, RenderOptions, A, AI, B, BI, COMPLEX_PRECISION};
+
+/// One limit set to draw: the traces handed to `grandma` and a stroke color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub ta: Complex<f64>,
+    pub tb: Complex<f64>,
+    pub stroke: String,
+}
+
+/// Traces each curve to `level` under `opts` and draws them in order into
+/// one document, each in its own group of class "curve" carrying its
+/// traces. The viewBox covers all of them.
+pub fn curves(curves: &[Curve], level: i64, opts: &RenderOptions) -> Document {
+    let inf = f64::INFINITY;
+    let mut bounds = (inf, inf, -inf, -inf);
+    let mut groups = Vec::new();
+    for curve in curves {
+        let mut g = grandma(curve.ta, curve.tb);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset_with(level, &mut g, opts);
+        let (x0, y0, x1, y1) = g.bounding_box();
+        bounds = (bounds.0.min(x0), bounds.1.min(y0), bounds.2.max(x1), bounds.3.max(y1));
+        groups.push(Group::new()
+            .set("class", "curve")
+            .set("data-ta", fmt_complex(curve.ta, COMPLEX_PRECISION))
+            .set("data-tb", fmt_complex(curve.tb, COMPLEX_PRECISION))
+            .add(stroke_path(g.points(), &curve.stroke, opts)));
+    }
+    let (x0, y0, x1, y1) = bounds;
+    let pad = 0.05 * (x1 - x0).max(y1 - y0);
+    let view_box = (x0 - pad, y0 - pad, x1 - x0 + 2.0 * pad, y1 - y0 + 2.0 * pad);
+    let mut document = Document::new()
+        .set("viewBox", view_box);
+    if let Some(ref color) = opts.background {
+        document = document.add(background(view_box, color));
+    }
+    for group in groups {
+        document = document.add(group);
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_curves_give_two_groups_in_their_strokes() {
+        let two = Complex::new(2.0, 0.0);
+        let input = [
+            Curve { ta: Complex::new(1.91, 0.05), tb: Complex::new(1.91, 0.05), stroke: "red".to_string() },
+            Curve { ta: two, tb: two, stroke: "blue".to_string() },
+        ];
+        let svg = curves(&input, 6, &RenderOptions::default()).to_string();
+        assert_eq!(svg.matches("<g ").count(), 2, "{}", svg);
+        assert_eq!(svg.matches("class=\"curve\"").count(), 2);
+        let red = svg.find("stroke=\"red\"").expect("no red stroke");
+        let blue = svg.find("stroke=\"blue\"").expect("no blue stroke");
+        assert!(red < blue, "curves out of order");
+        assert!(svg.contains(&format!("data-ta=\"{}\"", fmt_complex(input[0].ta, COMPLEX_PRECISION))));
+        assert!(svg.contains(&format!("data-ta=\"{}\"", fmt_complex(two, COMPLEX_PRECISION))));
+    }
+}
@@ -0,0 +1,175 @@
+//! Encapsulated PostScript output, behind the `eps` feature. Like the PDF
+//! writer this walks the paths usvg resolves the SVG into, but writes each
+//! straight to the output as it goes.
+
+use crate::output::create_output;
+use crate::Error;
+use resvg::tiny_skia::{PathSegment, Transform};
+use resvg::usvg::{self, FillRule, LineCap, LineJoin, Node, Paint};
+use std::io::{self, Write};
+use svg::Document;
+
+/// The most segments written into one stroked path before it is stroked
+/// and a new one started, keeping under the path limits of old
+/// interpreters. Filled paths are never split, as that would change them.
+pub const MAX_PATH_SEGMENTS: usize = 1000;
+
+fn parse(doc: &Document) -> Result<usvg::Tree, Error> {
+    usvg::Tree::from_str(&doc.to_string(), &usvg::Options::default())
+        .map_err(|e| Error::Raster(e.to_string()))
+}
+
+fn group<W: Write>(w: &mut W, parent: &usvg::Group) -> io::Result<()> {
+    for node in parent.children() {
+        match node {
+            Node::Group(g) => group(w, g)?,
+            Node::Path(p) => path(w, p)?,
+            Node::Text(t) => group(w, t.flattened())?,
+            Node::Image(_) => {}
+        }
+    }
+    Ok(())
+}
+
+// PostScript has no transparency, so opacity is dropped
+fn path<W: Write>(w: &mut W, path: &usvg::Path) -> io::Result<()> {
+    if !path.is_visible() {
+        return Ok(());
+    }
+    if let Some(fill) = path.fill() {
+        if let Paint::Color(c) = fill.paint() {
+            begin(w, path.abs_transform())?;
+            writeln!(w, "{} {} {} setrgbcolor", c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0)?;
+            let op = match fill.rule() {
+                FillRule::NonZero => "fill",
+                FillRule::EvenOdd => "eofill",
+            };
+            segments(w, path.data(), None)?;
+            writeln!(w, "{}\ngrestore", op)?;
+        }
+    }
+    if let Some(stroke) = path.stroke() {
+        if let Paint::Color(c) = stroke.paint() {
+            begin(w, path.abs_transform())?;
+            writeln!(w, "{} {} {} setrgbcolor", c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0)?;
+            let cap = match stroke.linecap() {
+                LineCap::Butt => 0,
+                LineCap::Round => 1,
+                LineCap::Square => 2,
+            };
+            let join = match stroke.linejoin() {
+                LineJoin::Miter | LineJoin::MiterClip => 0,
+                LineJoin::Round => 1,
+                LineJoin::Bevel => 2,
+            };
+            writeln!(w, "{} setlinewidth {} setlinecap {} setlinejoin", stroke.width().get(), cap, join)?;
+            segments(w, path.data(), Some(MAX_PATH_SEGMENTS))?;
+            writeln!(w, "stroke\ngrestore")?;
+        }
+    }
+    Ok(())
+}
+
+fn begin<W: Write>(w: &mut W, t: Transform) -> io::Result<()> {
+    writeln!(w, "gsave\n[{} {} {} {} {} {}] concat", t.sx, t.ky, t.kx, t.sy, t.tx, t.ty)
+}
+
+/// Writes the path, stroking and restarting it from the current point every
+/// `split` segments when given.
+fn segments<W: Write>(w: &mut W, data: &resvg::tiny_skia::Path, split: Option<usize>) -> io::Result<()> {
+    let mut last = (0.0, 0.0);
+    let mut count = 0;
+    for segment in data.segments() {
+        if split.is_some_and(|max| count >= max) {
+            if let PathSegment::LineTo(_) | PathSegment::QuadTo(..) | PathSegment::CubicTo(..) = segment {
+                writeln!(w, "stroke\n{} {} moveto", last.0, last.1)?;
+                count = 0;
+            }
+        }
+        count += 1;
+        match segment {
+            PathSegment::MoveTo(p) => {
+                writeln!(w, "{} {} moveto", p.x, p.y)?;
+                last = (p.x, p.y);
+            }
+            PathSegment::LineTo(p) => {
+                writeln!(w, "{} {} lineto", p.x, p.y)?;
+                last = (p.x, p.y);
+            }
+            PathSegment::QuadTo(q, p) => {
+                // PostScript has only cubics; raise the degree
+                let c1 = (last.0 + 2.0 / 3.0 * (q.x - last.0), last.1 + 2.0 / 3.0 * (q.y - last.1));
+                let c2 = (p.x + 2.0 / 3.0 * (q.x - p.x), p.y + 2.0 / 3.0 * (q.y - p.y));
+                writeln!(w, "{} {} {} {} {} {} curveto", c1.0, c1.1, c2.0, c2.1, p.x, p.y)?;
+                last = (p.x, p.y);
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                writeln!(w, "{} {} {} {} {} {} curveto", c1.x, c1.y, c2.x, c2.y, p.x, p.y)?;
+                last = (p.x, p.y);
+            }
+            PathSegment::Close => writeln!(w, "closepath")?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes `doc` as EPS, scaled to `width` points across, streaming the
+/// paths to the file. The bounding box is that of the drawn geometry,
+/// strokes included, rather than the whole viewBox.
+pub fn render_eps(doc: &Document, path: &str, width: f32) -> Result<(), Error> {
+    let tree = parse(doc)?;
+    let size = tree.size();
+    let scale = width / size.width();
+    let height = size.height() * scale;
+    // the y axis points up, so flip the picture within its height
+    let bounds = tree.root().abs_stroke_bounding_box();
+    let (x0, x1) = (bounds.left() * scale, bounds.right() * scale);
+    let (y0, y1) = (height - bounds.bottom() * scale, height - bounds.top() * scale);
+
+    let mut w = create_output(path)?;
+    writeln!(w, "%!PS-Adobe-3.0 EPSF-3.0")?;
+    writeln!(w, "%%BoundingBox: {} {} {} {}", x0.floor(), y0.floor(), x1.ceil(), y1.ceil())?;
+    writeln!(w, "%%HiResBoundingBox: {} {} {} {}", x0, y0, x1, y1)?;
+    writeln!(w, "%%Creator: svg_kleinian")?;
+    writeln!(w, "%%EndComments")?;
+    writeln!(w, "gsave\n[{} 0 0 {} 0 {}] concat", scale, -scale, height)?;
+    group(&mut w, tree.root())?;
+    writeln!(w, "grestore\nshowpage\n%%EOF")?;
+    w.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{extent, grandma, limitset, RenderOptions, A, AI, B, BI};
+    use num::complex::Complex;
+
+    #[test]
+    fn the_header_bounds_the_drawn_curve() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(8, &mut g);
+        let path = std::env::temp_dir().join(format!("svg_kleinian-header-{}.eps", std::process::id()));
+        render_eps(&g.document(&RenderOptions::default()), path.to_str().unwrap(), 240.0).unwrap();
+        let eps = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut lines = eps.lines();
+        assert_eq!(lines.next(), Some("%!PS-Adobe-3.0 EPSF-3.0"));
+        let bounds: Vec<f64> = lines.next().unwrap()
+            .strip_prefix("%%BoundingBox: ").unwrap()
+            .split(' ')
+            .map(|n| n.parse().unwrap())
+            .collect();
+        assert!(bounds.iter().all(|b| b.fract() == 0.0), "{:?}", bounds);
+        // the view is 2.4 across at 100 points to 1, with y flipped
+        let (x0, y0, x1, y1) = extent(g.points());
+        let expected = [(x0 + 1.2) * 100.0, (1.2 - y1) * 100.0, (x1 + 1.2) * 100.0, (1.2 - y0) * 100.0];
+        for (k, (b, e)) in bounds.iter().zip(&expected).enumerate() {
+            assert!((b - e).abs() <= 1.5, "bound {} is {}, not near {}", k, b, e);
+        }
+        assert!(eps.trim_end().ends_with("%%EOF"));
+    }
+}
@@ -0,0 +1,126 @@
+use num::complex::Complex;
+use std::fmt;
+use std::io;
+
+use crate::{fmt_complex, COMPLEX_PRECISION};
+
+/// Errors from the parts of the crate that touch the outside world, or
+/// that are handed parameters they can't work with.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The SVG could not be parsed or rasterized.
+    Raster(String),
+    /// Parameters outside the range a construction accepts.
+    Parameters(String),
+    /// Traces for which grandma's recipe divides by zero or overflows.
+    Degenerate { ta: Complex<f64>, tb: Complex<f64> },
+    /// A check found the group can't be discrete, saying what failed.
+    NotDiscrete(String),
+    /// A word with a letter other than a, b, A and B.
+    Word { word: String, letter: char },
+    /// An iterative solver stopped without an answer.
+    NoConvergence(String),
+    /// Render settings that contradict each other or are out of range.
+    Config(String),
+    /// A trace that produced no curve to draw: no points beyond the one
+    /// it starts from, as when its node budget runs out at once.
+    NoPoints { level: i64, epsilon: f64 },
+    /// A trace without a node budget expected to visit more branches than
+    /// `ceiling`; see `check_trace_size`.
+    TooLarge { level: i64, epsilon: f64, estimate: f64, ceiling: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Raster(msg) => write!(f, "rasterizing failed: {}", msg),
+            Error::Parameters(msg) | Error::Config(msg) => f.write_str(msg),
+            Error::Degenerate { ta, tb } => write!(f, "grandma's recipe is degenerate at ta = {}, tb = {}",
+                fmt_complex(*ta, COMPLEX_PRECISION), fmt_complex(*tb, COMPLEX_PRECISION)),
+            Error::NotDiscrete(msg) => write!(f, "the group is not discrete: {}", msg),
+            Error::Word { word, letter } => write!(f, "bad letter `{}` in word `{}`, expected a, b, A or B", letter, word),
+            Error::NoConvergence(msg) => write!(f, "no convergence: {}", msg),
+            Error::NoPoints { level, epsilon } => write!(f, "the trace found no curve to draw at level {} and epsilon {}", level, epsilon),
+            Error::TooLarge { level, epsilon, estimate, ceiling } => write!(f,
+                "a trace to level {} at epsilon {} would visit about {:.1e} branches, more than the {} allowed; \
+                 give a node budget with --max-nodes, or a lower level or larger epsilon",
+                level, epsilon, estimate, ceiling),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_command, try_grandma, RenderConfig, RenderOptions, Session, Word};
+
+    #[test]
+    fn an_unwritable_path_is_an_io_error_naming_it() {
+        let dir = std::env::temp_dir().join(format!("svg_kleinian-missing-{}", std::process::id()));
+        let path = dir.join("out.svg").to_string_lossy().into_owned();
+        let mut session = Session { config: RenderConfig { level: 4, epsilon: 0.05, ..RenderConfig::default() } };
+        match run_command(&mut session, &format!("render {}", path)) {
+            Err(e @ Error::Io(_)) => assert!(e.to_string().contains(&path), "{}", e),
+            other => panic!("expected an io error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn an_invalid_config_is_a_config_error() {
+        for config in [
+            RenderConfig { epsilon: -1.0, ..RenderConfig::default() },
+            RenderConfig { epsilon: f64::NAN, ..RenderConfig::default() },
+            RenderConfig { level: 0, ..RenderConfig::default() },
+        ] {
+            match config.render(&RenderOptions::default()) {
+                Err(Error::Config(msg)) => assert!(msg.contains("must be"), "{}", msg),
+                other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn a_bad_letter_is_a_word_error() {
+        match "abx".parse::<Word>() {
+            Err(e @ Error::Word { letter: 'x', .. }) => assert!(e.to_string().contains("`abx`"), "{}", e),
+            other => panic!("expected a word error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn degenerate_traces_are_a_degenerate_error() {
+        let (ta, tb) = (Complex::new(2.0, 0.0), Complex::new(0.0, 0.0));
+        match try_grandma(ta, tb) {
+            Err(Error::Degenerate { ta: a, tb: b }) => assert_eq!((a, b), (ta, tb)),
+            Err(e) => panic!("expected a degenerate error, got {:?}", e),
+            Ok(_) => panic!("grandma({}, {}) gave a group", ta, tb),
+        }
+    }
+
+    #[test]
+    fn a_trace_without_points_is_a_no_points_error() {
+        // a budget of no branches stops the trace at the point it starts from
+        let config = RenderConfig { level: 4, max_nodes: Some(0), ..RenderConfig::default() };
+        match config.render(&RenderOptions::default()) {
+            Err(Error::NoPoints { level: 4, .. }) => {}
+            other => panic!("expected a no points error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! The traced points as data rather than graphics: CSV, JSON, NDJSON or
+//! NumPy arrays, for post-processing elsewhere.
+
+use std::io::{self, BufRead, Write};
+
+use crate::npy::write_npy;
+use crate::{Error, Kleinian, Word, A, AI, B, BI};
+
+/// One traced point and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointRecord {
+    pub re: f64,
+    pub im: f64,
+    /// The length of the word of the branch that drew the point.
+    pub depth: usize,
+    /// Which top-level branch, or which prefix when tracing prefixes,
+    /// drew the point, in tracing order.
+    pub subpath: usize,
+    pub word: Word,
+}
+
+impl Kleinian {
+    /// The points of the last trace with their words, or `None` unless it
+    /// ran with `set_record_words(true)`.
+    pub fn point_records(&self) -> Option<Vec<PointRecord>> {
+        let labels = self.labels.as_ref()?;
+        let spans: Vec<(usize, usize)> = if self.prefix_spans.is_empty() {
+            [A, BI, AI, B].iter().map(|&l| *self.spans.at(l)).collect()
+        } else {
+            self.prefix_spans.clone()
+        };
+        let records = self.points.iter().zip(labels).enumerate()
+            .map(|(n, (z, word))| PointRecord {
+                re: z.re,
+                im: z.im,
+                depth: word.0.len(),
+                // the starting point belongs with the first branch
+                subpath: spans.iter().position(|&(_, end)| n < end).unwrap_or(0),
+                word: word.clone(),
+            })
+            .collect();
+        Some(records)
+    }
+}
+
+fn number(x: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(digits) => format!("{:.*}", digits, x),
+        None => x.to_string(),
+    }
+}
+
+/// Writes a header line and a row per record, with coordinates to
+/// `precision` decimal places when given; the word column is only written
+/// when `words` is set.
+pub fn write_csv<W: Write>(records: &[PointRecord], words: bool, precision: Option<usize>, w: &mut W) -> io::Result<()> {
+    writeln!(w, "re,im,depth,subpath{}", if words { ",word" } else { "" })?;
+    for r in records {
+        write!(w, "{},{},{},{}", number(r.re, precision), number(r.im, precision), r.depth, r.subpath)?;
+        if words {
+            write!(w, ",{}", r.word)?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// Reads back what `write_csv` wrote, with or without words.
+pub fn read_csv<R: BufRead>(r: R) -> Result<Vec<PointRecord>, Error> {
+    let bad = |n: usize, line: &str| -> Error {
+        let msg = format!("line {}: expected `re,im,depth,subpath[,word]`, got `{}`", n + 1, line);
+        io::Error::new(io::ErrorKind::InvalidData, msg).into()
+    };
+    let mut records = Vec::new();
+    for (n, line) in r.lines().enumerate().skip(1) {
+        let line = line?;
+        let fields: Vec<_> = line.split(',').collect();
+        let word = match fields.get(4) {
+            Some(w) => w.parse().map_err(|_| bad(n, &line))?,
+            None => Word(Vec::new()),
+        };
+        match (fields.len(), fields[0].parse(), fields.get(1).map(|s| s.parse()),
+               fields.get(2).map(|s| s.parse()), fields.get(3).map(|s| s.parse())) {
+            (4..=5, Ok(re), Some(Ok(im)), Some(Ok(depth)), Some(Ok(subpath))) => {
+                records.push(PointRecord { re, im, depth, subpath, word });
+            }
+            _ => return Err(bad(n, &line)),
+        }
+    }
+    Ok(records)
+}
+
+pub(crate) fn json_number(x: f64) -> String {
+    // JSON has no NaN or infinity
+    if x.is_finite() { x.to_string() } else { "null".to_string() }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn json_params(params: &[(String, String)]) -> String {
+    let fields: Vec<_> = params.iter()
+        .map(|(k, v)| format!("{}: {}", json_string(k), json_string(v)))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn json_record(r: &PointRecord, words: bool) -> String {
+    let word = if words { format!(", \"word\": {}", json_string(&r.word.to_string())) } else { String::new() };
+    format!("{{\"re\": {}, \"im\": {}, \"depth\": {}, \"subpath\": {}{}}}",
+        json_number(r.re), json_number(r.im), r.depth, r.subpath, word)
+}
+
+/// The coordinates of each record, with its depth as a third column when
+/// `depth` is set, flattened row by row for `write_npy`.
+pub fn point_columns(records: &[PointRecord], depth: bool) -> Vec<f64> {
+    records.iter()
+        .flat_map(|r| {
+            let row = [r.re, r.im, r.depth as f64];
+            row[..if depth { 3 } else { 2 }].to_vec()
+        })
+        .collect()
+}
+
+/// The `.npz` contents for `records`: `points.npy` as `point_columns`
+/// gives it and the parameters as `params.json`.
+pub fn npz_files(records: &[PointRecord], params: &[(String, String)], depth: bool) -> io::Result<Vec<(&'static str, Vec<u8>)>> {
+    let mut points = Vec::new();
+    write_npy(&point_columns(records, depth), if depth { 3 } else { 2 }, &mut points)?;
+    Ok(vec![("points.npy", points), ("params.json", json_params(params).into_bytes())])
+}
+
+/// Writes one JSON object, `{"params": {...}, "points": [...]}`, the
+/// parameters being strings as given.
+pub fn write_json<W: Write>(records: &[PointRecord], params: &[(String, String)], words: bool, w: &mut W) -> io::Result<()> {
+    writeln!(w, "{{\"params\": {}, \"points\": [", json_params(params))?;
+    for (n, r) in records.iter().enumerate() {
+        let comma = if n + 1 < records.len() { "," } else { "" };
+        writeln!(w, "  {}{}", json_record(r, words), comma)?;
+    }
+    writeln!(w, "]}}")
+}
+
+/// Writes newline-delimited JSON: a `{"params": {...}}` line, then one
+/// object per point, so readers can stream it.
+pub fn write_ndjson<W: Write>(records: &[PointRecord], params: &[(String, String)], words: bool, w: &mut W) -> io::Result<()> {
+    writeln!(w, "{{\"params\": {}}}", json_params(params))?;
+    for r in records {
+        writeln!(w, "{}", json_record(r, words))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with, RenderOptions};
+    use num::complex::Complex;
+
+    #[test]
+    fn csv_reads_back_what_it_wrote() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g.set_record_words(true);
+        limitset_with(6, &mut g, &RenderOptions::default());
+        let records = g.point_records().expect("words were recorded");
+        assert!(!records.is_empty());
+        // the coordinates come back as rounded to the precision
+        let round = |x: f64| number(x, Some(6)).parse::<f64>().unwrap();
+        for &words in &[true, false] {
+            let mut csv = Vec::new();
+            write_csv(&records, words, Some(6), &mut csv).unwrap();
+            let back = read_csv(&csv[..]).unwrap();
+            let expected: Vec<_> = records.iter()
+                .map(|r| PointRecord {
+                    re: round(r.re),
+                    im: round(r.im),
+                    word: if words { r.word.clone() } else { Word(Vec::new()) },
+                    ..r.clone()
+                })
+                .collect();
+            assert_eq!(back, expected, "with words {}", words);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Farey words: the word w_{p/q} representing the simple closed curve of
+//! slope p/q on the punctured torus, following Indra's Pearls in taking
+//! w_{0/1} = a and w_{1/0} = B. For Farey neighbours p/q < r/s,
+//! w_{(p+r)/(q+s)} = w_{p/q} w_{r/s}.
+
+use crate::{Word, A, B, BI};
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+impl Word {
+    /// The Farey word w_{p/q}, found by descending the Stern–Brocot tree
+    /// from 0/1 and ±1/0. `None` unless p/q is in lowest terms.
+    pub fn farey(p: i64, q: i64) -> Option<Word> {
+        let (p, q) = if q < 0 { (-p, -q) } else { (p, q) };
+        if gcd(p, q) != 1 {
+            return None;
+        }
+        // -1/0 is the same slope as 1/0; b rather than B keeps the
+        // products simple on the negative side
+        let (mut left, mut right) = if p < 0 {
+            ((-1, 0, vec![B]), (0, 1, vec![A]))
+        } else {
+            ((0, 1, vec![A]), (1, 0, vec![BI]))
+        };
+        for (r, s, w) in [&left, &right] {
+            if (*r, *s) == (p, q) {
+                return Some(Word(w.clone()));
+            }
+        }
+        loop {
+            let (r, s) = (left.0 + right.0, left.1 + right.1);
+            let mut w = left.2.clone();
+            w.extend_from_slice(&right.2);
+            if (r, s) == (p, q) {
+                return Some(Word(w));
+            }
+            // compare p/q with r/s; both denominators are positive here
+            if p * s < r * q {
+                right = (r, s, w);
+            } else {
+                left = (r, s, w);
+            }
+        }
+    }
+}
+
+/// The Farey sequence of order `n`: the fractions in [0, 1] with
+/// denominator at most `n`, in lowest terms and increasing order.
+pub fn farey_sequence(n: i64) -> Vec<(i64, i64)> {
+    let mut fractions = Vec::new();
+    if n < 1 {
+        return fractions;
+    }
+    let (mut a, mut b, mut c, mut d) = (0, 1, 1, n);
+    fractions.push((a, b));
+    while c <= n {
+        let k = (n + b) / d;
+        let next = (k * c - a, k * d - b);
+        a = c;
+        b = d;
+        c = next.0;
+        d = next.1;
+        fractions.push((a, b));
+    }
+    fractions
+}
@@ -0,0 +1,202 @@
+//! A C interface, for calling the tracer from Julia, C++ and the like
+//! without going through the command line; `include/svg_kleinian.h`
+//! declares it. Every function returns one of the `KLEINIAN_*` codes, and
+//! on failure `kleinian_last_error_message` says what went wrong. Panics
+//! are caught before they reach the caller.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use num::complex::Complex;
+
+use crate::web::{limit_points, render_svg};
+use crate::{grandma, traces, Word};
+
+/// The layout of `KleinianParams` this build reads; bumped whenever the
+/// struct changes, so an old caller gets an error instead of garbage.
+pub const KLEINIAN_PARAMS_VERSION: u32 = 1;
+
+pub const KLEINIAN_OK: i32 = 0;
+/// A pointer that must not be null was.
+pub const KLEINIAN_ERR_NULL: i32 = -1;
+/// `KleinianParams::version` is not `KLEINIAN_PARAMS_VERSION`.
+pub const KLEINIAN_ERR_VERSION: i32 = -2;
+/// The output buffer is too small; its needed length has been written back.
+pub const KLEINIAN_ERR_BUFFER_TOO_SMALL: i32 = -3;
+/// The trace failed, or panicked.
+pub const KLEINIAN_ERR_TRACE: i32 = -4;
+/// A word passed in has a letter other than a, b, A and B.
+pub const KLEINIAN_ERR_WORD: i32 = -5;
+
+/// What to trace: grandma's group for ta and tb, to `epsilon` but no
+/// deeper than `max_depth`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KleinianParams {
+    /// Must be `KLEINIAN_PARAMS_VERSION`.
+    pub version: u32,
+    pub max_depth: u32,
+    pub ta_re: f64,
+    pub ta_im: f64,
+    pub tb_re: f64,
+    pub tb_im: f64,
+    pub epsilon: f64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+    // the last result on this thread, so the second of a pair of calls,
+    // the first asking only for the size, doesn't trace again
+    static LAST_SVG: RefCell<Option<(KleinianParams, Vec<u8>)>> = const { RefCell::new(None) };
+    static LAST_POINTS: RefCell<Option<(KleinianParams, Vec<f64>)>> = const { RefCell::new(None) };
+}
+
+fn fail(code: i32, message: &str) -> i32 {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = message);
+    code
+}
+
+// runs `f` with the caller's params, checked, turning a panic into an error
+unsafe fn guarded(params: *const KleinianParams, f: impl FnOnce(&KleinianParams) -> i32) -> i32 {
+    if params.is_null() {
+        return fail(KLEINIAN_ERR_NULL, "params is null");
+    }
+    let params = &*params;
+    if params.version != KLEINIAN_PARAMS_VERSION {
+        return fail(KLEINIAN_ERR_VERSION, &format!(
+            "params has layout version {}, but this library reads version {}",
+            params.version, KLEINIAN_PARAMS_VERSION));
+    }
+    panic::catch_unwind(AssertUnwindSafe(|| f(params)))
+        .unwrap_or_else(|_| fail(KLEINIAN_ERR_TRACE, "the trace panicked"))
+}
+
+// the two-call pattern: with `out` null, or `*len` short of `data`, write
+// back the length needed; otherwise copy `data` into `out` and its length
+// into `*len`
+unsafe fn copy_out<T: Copy>(data: &[T], out: *mut T, len: *mut usize) -> i32 {
+    if len.is_null() {
+        return fail(KLEINIAN_ERR_NULL, "the length pointer is null");
+    }
+    let room = *len;
+    *len = data.len();
+    if out.is_null() {
+        return KLEINIAN_OK;
+    }
+    if room < data.len() {
+        return fail(KLEINIAN_ERR_BUFFER_TOO_SMALL, &format!("the buffer holds {} but {} are needed", room, data.len()));
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    KLEINIAN_OK
+}
+
+/// Renders the limit set as SVG text, NUL-terminated, into `out_buf`.
+/// Call it first with `out_buf` null to learn in `*out_len` how many bytes
+/// are needed, then again with a buffer that big and `*out_len` its size.
+///
+/// # Safety
+///
+/// `params` must point to a `KleinianParams` and `out_len` to a `usize`;
+/// `out_buf` must be null or point to `*out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kleinian_render_svg(params: *const KleinianParams, out_buf: *mut u8, out_len: *mut usize) -> i32 {
+    guarded(params, |p| {
+        LAST_SVG.with(|last| {
+            let mut last = last.borrow_mut();
+            if last.as_ref().is_none_or(|(q, _)| q != p) {
+                match render_svg(p.ta_re, p.ta_im, p.tb_re, p.tb_im, p.epsilon, p.max_depth) {
+                    Ok(svg) => {
+                        let mut bytes = svg.into_bytes();
+                        bytes.push(0);
+                        *last = Some((*p, bytes));
+                    }
+                    Err(e) => return fail(KLEINIAN_ERR_TRACE, &e),
+                }
+            }
+            let (_, bytes) = last.as_ref().expect("filled above");
+            copy_out(bytes, out_buf, out_len)
+        })
+    })
+}
+
+/// Writes the traced points into `out`, as x and y interleaved, in the
+/// same two calls as `kleinian_render_svg`, with `*out_len` counting
+/// doubles.
+///
+/// # Safety
+///
+/// `params` must point to a `KleinianParams` and `out_len` to a `usize`;
+/// `out` must be null or point to `*out_len` writable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn kleinian_limit_points(params: *const KleinianParams, out: *mut f64, out_len: *mut usize) -> i32 {
+    guarded(params, |p| {
+        LAST_POINTS.with(|last| {
+            let mut last = last.borrow_mut();
+            if last.as_ref().is_none_or(|(q, _)| q != p) {
+                match limit_points(p.ta_re, p.ta_im, p.tb_re, p.tb_im, p.epsilon, p.max_depth) {
+                    Ok(points) => *last = Some((*p, points)),
+                    Err(e) => return fail(KLEINIAN_ERR_TRACE, &e),
+                }
+            }
+            let (_, points) = last.as_ref().expect("filled above");
+            copy_out(points, out, out_len)
+        })
+    })
+}
+
+/// Writes the traces of a, b and ab for `params` into `out`, as three
+/// pairs of real and imaginary parts.
+///
+/// # Safety
+///
+/// `params` must point to a `KleinianParams`, and `out` to 6 writable
+/// doubles.
+#[no_mangle]
+pub unsafe extern "C" fn kleinian_traces(params: *const KleinianParams, out: *mut f64) -> i32 {
+    guarded(params, |p| {
+        if out.is_null() {
+            return fail(KLEINIAN_ERR_NULL, "out is null");
+        }
+        let g = grandma(Complex::new(p.ta_re, p.ta_im), Complex::new(p.tb_re, p.tb_im));
+        let (ta, tb, tab) = traces(&g);
+        let parts = [ta.re, ta.im, tb.re, tb.im, tab.re, tab.im];
+        ptr::copy_nonoverlapping(parts.as_ptr(), out, parts.len());
+        KLEINIAN_OK
+    })
+}
+
+/// Writes the matrix of `word`, a NUL-terminated string of a, b and their
+/// inverses A and B, in the group for `params`, into `out` as the real and
+/// imaginary parts of its entries in the order a, b, c, d.
+///
+/// # Safety
+///
+/// `params` must point to a `KleinianParams`, `word` to a NUL-terminated
+/// string, and `out` to 8 writable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn kleinian_word(params: *const KleinianParams, word: *const c_char, out: *mut f64) -> i32 {
+    guarded(params, |p| {
+        if word.is_null() || out.is_null() {
+            return fail(KLEINIAN_ERR_NULL, "word or out is null");
+        }
+        let w = match CStr::from_ptr(word).to_str().map_err(|e| e.to_string()).and_then(|s| s.parse::<Word>().map_err(|e| e.to_string())) {
+            Ok(w) => w,
+            Err(e) => return fail(KLEINIAN_ERR_WORD, &e),
+        };
+        let m = grandma(Complex::new(p.ta_re, p.ta_im), Complex::new(p.tb_re, p.tb_im)).word(&w);
+        let parts = [m.a.re, m.a.im, m.b.re, m.b.im, m.c.re, m.c.im, m.d.re, m.d.im];
+        ptr::copy_nonoverlapping(parts.as_ptr(), out, parts.len());
+        KLEINIAN_OK
+    })
+}
+
+/// The message for the last error on this thread, NUL-terminated and empty
+/// when there has been none, valid until the next call that fails.
+#[no_mangle]
+pub extern "C" fn kleinian_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
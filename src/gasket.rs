@@ -0,0 +1,136 @@
+//! Exact rendering of the Apollonian gasket as circles.
+
+use num::complex::Complex;
+use svg::node::element::{Circle, Group};
+use std::collections::HashSet;
+
+use crate::{traces, Kleinian, Letter, Mat, RenderOptions, Word, COMMUTATORS,
+    GASKET_TOLERANCE, A, B, AI, BI};
+
+/// Deepest word applied to the starting circles; the radius cutoff usually
+/// stops the search well before this.
+pub const MAX_GASKET_DEPTH: usize = 40;
+
+type Circ = (Complex<f64>, f64);
+
+impl Kleinian {
+    /// Whether a and b have trace 2 and ab has trace 2 - 2i, so that the
+    /// limit set is the Apollonian gasket.
+    pub fn is_gasket(&self) -> bool {
+        let (ta, tb, tab) = traces(self);
+        let close = |z: Complex<f64>, w: Complex<f64>| (z - w).norm() < GASKET_TOLERANCE.sqrt();
+        close(ta, Complex::new(2.0, 0.0)) && close(tb, Complex::new(2.0, 0.0))
+            && close(tab, Complex::new(2.0, -2.0))
+    }
+
+    /// The circles of the gasket down to `min_radius`, largest first.
+    pub fn gasket_circles(&self, min_radius: f64) -> Vec<Circ> {
+        let cusps: Vec<_> = COMMUTATORS.iter()
+            .map(|w| self.word(&Word(w.to_vec())).fix())
+            .collect();
+        // The gasket's circles through the generators' fixed points are the
+        // horocircles there that also pass through a cusp; every other
+        // circle of the gasket is an image of one of these.
+        let base: Vec<Circ> = [A, B].iter()
+            .filter_map(|&l| self.mat(l).horocircles(0.5))
+            .flat_map(|pair| pair.to_vec())
+            .filter(|&(c, r)| cusps.iter().any(|&v| ((v - c).norm() - r).abs() < 1e-6))
+            .collect();
+        let mut seen = HashSet::new();
+        let mut circles = Vec::new();
+        for &circle in &base {
+            push_new(circle, &mut seen, &mut circles);
+        }
+        for &l in &[A, BI, AI, B] {
+            images(self, l, &Mat::id(), &base, min_radius, 1, &mut seen, &mut circles);
+        }
+        circles.sort_by(|x, y| y.1.partial_cmp(&x.1).expect("radii are finite"));
+        circles
+    }
+}
+
+fn push_new(circle: Circ, seen: &mut HashSet<(i64, i64, i64)>, circles: &mut Vec<Circ>) -> bool {
+    let q = |x: f64| (x / 1e-7).round() as i64;
+    let (c, r) = circle;
+    if seen.insert((q(c.re), q(c.im), q(r))) {
+        circles.push(circle);
+        true
+    } else {
+        false
+    }
+}
+
+fn fixes(m: &Mat, (c, r): Circ) -> bool {
+    m.circle_image((c, r)).is_some_and(|(d, s)| (d - c).norm() + (s - r).abs() < 1e-9)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn images(g: &Kleinian, l: Letter, t: &Mat, base: &[Circ], min_radius: f64, depth: usize,
+          seen: &mut HashSet<(i64, i64, i64)>, circles: &mut Vec<Circ>) {
+    let t = t * g.mat(l);
+    // A circle the last letter fixes has the same image as at the parent,
+    // so it doesn't count towards descending: otherwise the powers of a
+    // parabolic generator would go on forever.
+    let mut large = false;
+    for &circle in base {
+        if let Some((c, r)) = t.circle_image(circle) {
+            if r >= min_radius && c.is_finite() {
+                push_new((c, r), seen, circles);
+                large |= !fixes(g.mat(l), circle);
+            }
+        }
+    }
+    if large && depth < MAX_GASKET_DEPTH {
+        for &next in &l.successors() {
+            images(g, next, &t, base, min_radius, depth + 1, seen, circles);
+        }
+    }
+}
+
+/// The gasket's circles as `<circle>` elements, each carrying its signed
+/// curvature; the outer circle, which encloses the rest, has negative
+/// curvature.
+pub fn draw(g: &Kleinian, opts: &RenderOptions) -> Group {
+    let circles = g.gasket_circles(opts.min_radius);
+    let mut group = Group::new().set("class", "gasket");
+    for (n, &(c, r)) in circles.iter().enumerate() {
+        let outer = n == 0 && circles.iter().all(|&(d, s)| (d - c).norm() + s <= r + 1e-9);
+        let curvature = if outer { -1.0 / r } else { 1.0 / r };
+        group = group.add(Circle::new()
+            .set("cx", c.re)
+            .set("cy", c.im)
+            .set("r", r)
+            .set("fill", "none")
+            .set("stroke", opts.stroke.as_str())
+            .set("stroke-width", opts.stroke_width)
+            .set("data-curvature", curvature));
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with};
+
+    #[test]
+    fn the_circles_lie_along_the_traced_curve() {
+        let two = Complex::new(2.0, 0.0);
+        let mut g = grandma(two, two);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        assert!(g.is_gasket());
+        let epsilon = 0.004;
+        limitset_with(40, &mut g, &RenderOptions { epsilon, ..RenderOptions::default() });
+        let circles = g.gasket_circles(0.08);
+        assert!(circles.len() > 10, "{} circles", circles.len());
+        for &(c, r) in &circles {
+            for k in 0..16 {
+                let z = c + Complex::from_polar(&r, &(k as f64 * std::f64::consts::PI / 8.0));
+                let near = g.points().iter().map(|w| (w - z).norm()).fold(f64::INFINITY, f64::min);
+                assert!(near < 2.0 * epsilon, "({}, {}) is {} from the trace at {}", c, r, near, z);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Animated GIFs of a limit set deforming, behind the `gif` feature.
+
+use gif::{Encoder, Frame, Repeat};
+use num::complex::Complex;
+
+use crate::output::create_output;
+use crate::raster::rasterize;
+use crate::{grandma, limitset_with, Error, PngOptions, RenderOptions, A, AI, B, BI};
+
+/// Width and height of each frame, in pixels.
+pub const GIF_SIZE: u32 = 400;
+
+/// How long each frame shows, in hundredths of a second.
+pub const GIF_FRAME_DELAY: u16 = 10;
+
+fn gif_error(e: gif::EncodingError) -> Error {
+    Error::Raster(e.to_string())
+}
+
+/// Traces `frames` limit sets to `depth`, with traces moving in a straight
+/// line from (`ta0`, `tb0`) to (`ta1`, `tb1`), and writes them to `path` as
+/// a looping GIF of `GIF_SIZE` square frames on white.
+pub fn render_gif(ta0: Complex<f64>, tb0: Complex<f64>, ta1: Complex<f64>, tb1: Complex<f64>,
+                  frames: usize, depth: i64, path: &str) -> Result<(), Error> {
+    let mut opts = RenderOptions::default();
+    // a pixel wide, as the frames are small
+    opts.stroke_width = opts.view_box.2 / GIF_SIZE as f64;
+    opts.background = Some("white".to_string());
+    let png = PngOptions { width: GIF_SIZE, height: GIF_SIZE, supersample: 1 };
+
+    let mut w = create_output(path)?;
+    {
+        let size = GIF_SIZE as u16;
+        let mut encoder = Encoder::new(&mut w, size, size, &[]).map_err(gif_error)?;
+        encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+        for n in 0..frames {
+            let s = if frames > 1 { n as f64 / (frames - 1) as f64 } else { 0.0 };
+            let mut g = grandma(ta0 + (ta1 - ta0) * s, tb0 + (tb1 - tb0) * s);
+            for &l in &[A, B, AI, BI] {
+                g.add_end(vec![l]);
+            }
+            limitset_with(depth, &mut g, &opts);
+            // the background makes every pixel opaque, so the premultiplied
+            // data is plain RGBA
+            let mut pixels = rasterize(&g.document(&opts), &png)?.take();
+            let mut frame = Frame::from_rgba_speed(size, size, &mut pixels, 10);
+            frame.delay = GIF_FRAME_DELAY;
+            encoder.write_frame(&frame).map_err(gif_error)?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_gif_has_its_header_and_a_frame_per_step() {
+        let path = std::env::temp_dir().join(format!("svg_kleinian-gif-{}.gif", std::process::id()));
+        let t = Complex::new(2.0, 0.0);
+        render_gif(t, t, Complex::new(1.91, 0.05), t, 3, 4, path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (GIF_SIZE as u16, GIF_SIZE as u16));
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+    }
+}
@@ -0,0 +1,71 @@
+//! A known render checked against points saved in the repository, so a
+//! change to `mob`, `fix` or the traversal that moves the limit set shows
+//! up. Run `svg_kleinian golden` to check it; after a deliberate change to
+//! the output, run `svg_kleinian golden --update` and commit the new file.
+
+use num::complex::Complex;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use crate::{grandma, limitset, polylines_approx_equal, Error, A, AI, B, BI};
+
+/// Where the saved points live, relative to the repository root.
+pub const GOLDEN_PATH: &str = "golden/grandma-2-2.txt";
+
+/// The depth of the saved trace.
+pub const GOLDEN_DEPTH: i64 = 6;
+
+/// How far a point may move before the trace counts as changed.
+pub const GOLDEN_EPSILON: f64 = 1e-9;
+
+/// The points of `grandma(2, 2)` traced to `GOLDEN_DEPTH`, with the ends
+/// the command line adds.
+pub fn golden_trace() -> Vec<(f64, f64)> {
+    let two = Complex::new(2.0, 0.0);
+    let mut g = grandma(two, two);
+    for &l in &[A, B, AI, BI] {
+        g.add_end(vec![l]);
+    }
+    limitset(GOLDEN_DEPTH, &mut g);
+    g.points().iter().map(|z| (z.re, z.im)).collect()
+}
+
+/// Writes `points` as lines `x y`, like `Kleinian::stream_points`.
+pub fn write_points<W: Write>(points: &[(f64, f64)], w: &mut W) -> io::Result<()> {
+    for &(x, y) in points {
+        writeln!(w, "{} {}", x, y)?;
+    }
+    Ok(())
+}
+
+/// Reads the lines `x y` written by `write_points`.
+pub fn read_points<R: BufRead>(r: R) -> Result<Vec<(f64, f64)>, Error> {
+    let mut points = Vec::new();
+    for (n, line) in r.lines().enumerate() {
+        let line = line?;
+        let mut coords = line.split_whitespace().map(str::parse::<f64>);
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => points.push((x, y)),
+            _ => {
+                let msg = format!("line {}: expected `x y`, got `{}`", n + 1, line);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// Whether the current trace matches the points saved at `path`; the first
+/// difference is printed to stderr.
+pub fn check_golden(path: &str) -> Result<bool, Error> {
+    let saved = read_points(BufReader::new(File::open(path)?))?;
+    Ok(polylines_approx_equal(&golden_trace(), &saved, GOLDEN_EPSILON))
+}
+
+/// Saves the current trace to `path`, replacing what was there.
+pub fn update_golden(path: &str) -> Result<(), Error> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_points(&golden_trace(), &mut w)?;
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! A montage of limit sets over a grid of trace pairs, for looking over a
+//! region of parameter space at once.
+
+use num::complex::Complex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use svg::Document;
+use svg::node;
+use svg::node::element::{Group, Path, Rectangle, Text};
+use svg::node::element::path::Data;
+
+use crate::{fmt_complex, grandma, limitset_with, maskit_traces, stroke_path, Kleinian, Mat, RenderOptions, A, AI, B, BI};
+
+// a cell's column, row, traces and label
+type Cell = (usize, usize, Complex<f64>, Complex<f64>, String);
+
+fn lerp(range: (Complex<f64>, Complex<f64>), k: usize, n: usize) -> Complex<f64> {
+    if n < 2 {
+        range.0
+    } else {
+        range.0 + (range.1 - range.0) * (k as f64 / (n - 1) as f64)
+    }
+}
+
+fn is_finite(m: &Mat) -> bool {
+    [m.a, m.b, m.c, m.d].iter().all(|z| z.is_finite())
+}
+
+/// The traced points for one cell, or `None` when the recipe breaks down
+/// or the traversal runs out of budget, a sign the group isn't discrete.
+pub(crate) fn trace_cell(ta: Complex<f64>, tb: Complex<f64>, level: i64, opts: &RenderOptions) -> Option<Vec<Complex<f64>>> {
+    trace_group(grandma(ta, tb), level, opts)
+}
+
+/// `trace_cell` for a group already made.
+pub(crate) fn trace_group(mut g: Kleinian, level: i64, opts: &RenderOptions) -> Option<Vec<Complex<f64>>> {
+    if !is_finite(g.mat(A)) || !is_finite(g.mat(B)) {
+        return None;
+    }
+    for &l in &[A, B, AI, BI] {
+        g.add_end(vec![l]);
+    }
+    limitset_with(level, &mut g, opts);
+    if g.exhausted() || g.points().iter().any(|z| !z.is_finite()) {
+        return None;
+    }
+    Some(g.points().to_vec())
+}
+
+/// Renders `cols` by `rows` limit sets into unit cells of one document,
+/// with ta running from `ta.0` to `ta.1` across the columns and tb from
+/// `tb.0` to `tb.1` down the rows. Each cell is traced to `level` with
+/// `opts`, on as many threads as there are cores; cells that fail are
+/// crossed out.
+pub fn grid(
+    ta: (Complex<f64>, Complex<f64>),
+    tb: (Complex<f64>, Complex<f64>),
+    (cols, rows): (usize, usize),
+    level: i64,
+    opts: &RenderOptions,
+) -> Document {
+    let cells: Vec<Cell> = (0..rows)
+        .flat_map(|j| (0..cols).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let (ta, tb) = (lerp(ta, i, cols), lerp(tb, j, rows));
+            let label = format!("ta = {}, tb = {}", fmt_complex(ta, 3), fmt_complex(tb, 3));
+            (i, j, ta, tb, label)
+        })
+        .collect();
+    montage(&cells, (cols, rows), level, opts)
+}
+
+/// `grid` over the Maskit slice: μ runs over the rectangle with corners
+/// `mu.0` and `mu.1`, its real part across the columns and its imaginary
+/// part down the rows, and each cell draws `grandma` at `maskit_traces(μ)`.
+pub fn grid_mu(
+    mu: (Complex<f64>, Complex<f64>),
+    (cols, rows): (usize, usize),
+    level: i64,
+    opts: &RenderOptions,
+) -> Document {
+    let cells: Vec<Cell> = (0..rows)
+        .flat_map(|j| (0..cols).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let mu = Complex::new(lerp(mu, i, cols).re, lerp(mu, j, rows).im);
+            let (ta, tb) = maskit_traces(mu);
+            (i, j, ta, tb, format!("μ = {}", fmt_complex(mu, 3)))
+        })
+        .collect();
+    montage(&cells, (cols, rows), level, opts)
+}
+
+// the document of `grid` and `grid_mu`
+fn montage(
+    cells: &[Cell],
+    (cols, rows): (usize, usize),
+    level: i64,
+    opts: &RenderOptions,
+) -> Document {
+    let traced = Mutex::new(vec![None; cells.len()]);
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(cells.len());
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&(_, _, ta, tb, _)) = cells.get(n) else { break };
+                let points = trace_cell(ta, tb, level, opts);
+                traced.lock().unwrap()[n] = points;
+            });
+        }
+    });
+
+    let mut document = Document::new()
+        .set("viewBox", (0, 0, cols, rows));
+    for (&(i, j, _, _, ref label), points) in cells.iter().zip(traced.into_inner().unwrap()) {
+        let (x, y) = (i as f64, j as f64);
+        let mut group = Group::new()
+            .set("class", "cell")
+            .add(Rectangle::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", 1)
+                .set("height", 1)
+                .set("fill", "none")
+                .set("stroke", "#bbbbbb")
+                .set("stroke-width", 2.0 * opts.stroke_width));
+        match points {
+            Some(points) => {
+                // fit the limit set into the cell above its label
+                let inf = f64::INFINITY;
+                let (x0, y0, x1, y1) = points.iter()
+                    .fold((inf, inf, -inf, -inf), |(x0, y0, x1, y1), z| {
+                        (x0.min(z.re), y0.min(z.im), x1.max(z.re), y1.max(z.im))
+                    });
+                let scale = 0.8 / (x1 - x0).max(y1 - y0).max(f64::MIN_POSITIVE);
+                let from = Complex::new((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+                let to = Complex::new(x + 0.5, y + 0.45);
+                let fitted: Vec<_> = points.iter().map(|&z| (z - from) * scale + to).collect();
+                group = group.add(stroke_path(&fitted, &opts.stroke, opts));
+            }
+            None => {
+                let cross = Data::new()
+                    .move_to((x + 0.1, y + 0.1))
+                    .line_to((x + 0.9, y + 0.9))
+                    .move_to((x + 0.9, y + 0.1))
+                    .line_to((x + 0.1, y + 0.9));
+                group = group.add(Path::new()
+                    .set("fill", "none")
+                    .set("stroke", "#d62728")
+                    .set("stroke-width", 2.0 * opts.stroke_width)
+                    .set("d", cross));
+            }
+        }
+        document = document.add(group.add(Text::new()
+            .set("x", x + 0.5)
+            .set("y", y + 0.96)
+            .set("font-size", 0.04)
+            .set("text-anchor", "middle")
+            .add(node::Text::new(label.as_str()))));
+    }
+    document
+}
@@ -0,0 +1,172 @@
+//! Point density images: the traced points binned into a pixel grid and
+//! tone mapped, showing where the limit set is thick rather than just
+//! where it is.
+
+use num::complex::Complex;
+use std::str::FromStr;
+
+use crate::hex_rgb;
+use crate::overlay::PALETTE;
+
+/// A count of points per pixel over a window of the plane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    counts: Vec<u32>,
+}
+
+/// How bin counts are turned into brightness in [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMap {
+    /// Scale by `ln(1 + count)` rather than the count itself, so sparse
+    /// regions still show next to dense ones.
+    pub log: bool,
+    /// Brightness is raised to `1 / gamma`; above 1 lifts faint pixels.
+    pub gamma: f64,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        ToneMap { log: false, gamma: 1.0 }
+    }
+}
+
+/// The colors brightness runs through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    /// Black to white.
+    Gray,
+    /// Black through the `n`th overlay palette color to white.
+    Palette(usize),
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gray" => Ok(Colormap::Gray),
+            _ => match s.strip_prefix("palette=").map(str::parse) {
+                Some(Ok(n)) if n < PALETTE.len() => Ok(Colormap::Palette(n)),
+                _ => Err(format!("unknown colormap `{}`, expected gray or palette=N with N below {}", s, PALETTE.len())),
+            },
+        }
+    }
+}
+
+impl Colormap {
+    /// The color for brightness `v` in [0, 1].
+    pub fn rgb(&self, v: f64) -> [u8; 3] {
+        let v = v.clamp(0.0, 1.0);
+        let mid = match *self {
+            Colormap::Gray => [127.5; 3],
+            Colormap::Palette(n) => hex_rgb(PALETTE[n]).expect("palette colors are #rrggbb"),
+        };
+        // black to mid over the first half, mid to white over the second
+        let mut rgb = [0; 3];
+        for (c, m) in rgb.iter_mut().zip(mid.iter()) {
+            let x = if v < 0.5 {
+                m * 2.0 * v
+            } else {
+                m + (255.0 - m) * (2.0 * v - 1.0)
+            };
+            *c = x.round() as u8;
+        }
+        rgb
+    }
+}
+
+impl Heatmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Heatmap {
+            width,
+            height,
+            counts: vec![0; width * height],
+        }
+    }
+
+    /// Bins `points` over `view_box`, given like `RenderOptions::view_box`,
+    /// stretched to fill the grid. Points outside it are dropped.
+    pub fn from_points(points: &[Complex<f64>], view_box: (f64, f64, f64, f64), width: usize, height: usize) -> Self {
+        let mut heatmap = Heatmap::new(width, height);
+        for &z in points {
+            heatmap.add(z, view_box);
+        }
+        heatmap
+    }
+
+    /// Counts `z` in its bin, returning whether it landed in the grid.
+    pub fn add(&mut self, z: Complex<f64>, view_box: (f64, f64, f64, f64)) -> bool {
+        let (x, y, w, h) = view_box;
+        let i = ((z.re - x) / w * self.width as f64).floor();
+        let j = ((z.im - y) / h * self.height as f64).floor();
+        // also rejects NaN
+        if !(0.0..self.width as f64).contains(&i) || !(0.0..self.height as f64).contains(&j) {
+            return false;
+        }
+        self.counts[j as usize * self.width + i as usize] += 1;
+        true
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Bin counts row by row, top row first.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// The number of points binned.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|&c| c as u64).sum()
+    }
+
+    /// Brightness of each bin in [0, 1], relative to the fullest bin.
+    pub fn tone(&self, tone: &ToneMap) -> Vec<f64> {
+        let scale = |c: u32| if tone.log { (c as f64).ln_1p() } else { c as f64 };
+        let max = self.counts.iter().copied().max().map_or(0.0, scale);
+        self.counts.iter()
+            .map(|&c| if max > 0.0 { (scale(c) / max).powf(1.0 / tone.gamma) } else { 0.0 })
+            .collect()
+    }
+
+    /// Opaque RGBA pixels, row by row.
+    pub fn rgba(&self, tone: &ToneMap, colormap: Colormap) -> Vec<u8> {
+        self.tone(tone).into_iter()
+            .flat_map(|v| {
+                let [r, g, b] = colormap.rgb(v);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, A, AI, B, BI};
+
+    #[test]
+    fn the_bins_hold_every_point_in_view() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(10, &mut g);
+        // the right half of the picture, so some points fall outside
+        let view_box = (0.0, -1.2, 1.2, 2.4);
+        let heatmap = Heatmap::from_points(g.points(), view_box, 30, 50);
+        assert_eq!((heatmap.width(), heatmap.height()), (30, 50));
+        assert_eq!(heatmap.counts().len(), 30 * 50);
+        let (x, y, w, h) = view_box;
+        let in_view = g.points().iter().filter(|z| z.re >= x && z.re < x + w && z.im >= y && z.im < y + h).count();
+        assert!(in_view > 0 && in_view < g.points().len());
+        assert_eq!(heatmap.total(), in_view as u64);
+    }
+}
@@ -0,0 +1,83 @@
+//! A self-contained HTML page for exploring a render in the browser.
+
+const SVG_ID: &str = "kleinian";
+
+const SCRIPT: &str = r#"(function () {
+  var svg = document.getElementById("kleinian");
+  var initial = svg.getAttribute("viewBox").split(/[\s,]+/).map(Number);
+  var box = initial.slice();
+  var drag = null;
+  function apply() { svg.setAttribute("viewBox", box.join(" ")); }
+  function toUser(e) {
+    var r = svg.getBoundingClientRect();
+    return [box[0] + (e.clientX - r.left) / r.width * box[2],
+            box[1] + (e.clientY - r.top) / r.height * box[3]];
+  }
+  svg.addEventListener("wheel", function (e) {
+    e.preventDefault();
+    var p = toUser(e), k = Math.exp(e.deltaY * 0.001);
+    box = [p[0] - (p[0] - box[0]) * k, p[1] - (p[1] - box[1]) * k, box[2] * k, box[3] * k];
+    apply();
+  }, { passive: false });
+  svg.addEventListener("mousedown", function (e) { drag = toUser(e); });
+  window.addEventListener("mouseup", function () { drag = null; });
+  svg.addEventListener("mousemove", function (e) {
+    if (!drag) return;
+    var p = toUser(e);
+    box[0] += drag[0] - p[0];
+    box[1] += drag[1] - p[1];
+    apply();
+  });
+  svg.addEventListener("dblclick", function () { box = initial.slice(); apply(); });
+})();"#;
+
+/// Wraps `svg` in a page with wheel zoom, drag to pan and double-click to
+/// reset, with `caption` shown under the picture. The only change to the
+/// SVG is an `id` on the root element for the script to find.
+pub fn page(svg: &str, caption: &str) -> String {
+    let svg = svg.replacen("<svg ", &format!("<svg id=\"{}\" ", SVG_ID), 1);
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{caption}</title>
+<style>
+body {{ margin: 0; font-family: sans-serif; }}
+#{id} {{ display: block; width: 100vw; height: calc(100vh - 2em); cursor: move; }}
+p {{ margin: 0; height: 2em; line-height: 2em; text-align: center; }}
+</style>
+</head>
+<body>
+{svg}
+<p>{caption}</p>
+<script>
+{script}
+</script>
+</body>
+</html>
+"#, caption = escape(caption), id = SVG_ID, svg = svg, script = SCRIPT)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_page_holds_the_svg_and_the_script_hooks() {
+        let svg = r#"<svg viewBox="-1 -1 2 2" xmlns="http://www.w3.org/2000/svg"><path d="M0,0 L1,1"/></svg>"#;
+        let html = page(svg, "ta = 2 & tb = <2>");
+        let tagged = svg.replacen("<svg ", "<svg id=\"kleinian\" ", 1);
+        assert!(html.contains(&tagged), "{}", html);
+        assert_eq!(html.matches("<svg").count(), 1);
+        assert!(html.contains("<script>") && html.contains("</script>"));
+        assert!(html.contains("document.getElementById(\"kleinian\")"));
+        for hook in ["\"wheel\"", "\"mousedown\"", "\"mousemove\"", "\"mouseup\"", "\"dblclick\""] {
+            assert!(html.contains(hook), "no {} handler", hook);
+        }
+        assert!(html.contains("<p>ta = 2 &amp; tb = &lt;2&gt;</p>"));
+    }
+}
@@ -0,0 +1,81 @@
+//! The convex hull of the traced points, for framing the limit set.
+
+use crate::{limitset, Kleinian};
+
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+// one chain of the monotone chain algorithm, keeping only left turns
+fn chain<'a>(points: impl Iterator<Item = &'a (f64, f64)>) -> Vec<(f64, f64)> {
+    let mut chain: Vec<(f64, f64)> = Vec::new();
+    for &p in points {
+        while chain.len() >= 2 && cross(chain[chain.len() - 2], chain[chain.len() - 1], p) <= 0.0 {
+            chain.pop();
+        }
+        chain.push(p);
+    }
+    chain
+}
+
+/// The convex hull of the finite `points` by Andrew's monotone chain,
+/// counterclockwise (with y up) from the leftmost, then lowest, point.
+/// Points in the middle of an edge are left out.
+pub fn convex_hull(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut sorted: Vec<_> = points.iter().copied()
+        .filter(|p| p.0.is_finite() && p.1.is_finite())
+        .collect();
+    sorted.sort_by(|p, q| p.partial_cmp(q).expect("the points are finite"));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut lower = chain(sorted.iter());
+    let mut upper = chain(sorted.iter().rev());
+    // each chain ends where the other starts
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+impl Kleinian {
+    /// Traces to `level` and returns the convex hull of the points; see
+    /// `convex_hull`.
+    pub fn convex_hull(&mut self, level: i64) -> Vec<(f64, f64)> {
+        limitset(level, self);
+        let points: Vec<_> = self.points().iter().map(|z| (z.re, z.im)).collect();
+        convex_hull(&points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, A, AI, B, BI};
+    use num::complex::Complex;
+
+    #[test]
+    fn a_square_has_four_corners() {
+        let mut points = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        // points inside, in the middle of edges, repeated and at ∞
+        points.extend([(0.5, 0.5), (0.5, 0.0), (1.0, 0.25), (0.0, 0.0), (f64::INFINITY, 0.5)]);
+        assert_eq!(convex_hull(&points), vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn every_traced_point_lies_in_the_hull() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let hull = g.convex_hull(10);
+        assert!(hull.len() >= 3);
+        let edges: Vec<_> = hull.iter().zip(hull.iter().cycle().skip(1)).collect();
+        for z in g.points() {
+            for &(&a, &b) in &edges {
+                assert!(cross(a, b, (z.re, z.im)) >= -1e-12, "{} is outside the edge {:?} to {:?}", z, a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,2354 @@
+use svg::Document;
+use svg::node::element::{Group, Path, Rectangle};
+use svg::node::element::path::Data;
+use num::complex::Complex;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Mul;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+
+mod analyze;
+mod animate;
+#[cfg(feature = "animation")]
+mod animation;
+mod builder;
+mod checkpoint;
+mod classify;
+mod clip;
+mod config;
+mod curves;
+#[cfg(feature = "eps")]
+mod eps;
+mod error;
+mod export;
+mod farey;
+mod ffi;
+mod gasket;
+#[cfg(feature = "gif")]
+mod gif;
+mod golden;
+mod grid;
+mod heatmap;
+mod hull;
+pub mod html;
+mod logging;
+mod maskit;
+mod mat32;
+mod model;
+mod npy;
+mod output;
+mod outline;
+mod overlay;
+mod parse;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod presets;
+#[cfg(feature = "python")]
+mod python;
+mod quasi_fuchsian;
+mod repl;
+mod report;
+mod scene;
+#[cfg(feature = "serve")]
+mod serve;
+mod sphere;
+mod stream;
+mod svgz;
+mod tikz;
+mod tiles;
+mod verify;
+mod web;
+#[cfg(feature = "png")]
+mod raster;
+
+pub use analyze::{analyze_depths, write_analysis, DepthRow};
+pub use animate::{animate, frame_path, reference_circle, render_frames, AnimateOptions, Interpolation, PREPASS_SAMPLES, ROOT_SUBSTEPS};
+#[cfg(feature = "animation")]
+pub use animation::{encode_animation, AnimationFormat, EncodeOptions};
+pub use builder::{KleinianRenderer, PointSink, RenderOutput, RenderStats, RenderStyle, RendererBuilder};
+pub use checkpoint::{Frame, CHECKPOINT_VERSION};
+pub use classify::{Classification, LimitSetKind, CONNECTED_GAP, MAX_ELLIPTIC_ORDER, ORDER_TOLERANCE, PINCH_DENOMINATOR, TRACE_TOLERANCE};
+pub use config::{Recipe, RenderConfig};
+pub use curves::{curves, Curve};
+#[cfg(feature = "eps")]
+pub use eps::{render_eps, MAX_PATH_SEGMENTS};
+pub use error::Error;
+pub use export::{npz_files, point_columns, read_csv, write_csv, write_json, write_ndjson, PointRecord};
+pub use farey::farey_sequence;
+pub use ffi::{KleinianParams, KLEINIAN_ERR_BUFFER_TOO_SMALL, KLEINIAN_ERR_NULL, KLEINIAN_ERR_TRACE, KLEINIAN_ERR_VERSION, KLEINIAN_ERR_WORD, KLEINIAN_OK, KLEINIAN_PARAMS_VERSION};
+#[cfg(feature = "gif")]
+pub use gif::{render_gif, GIF_FRAME_DELAY, GIF_SIZE};
+pub use golden::{check_golden, golden_trace, read_points, update_golden, write_points, GOLDEN_DEPTH, GOLDEN_EPSILON, GOLDEN_PATH};
+pub use grid::{grid, grid_mu};
+pub use heatmap::{Colormap, Heatmap, ToneMap};
+pub use hull::convex_hull;
+pub use logging::{init_logging, verbosity_level};
+pub use maskit::{farey_path, maskit, maskit_cusp, maskit_traces, CuspCache, CUSP_ITERATIONS, CUSP_TOLERANCE, FAREY_OFFSET};
+pub use mat32::MatF32;
+pub use model::{disk_to_halfplane, three_point_map, Model};
+pub use npy::{write_npy, write_npz};
+pub use output::{create_output, read_input, STDIO};
+#[cfg(feature = "png")]
+pub use raster::{render_png, render_png_sized, render_tiled_png, save_heatmap, PngOptions};
+#[cfg(feature = "pdf")]
+pub use pdf::{render_pdf, render_pdf_pages, save_pdf, PDF_WIDTH};
+pub use overlay::{circle_through, inversive_distance, Overlay};
+pub use parse::{parse_complex, ParseError};
+pub use quasi_fuchsian::quasi_fuchsian;
+pub use repl::{run_command, Reply, Session, REPL_HELP};
+pub use report::{sha256_hex, OutputFile, RenderReport, ReportTraces, REPORT_SCHEMA_VERSION};
+pub use scene::{parse_jobs, Job, Scene, SceneAnimation, SceneOutput, SceneStyle, SceneValue, SceneView};
+#[cfg(feature = "serve")]
+pub use serve::{serve, ServeOptions};
+pub use sphere::{from_sphere, to_sphere};
+pub use svgz::{read_svg, svg_metadata, write_svg, DEFAULT_COMPRESSION};
+pub use tikz::{TikzOptions, MAX_TIKZ_POINTS};
+pub use tiles::{tile, tile_window};
+pub use verify::{verify_invariance, Invariance, VERIFY_TOLERANCE};
+pub use web::{limit_points, render_svg, WEB_MAX_NODES};
+#[cfg(feature = "wasm")]
+pub use web::wasm;
+pub use presets::{preset, preset_by_name, Preset, PRESETS};
+
+pub const EPSILON: f64 = 0.001;
+
+/// The most points `limitset_with` makes room for before tracing. A trace
+/// to depth n visits up to 4·3ⁿ⁻¹ branches, but one that stops at
+/// `epsilon` usually visits far fewer, so the guess is capped here rather
+/// than taken at its word; a bigger trace grows the buffer as it goes.
+pub const MAX_RESERVED_POINTS: usize = 1 << 18;
+
+/// The most branches `check_trace_size` lets a trace without a node budget
+/// be expected to visit. Each branch leaves a point or so of 16 bytes
+/// behind, and `estimate_nodes` guesses high by a factor of several, so a
+/// trace let through at the ceiling holds a few gigabytes of points.
+pub const MAX_ESTIMATED_NODES: usize = 1_000_000_000;
+
+/// The branches `estimate_nodes` expects at an epsilon of 1. Fitted, with
+/// some room to spare, to the gasket and to grandma's groups near it at
+/// the default view, which visit 3·10⁵ to 6·10⁵ branches at epsilon 10⁻³
+/// and about twenty times as many for each tenfold smaller epsilon.
+pub const NODE_ESTIMATE_SCALE: f64 = 100.0;
+
+/// The power of 1/epsilon by which `estimate_nodes` grows, a little above
+/// the dimension of the limit sets it was fitted to.
+pub const NODE_ESTIMATE_EXPONENT: f64 = 1.4;
+
+/// The node budget of `Kleinian::render_centered_on`. The whole limit set
+/// is traced to the epsilon the cusp needs, most of it off the picture, so
+/// the branches needed grow faster than the zoom; around the cusp at 1 of
+/// the Apollonian gasket this runs out at a zoom of about 20.
+pub const CENTERED_MAX_NODES: usize = 20_000_000;
+
+/// How many branches the traversal visits between looks at
+/// `RenderOptions::cancel`.
+pub const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+/// How many points `Kleinian::stream_points_with` holds before writing
+/// them out.
+pub const STREAM_CHUNK: usize = 4096;
+
+/// The stroke of the second curve `Kleinian::render_with_dual` draws.
+pub const DUAL_STROKE: &str = "#d62728";
+
+#[derive(Debug, Clone)]
+pub struct Mat {
+    pub a: Complex<f64>,
+    pub b: Complex<f64>,
+    pub c: Complex<f64>,
+    pub d: Complex<f64>,
+}
+
+impl Mat {
+    pub fn new(a: Complex<f64>, b: Complex<f64>, c: Complex<f64>, d: Complex<f64>) -> Self {
+        Mat {
+            a,
+            b,
+            c,
+            d,
+        }
+    }
+
+    pub fn id() -> Self {
+        Mat {
+            a: Complex::new(1.0,0.0),
+            b: Complex::new(0.0,0.0),
+            c: Complex::new(0.0,0.0),
+            d: Complex::new(1.0,0.0),
+        }
+    }
+
+    /// Rotation by `theta` about 0 (and ∞), elliptic unless `theta` is a
+    /// multiple of 2π.
+    pub fn rotation(theta: f64) -> Self {
+        let half = Complex::from_polar(&1.0, &(theta / 2.0));
+        Mat::new(half, Complex::new(0.0, 0.0), Complex::new(0.0, 0.0), half.inv())
+    }
+
+    /// z ↦ z + t, parabolic fixing ∞ for t ≠ 0.
+    pub fn translation(t: Complex<f64>) -> Self {
+        let one = Complex::new(1.0, 0.0);
+        Mat::new(one, t, Complex::new(0.0, 0.0), one)
+    }
+
+    /// z ↦ λz, fixing 0 and ∞: loxodromic in general, hyperbolic for real
+    /// positive λ and elliptic for |λ| = 1.
+    pub fn scaling(lambda: Complex<f64>) -> Self {
+        let root = lambda.sqrt();
+        Mat::new(root, Complex::new(0.0, 0.0), Complex::new(0.0, 0.0), root.inv())
+    }
+
+    /// The Möbius map sending each of `src` to the matching point of
+    /// `dst`, for framing a picture by where three of its points land;
+    /// see `three_point_map`. The points of each must be distinct.
+    pub fn from_three_points(src: [Complex<f64>; 3], dst: [Complex<f64>; 3]) -> Self {
+        three_point_map(src, dst)
+    }
+
+    /// The entrywise complex conjugate, acting as z ↦ conj(M(conj z)):
+    /// the generator of the group mirrored across the real axis.
+    pub fn conj(&self) -> Self {
+        Mat::new(self.a.conj(), self.b.conj(), self.c.conj(), self.d.conj())
+    }
+
+    pub fn adj(&self) -> Self {
+        Mat {
+            a: self.d,
+            b: -self.b,
+            c: -self.c,
+            d: self.a,
+        }
+    }
+
+    pub fn trace(&self) -> Complex<f64> {
+        self.a + self.d
+    }
+
+    pub fn mob(&self, z: Complex<f64>) -> Complex<f64> {
+        (self.a * z + self.b) / (self.c * z + self.d)
+    }
+
+    /// `mob` over a slice of points. An affine map (c = 0) skips the
+    /// division per point.
+    pub fn mob_batch(&self, zs: &[Complex<f64>]) -> Vec<Complex<f64>> {
+        if self.c.norm_sqr() == 0.0 {
+            let (scale, shift) = (self.a / self.d, self.b / self.d);
+            zs.iter().map(|&z| scale * z + shift).collect()
+        } else {
+            zs.iter().map(|&z| self.mob(z)).collect()
+        }
+    }
+
+    pub fn fix(&self) -> Complex<f64> {
+        // gives the attracting fixed point
+        // z = az+b/cz+d, with big cz+d
+        // cz^2 + (d-a) z - b = 0
+        let a = self.a;
+        let b = self.b;
+        let c = self.c;
+        let d = self.d;
+        if c.norm_sqr() == 0.0 {
+            if a.norm_sqr() > d.norm_sqr() {
+                Complex::new(1.0 / 0.0, 0.0)
+            } else {
+                b / (d-a)
+            }
+        } else {
+            let disc = (d - a) * (d - a) + 4.0 * b * c;
+            let sd = if (a + d).re > 0.0 {
+                -disc.sqrt()
+            } else {
+                disc.sqrt()
+            };
+            (a - d - sd) / (2.0 * c)
+        }
+    }
+}
+
+impl Mul<&Mat> for &Mat {
+    type Output = Mat;
+    fn mul(self, rhs: &Mat) -> Mat {
+        let v = rhs;
+        Mat {
+            a: self.a * v.a + self.b * v.c,
+            b: self.a * v.b + self.b * v.d,
+            c: self.c * v.a + self.d * v.c,
+            d: self.c * v.b + self.d * v.d,
+        }
+    }
+}
+
+impl Mul<Mat> for Mat {
+    type Output = Mat;
+    fn mul(self, rhs: Mat) -> Mat {
+        &self * &rhs
+    }
+}
+
+impl Mul<&Mat> for Mat {
+    type Output = Mat;
+    fn mul(self, rhs: &Mat) -> Mat {
+        &self * rhs
+    }
+}
+
+/// The decimal places complex numbers are written to where no
+/// `--precision` says otherwise: enough to paste a trace back in without
+/// the limit set visibly moving.
+pub const COMPLEX_PRECISION: usize = 10;
+
+// `x` to `precision` places, without trailing zeros, and `-0` as `0`
+fn fmt_part(x: f64, precision: usize) -> String {
+    if !x.is_finite() {
+        return x.to_string();
+    }
+    let s = format!("{:.*}", precision, x);
+    let s = if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.') } else { &s };
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// `z` as `a+bi` or `a-bi`, each part rounded to `precision` decimal
+/// places with trailing zeros dropped. A part that rounds to zero is left
+/// out, unless both do, and a unit imaginary part is written `i`, so
+/// `2`, `-0.5i` and `1+i`; `parse_complex` reads all of these back.
+pub fn fmt_complex(z: Complex<f64>, precision: usize) -> String {
+    let (re, im) = (fmt_part(z.re, precision), fmt_part(z.im.abs(), precision));
+    let im = match im.as_str() {
+        "1" => "i".to_string(),
+        _ => format!("{}i", im),
+    };
+    let sign = if z.im < 0.0 { "-" } else { "+" };
+    if im == "0i" {
+        re
+    } else if re == "0" {
+        format!("{}{}", sign.trim_start_matches('+'), im)
+    } else {
+        format!("{}{}{}", re, sign, im)
+    }
+}
+
+impl Mat {
+    /// The matrix as a LaTeX `pmatrix`, entries written `a+bi`.
+    pub fn to_latex(&self) -> String {
+        format!("\\begin{{pmatrix}} {} & {} \\\\ {} & {} \\end{{pmatrix}}",
+            fmt_complex(self.a, COMPLEX_PRECISION), fmt_complex(self.b, COMPLEX_PRECISION),
+            fmt_complex(self.c, COMPLEX_PRECISION), fmt_complex(self.d, COMPLEX_PRECISION))
+    }
+}
+
+/// Two lines, the entries aligned in columns:
+///
+/// ```text
+/// [ 1   2i ]
+/// [ 0  1-i ]
+/// ```
+impl fmt::Display for Mat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d] = [self.a, self.b, self.c, self.d].map(|z| fmt_complex(z, COMPLEX_PRECISION));
+        let left = a.chars().count().max(c.chars().count());
+        let right = b.chars().count().max(d.chars().count());
+        writeln!(f, "[ {:>left$}  {:>right$} ]", a, b, left = left, right = right)?;
+        write!(f, "[ {:>left$}  {:>right$} ]", c, d, left = left, right = right)
+    }
+}
+
+
+
+/// How close both traces must be to 2 for `grandma` to return the exact
+/// gasket generators instead of evaluating its (nearly singular) formulas.
+pub const GASKET_TOLERANCE: f64 = 1e-9;
+
+pub fn grandma(ta: Complex<f64>, tb: Complex<f64>) -> Kleinian {
+    let i = Complex::i();
+    let two = Complex::new(2.0, 0.0);
+    if (ta - two).norm() < GASKET_TOLERANCE && (tb - two).norm() < GASKET_TOLERANCE {
+        // the Apollonian gasket: tab = 2 - 2i and scale = -i, so the
+        // formulas below reduce to these
+        let one = Complex::new(1.0, 0.0);
+        let zero = Complex::new(0.0, 0.0);
+        let a = Mat::new(one, zero, -2.0 * i, one);
+        let b = Mat::new(one - i, one, one, one + i);
+        return Kleinian::new(a, b);
+    }
+    grandma_with_root(ta, tb, markov_discriminant(ta, tb).sqrt())
+}
+
+/// `grandma`, failing with `Error::Degenerate` where its formulas divide
+/// by zero or overflow, as they do when either trace isn't finite or
+/// tab comes out as ±2.
+pub fn try_grandma(ta: Complex<f64>, tb: Complex<f64>) -> Result<Kleinian, Error> {
+    let g = grandma(ta, tb);
+    let finite = |m: &Mat| [m.a, m.b, m.c, m.d].iter().all(|z| z.is_finite());
+    if finite(g.mat(A)) && finite(g.mat(B)) {
+        Ok(g)
+    } else {
+        log::debug!("grandma's generators are not finite:\na =\n{}\nb =\n{}", g.mat(A), g.mat(B));
+        Err(Error::Degenerate { ta, tb })
+    }
+}
+
+/// ta²tb² - 4ta² - 4tb², whose square roots give the two tab that `grandma`
+/// can pair with ta and tb.
+pub fn markov_discriminant(ta: Complex<f64>, tb: Complex<f64>) -> Complex<f64> {
+    ta * ta * tb * tb - 4.0 * ta * ta - 4.0 * tb * tb
+}
+
+/// `grandma`, taking tab = (ta tb - root) / 2 for `root` either square
+/// root of `markov_discriminant(ta, tb)`, where `grandma` always takes the
+/// principal one. Following the root continuously keeps a family of
+/// groups from jumping to the other solution where the principal square
+/// root crosses its branch cut.
+pub fn grandma_with_root(ta: Complex<f64>, tb: Complex<f64>, root: Complex<f64>) -> Kleinian {
+    let i = Complex::i();
+    let tab = 0.5 * (ta * tb - root);
+    let scale = (tab - 2.0) * tb / (tb * tab - 2.0 * ta + 2.0 * i * tab);
+
+    let a = Mat::new(ta / 2.0, (ta * tab - 2.0 * tb + 4.0 * i) / ((2.0 * tab + 4.0) * scale),
+        scale * (ta * tab - 2.0 * tb - 4.0 * i) / (2.0 * tab - 4.0), ta / 2.0);
+    let b = Mat::new((tb - 2.0 * i) / 2.0, tb / 2.0,
+        tb / 2.0, (tb + 2.0 * i) / 2.0);
+    log::trace!("grandma with tab = {}:\na =\n{}\nb =\n{}", fmt_complex(tab, COMPLEX_PRECISION), a, b);
+    Kleinian::new(a,b)
+}
+
+/// The traces of a, b and ab, the parameters `grandma` starts from.
+pub fn traces(g: &Kleinian) -> (Complex<f64>, Complex<f64>, Complex<f64>) {
+    let ab = g.mat(A) * g.mat(B);
+    (g.mat(A).trace(), g.mat(B).trace(), ab.trace())
+}
+
+/// Writes the entries and trace of each generator and its inverse, then the
+/// trace of the commutator.
+pub fn dump_generators<W: Write>(g: &Kleinian, w: &mut W) -> io::Result<()> {
+    for &l in &[A, B, AI, BI] {
+        let m = g.mat(l);
+        let [a, b, c, d, tr] = [m.a, m.b, m.c, m.d, m.trace()].map(|z| fmt_complex(z, COMPLEX_PRECISION));
+        writeln!(w, "{} = [{}, {}; {}, {}]  tr = {}", l, a, b, c, d, tr)?;
+    }
+    let commutator = g.word(&Word(COMMUTATORS[1].to_vec()));
+    writeln!(w, "tr(abAB) = {}", fmt_complex(commutator.trace(), COMPLEX_PRECISION))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Letter {
+    A,
+    B,
+    AI,
+    BI,
+}
+
+pub const A: Letter = Letter::A;
+pub const B: Letter = Letter::B;
+pub const AI: Letter = Letter::AI;
+pub const BI: Letter = Letter::BI;
+
+impl Letter {
+    pub fn inv(&self) -> Self {
+        match *self {
+            A => AI,
+            B => BI,
+            AI => A,
+            BI => B,
+        }
+    }
+
+    /// The letter with a and b exchanged.
+    pub fn swapped(&self) -> Self {
+        match *self {
+            A => B,
+            B => A,
+            AI => BI,
+            BI => AI,
+        }
+    }
+
+    /// The letters that may follow this one in a reduced word, in the
+    /// order the traversal visits them.
+    pub fn successors(&self) -> [Letter; 3] {
+        match *self {
+            A => [B, A, BI],
+            B => [AI, B, A],
+            AI => [BI, AI, B],
+            BI => [A, BI, AI],
+        }
+    }
+}
+
+impl fmt::Display for Letter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match *self {
+            A => "a",
+            B => "b",
+            AI => "A",
+            BI => "B",
+        };
+        f.write_str(c)
+    }
+}
+
+/// A word in the generators, written a, b for the generators and A, B for
+/// their inverses.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Word(pub Vec<Letter>);
+
+impl FromStr for Word {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().map(|c| match c {
+            'a' => Ok(A),
+            'b' => Ok(B),
+            'A' => Ok(AI),
+            'B' => Ok(BI),
+            _ => Err(Error::Word { word: s.to_string(), letter: c }),
+        }).collect::<Result<Vec<_>, _>>().map(Word)
+    }
+}
+
+impl Word {
+    /// Whether no letter is followed by its inverse.
+    pub fn is_reduced(&self) -> bool {
+        self.0.windows(2).all(|p| p[1] != p[0].inv())
+    }
+
+    /// All reduced words of length at most `n`, shortest first, starting
+    /// with the empty word.
+    pub fn reduced_up_to(n: usize) -> Vec<Word> {
+        let mut words = vec![Word::default()];
+        let mut frontier = vec![Word::default()];
+        for _ in 0..n {
+            let mut next = Vec::new();
+            for w in &frontier {
+                let letters = match w.0.last() {
+                    Some(l) => l.successors().to_vec(),
+                    None => vec![A, BI, AI, B],
+                };
+                for l in letters {
+                    let mut v = w.0.clone();
+                    v.push(l);
+                    next.push(Word(v));
+                }
+            }
+            words.extend(next.iter().cloned());
+            frontier = next;
+        }
+        words
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for l in &self.0 {
+            write!(f, "{}", l)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bag<T> {
+    pub a: T,
+    pub b: T,
+    pub ainv: T,
+    pub binv: T,
+}
+
+impl<T> Bag<T> {
+    pub fn new(a: T, b: T, ainv: T, binv: T) -> Self {
+        Bag {
+            a,
+            b,
+            ainv,
+            binv,
+        }
+    }
+
+    pub fn at(&self, l: Letter) -> &T {
+        match l {
+            A => &self.a,
+            B => &self.b,
+            AI => &self.ainv,
+            BI => &self.binv,
+        }
+    }
+
+    pub fn at_mut(&mut self, l: Letter) -> &mut T {
+        match l {
+            A => &mut self.a,
+            B => &mut self.b,
+            AI => &mut self.ainv,
+            BI => &mut self.binv,
+        }
+    }
+}
+
+/// A reflection for drawing a mirrored copy of the limit set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    /// Across the real axis, z ↦ z̄: the limit set of the group with
+    /// conjugated traces.
+    Conj,
+    /// In the unit circle, z ↦ 1/z̄.
+    UnitCircle,
+}
+
+impl Mirror {
+    pub fn apply(&self, z: Complex<f64>) -> Complex<f64> {
+        match *self {
+            Mirror::Conj => z.conj(),
+            Mirror::UnitCircle => z / z.norm_sqr(),
+        }
+    }
+
+    /// The map r m r for this reflection r, a Möbius map again since the
+    /// two reflections cancel out each other's orientation reversal.
+    pub fn mirror_mat(&self, m: &Mat) -> Mat {
+        match *self {
+            Mirror::Conj => m.conj(),
+            // 1/z̄ is z ↦ 1/z after conjugation, and z ↦ 1/z swaps the
+            // rows and columns
+            Mirror::UnitCircle => {
+                let m = m.conj();
+                Mat::new(m.d, m.c, m.b, m.a)
+            }
+        }
+    }
+}
+
+impl FromStr for Mirror {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "conj" => Ok(Mirror::Conj),
+            "unit-circle" => Ok(Mirror::UnitCircle),
+            _ => Err(format!("unknown mirror `{}`, expected conj or unit-circle", s)),
+        }
+    }
+}
+
+/// How a traced limit set is turned into an SVG document.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub stroke: String,
+    pub stroke_width: f64,
+    /// The SVG `stroke-linejoin` of the traced curve: `round` by default,
+    /// as a miter join spikes out past the sharp turns at the cusps.
+    pub linejoin: String,
+    /// The SVG `stroke-linecap` of the traced curve, `round` by default.
+    pub linecap: String,
+    pub view_box: (f64, f64, f64, f64),
+    /// When set, each of the four top-level branches gets its own path,
+    /// stroked with the color for its leading letter.
+    pub letter_colors: Option<Bag<String>>,
+    pub overlays: Vec<Overlay>,
+    /// Radius of point markers in overlays, in multiples of `stroke_width`.
+    pub marker_size: f64,
+    pub marker_color: String,
+    /// Draw the sides of the fundamental domain as straight chords rather
+    /// than geodesic arcs.
+    pub chords: bool,
+    pub domain_fill: String,
+    pub domain_opacity: f64,
+    /// Draw the curve as cubic Béziers through the traced points instead of
+    /// line segments. The limit set is fractal, so this is only a nicer
+    /// looking approximation at low depth, not a more exact one.
+    pub smooth: bool,
+    /// Ratio between the stroke widths of successive levels of the Cayley
+    /// tree overlay; below 1 the tree thins away from the root.
+    pub tree_taper: f64,
+    /// Stop tracing after visiting this many branches.
+    pub max_nodes: Option<usize>,
+    /// Stop tracing, as if the node budget had run out, once this is set
+    /// from another thread; it is looked at every `CANCEL_CHECK_INTERVAL`
+    /// branches.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Stop refining a branch once its points are this close together.
+    pub epsilon: f64,
+    /// Scale `epsilon` by this factor for every letter of a branch's word,
+    /// so a branch of depth n stops at `epsilon * factor^n`. Below 1,
+    /// shallow branches stop early and deep ones, which crowd near the
+    /// cusps, are resolved more finely. The node count grows quickly as
+    /// the factor drops, so pair it with `max_nodes`: the trace stops where
+    /// the budget runs out, and the deep branches met late are the first
+    /// to go missing.
+    pub epsilon_decay: Option<f64>,
+    /// Circles in overlays smaller than this are left out.
+    pub min_radius: f64,
+    /// Height of the horoball overlay's circles; see `Mat::horocircles`.
+    pub horoball_height: f64,
+    /// Draw the Apollonian gasket as exact circles instead of the traced
+    /// curve; only meaningful when `Kleinian::is_gasket` holds.
+    pub gasket: bool,
+    /// When nonempty, trace only the words starting with one of these
+    /// reduced words, each drawn as its own path.
+    pub prefixes: Vec<Word>,
+    /// The top-level branches in the order they are traced, each letter
+    /// once; see `check_branch_order`. The default runs around the curve
+    /// one way, and `[B, AI, BI, A]`, or any rotation of it, runs the
+    /// other way, reversing the order at every level, so the same points
+    /// are drawn in reverse. Other orders trace the top-level arcs out of
+    /// turn, with jumps between them.
+    pub branch_order: [Letter; 4],
+    /// Start every branch from this word's matrix instead of the identity,
+    /// so the whole trace is moved by it, in the manner of
+    /// `post_transform` but through the traversal: the curve is traced in
+    /// the moved position and refined to `epsilon` there, rather than
+    /// traced and then mapped. An empty word is the same as none.
+    pub seed_word: Option<Word>,
+    /// Also draw the trace reflected by this, as a path of class "mirror".
+    pub mirror: Option<Mirror>,
+    /// Stroke for the mirrored copy; `stroke` when unset.
+    pub mirror_stroke: Option<String>,
+    /// A Möbius map applied to the traced points as they are drawn, for
+    /// recentering or mapping to the disk; overlays are left as they are.
+    pub post_transform: Option<Mat>,
+    /// Extra attributes for the root element, each written as `data-NAME`.
+    pub metadata: Vec<(String, String)>,
+    /// Leave out the parts of the traced curve outside this window, given
+    /// like `view_box`, cutting segments at its edge.
+    pub clip: Option<(f64, f64, f64, f64)>,
+    /// While tracing, skip the branches whose piece of the curve lies
+    /// outside this window, given like `view_box` in the traced points'
+    /// own coordinates, keeping only where each skipped piece ends, after
+    /// a break. A piece is taken to lie within the bounding box of its
+    /// start and ends grown by twice its own size each way, so the segments in
+    /// the window come out as the whole trace has them; pair it with
+    /// `clip` on the same window. The breaks are non-finite points, which
+    /// the drawing skips.
+    pub cull: Option<(f64, f64, f64, f64)>,
+    /// Round the traced curve's coordinates to this many decimal places,
+    /// for smaller files.
+    pub coordinate_precision: Option<usize>,
+    /// Draw the traced curve as a filled band of this width instead of a
+    /// stroke.
+    pub outline: Option<f64>,
+    /// Draw the traced curve as a mitered ribbon of this width, filled
+    /// even-odd, instead of a stroke; `outline` wins when both are set.
+    pub ribbon_width: Option<f64>,
+    /// Fill the whole viewBox with this color behind everything else.
+    pub background: Option<String>,
+    /// Color the traced curve along its length, running through these
+    /// colors in order from its start to its end. Neighbouring `#rrggbb`
+    /// or `#rgb` colors are blended; other colors change in steps.
+    pub gradient: Option<Vec<String>>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            stroke: "black".to_string(),
+            stroke_width: 0.001,
+            linejoin: "round".to_string(),
+            linecap: "round".to_string(),
+            view_box: (-1.2, -1.2, 2.4, 2.4),
+            letter_colors: None,
+            overlays: Vec::new(),
+            marker_size: 4.0,
+            marker_color: "#d62728".to_string(),
+            chords: false,
+            domain_fill: "#1f77b4".to_string(),
+            domain_opacity: 0.15,
+            smooth: false,
+            tree_taper: 1.0,
+            max_nodes: None,
+            cancel: None,
+            epsilon: EPSILON,
+            epsilon_decay: None,
+            min_radius: EPSILON,
+            horoball_height: 0.5,
+            gasket: false,
+            prefixes: Vec::new(),
+            branch_order: [A, BI, AI, B],
+            seed_word: None,
+            mirror: None,
+            mirror_stroke: None,
+            post_transform: None,
+            metadata: Vec::new(),
+            clip: None,
+            cull: None,
+            coordinate_precision: None,
+            outline: None,
+            ribbon_width: None,
+            background: None,
+            gradient: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// A red/blue/green/orange palette for `letter_colors`.
+    pub fn default_letter_colors() -> Bag<String> {
+        Bag::new("#d62728".to_string(), "#1f77b4".to_string(),
+            "#2ca02c".to_string(), "#ff7f0e".to_string())
+    }
+}
+
+// #[derive(Debug)]
+pub struct Kleinian {
+    mats: Bag<Mat>,
+    points: Vec<Complex<f64>>,
+    // the range of `points` traced by each top-level branch
+    spans: Bag<(usize, usize)>,
+    // the same for each of `RenderOptions::prefixes`, when given
+    prefix_spans: Vec<(usize, usize)>,
+    ends: Bag<Vec<Complex<f64>>>,
+    last: Complex<f64>,
+    // branches visited by the last traversal, and the budget it ran under
+    nodes: usize,
+    max_nodes: Option<usize>,
+    // the flag that stops the traversal early, and whether it has been seen
+    cancel: Option<Arc<AtomicBool>>,
+    cancelled: bool,
+    epsilon: f64,
+    epsilon_decay: Option<f64>,
+    // the window branches off which are skipped; see `RenderOptions::cull`
+    cull: Option<(f64, f64, f64, f64)>,
+    // the longest run of each letter a reduced word may contain; finite
+    // for generators of finite order, whose higher powers repeat
+    max_runs: Bag<usize>,
+    // the word of each point in `points`, recorded only when Some, and the
+    // word of the branch being traced
+    labels: Option<Vec<Word>>,
+    path: Vec<Letter>,
+    // words equal to the identity; a branch whose word ends in one is cut
+    relators: Vec<Vec<Letter>>,
+    // whether the traversal runs against the usual direction
+    reversed: bool,
+    // a map applied to the traced points as they are drawn; see `set_view`
+    view: Option<Mat>,
+    // the branches the node budget cut off, in the order they would have
+    // been traced
+    pending: Vec<Frame>,
+    // where `stream_points_with` takes the points in chunks, and how many
+    // it has taken
+    sink: Option<SyncSender<Vec<Complex<f64>>>>,
+    streamed: usize,
+}
+
+impl Kleinian {
+    pub fn new(a: Mat, b: Mat) -> Kleinian {
+        let (ainv, binv) = (a.adj(), b.adj());
+        let max_runs = Bag::new(max_run(&a, false), max_run(&b, false),
+            max_run(&a, true), max_run(&b, true));
+        let bag = Bag::new(a, b, ainv, binv);
+        let vecs = Bag::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        Kleinian {
+            mats: bag,
+            points: Vec::new(),
+            spans: Bag::new((0, 0), (0, 0), (0, 0), (0, 0)),
+            prefix_spans: Vec::new(),
+            ends: vecs,
+            last: Complex::new(1.0, 0.0),
+            nodes: 0,
+            max_nodes: None,
+            cancel: None,
+            cancelled: false,
+            epsilon: EPSILON,
+            epsilon_decay: None,
+            cull: None,
+            max_runs,
+            labels: None,
+            path: Vec::new(),
+            relators: Vec::new(),
+            reversed: false,
+            view: None,
+            pending: Vec::new(),
+            sink: None,
+            streamed: 0,
+        }
+    }
+
+    /// The group generated by `a` and `b`, with the fixed point of each
+    /// generator and its inverse added as an end, as the command line
+    /// does, so it is ready to trace.
+    pub fn from_generators(a: Mat, b: Mat) -> Kleinian {
+        let mut g = Kleinian::new(a, b);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g
+    }
+
+    pub fn mat(&self, l: Letter) -> &Mat {
+        self.mats.at(l)
+    }
+
+    pub fn prod(&self, word: Vec<Letter>) -> Mat {
+        word.iter().fold(Mat::id(), |acc, &l| acc * self.mat(l))
+    }
+
+    pub fn word(&self, w: &Word) -> Mat {
+        self.prod(w.0.clone())
+    }
+
+    pub fn add_end(&mut self, word: Vec<Letter>) {
+        // be careful to add ends in the correct order!
+        if let Some(&l) = word.last() {
+            let z = self.prod(word).fix();
+            self.ends.at_mut(l).push(z);
+        } else {
+            panic!("can't add the fixed point of the identity");
+        }
+    }
+
+    /// How many branches the last traversal visited.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Declares that `r` is the identity in this group, so the traversal
+    /// cuts every branch whose word ends in `r`, its inverse, or a cyclic
+    /// shift of either: past that point the word is equal to a shorter
+    /// one, and the branch retraces ground covered elsewhere. This is a
+    /// cheap heuristic, not a solution of the word problem (no Todd–Coxeter
+    /// enumeration is done), so branches that are equal only through
+    /// combinations of relators are still traced, and a cut can leave a
+    /// visible gap in the curve where the shorter word's branch lies far
+    /// away in traversal order. `r` should be reduced and nonempty.
+    pub fn with_relator(mut self, r: Word) -> Self {
+        let inverse: Vec<Letter> = r.0.iter().rev().map(Letter::inv).collect();
+        for word in [r.0, inverse] {
+            for shift in 0..word.len() {
+                let mut rotated = word.clone();
+                rotated.rotate_left(shift);
+                if !self.relators.contains(&rotated) {
+                    self.relators.push(rotated);
+                }
+            }
+        }
+        self
+    }
+
+    /// Whether the last traversal was cut short by its node budget or by
+    /// `RenderOptions::cancel`.
+    pub fn exhausted(&self) -> bool {
+        self.cancelled || self.max_nodes.is_some_and(|max| self.nodes > max)
+    }
+
+    /// Whether the last traversal was cancelled.
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Traces the limit set at decreasing depth, starting from `start_depth`,
+    /// until a traversal fits within `max_nodes`. Returns the document and
+    /// the depth actually used.
+    pub fn render_within_budget(&mut self, max_nodes: usize, start_depth: i64) -> (Document, i64) {
+        let opts = RenderOptions {
+            max_nodes: Some(max_nodes),
+            ..RenderOptions::default()
+        };
+        let mut depth = start_depth;
+        loop {
+            limitset_with(depth, self, &opts);
+            if !self.exhausted() || depth <= 1 {
+                return (self.document(&opts), depth);
+            }
+            depth -= 1;
+        }
+    }
+
+    pub fn points(&self) -> &[Complex<f64>] {
+        &self.points
+    }
+
+    /// The view set by `set_view`, if any.
+    pub fn view(&self) -> Option<Mat> {
+        self.view.clone()
+    }
+
+    /// Draws the trace, from now on, through the Möbius map `view`, after
+    /// any `RenderOptions::post_transform`, so panning or zooming around a
+    /// trace only maps its points again rather than retracing. `document`
+    /// then draws what it would with the composite as the post-transform.
+    /// `None` goes back to drawing the points as traced.
+    pub fn set_view(&mut self, view: Option<Mat>) {
+        self.view = view;
+    }
+
+    /// The extent (min x, min y, max x, max y) of the finite traced points.
+    pub fn bounding_box(&self) -> (f64, f64, f64, f64) {
+        extent(&self.points)
+    }
+
+    /// Traces to `level` and draws the limit set through the rotation of
+    /// the Riemann sphere taking `cusp` to 0, scaled so that around `cusp`
+    /// the picture is `zoom` times the size of the plain one; the point
+    /// opposite `cusp` goes to ∞. Epsilon shrinks by the same factor, so
+    /// the detail near the cusp holds up, within a budget of
+    /// `CENTERED_MAX_NODES`. The view box is centred on 0 and fitted to the
+    /// points, but no wider than the default one. The points are kept as
+    /// traced, with the map left set as this group's view.
+    pub fn render_centered_on(&mut self, cusp: Complex<f64>, zoom: f64, level: i64) -> Document {
+        // (z - c)/(1 + c̄z) has derivative 1/(1 + |c|²) at c
+        let scale = zoom * (1.0 + cusp.norm_sqr());
+        let one = Complex::new(1.0, 0.0);
+        let view = Mat::new(one * scale, -cusp * scale, cusp.conj(), one);
+        let default = RenderOptions::default();
+        let mut opts = RenderOptions {
+            epsilon: default.epsilon / scale,
+            max_nodes: Some(CENTERED_MAX_NODES),
+            ..RenderOptions::default()
+        };
+        limitset_with(level, self, &opts);
+        let (x0, y0, x1, y1) = extent(&view.mob_batch(&self.points));
+        let widest = default.view_box.2 / 2.0;
+        let half = x0.abs().max(y0.abs()).max(x1.abs()).max(y1.abs());
+        let half = if half.is_finite() && half > 0.0 { half.min(widest) } else { widest };
+        let half = 1.05 * half;
+        opts.view_box = (-half, -half, 2.0 * half, 2.0 * half);
+        opts.stroke_width = default.stroke_width * half / widest;
+        self.set_view(Some(view));
+        self.document(&opts)
+    }
+
+    /// Traces to `level` and draws the limit set twice: first as usual,
+    /// then in `DUAL_STROKE`, as a path of class "dual", traced by the
+    /// same group with a and b exchanged, their ends and relators swapped
+    /// to match. Exchanging the generators is an automorphism of the free
+    /// group on a and b, so the group, and its limit set, are the same;
+    /// only the marking changes. The commutator becomes baBA, which is
+    /// abAB inverted and conjugated, so its fixed point is the same cusp,
+    /// and the words of the dual's traversal are those of this group's
+    /// with the letters exchanged. The dual's traversal therefore meets
+    /// the points of the set in a different order, and it gets to
+    /// different points by the time a branch is cut off. Where the two
+    /// curves disagree, the trace has not settled at this depth and
+    /// epsilon.
+    pub fn render_with_dual(&mut self, level: i64) -> Document {
+        let mut dual = Kleinian::new(self.mat(B).clone(), self.mat(A).clone());
+        for &l in &[A, B, AI, BI] {
+            *dual.ends.at_mut(l.swapped()) = self.ends.at(l).clone();
+        }
+        dual.relators = self.relators.iter()
+            .map(|r| r.iter().map(Letter::swapped).collect())
+            .collect();
+        let opts = RenderOptions::default();
+        limitset_with(level, self, &opts);
+        limitset_with(level, &mut dual, &opts);
+        let points = match self.view {
+            Some(ref v) => v.mob_batch(&dual.points),
+            None => dual.points,
+        };
+        self.document(&opts)
+            .add(stroke_path(&points, DUAL_STROKE, &opts).set("class", "dual"))
+    }
+
+    /// The limit set traced to each depth of `layers`, one path per layer
+    /// in the order given, with the layer's opacity as its
+    /// `stroke-opacity`: faint shallow traces under a bold deep one show
+    /// how the curve converges. Each layer is traced by `limitset_with`
+    /// under `opts` and drawn in `opts.stroke`; letter colors, gradients
+    /// and overlays are left to `document`. The group's ends must have
+    /// been added, as for `limitset`; the points of earlier traces are
+    /// left alone.
+    ///
+    /// # Panics
+    ///
+    /// As `limitset_with` does.
+    pub fn render_layers(&self, layers: &[(i64, f64)], opts: &RenderOptions) -> Document {
+        let viewed = self.viewed(opts);
+        let mut document = document_base(&viewed);
+        let mut g = Kleinian::new(self.mat(A).clone(), self.mat(B).clone());
+        g.ends = self.ends.clone();
+        g.relators = self.relators.clone();
+        for &(depth, opacity) in layers {
+            limitset_with(depth, &mut g, opts);
+            document = document.add(stroke_path(&g.points, &viewed.stroke, &viewed)
+                .set("stroke-opacity", opacity)
+                .set("class", format!("depth-{}", depth)));
+        }
+        document
+    }
+
+    // `opts` with the view, if any, folded into its post-transform
+    fn viewed<'a>(&self, opts: &'a RenderOptions) -> Cow<'a, RenderOptions> {
+        match self.view {
+            Some(ref v) => {
+                let post_transform = Some(match opts.post_transform {
+                    Some(ref m) => v * m,
+                    None => v.clone(),
+                });
+                Cow::Owned(RenderOptions { post_transform, ..opts.clone() })
+            }
+            None => Cow::Borrowed(opts),
+        }
+    }
+
+    /// Traces to `level` and sums the lengths of the segments between
+    /// finite points. The limit curve is usually not rectifiable, so this
+    /// keeps growing with depth.
+    pub fn limit_set_length(&mut self, level: i64) -> f64 {
+        limitset(level, self);
+        self.points.windows(2)
+            .filter(|p| p[0].is_finite() && p[1].is_finite())
+            .map(|p| (p[1] - p[0]).norm())
+            .sum()
+    }
+
+    /// Traces the limit set and maps it affinely into [-1, 1]², centered
+    /// and with its aspect ratio kept, so the longer side spans the square.
+    /// Points that aren't finite, such as one at ∞, are left out; if the
+    /// rest all coincide, they go to the origin.
+    pub fn scale_to_fit(&mut self, level: i64) -> Vec<(f64, f64)> {
+        limitset(level, self);
+        fit_square(&self.points)
+    }
+
+    /// Traces to `level` like `limitset`, also recording for each point
+    /// the reduced word of the branch that drew it, of length at most
+    /// `level`; the starting point gets the empty word. This keeps a word
+    /// per point, so it needs much more memory than a plain trace.
+    pub fn limit_set_labeled(&mut self, level: i64) -> Vec<((f64, f64), Word)> {
+        self.labels = Some(Vec::new());
+        limitset(level, self);
+        let labels = self.labels.take().expect("labels were switched on");
+        self.points.iter().map(|z| (z.re, z.im)).zip(labels).collect()
+    }
+
+    /// Whether later traces record the word of each point, as
+    /// `limit_set_labeled` does, for `point_records`.
+    pub fn set_record_words(&mut self, on: bool) {
+        self.labels = if on { Some(Vec::new()) } else { None };
+    }
+
+    fn line(&mut self, z: Complex<f64>) {
+        self.points.push(z);
+        self.last = z;
+        if let Some(ref mut labels) = self.labels {
+            labels.push(Word(self.path.clone()));
+        }
+        if self.sink.is_some() && self.points.len() >= STREAM_CHUNK {
+            self.flush_sink();
+        }
+    }
+
+    // breaks the path at a branch skipped by `RenderOptions::cull` and
+    // picks it up again at `z`, where the branch ends; a run of skipped
+    // branches leaves just the last one's end
+    fn skip_to(&mut self, z: Complex<f64>) {
+        let n = self.points.len();
+        if n >= 2 && !self.points[n - 2].is_finite() {
+            self.points[n - 1] = z;
+            self.last = z;
+            if let Some(ref mut labels) = self.labels {
+                labels[n - 1] = Word(self.path.clone());
+            }
+        } else {
+            self.line(Complex::new(f64::NAN, f64::NAN));
+            self.line(z);
+        }
+    }
+
+    // hands the points traced so far to the sink; if nobody is taking
+    // them any more, the traversal stops as if cancelled
+    fn flush_sink(&mut self) {
+        if let Some(ref sink) = self.sink {
+            let chunk = std::mem::take(&mut self.points);
+            self.streamed += chunk.len();
+            if sink.send(chunk).is_err() {
+                self.cancelled = true;
+            }
+        }
+    }
+
+    pub fn document(&self, opts: &RenderOptions) -> Document {
+        let opts = &*self.viewed(opts);
+        let mut document = document_base(opts);
+        match opts.letter_colors {
+            _ if opts.gasket => {
+                document = document.add(gasket::draw(self, opts));
+            }
+            _ if opts.gradient.as_ref().is_some_and(|c| !c.is_empty()) => {
+                let colors = opts.gradient.as_ref().expect("checked above");
+                document = document.add(gradient_group(&self.points, colors, opts));
+            }
+            // traced under prefixes, so `spans` is empty; one prefix keeps
+            // the plain stroke, several get a color each
+            _ if !self.prefix_spans.is_empty() => {
+                let single = self.prefix_spans.len() == 1;
+                for (n, &(start, end)) in self.prefix_spans.iter().enumerate() {
+                    let color = if single { &opts.stroke } else { overlay::PALETTE[n % overlay::PALETTE.len()] };
+                    document = document.add(stroke_path(&self.points[start..end], color, opts));
+                }
+            }
+            Some(ref colors) => {
+                for &l in &[A, BI, AI, B] {
+                    let (start, end) = *self.spans.at(l);
+                    // start one point early so the branches join up
+                    let pts = &self.points[start.saturating_sub(1)..end];
+                    document = document.add(stroke_path(pts, colors.at(l), opts));
+                }
+            }
+            None => {
+                document = document.add(stroke_path(&self.points, &opts.stroke, opts));
+            }
+        }
+        if let Some(mirror) = opts.mirror {
+            let reflected: Vec<_> = self.points.iter().map(|&z| mirror.apply(z)).collect();
+            let stroke = opts.mirror_stroke.as_ref().unwrap_or(&opts.stroke);
+            document = document.add(stroke_path(&reflected, stroke, opts).set("class", "mirror"));
+        }
+        for overlay in &opts.overlays {
+            document = document.add(overlay::draw(overlay, self, opts));
+        }
+        document
+    }
+
+    pub fn to_svg_string(&self, opts: &RenderOptions) -> String {
+        let mut buf = Vec::new();
+        svg::write(&mut buf, &self.document(opts)).expect("writing to a Vec can't fail");
+        String::from_utf8(buf).expect("svg output is valid utf-8")
+    }
+}
+
+/// How many pieces a gradient stroke is cut into, each in one color.
+pub const GRADIENT_STEPS: usize = 256;
+
+// the extent (min x, min y, max x, max y) of the finite points in `points`
+pub(crate) fn extent(points: &[Complex<f64>]) -> (f64, f64, f64, f64) {
+    let inf = f64::INFINITY;
+    points.iter().filter(|z| z.is_finite())
+        .fold((inf, inf, -inf, -inf), |(x0, y0, x1, y1), z| {
+            (x0.min(z.re), y0.min(z.im), x1.max(z.re), y1.max(z.im))
+        })
+}
+
+// `scale_to_fit`'s map of the finite points into [-1, 1]²
+fn fit_square(points: &[Complex<f64>]) -> Vec<(f64, f64)> {
+    let (x0, y0, x1, y1) = extent(points);
+    let (cx, cy) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+    let half = (x1 - x0).max(y1 - y0) / 2.0;
+    let finite = points.iter().filter(|z| z.is_finite());
+    if half > 0.0 {
+        // clamped against rounding at the edges
+        finite.map(|z| (((z.re - cx) / half).clamp(-1.0, 1.0), ((z.im - cy) / half).clamp(-1.0, 1.0))).collect()
+    } else {
+        finite.map(|_| (0.0, 0.0)).collect()
+    }
+}
+
+/// Parses `#rrggbb` or `#rgb`.
+pub(crate) fn hex_rgb(color: &str) -> Option<[f64; 3]> {
+    let hex = color.strip_prefix('#')?;
+    let digits: Vec<u8> = hex.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect::<Option<_>>()?;
+    let channels: Vec<u8> = match digits.len() {
+        3 => digits.iter().map(|&d| d * 17).collect(),
+        6 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+        _ => return None,
+    };
+    Some([channels[0] as f64, channels[1] as f64, channels[2] as f64])
+}
+
+/// The color a fraction `u` in [0, 1] of the way through `colors`.
+fn gradient_color(colors: &[String], u: f64) -> String {
+    let x = u * (colors.len() - 1) as f64;
+    let n = (x.floor() as usize).min(colors.len().saturating_sub(2));
+    let (from, to) = (&colors[n], &colors[(n + 1).min(colors.len() - 1)]);
+    let f = x - n as f64;
+    match (hex_rgb(from), hex_rgb(to)) {
+        (Some(a), Some(b)) => {
+            let mix = |i: usize| (a[i] + (b[i] - a[i]) * f).round() as u8;
+            format!("#{:02x}{:02x}{:02x}", mix(0), mix(1), mix(2))
+        }
+        _ if f < 0.5 => from.clone(),
+        _ => to.clone(),
+    }
+}
+
+/// The curve cut into `GRADIENT_STEPS` pieces by point index, each with
+/// its color along `colors`.
+pub(crate) fn gradient_pieces<'a>(points: &'a [Complex<f64>], colors: &[String]) -> Vec<(&'a [Complex<f64>], String)> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+    let steps = GRADIENT_STEPS.min(points.len() - 1).max(1);
+    (0..steps).map(|k| {
+        // neighbouring pieces share an end point so the curve stays joined
+        let start = k * (points.len() - 1) / steps;
+        let end = ((k + 1) * (points.len() - 1) / steps + 1).min(points.len());
+        let u = (k as f64 + 0.5) / steps as f64;
+        (&points[start..end], gradient_color(colors, u))
+    }).collect()
+}
+
+/// The gradient pieces of the curve, in a group of class "gradient".
+fn gradient_group(points: &[Complex<f64>], colors: &[String], opts: &RenderOptions) -> Group {
+    gradient_pieces(points, colors).into_iter()
+        .fold(Group::new().set("class", "gradient"), |group, (piece, color)| {
+            group.add(stroke_path(piece, &color, opts))
+        })
+}
+
+/// The pieces of `points` as they are drawn: mapped by the post-transform,
+/// broken at non-finite points, clipped and rounded as `opts` asks.
+pub(crate) fn drawn_runs(points: &[Complex<f64>], opts: &RenderOptions) -> Vec<Vec<Complex<f64>>> {
+    let transformed;
+    let points = match opts.post_transform {
+        Some(ref m) => {
+            transformed = post_transform(m, points, opts.view_box);
+            &transformed[..]
+        }
+        None => points,
+    };
+    let mut runs = Vec::new();
+    // non-finite points, like those clipped near a pole, break the path
+    for run in points.split(|z| !z.is_finite()).filter(|run| !run.is_empty()) {
+        match opts.clip {
+            Some(window) => runs.extend(clip::clip_polyline(run, window)),
+            None => runs.push(run.to_vec()),
+        }
+    }
+    if let Some(digits) = opts.coordinate_precision {
+        for run in &mut runs {
+            *run = round_points(run, digits);
+        }
+    }
+    runs
+}
+
+// the document everything is drawn into: the view box, the post-transform
+// and metadata as attributes, and the background
+fn document_base(opts: &RenderOptions) -> Document {
+    let mut document = Document::new()
+        .set("viewBox", opts.view_box);
+    if let Some(ref m) = opts.post_transform {
+        document = document.set("data-post-transform", [m.a, m.b, m.c, m.d].map(|z| fmt_complex(z, COMPLEX_PRECISION)).join(","));
+    }
+    for (name, value) in &opts.metadata {
+        document = document.set(format!("data-{}", name), value.as_str());
+    }
+    if let Some(ref color) = opts.background {
+        document = document.add(background(opts.view_box, color));
+    }
+    document
+}
+
+fn stroke_path(points: &[Complex<f64>], stroke: &str, opts: &RenderOptions) -> Path {
+    let mut data = Data::new();
+    for run in drawn_runs(points, opts) {
+        data = if let Some(width) = opts.outline {
+            outline::outline_data(data, &run, width / 2.0)
+        } else if let Some(width) = opts.ribbon_width {
+            outline::ribbon_data(data, &run, width / 2.0)
+        } else if opts.smooth {
+            smooth_data(data, &run)
+        } else {
+            polyline_data(data, &run)
+        };
+    }
+    if opts.outline.is_some() {
+        return Path::new()
+            .set("fill", stroke)
+            .set("fill-rule", "nonzero")
+            .set("stroke", "none")
+            .set("d", data);
+    }
+    if opts.ribbon_width.is_some() {
+        return Path::new()
+            .set("fill", stroke)
+            .set("fill-rule", "evenodd")
+            .set("stroke", "none")
+            .set("d", data);
+    }
+    Path::new()
+        .set("fill", "none")
+        .set("stroke", stroke)
+        .set("stroke-width", opts.stroke_width)
+        .set("stroke-linejoin", opts.linejoin.as_str())
+        .set("stroke-linecap", opts.linecap.as_str())
+        .set("d", data)
+}
+
+// Rounds to `digits` decimal places, dropping points that then repeat.
+fn round_points(points: &[Complex<f64>], digits: usize) -> Vec<Complex<f64>> {
+    let scale = 10f64.powi(digits as i32);
+    let mut rounded: Vec<Complex<f64>> = points.iter()
+        .map(|z| Complex::new((z.re * scale).round() / scale, (z.im * scale).round() / scale))
+        .collect();
+    rounded.dedup();
+    rounded
+}
+
+// Points sent more than a few viewBox widths away, as happens near the
+// pole, become NaN so the path breaks there instead of streaking across.
+fn post_transform(m: &Mat, points: &[Complex<f64>], (x, y, w, h): (f64, f64, f64, f64)) -> Vec<Complex<f64>> {
+    let center = Complex::new(x + w / 2.0, y + h / 2.0);
+    let limit = 4.0 * w.max(h);
+    let mut mapped = m.mob_batch(points);
+    for z in &mut mapped {
+        if !z.is_finite() || (*z - center).norm() > limit {
+            *z = Complex::new(f64::NAN, f64::NAN);
+        }
+    }
+    mapped
+}
+
+fn polyline_data(mut data: Data, points: &[Complex<f64>]) -> Data {
+    for (n, z) in points.iter().enumerate() {
+        data = if n == 0 {
+            data.move_to((z.re, z.im))
+        } else {
+            data.line_to((z.re, z.im))
+        };
+    }
+    data
+}
+
+// Catmull-Rom through the points, written as cubic Béziers
+fn smooth_data(mut data: Data, points: &[Complex<f64>]) -> Data {
+    if let Some(z) = points.first() {
+        data = data.move_to((z.re, z.im));
+    }
+    let n = points.len();
+    for i in 1..n {
+        let p0 = points[i.saturating_sub(2)];
+        let (p1, p2) = (points[i - 1], points[i]);
+        let p3 = points[(i + 1).min(n - 1)];
+        let c1 = p1 + (p2 - p0) / 6.0;
+        let c2 = p2 - (p3 - p1) / 6.0;
+        data = data.cubic_curve_to((c1.re, c1.im, c2.re, c2.im, p2.re, p2.im));
+    }
+    data
+}
+
+/// The longest run of `m` (or its inverse) worth following. A generator of
+/// order `n` needs only the powers `-(⌈n/2⌉ - 1)..=⌊n/2⌋`; the others are
+/// the same transformations again. For order 2 that leaves the inverse no
+/// run at all, as it is the generator itself and would only trace the
+/// generator's branches a second time.
+fn max_run(m: &Mat, inverse: bool) -> usize {
+    match m.elliptic_order() {
+        Some(n) if inverse => n.div_ceil(2) - 1,
+        Some(n) => n / 2,
+        None => usize::MAX,
+    }
+}
+
+/// The run length after stepping from the end of a run of `run` copies of
+/// `l` onto `next`, or `None` if that passes the letter's `max_runs`.
+pub(crate) fn run_after(max_runs: &Bag<usize>, l: Letter, next: Letter, run: usize) -> Option<usize> {
+    if next != l {
+        Some(1).filter(|_| *max_runs.at(next) > 0)
+    } else if run < *max_runs.at(l) {
+        Some(run + 1)
+    } else {
+        None
+    }
+}
+
+/// A rectangle filling `view_box` with `color`, of class "background".
+pub(crate) fn background(view_box: (f64, f64, f64, f64), color: &str) -> Rectangle {
+    let (x, y, w, h) = view_box;
+    Rectangle::new()
+        .set("class", "background")
+        .set("x", x)
+        .set("y", y)
+        .set("width", w)
+        .set("height", h)
+        .set("fill", color)
+}
+
+fn branch(level: i64, l: Letter, run: usize, t: &Mat, g: &mut Kleinian) {
+
+    g.nodes += 1;
+    if g.nodes.is_multiple_of(CANCEL_CHECK_INTERVAL) {
+        // once seen, the cancellation stays, so everything after this
+        // branch is left pending like a spent budget
+        g.cancelled |= g.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed));
+    }
+    if g.exhausted() {
+        let path = g.path.clone();
+        g.pending.push(Frame { level, letter: l, run, t: t.clone(), path, start: None });
+        return;
+    }
+
+    let [l1, l2, l3] = successors(l, g.reversed);
+
+    g.path.push(l);
+    if g.relators.iter().any(|r| g.path.ends_with(r)) {
+        g.path.pop();
+        return;
+    }
+    let t = t * g.mat(l);
+    let epsilon = branch_epsilon(g.epsilon, g.epsilon_decay, g.path.len());
+    if let Some(window) = g.cull {
+        let ends = g.ends.at(l);
+        if off_window(&t, ends, g.last, epsilon, window) {
+            let end = t.mob(*ends.last().expect("every branch has an end"));
+            g.skip_to(end);
+            g.path.pop();
+            return;
+        }
+    }
+    let mut z = g.last;
+    let mut end_branch = true;
+
+    let mut to_draw = Vec::new();
+
+    for &pt in g.ends.at(l) {
+        let w = t.mob(pt);
+        if level > 0 && (z - w).norm_sqr() > epsilon * epsilon {
+
+            end_branch = false;
+            break;
+        }
+        to_draw.push(w);
+        z = w;
+    }
+
+    if end_branch {
+        for w in to_draw {
+            g.line(w);
+        }
+    } else {
+        for &next in &[l1, l2, l3] {
+            if let Some(run) = run_after(&g.max_runs, l, next, run) {
+                branch(level - 1, next, run, &t, g);
+            }
+        }
+    }
+    g.path.pop();
+}
+
+// Whether the piece of the curve under the branch with matrix `t`, running
+// from `start` through the images of `ends`, lies outside the window: it is
+// taken to lie within their bounding box grown by twice its size each way.
+// The growth is at least `epsilon`, so the segment joining the piece's end
+// to the next one's first point stays outside too; the end the skip leaves
+// may differ in its last bits from the one a full trace draws.
+fn off_window(
+    t: &Mat,
+    ends: &[Complex<f64>],
+    start: Complex<f64>,
+    epsilon: f64,
+    (x, y, w, h): (f64, f64, f64, f64),
+) -> bool {
+    let (mut x0, mut y0, mut x1, mut y1) = (start.re, start.im, start.re, start.im);
+    for &pt in ends {
+        let z = t.mob(pt);
+        if !z.is_finite() {
+            return false;
+        }
+        x0 = x0.min(z.re);
+        y0 = y0.min(z.im);
+        x1 = x1.max(z.re);
+        y1 = y1.max(z.im);
+    }
+    let grow = (2.0 * (x1 - x0).max(y1 - y0)).max(epsilon);
+    x1 + grow < x || x0 - grow > x + w || y1 + grow < y || y0 - grow > y + h
+}
+
+/// The commutator and its cyclic conjugates; their fixed points are the
+/// parabolic points where consecutive branches of the limit set meet.
+pub const COMMUTATORS: [[Letter; 4]; 4] = [
+    [BI, AI, B, A],
+    [A, BI, AI, B],
+    [B, A, BI, AI],
+    [AI, B, A, BI],
+];
+
+/// Checks that `order` is a permutation of the four letters, as
+/// `RenderOptions::branch_order` must be.
+pub fn check_branch_order(order: &[Letter; 4]) -> Result<(), String> {
+    for &l in &[A, B, AI, BI] {
+        if !order.contains(&l) {
+            let order: String = order.iter().map(|l| l.to_string()).collect();
+            return Err(format!("branch order `{}` is missing `{}`, expected each of a, b, A and B once", order, l));
+        }
+    }
+    Ok(())
+}
+
+// whether `order` runs around the cycle A, BI, AI, B backwards
+pub(crate) fn reverses(order: &[Letter; 4]) -> bool {
+    let backwards = [B, AI, BI, A];
+    (0..4).any(|k| (0..4).all(|n| order[n] == backwards[(n + k) % 4]))
+}
+
+// the epsilon that stops a branch whose word has `depth` letters
+pub(crate) fn branch_epsilon(epsilon: f64, decay: Option<f64>, depth: usize) -> f64 {
+    match decay {
+        Some(factor) => epsilon * factor.powi(depth as i32),
+        None => epsilon,
+    }
+}
+
+// `l.successors()`, or backwards for a reversed traversal
+pub(crate) fn successors(l: Letter, reversed: bool) -> [Letter; 3] {
+    let [l1, l2, l3] = l.successors();
+    if reversed { [l3, l2, l1] } else { [l1, l2, l3] }
+}
+
+pub fn limitset(level: i64, g: &mut Kleinian) {
+    limitset_with(level, g, &RenderOptions::default());
+}
+
+// The branch under l first follows the first successor at every step, so
+// it starts at the attracting fixed point of that four-letter cycle.
+pub(crate) fn first_cycle(l: Letter) -> Vec<Letter> {
+    cycle_from(l, false)
+}
+
+// `first_cycle`, for a traversal that may be reversed
+fn cycle_from(l: Letter, reversed: bool) -> Vec<Letter> {
+    let mut cycle = vec![l];
+    for _ in 0..3 {
+        let next = successors(cycle[cycle.len() - 1], reversed)[0];
+        cycle.push(next);
+    }
+    cycle
+}
+
+/// A rough guess at the branches a trace to `level` at `epsilon` visits:
+/// the 4·3ⁿ⁻¹ branches of the words of length n = `level` when epsilon
+/// stops none of them, and for a `discrete` group, whose branches shrink,
+/// a power law fitted to real traces when it stops most. A group not
+/// known to be discrete gets the whole tree: one that isn't has branches
+/// that never shrink, so epsilon stops none of them and the trace visits
+/// every word up to the level.
+pub fn estimate_nodes(level: i64, epsilon: f64, discrete: bool) -> f64 {
+    let tree = 4.0 * 3f64.powf((level - 1).max(0) as f64);
+    if !discrete {
+        return tree;
+    }
+    let spread = NODE_ESTIMATE_SCALE * epsilon.powf(-NODE_ESTIMATE_EXPONENT);
+    tree.min(spread)
+}
+
+/// Refuses a trace without a node budget that `estimate_nodes` expects to
+/// visit more than `ceiling` branches, which would take hours and more
+/// memory than there is, rather than let it start. With `max_nodes` given
+/// the budget bounds the trace and anything goes.
+///
+/// Only for a `discrete` group, as `Kleinian::is_known_discrete` tells, is
+/// a deep level let through on the strength of epsilon; any other group
+/// is refused past a level of about 19, where its tree passes the default
+/// ceiling, unless it is given a budget.
+pub fn check_trace_size(level: i64, epsilon: f64, discrete: bool, max_nodes: Option<usize>, ceiling: usize) -> Result<(), Error> {
+    let estimate = estimate_nodes(level, epsilon, discrete);
+    if max_nodes.is_none() && estimate > ceiling as f64 {
+        return Err(Error::TooLarge { level, epsilon, estimate, ceiling });
+    }
+    Ok(())
+}
+
+/// Traces the limit set afresh, replacing any earlier trace, under the
+/// traversal settings in `opts`.
+///
+/// # Panics
+///
+/// If `opts.branch_order` is not a permutation of the four letters.
+pub fn limitset_with(level: i64, g: &mut Kleinian, opts: &RenderOptions) {
+    if let Err(e) = check_branch_order(&opts.branch_order) {
+        panic!("{}", e);
+    }
+    let reversed = reverses(&opts.branch_order);
+    let one = Complex::new(1.0, 0.0);
+    let seed = opts.seed_word.as_ref().map_or_else(Mat::id, |w| g.word(w));
+    g.reversed = reversed;
+    g.points.clear();
+    let branches = 4.0 * 3f64.powi((level - 1).clamp(0, 64) as i32);
+    let budget = opts.max_nodes.map_or(f64::INFINITY, |n| n as f64);
+    g.points.reserve(branches.min(budget).min(MAX_RESERVED_POINTS as f64) as usize);
+    g.last = one;
+    g.nodes = 0;
+    g.max_nodes = opts.max_nodes;
+    g.cancel = opts.cancel.clone();
+    g.cancelled = false;
+    g.epsilon = opts.epsilon;
+    g.epsilon_decay = opts.epsilon_decay;
+    g.cull = opts.cull;
+    g.pending.clear();
+    g.streamed = 0;
+    let ends = add_trace_ends(g);
+
+    g.spans = Bag::new((0, 0), (0, 0), (0, 0), (0, 0));
+    g.prefix_spans.clear();
+    g.path.clear();
+    if let Some(ref mut labels) = g.labels {
+        labels.clear();
+    }
+    if opts.prefixes.is_empty() {
+        let start = trace_start(g, &opts.branch_order);
+        g.line(seed.mob(start));
+        for &l in &opts.branch_order {
+            if *g.max_runs.at(l) == 0 {
+                continue;
+            }
+            let start = g.points.len();
+            branch(level - 1, l, 1, &seed, g);
+            *g.spans.at_mut(l) = (start, g.points.len());
+        }
+    }
+    for w in &opts.prefixes {
+        let (&l, init) = w.0.split_last().expect("prefixes are nonempty");
+        let t = &seed * &g.prod(init.to_vec());
+        let start = g.points.len();
+        let first = t.mob(g.prod(cycle_from(l, reversed)).fix());
+        let run = w.0.iter().rev().take_while(|&&m| m == l).count();
+        let level = level - w.0.len() as i64;
+        run_frame(g, Frame { level, letter: l, run, t, path: init.to_vec(), start: Some(first) });
+        g.prefix_spans.push((start, g.points.len()));
+    }
+    g.ends = ends;
+    if g.cancelled {
+        log::debug!("cancelled after {} branches", g.nodes);
+    } else if !g.pending.is_empty() {
+        log::debug!("the budget of {} branches ran out with {} left unvisited",
+            g.nodes, g.pending.len());
+    }
+    log::info!("traced {} points over {} branches to level {} at epsilon {}",
+        g.streamed + g.points.len(), g.nodes, level, opts.epsilon);
+}
+
+// The default order starts at 1, as it always has; others start where
+// their first branch does.
+pub(crate) fn trace_start(g: &Kleinian, order: &[Letter; 4]) -> Complex<f64> {
+    if *order == [A, BI, AI, B] {
+        Complex::new(1.0, 0.0)
+    } else {
+        g.prod(cycle_from(order[0], reverses(order))).fix()
+    }
+}
+
+// The caller's ends with those every trace needs added, for a traversal in
+// the direction `reversed`.
+pub(crate) fn trace_ends(g: &Kleinian, reversed: bool) -> Bag<Vec<Complex<f64>>> {
+    let mut ends = g.ends.clone();
+    //add the end of each segment as an end
+    for w in COMMUTATORS.iter() {
+        // each branch of a reversed traversal runs from its end to its
+        // start, which is the fixed point of the reversed commutator
+        let word: Vec<Letter> = if reversed { w.iter().rev().copied().collect() } else { w.to_vec() };
+        ends.at_mut(word[3]).push(g.prod(word).fix());
+    }
+    if reversed {
+        // the points inside each branch come in the other order, the end
+        // staying last
+        for &l in &[A, B, AI, BI] {
+            let ends = ends.at_mut(l);
+            let n = ends.len();
+            ends[..n - 1].reverse();
+        }
+    }
+    ends
+}
+
+// Adds the ends every trace needs besides the caller's, for the direction
+// `g.reversed`, returning the caller's to be put back afterwards.
+fn add_trace_ends(g: &mut Kleinian) -> Bag<Vec<Complex<f64>>> {
+    let ends = trace_ends(g, g.reversed);
+    std::mem::replace(&mut g.ends, ends)
+}
+
+// Traces the branch `frame` stands for, from where its path leaves off;
+// once the budget has run out it is only queued in `g.pending`.
+pub(crate) fn run_frame(g: &mut Kleinian, frame: Frame) {
+    if g.exhausted() {
+        g.pending.push(frame);
+        return;
+    }
+    g.path = frame.path;
+    if let Some(start) = frame.start {
+        g.line(start);
+    }
+    branch(frame.level, frame.letter, frame.run, &frame.t, g);
+    g.path.clear();
+}
+
+/// Whether two polylines agree point by point to within `eps`. On a mismatch
+/// the first differing index is logged as a warning, so a failing comparison
+/// says where the renders diverged.
+pub fn polylines_approx_equal(a: &[(f64, f64)], b: &[(f64, f64)], eps: f64) -> bool {
+    if a.len() != b.len() {
+        log::warn!("polylines differ in length: {} vs {}", a.len(), b.len());
+        return false;
+    }
+    for (n, (p, q)) in a.iter().zip(b).enumerate() {
+        if (p.0 - q.0).hypot(p.1 - q.1) > eps {
+            log::warn!("polylines differ at index {}: {:?} vs {:?}", n, p, q);
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group() -> Kleinian {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g
+    }
+
+    #[test]
+    fn letter_colors_give_four_paths_in_four_colors() {
+        let mut g = group();
+        let opts = RenderOptions { letter_colors: Some(RenderOptions::default_letter_colors()), ..RenderOptions::default() };
+        limitset_with(10, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        let strokes: Vec<&str> = svg.split("stroke=\"").skip(1).map(|s| s.split('"').next().unwrap()).collect();
+        assert_eq!(strokes.len(), 4, "{}", svg);
+        for (n, s) in strokes.iter().enumerate() {
+            assert!(!strokes[..n].contains(s), "{} twice", s);
+        }
+    }
+
+    #[test]
+    fn polylines_compare_within_epsilon() {
+        let a = [(0.0, 0.0), (1.0, 0.5), (2.0, -1.0)];
+        assert!(polylines_approx_equal(&a, &a, 0.0));
+        assert!(!polylines_approx_equal(&a, &a[..2], 1.0));
+        let mut b = a;
+        b[1].1 += 1e-4;
+        assert!(polylines_approx_equal(&a, &b, 1e-3));
+        assert!(!polylines_approx_equal(&a, &b, 1e-5));
+    }
+
+    #[test]
+    fn the_svg_string_parses_back_with_balanced_tags() {
+        use svg::node::element::tag::Type;
+        use svg::parser::Event;
+
+        let mut g = group();
+        let opts = RenderOptions { overlays: vec![Overlay::IsometricCircles], ..RenderOptions::default() };
+        limitset_with(8, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        let mut open = Vec::new();
+        let mut paths = 0;
+        for event in svg::read(svg.as_bytes()).unwrap() {
+            match event {
+                Event::Tag(name, Type::Start, _) => open.push(name),
+                Event::Tag(name, Type::End, _) => assert_eq!(open.pop(), Some(name)),
+                Event::Tag("path", Type::Empty, _) => paths += 1,
+                Event::Error(e) => panic!("{}", e),
+                _ => {}
+            }
+        }
+        assert!(open.is_empty(), "unclosed {:?}", open);
+        assert_eq!(paths, 1);
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn the_gasket_generators_are_finite_and_unimodular() {
+        let two = Complex::new(2.0, 0.0);
+        let mut g = grandma(two, two);
+        for &l in &[A, B, AI, BI] {
+            let m = g.mat(l);
+            assert!([m.a, m.b, m.c, m.d].iter().all(|z| z.is_finite()), "{}", m);
+            assert!((m.a * m.d - m.b * m.c - 1.0).norm() < 1e-12, "{}", m);
+            g.add_end(vec![l]);
+        }
+        limitset(10, &mut g);
+        assert!(g.points().len() > 1 && g.points().iter().all(|z| z.is_finite()));
+    }
+
+    #[test]
+    fn smooth_output_draws_cubic_curves() {
+        let mut g = group();
+        limitset(8, &mut g);
+        let d = |svg: String| svg.split(" d=\"").nth(1).unwrap().split('"').next().unwrap().to_string();
+        let plain = d(g.to_svg_string(&RenderOptions::default()));
+        let smooth = d(g.to_svg_string(&RenderOptions { smooth: true, ..RenderOptions::default() }));
+        assert!(plain.contains('L') && !plain.contains('C'));
+        assert!(smooth.contains('C') && !smooth.contains('L'));
+        assert_eq!(smooth.matches('C').count(), g.points().len() - 1);
+    }
+
+    #[test]
+    fn traces_recovers_grandmas_parameters() {
+        let two = Complex::new(2.0, 0.0);
+        let (ta, tb, tab) = traces(&grandma(two, two));
+        assert!((ta - two).norm() < 1e-12 && (tb - two).norm() < 1e-12);
+        assert!((tab - Complex::new(2.0, -2.0)).norm() < 1e-12, "{}", tab);
+        let (ta, tb) = (Complex::new(1.91, 0.05), Complex::new(2.1, -0.3));
+        let (a, b, ab) = traces(&grandma(ta, tb));
+        let expected = 0.5 * (ta * tb - markov_discriminant(ta, tb).sqrt());
+        assert!((a - ta).norm() < 1e-9 && (b - tb).norm() < 1e-9 && (ab - expected).norm() < 1e-9);
+    }
+
+    #[test]
+    fn a_tight_budget_lowers_the_depth() {
+        let mut g = group();
+        let (_, depth) = g.render_within_budget(2000, 30);
+        assert!(depth < 30, "{}", depth);
+        assert!(!g.exhausted() && g.nodes() <= 2000, "{} branches", g.nodes());
+        let (_, depth) = g.render_within_budget(1_000_000, 10);
+        assert_eq!(depth, 10);
+    }
+
+    #[test]
+    fn scale_to_fit_fills_the_square() {
+        let mut g = group();
+        let points = g.scale_to_fit(10);
+        assert_eq!(points.len(), g.points().len());
+        assert!(points.iter().all(|&(x, y)| (-1.0..=1.0).contains(&x) && (-1.0..=1.0).contains(&y)));
+        assert!(points.iter().any(|&(x, y)| x.abs() == 1.0 || y.abs() == 1.0));
+        // a point at ∞ is left out, and a single point goes to the origin
+        let (z, inf) = (Complex::new(0.3, -2.0), Complex::new(f64::INFINITY, 0.0));
+        assert_eq!(fit_square(&[z, inf, z]), vec![(0.0, 0.0); 2]);
+        let points = fit_square(&[z, inf, Complex::new(0.5, -1.0)]);
+        assert!(polylines_approx_equal(&points, &[(-0.2, -1.0), (0.2, 1.0)], 1e-12), "{:?}", points);
+    }
+
+    #[test]
+    fn mob_batch_is_mob_pointwise() {
+        let zs: Vec<_> = (0..20).map(|k| Complex::from_polar(&(0.1 * k as f64), &(0.7 * k as f64))).collect();
+        let (i, one) = (Complex::i(), Complex::new(1.0, 0.0));
+        // a general map, and an affine one that takes the shortcut
+        for m in [Mat::new(one + i, 2.0 * one, 0.5 * i, one), Mat::new(2.0 * i, one - i, 0.0 * one, 3.0 * one)] {
+            let batch = m.mob_batch(&zs);
+            assert_eq!(batch.len(), zs.len());
+            for (w, &z) in batch.iter().zip(&zs) {
+                assert!((w - m.mob(z)).norm() < 1e-12, "{} vs {}", w, m.mob(z));
+            }
+        }
+    }
+
+    #[test]
+    fn the_mirrored_path_is_the_exact_conjugate() {
+        use svg::parser::Event;
+
+        let mut g = group();
+        let opts = RenderOptions { mirror: Some(Mirror::Conj), mirror_stroke: Some("red".to_string()), ..RenderOptions::default() };
+        limitset_with(8, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        let mut paths = Vec::new();
+        for event in svg::read(svg.as_bytes()).unwrap() {
+            if let Event::Tag("path", _, attributes) = event {
+                let numbers: Vec<f64> = attributes["d"].split(|c: char| c.is_ascii_alphabetic() || c == ',' || c == ' ')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse().unwrap())
+                    .collect();
+                let class = attributes.get("class").map(|c| c.to_string());
+                paths.push((class, attributes["stroke"].to_string(), numbers));
+            }
+        }
+        assert_eq!(paths.len(), 2, "{}", svg);
+        let (original, mirrored) = (&paths[0], &paths[1]);
+        assert_eq!(mirrored.0.as_deref(), Some("mirror"));
+        assert_eq!(mirrored.1, "red");
+        assert!(original.2.len() > 10);
+        assert_eq!(original.2.len(), mirrored.2.len());
+        for (k, (x, y)) in original.2.iter().zip(&mirrored.2).enumerate() {
+            let expected = if k % 2 == 0 { *x } else { -x };
+            assert_eq!(*y, expected, "coordinate {}", k);
+        }
+    }
+
+    #[test]
+    fn a_rotation_post_transform_turns_every_point() {
+        let mut g = group();
+        g.points = (0..100).map(|k| Complex::from_polar(&0.8, &(0.05 * k as f64))).collect();
+        let turn = Complex::from_polar(&1.0, &0.3);
+        let m = Mat::new(turn, Complex::new(0.0, 0.0), Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        let opts = RenderOptions { post_transform: Some(m.clone()), ..RenderOptions::default() };
+        let runs = drawn_runs(g.points(), &opts);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].len(), g.points().len());
+        for (w, z) in runs[0].iter().zip(g.points()) {
+            assert!((w - turn * z).norm() < 1e-12, "{} is not {} turned", w, z);
+        }
+        let svg = g.to_svg_string(&opts);
+        let recorded = [m.a, m.b, m.c, m.d].map(|z| fmt_complex(z, COMPLEX_PRECISION)).join(",");
+        assert!(svg.contains(&format!("data-post-transform=\"{}\"", recorded)), "{}", svg);
+    }
+
+    #[test]
+    fn an_inversion_post_transform_breaks_the_path_at_its_pole() {
+        let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        let inversion = Mat::new(zero, one, one, zero);
+        let opts = RenderOptions { post_transform: Some(inversion), ..RenderOptions::default() };
+        // a segment through the origin, which 1/z sends to infinity
+        let points: Vec<_> = (-10..=10).map(|k| Complex::new(0.1 * k as f64, 0.05)).collect();
+        let runs = drawn_runs(&points, &opts);
+        assert_eq!(runs.len(), 2, "{:?}", runs);
+        let drawn: Vec<_> = runs.concat();
+        assert!(drawn.len() < points.len());
+        for w in &drawn {
+            assert!(w.is_finite());
+            assert!(points.iter().any(|z| (w - 1.0 / z).norm() < 1e-12), "{} is no point's inverse", w);
+        }
+    }
+
+    #[test]
+    fn precision_three_writes_at_most_three_decimals() {
+        let mut g = group();
+        let opts = RenderOptions { coordinate_precision: Some(3), ..RenderOptions::default() };
+        limitset_with(8, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        let d = svg.split(" d=\"").nth(1).unwrap().split('"').next().unwrap();
+        let numbers: Vec<&str> = d.split(|c: char| c.is_ascii_alphabetic() || c == ',' || c == ' ')
+            .filter(|s| !s.is_empty())
+            .collect();
+        assert!(numbers.len() > 20, "{}", d);
+        for n in &numbers {
+            let decimals = n.split('.').nth(1).map_or(0, str::len);
+            assert!(decimals <= 3, "{} in {}", n, d);
+        }
+        assert!(numbers.iter().any(|n| n.contains('.')));
+        // the viewBox is left alone
+        assert!(svg.contains("viewBox=\"-1.2 -1.2 2.4 2.4\""), "{}", svg);
+    }
+
+    #[test]
+    fn each_layer_gets_a_path_with_its_opacity() {
+        let g = group();
+        let opts = RenderOptions { epsilon: 0.02, stroke: "blue".to_string(), linejoin: "bevel".to_string(), ..RenderOptions::default() };
+        let svg = g.render_layers(&[(4, 0.2), (8, 0.5), (12, 1.0)], &opts).to_string();
+        assert_eq!(svg.matches("<path").count(), 3);
+        for (depth, opacity) in [(4, "0.2"), (8, "0.5"), (12, "1")] {
+            let class = format!("class=\"depth-{}\"", depth);
+            let path = svg.split("<path").find(|p| p.contains(&class)).unwrap_or_else(|| panic!("no {}", class));
+            assert!(path.contains(&format!("stroke-opacity=\"{}\"", opacity)), "{}", path);
+            assert!(path.contains("stroke=\"blue\"") && path.contains("stroke-linejoin=\"bevel\""), "{}", path);
+        }
+        let mut traced = group();
+        limitset_with(12, &mut traced, &opts);
+        let deepest = traced.document(&opts).to_string();
+        let d = |svg: &str| svg.split(" d=\"").last().unwrap().split('"').next().unwrap().to_string();
+        assert_eq!(d(&svg), d(&deepest));
+    }
+
+    #[test]
+    fn an_order_two_generator_is_traced_by_one_letter() {
+        let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        // z -> -1/z, of order 2, and z -> z + 2
+        let a = Mat::new(zero, -one, one, zero);
+        let b = Mat::new(one, Complex::new(2.0, 0.0), zero, one);
+        assert_eq!((max_run(&a, false), max_run(&a, true)), (1, 0));
+        let mut g = Kleinian::new(a, b);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        assert_eq!(run_after(&g.max_runs, B, AI, 1), None);
+        assert_eq!(run_after(&g.max_runs, B, A, 1), Some(1));
+        assert_eq!(run_after(&g.max_runs, A, A, 1), None);
+        let labeled = g.limit_set_labeled(8);
+        assert!(labeled.iter().any(|(_, w)| w.0.contains(&A)));
+        assert!(labeled.iter().all(|(_, w)| !w.0.contains(&AI)), "a word with A⁻¹");
+    }
+
+    #[test]
+    fn an_order_three_generator_never_repeats_its_letter() {
+        let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        // z -> -1/(z + 1), of order 3, and z -> z + 2
+        let a = Mat::new(zero, -one, one, one);
+        let b = Mat::new(one, Complex::new(2.0, 0.0), zero, one);
+        assert_eq!(a.elliptic_order(), Some(3));
+        assert_eq!((max_run(&a, false), max_run(&a, true)), (1, 1));
+        let mut g = Kleinian::new(a, b);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        assert_eq!(run_after(&g.max_runs, A, A, 1), None);
+        assert_eq!(run_after(&g.max_runs, AI, AI, 1), None);
+        let labeled = g.limit_set_labeled(8);
+        for &l in &[A, AI] {
+            assert!(labeled.iter().any(|(_, w)| w.0.contains(&l)));
+            assert!(labeled.iter().all(|(_, w)| !w.0.windows(2).any(|p| p == [l, l])), "a word with {}{}", l, l);
+        }
+    }
+
+    #[test]
+    fn cancelling_mid_trace_keeps_what_was_traced() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let opts = RenderOptions { epsilon: 1e-5, cancel: Some(cancel.clone()), ..RenderOptions::default() };
+        let setter = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            cancel.store(true, Ordering::Relaxed);
+        });
+        let mut g = group();
+        limitset_with(60, &mut g, &opts);
+        setter.join().unwrap();
+        assert!(g.cancelled());
+        assert!(g.nodes() > 0 && (g.nodes() as f64) < estimate_nodes(60, 1e-5, true));
+        // what was traced is the start of the whole trace
+        let partial = g.points().to_vec();
+        assert!(!partial.is_empty());
+        let bounded = RenderOptions { max_nodes: Some(g.nodes()), cancel: None, ..opts };
+        limitset_with(60, &mut g, &bounded);
+        let n = partial.len().min(g.points().len());
+        assert!(n + 1 >= partial.len());
+        assert_eq!(partial[..n], g.points()[..n]);
+    }
+
+    #[test]
+    fn a_flag_set_beforehand_stops_at_the_first_look() {
+        let opts = RenderOptions { cancel: Some(Arc::new(AtomicBool::new(true))), ..RenderOptions::default() };
+        let mut g = group();
+        limitset_with(50, &mut g, &opts);
+        assert!(g.cancelled());
+        // the branches after the first look are counted only to be left pending
+        assert!(g.nodes() >= CANCEL_CHECK_INTERVAL && g.nodes() < 2 * CANCEL_CHECK_INTERVAL, "{} branches", g.nodes());
+    }
+
+    #[test]
+    fn one_prefix_with_letter_colors_draws_its_points() {
+        let mut g = group();
+        // a full trace first, so stale spans would reach past the prefix's points
+        limitset_with(12, &mut g, &RenderOptions::default());
+        let opts = RenderOptions {
+            letter_colors: Some(RenderOptions::default_letter_colors()),
+            prefixes: vec!["aB".parse().unwrap()],
+            ..RenderOptions::default()
+        };
+        limitset_with(12, &mut g, &opts);
+        assert!(!g.points().is_empty());
+        let svg = g.to_svg_string(&opts);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains(&format!("stroke=\"{}\"", opts.stroke)));
+    }
+
+    #[test]
+    fn several_prefixes_get_a_path_each() {
+        let mut g = group();
+        let opts = RenderOptions {
+            letter_colors: Some(RenderOptions::default_letter_colors()),
+            prefixes: vec!["aB".parse().unwrap(), "b".parse().unwrap()],
+            ..RenderOptions::default()
+        };
+        limitset_with(12, &mut g, &opts);
+        assert_eq!(g.to_svg_string(&opts).matches("<path").count(), 2);
+    }
+
+    #[test]
+    fn the_gasket_grows_longer_with_depth() {
+        let two = Complex::new(2.0, 0.0);
+        let mut g = grandma(two, two);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let lengths: Vec<f64> = [4, 8, 12].iter().map(|&level| g.limit_set_length(level)).collect();
+        assert!(lengths[0] > 0.0);
+        assert!(lengths[0] < lengths[1] && lengths[1] < lengths[2], "{:?}", lengths);
+    }
+
+    #[test]
+    fn every_label_is_a_reduced_word_within_the_level() {
+        let mut g = group();
+        let labeled = g.limit_set_labeled(10);
+        assert_eq!(labeled.len(), g.points().len());
+        assert!(labeled[0].1.0.is_empty());
+        assert!(labeled.iter().any(|(_, w)| w.0.len() == 10));
+        for (_, w) in &labeled {
+            assert!(w.0.len() <= 10, "{:?}", w);
+            assert!(w.0.windows(2).all(|p| p[1] != p[0].inv()), "{:?} is not reduced", w);
+        }
+    }
+
+    #[test]
+    fn the_gradient_runs_from_its_first_color_to_its_last() {
+        let colors = vec!["#ff0000".to_string(), "#00f".to_string()];
+        assert_eq!(gradient_color(&colors, 0.0), "#ff0000");
+        assert_eq!(gradient_color(&colors, 0.5), "#800080");
+        assert_eq!(gradient_color(&colors, 1.0), "#0000ff");
+        // colors that aren't hex change in a step halfway
+        let named = vec!["red".to_string(), "blue".to_string()];
+        assert_eq!((gradient_color(&named, 0.4), gradient_color(&named, 0.6)), ("red".to_string(), "blue".to_string()));
+
+        let mut g = group();
+        let opts = RenderOptions { gradient: Some(colors), ..RenderOptions::default() };
+        limitset_with(10, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        assert!(svg.contains("class=\"gradient\""), "{}", svg);
+        let strokes: Vec<&str> = svg.split("stroke=\"").skip(1).map(|s| s.split('"').next().unwrap()).collect();
+        assert_eq!(strokes.len(), GRADIENT_STEPS);
+        let red = |s: &str| u8::from_str_radix(&s[1..3], 16).unwrap();
+        let blue = |s: &str| u8::from_str_radix(&s[5..7], 16).unwrap();
+        assert!(red(strokes[0]) > 250 && blue(strokes[0]) < 5, "{}", strokes[0]);
+        assert!(red(strokes[GRADIENT_STEPS - 1]) < 5 && blue(strokes[GRADIENT_STEPS - 1]) > 250);
+        assert!(strokes.windows(2).all(|p| red(p[0]) >= red(p[1]) && blue(p[0]) <= blue(p[1])));
+    }
+
+    #[test]
+    fn three_points_land_where_they_are_sent() {
+        let c = Complex::new;
+        let src = [c(0.0, 0.0), c(1.0, 0.0), c(-0.5, 2.0)];
+        let dst = [c(3.0, -1.0), c(0.0, 1.0), c(2.0, 2.0)];
+        let m = Mat::from_three_points(src, dst);
+        for k in 0..3 {
+            assert!((m.mob(src[k]) - dst[k]).norm() < 1e-9, "{} goes to {}, not {}", src[k], m.mob(src[k]), dst[k]);
+        }
+    }
+
+    #[test]
+    fn a_decaying_epsilon_puts_more_points_in_deep_branches() {
+        // the mean length of the words of the branches that drew the points
+        let mean_depth = |opts: &RenderOptions| {
+            let mut g = group();
+            g.set_record_words(true);
+            limitset_with(12, &mut g, opts);
+            let records = g.point_records().unwrap();
+            records.iter().map(|r| r.depth as f64).sum::<f64>() / records.len() as f64
+        };
+        let plain = mean_depth(&RenderOptions { epsilon: 0.05, ..RenderOptions::default() });
+        let decayed = mean_depth(&RenderOptions { epsilon: 0.5, epsilon_decay: Some(0.7), ..RenderOptions::default() });
+        assert!(decayed > plain, "{} <= {}", decayed, plain);
+    }
+
+    #[test]
+    fn a_relator_cuts_branches() {
+        let opts = RenderOptions::default();
+        let mut g = group();
+        limitset_with(8, &mut g, &opts);
+        let mut cut = group().with_relator("abAB".parse().unwrap());
+        limitset_with(8, &mut cut, &opts);
+        assert!(cut.nodes() < g.nodes(), "{} branches with the relator, {} without", cut.nodes(), g.nodes());
+        assert!(!cut.points().is_empty());
+    }
+
+    #[test]
+    fn matrices_print_as_latex_and_in_aligned_columns() {
+        assert_eq!(Mat::id().to_latex(), "\\begin{pmatrix} 1 & 0 \\\\ 0 & 1 \\end{pmatrix}");
+        let c = Complex::new;
+        let m = Mat::new(c(1.0, 0.0), c(0.0, 2.0), c(0.0, 0.0), c(1.0, -1.0));
+        assert_eq!(m.to_string(), "[ 1   2i ]\n[ 0  1-i ]");
+    }
+
+    #[test]
+    fn a_reversed_order_draws_the_same_points_backwards() {
+        let mut forwards = group();
+        limitset_with(8, &mut forwards, &RenderOptions::default());
+        let mut backwards = group();
+        limitset_with(8, &mut backwards, &RenderOptions { branch_order: [B, AI, BI, A], ..RenderOptions::default() });
+        let pairs = |points: &[Complex<f64>]| points.iter().map(|z| (z.re, z.im)).collect::<Vec<_>>();
+        let mut reversed = pairs(backwards.points());
+        reversed.reverse();
+        // the fixed points the two start from are found by different products
+        assert!(polylines_approx_equal(&pairs(forwards.points()), &reversed, 1e-6));
+        assert!(check_branch_order(&[A, A, B, BI]).unwrap_err().contains("missing `A`"));
+    }
+
+    #[test]
+    fn an_empty_seed_word_draws_the_default_trace() {
+        let opts = RenderOptions::default();
+        let mut plain = group();
+        limitset_with(8, &mut plain, &opts);
+        let mut seeded = group();
+        let seeded_opts = RenderOptions { seed_word: Some(Word(Vec::new())), ..opts.clone() };
+        limitset_with(8, &mut seeded, &seeded_opts);
+        assert_eq!(seeded.points(), plain.points());
+        assert_eq!(seeded.to_svg_string(&seeded_opts), plain.to_svg_string(&opts));
+    }
+
+    #[test]
+    fn a_view_draws_as_the_transformed_render() {
+        let c = Complex::new;
+        let view = Mat::new(c(2.0, 1.0), c(0.5, 0.0), c(0.1, 0.0), c(1.0, 0.0));
+        let opts = RenderOptions::default();
+        let mut g = group();
+        limitset_with(8, &mut g, &opts);
+        let traced = g.points().to_vec();
+        g.set_view(Some(view.clone()));
+        let viewed = g.to_svg_string(&opts);
+        // the points are kept as traced
+        assert_eq!(g.points(), &traced[..]);
+        g.set_view(None);
+        let transformed = g.to_svg_string(&RenderOptions { post_transform: Some(view), ..opts.clone() });
+        assert_eq!(viewed, transformed);
+        assert_ne!(viewed, g.to_svg_string(&opts));
+    }
+
+    #[test]
+    fn a_centered_render_puts_the_cusp_in_the_middle() {
+        let mut g = group();
+        let svg = g.render_centered_on(Complex::new(1.0, 0.0), 4.0, 8).to_string();
+        let attr = |name: &str| svg.split(&format!(" {}=\"", name)).nth(1).unwrap().split('"').next().unwrap().to_string();
+        let vb: Vec<f64> = attr("viewBox").split(' ').map(|s| s.parse().unwrap()).collect();
+        let (cx, cy) = (vb[0] + vb[2] / 2.0, vb[1] + vb[3] / 2.0);
+        assert!(cx.abs() < 1e-9 && cy.abs() < 1e-9, "{:?}", vb);
+        // the cusp lies on the limit set, so the path passes through the centre
+        let numbers: Vec<f64> = attr("d").split(|ch: char| !(ch.is_ascii_digit() || ch == '.' || ch == '-' || ch == 'e'))
+            .filter_map(|s| s.parse().ok()).collect();
+        let nearest = numbers.chunks(2).map(|p| (p[0] - cx).hypot(p[1] - cy)).fold(f64::INFINITY, f64::min);
+        assert!(nearest < 0.01 * vb[2], "{} in {:?}", nearest, vb);
+    }
+
+    #[test]
+    fn complex_numbers_print_in_their_shortest_form() {
+        let c = Complex::new;
+        assert_eq!(fmt_complex(c(2.0, 0.0), 6), "2");
+        assert_eq!(fmt_complex(c(-0.5, 0.0), 6), "-0.5");
+        assert_eq!(fmt_complex(c(0.0, 1.0), 6), "i");
+        assert_eq!(fmt_complex(c(0.0, -1.0), 6), "-i");
+        assert_eq!(fmt_complex(c(0.0, 2.5), 6), "2.5i");
+        assert_eq!(fmt_complex(c(1.0, -2.0), 6), "1-2i");
+        assert_eq!(fmt_complex(c(1.0, 1.0), 6), "1+i");
+        assert_eq!(fmt_complex(c(0.0, 0.0), 6), "0");
+        assert_eq!(fmt_complex(c(-0.0, -0.0), 6), "0");
+        // rounding drops trailing zeros, and parts that round away
+        assert_eq!(fmt_complex(c(1.23456789, 0.1), 3), "1.235+0.1i");
+        assert_eq!(fmt_complex(c(1.5, 1e-9), 6), "1.5");
+        assert_eq!(fmt_complex(c(1e-9, -3.0), 6), "-3i");
+        assert_eq!(parse_complex(&fmt_complex(c(1.0, -2.0), 6)).unwrap(), c(1.0, -2.0));
+    }
+
+    #[test]
+    fn the_dual_is_a_second_different_path() {
+        let mut g = group();
+        let svg = g.render_with_dual(8).to_string();
+        let paths: Vec<&str> = svg.split("<path").skip(1).collect();
+        assert_eq!(paths.len(), 2, "{}", svg);
+        let d = |p: &str| p.split(" d=\"").nth(1).unwrap().split('"').next().unwrap().to_string();
+        let (primary, dual) = (paths[0], paths[1]);
+        assert!(!primary.contains("class=\"dual\""));
+        assert!(dual.contains("class=\"dual\"") && dual.contains(DUAL_STROKE));
+        assert!(d(dual).len() > 2);
+        assert_ne!(d(primary), d(dual));
+    }
+
+    #[test]
+    fn the_curve_is_joined_and_capped_as_asked() {
+        let mut g = group();
+        let opts = RenderOptions::default();
+        limitset_with(6, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        assert!(svg.contains("stroke-linejoin=\"round\"") && svg.contains("stroke-linecap=\"round\""), "{}", svg);
+        let opts = RenderOptions { linejoin: "miter".to_string(), linecap: "butt".to_string(), ..opts };
+        let svg = g.to_svg_string(&opts);
+        assert!(svg.contains("stroke-linejoin=\"miter\"") && svg.contains("stroke-linecap=\"butt\""), "{}", svg);
+        assert!(!svg.contains("\"round\""), "{}", svg);
+    }
+
+    #[test]
+    fn the_conjugate_matrix_mirrors_across_the_real_axis() {
+        let c = Complex::new;
+        let m = Mat::new(c(1.0, 2.0), c(-0.5, 0.25), c(0.3, -1.0), c(2.0, 0.5));
+        let twice = m.conj().conj();
+        assert_eq!((twice.a, twice.b, twice.c, twice.d), (m.a, m.b, m.c, m.d));
+        for &z in &[c(0.0, 0.0), c(1.5, -0.5), c(-2.0, 3.0)] {
+            let mirrored = m.conj().mob(z.conj());
+            assert!((mirrored - m.mob(z).conj()).norm() < 1e-12, "{} at {}", mirrored, z);
+        }
+    }
+
+    #[test]
+    fn an_absurd_depth_without_a_budget_is_too_large() {
+        match check_trace_size(200, EPSILON, false, None, MAX_ESTIMATED_NODES) {
+            Err(Error::TooLarge { level: 200, ceiling: MAX_ESTIMATED_NODES, estimate, .. }) => {
+                assert!(estimate > MAX_ESTIMATED_NODES as f64);
+            }
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+        // a node budget bounds the trace whatever the estimate
+        assert!(check_trace_size(200, EPSILON, false, Some(1_000_000), MAX_ESTIMATED_NODES).is_ok());
+        // a discrete group is held to its epsilon instead
+        assert!(check_trace_size(200, 1e-3, true, None, MAX_ESTIMATED_NODES).is_ok());
+        assert!(check_trace_size(200, 1e-7, true, None, MAX_ESTIMATED_NODES).is_err());
+        // the estimate grows with the level, unless epsilon stops it
+        assert!(estimate_nodes(40, 1e-3, false) > estimate_nodes(20, 1e-3, false));
+        assert_eq!(estimate_nodes(40, 1e-3, true), estimate_nodes(200, 1e-3, true));
+    }
+}
@@ -0,0 +1,69 @@
+//! A logger for the `log` facade that writes each record to stderr on a
+//! line of its own, so that output written to stdout stays clean. Warnings
+//! and errors are marked the way the command line has always marked them,
+//! debug and trace lines with their level and module.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let stderr = std::io::stderr();
+        let mut w = stderr.lock();
+        // nothing useful can be done if stderr is gone
+        let _ = match record.level() {
+            Level::Error => writeln!(w, "error: {}", record.args()),
+            Level::Warn => writeln!(w, "warning: {}", record.args()),
+            Level::Info => writeln!(w, "{}", record.args()),
+            level => writeln!(w, "{}: {}: {}", level.as_str().to_lowercase(), record.target(), record.args()),
+        };
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Sends the `log` records at `level` and above to stderr. Only the first
+/// call installs the logger; later ones just change the level.
+pub fn init_logging(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/// The level for `-q`, nothing, `-v`, `-vv` and `-vvv`: `quiet` silences
+/// even warnings, and each `-v` shows one level more than warnings.
+pub fn verbosity_level(quiet: bool, verbose: usize) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Off,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_wins_over_verbose() {
+        assert_eq!(verbosity_level(false, 0), LevelFilter::Warn);
+        assert_eq!(verbosity_level(false, 1), LevelFilter::Info);
+        assert_eq!(verbosity_level(false, 2), LevelFilter::Debug);
+        assert_eq!(verbosity_level(false, 5), LevelFilter::Trace);
+        for verbose in 0..4 {
+            assert_eq!(verbosity_level(true, verbose), LevelFilter::Off);
+        }
+    }
+}
@@ -1,326 +1,1182 @@
-use svg::Document;
-use svg::node::element::Path;
-use svg::node::element::path::Data;
+use clap::ArgMatches;
+use svg_kleinian::{run_command, Reply, Session, fmt_complex, init_logging, verbosity_level, parse_jobs, COMPLEX_PRECISION, RenderConfig, Recipe, Scene, Interpolation, farey_path, maskit_traces, CuspCache, FAREY_OFFSET, analyze_depths, animate, render_frames, AnimateOptions, OutputFile, RenderReport, create_output, read_input, read_svg, svg_metadata, VERIFY_TOLERANCE, npz_files, point_columns, write_npy, write_npz, write_csv, write_json, write_ndjson, write_svg, DEFAULT_COMPRESSION, Colormap, Heatmap, TikzOptions, ToneMap, write_analysis, dump_generators, grandma, Curve, disk_to_halfplane, grid, grid_mu, html, inversive_distance, limitset_with, tile, tile_window, Mat, Model, EPSILON, RenderOptions, Overlay, Word, Preset, A, B, AI, BI};
 use num::complex::Complex;
-use std::ops::Mul;
+use std::io::Write;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-const EPSILON: f64 = 0.001;
+mod cli;
 
-#[derive(Debug)]
-struct Mat {
-    a: Complex<f64>,
-    b: Complex<f64>,
-    c: Complex<f64>,
-    d: Complex<f64>,
+/// `eprintln!`, unless --quiet: for the notes a command leaves on stderr
+/// that are its report rather than a log record.
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if log::log_enabled!(log::Level::Warn) {
+            eprintln!($($arg)*);
+        }
+    };
 }
 
-impl Mat {
-    fn new(a: Complex<f64>, b: Complex<f64>, c: Complex<f64>, d: Complex<f64>) -> Self {
-        Mat {
-            a:a,
-            b:b,
-            c:c,
-            d:d,
-        }
-    }
+/// The value, or exits after printing `context: error` on one line, for
+/// failures the user can do something about, such as an unwritable path.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>, context: &str) -> T {
+    or_fail(result, context).unwrap_or_else(|f| f.exit())
+}
 
-    fn id() -> Self {
-        Mat {
-            a: Complex::new(1.0,0.0),
-            b: Complex::new(0.0,0.0),
-            c: Complex::new(0.0,0.0),
-            d: Complex::new(1.0,0.0),
-        }
-    }
+/// Why a render stopped short, for `render_main` to exit on and `--watch`
+/// to report and carry on from.
+enum Failure {
+    /// The message, and the status to exit with: 1 for a failure to read
+    /// or write, 2 for options that can't be rendered.
+    Error(String, i32),
+    /// `RenderOptions::cancel` was set, and nothing was written.
+    Cancelled,
+}
 
-    fn adj(&self) -> Self {
-        Mat {
-            a: self.d,
-            b: -self.b,
-            c: -self.c,
-            d: self.a,
+impl Failure {
+    fn exit(self) -> ! {
+        match self {
+            Failure::Error(message, status) => {
+                eprintln!("{}", message);
+                process::exit(status);
+            }
+            Failure::Cancelled => process::exit(130),
         }
     }
+}
 
-    fn mob(&self, z: Complex<f64>) -> Complex<f64> {
-        (self.a * z + self.b) / (self.c * z + self.d)
-    }
+/// As `or_exit`, but leaving the exit to the caller.
+fn or_fail<T, E: std::fmt::Display>(result: Result<T, E>, context: &str) -> Result<T, Failure> {
+    result.map_err(|e| Failure::Error(format!("{}: {}", context, e), 1))
+}
 
-    fn fix(&self) -> Complex<f64> {
-        // gives the attracting fixed point
-        // z = az+b/cz+d, with big cz+d
-        // cz^2 + (d-a) z - b = 0
-        let a = self.a;
-        let b = self.b;
-        let c = self.c;
-        let d = self.d;
-        if c.norm_sqr() == 0.0 {
-            if a.norm_sqr() > d.norm_sqr() {
-                Complex::new(1.0 / 0.0, 0.0)
-            } else {
-                b / (d-a)
+#[cfg(feature = "png")]
+fn save_png(path: &str, document: &svg::Document, (width, height): (u32, u32), supersample: u32) -> Result<(), Failure> {
+    let opts = svg_kleinian::PngOptions { width, height, supersample };
+    svg_kleinian::render_png_sized(document, path, &opts).map_err(|e| Failure::Error(e.to_string(), 1))
+}
+
+#[cfg(not(feature = "png"))]
+fn save_png(_: &str, _: &svg::Document, _: (u32, u32), _: u32) -> Result<(), Failure> {
+    Err(Failure::Error("PNG output needs the `png` feature: cargo build --features png".to_string(), 1))
+}
+
+#[cfg(feature = "png")]
+fn save_heatmap(path: &str, heatmap: &Heatmap, tone: &ToneMap, colormap: Colormap) -> Result<(), Failure> {
+    svg_kleinian::save_heatmap(heatmap, path, tone, colormap).map_err(|e| Failure::Error(e.to_string(), 1))
+}
+
+#[cfg(not(feature = "png"))]
+fn save_heatmap(_: &str, _: &Heatmap, _: &ToneMap, _: Colormap) -> Result<(), Failure> {
+    Err(Failure::Error("heatmap output needs the `png` feature: cargo build --features png".to_string(), 1))
+}
+
+/// Saves a PDF page of `size` points, or at the document's aspect ratio
+/// when not given.
+#[cfg(feature = "pdf")]
+fn save_pdf(path: &str, document: &svg::Document, size: Option<(u32, u32)>) -> Result<(), Failure> {
+    let saved = match size {
+        Some((width, height)) => svg_kleinian::render_pdf(document, path, (width as f32, height as f32)),
+        None => svg_kleinian::save_pdf(document, path),
+    };
+    saved.map_err(|e| Failure::Error(e.to_string(), 1))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn save_pdf(_: &str, _: &svg::Document, _: Option<(u32, u32)>) -> Result<(), Failure> {
+    Err(Failure::Error("PDF output needs the `pdf` feature: cargo build --features pdf".to_string(), 1))
+}
+
+#[cfg(feature = "eps")]
+fn save_eps(path: &str, document: &svg::Document, (width, _): (u32, u32)) -> Result<(), Failure> {
+    svg_kleinian::render_eps(document, path, width as f32).map_err(|e| Failure::Error(e.to_string(), 1))
+}
+
+#[cfg(not(feature = "eps"))]
+fn save_eps(_: &str, _: &svg::Document, _: (u32, u32)) -> Result<(), Failure> {
+    Err(Failure::Error("EPS output needs the `eps` feature: cargo build --features eps".to_string(), 1))
+}
+
+/// Rasterizes the sweep into an animated GIF or APNG at `path`, playing
+/// `plays` times, or forever for 0.
+#[cfg(feature = "animation")]
+#[allow(clippy::too_many_arguments)]
+fn save_animation(ta: (Complex<f64>, Complex<f64>), tb: (Complex<f64>, Complex<f64>), anim: &AnimateOptions,
+                  opts: &RenderOptions, format: &str, path: &str, (width, height): (u32, u32), plays: u16) {
+    let format = match format.parse() {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    let plays = if plays == 0 { None } else { Some(plays) };
+    let enc = svg_kleinian::EncodeOptions { format, width, height, plays };
+    let progress = |k: usize, n: usize| {
+        // shown unless --quiet
+        if !log::log_enabled!(log::Level::Warn) {
+            return;
+        }
+        eprint!("\rframe {}/{}", k, n);
+        if k == n {
+            eprintln!();
+        }
+    };
+    match svg_kleinian::encode_animation(ta, tb, anim, opts, &enc, path, progress) {
+        Ok(warnings) => {
+            for warning in warnings {
+                log::warn!("{}", warning);
             }
-        } else {
-            let disc = (d - a) * (d - a) + 4.0 * b * c;
-            let sd = if (a + d).re > 0.0 {
-                -disc.sqrt()
-            } else {
-                disc.sqrt()
-            };
-            (a - d - sd) / (2.0 * c)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
         }
     }
 }
 
-impl<'a,'b> Mul<&'b Mat> for &'a Mat {
-    type Output = Mat;
-    fn mul(self, rhs: &'b Mat) -> Mat {
-        let v = &rhs;
-        Mat {
-            a: self.a * v.a + self.b * v.c,
-            b: self.a * v.b + self.b * v.d,
-            c: self.c * v.a + self.d * v.c,
-            d: self.c * v.b + self.d * v.d,
+#[cfg(not(feature = "animation"))]
+#[allow(clippy::too_many_arguments)]
+fn save_animation(_: (Complex<f64>, Complex<f64>), _: (Complex<f64>, Complex<f64>), _: &AnimateOptions,
+                  _: &RenderOptions, _: &str, _: &str, _: (u32, u32), _: u16) {
+    eprintln!("GIF and APNG animations need the `animation` feature: cargo build --features animation");
+    process::exit(1);
+}
+
+#[cfg(feature = "serve")]
+fn serve_main(m: &ArgMatches) {
+    let mut opts = svg_kleinian::ServeOptions::default();
+    let port = m.get_one::<u16>("port").copied().unwrap_or(8000);
+    if let Some(&n) = m.get_one("workers") {
+        opts.workers = n;
+    }
+    if let Some(&n) = m.get_one("max-nodes") {
+        opts.max_nodes = n;
+    }
+    if let Some(&t) = m.get_one::<f64>("timeout") {
+        opts.timeout = std::time::Duration::from_secs_f64(t);
+    }
+    if let Some(&n) = m.get_one("cache") {
+        opts.cache_size = n;
+    }
+    // only this machine: it's for a port forward, not the open internet
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|e| {
+        eprintln!("can't listen on port {}: {}", port, e);
+        process::exit(1);
+    });
+    note!("serving on http://127.0.0.1:{}/", port);
+    if let Err(e) = svg_kleinian::serve(listener, &opts) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve_main(_: &ArgMatches) {
+    eprintln!("serve needs the `serve` feature: cargo build --features serve");
+    process::exit(1);
+}
+
+fn info_main(m: &ArgMatches) {
+    let config = cli::render_config(m, RenderConfig::default());
+    match config.write_info(&mut std::io::stdout()) {
+        Ok(()) => {}
+        Err(e @ svg_kleinian::Error::Io(_)) => {
+            eprintln!("stdout: {}", e);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
         }
     }
 }
 
-impl Mul<Mat> for Mat {
-    type Output = Mat;
-    fn mul(self, rhs: Mat) -> Mat {
-        &self * &rhs
+/// Reads commands from standard input until `quit` or the end of input,
+/// printing each reply; see repl.rs. A failed command is reported and the
+/// loop goes on.
+fn repl_main(m: &ArgMatches) {
+    use std::io::{BufRead, IsTerminal};
+
+    let mut session = Session { config: cli::render_config(m, RenderConfig::default()) };
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    if interactive {
+        eprintln!("type help for the commands, quit to leave");
+    }
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            eprint!("> ");
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("stdin: {}", e);
+                process::exit(1);
+            }
+            None => return,
+        };
+        match run_command(&mut session, &line) {
+            Ok(Reply::Continue(text)) if text.is_empty() => {}
+            Ok(Reply::Continue(text)) => println!("{}", text),
+            Ok(Reply::Quit) => return,
+            Err(e) => eprintln!("{}", e),
+        }
     }
 }
 
-impl Mul<&Mat> for Mat {
-    type Output = Mat;
-    fn mul(self, rhs: &Mat) -> Mat {
-        &self * rhs
+fn cusp_main(m: &ArgMatches) {
+    let mut config = cli::render_config(m, RenderConfig { recipe: Recipe::Maskit, ..RenderConfig::default() });
+    let render = m.get_flag("render") || config.output.is_some();
+    let (p, q) = (*m.get_one::<i64>("p").expect("required"), *m.get_one::<i64>("q").expect("required"));
+    let mut opts = RenderOptions::default();
+    cli::style_options(m, &mut opts);
+    let mu = svg_kleinian::maskit_cusp(p, q).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    if !render {
+        println!("μ = {}", fmt_complex(mu, COMPLEX_PRECISION));
+        return;
+    }
+    // standard output may be carrying the SVG
+    note!("μ = {}", fmt_complex(mu, COMPLEX_PRECISION));
+    config.ta = mu;
+    let path = config.output.clone().unwrap_or_else(|| format!("cusp-{}-{}.svg", p, q));
+    let document = config.render(&opts).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let mut w = create_output(&path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    if let Err(e) = svg::write(&mut w, &document) {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
     }
 }
 
+// how a job of a batch went
+enum JobStatus {
+    Ok,
+    Failed,
+    // not started, after another failed under --fail-fast
+    Skipped,
+}
 
+// runs this program on a job's options, returning whether it succeeded
+// and its output, each line marked with the job's name
+fn run_job(job: &svg_kleinian::Job) -> (bool, String) {
+    let scene = match job.scene {
+        Ok(ref scene) => scene,
+        Err(ref e) => return (false, format!("[{}] {}\n", job.name, e)),
+    };
+    let exe = std::env::current_exe().unwrap_or_else(|_| "svg_kleinian".into());
+    let mut args = scene.to_args();
+    if scene.animation.is_none() {
+        args.insert(0, "render".to_string());
+    }
+    match process::Command::new(exe).args(args).output() {
+        Ok(out) => {
+            let mut log = String::new();
+            for text in [&out.stdout, &out.stderr] {
+                for line in String::from_utf8_lossy(text).lines() {
+                    log.push_str(&format!("[{}] {}\n", job.name, line));
+                }
+            }
+            (out.status.success(), log)
+        }
+        Err(e) => (false, format!("[{}] can't run: {}\n", job.name, e)),
+    }
+}
 
-fn grandma(ta: Complex<f64>, tb: Complex<f64>) -> Kleinian {
-    let i = Complex::i();
-    let disc = ta * ta * tb * tb - 4.0 * ta * ta - 4.0 * tb * tb;
-    let tab = 0.5 * (ta * tb - disc.sqrt());
-    let scale = (tab - 2.0) * tb / (tb * tab - 2.0 * ta + 2.0 * i * tab);
+/// How often `--watch` checks on the render it has going while it waits
+/// for the scene file to change.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
 
-    let a = Mat::new(ta / 2.0, (ta * tab - 2.0 * tb + 4.0 * i) / ((2.0 * tab + 4.0) * scale),
-        scale * (ta * tab - 2.0 * tb - 4.0 * i) / (2.0 * tab - 4.0), ta / 2.0);
-    let b = Mat::new((tb - 2.0 * i) / 2.0, tb / 2.0,
-        tb / 2.0, (tb + 2.0 * i) / 2.0);
-    return Kleinian::new(a,b);
-}
+/// How long the scene file must stay unchanged after a save before
+/// `--watch` renders it, so an editor's several writes make one render.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Letter {
-    A,
-    B,
-    AI,
-    BI,
+// a render `--watch` has going, with the flag that cancels it
+struct Watched {
+    thread: std::thread::JoinHandle<Result<(), Failure>>,
+    cancel: Arc<AtomicBool>,
+    start: Instant,
 }
 
-const A: Letter = Letter::A;
-const B: Letter = Letter::B;
-const AI: Letter = Letter::AI;
-const BI: Letter = Letter::BI;
+/// Renders with `args` whenever the `--config` file among them changes,
+/// reading it afresh each time and rendering on a thread of its own, so a
+/// bad scene only fails that render. A render still going when the file
+/// changes again is cancelled through `RenderOptions::cancel` for the new
+/// one.
+fn watch_main(args: Vec<String>) {
+    use notify::RecursiveMode;
+    use std::path::Path;
+    use std::sync::mpsc::{self, RecvTimeoutError};
 
-impl Letter {
-    fn inv(&self) -> Self {
-        match self {
-            &A => AI,
-            &B => BI,
-            &AI => A,
-            &BI => B,
+    let path = match args.iter().position(|a| a == "--config").and_then(|k| args.get(k + 1)) {
+        Some(path) if path != "-" => path.clone(),
+        _ => {
+            eprintln!("--watch needs a --config file to watch");
+            process::exit(2);
+        }
+    };
+    let file = Path::new(&path);
+    let name = file.file_name();
+    // the directory, as an editor may save by replacing the file
+    let dir = file.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let (events, saves) = mpsc::channel();
+    // kept until the end, as dropping it stops the watch
+    let _debouncer = notify_debouncer_mini::new_debouncer(WATCH_DEBOUNCE, events)
+        .and_then(|mut d| d.watcher().watch(dir, RecursiveMode::NonRecursive).map(|()| d))
+        .unwrap_or_else(|e| {
+            eprintln!("can't watch {}: {}", path, e);
+            process::exit(1);
+        });
+    note!("watching {}, rendering on every save", path);
+    let mut changed = true;
+    let mut running: Option<Watched> = None;
+    loop {
+        if running.as_ref().is_some_and(|r| r.thread.is_finished()) {
+            let Watched { thread, start, .. } = running.take().expect("checked above");
+            match thread.join() {
+                Ok(Ok(())) => note!("rendered in {:.2}s", start.elapsed().as_secs_f64()),
+                Ok(Err(Failure::Error(message, _))) => eprintln!("{}\nrender failed, still watching", message),
+                Ok(Err(Failure::Cancelled)) => {}
+                Err(_) => eprintln!("the render panicked, still watching"),
+            }
+        }
+        if changed {
+            changed = false;
+            if let Some(Watched { thread, cancel, .. }) = running.take() {
+                cancel.store(true, Ordering::Relaxed);
+                let _ = thread.join();
+                note!("cancelled the render in progress");
+            }
+            match start_render(&args) {
+                Ok(watched) => running = Some(watched),
+                Err(message) => eprintln!("{}\nstill watching", message),
+            }
+        }
+        match saves.recv_timeout(WATCH_INTERVAL) {
+            Ok(Ok(saved)) => changed = saved.iter().any(|e| e.path.file_name() == name),
+            Ok(Err(e)) => eprintln!("watching {}: {}", path, e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
         }
     }
 }
 
-struct Bag<T> {
-    a: T,
-    b: T,
-    ainv: T,
-    binv: T,
+// reads the options afresh and starts rendering them, or says why not
+fn start_render(args: &[String]) -> Result<Watched, String> {
+    let arguments = match with_config(args.iter().cloned()) {
+        Ok(arguments) => arguments,
+        Err(Failure::Error(message, _)) => return Err(message),
+        Err(Failure::Cancelled) => unreachable!("reading options can't be cancelled"),
+    };
+    let (arguments, _) = cli::with_subcommand(arguments);
+    let m = cli::command()
+        .try_get_matches_from(std::iter::once("svg_kleinian".to_string()).chain(arguments.iter().cloned()))
+        .map_err(|e| e.to_string().trim_end().to_string())?;
+    let m = match m.subcommand() {
+        Some(("render", m)) => m.clone(),
+        _ => return Err("--watch renders still pictures, not animations".to_string()),
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    let arguments = report_arguments(arguments);
+    let thread = std::thread::spawn(move || render(&m, arguments, Some(flag)));
+    Ok(Watched { thread, cancel, start: Instant::now() })
 }
 
-impl<T> Bag<T> {
-    fn new(a: T, b: T, ainv: T, binv: T) -> Self {
-        Bag {
-            a: a,
-            b: b,
-            ainv: ainv,
-            binv: binv,
+fn batch_main(m: &ArgMatches) {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    let path = m.get_one::<String>("jobs-file").expect("required");
+    let workers = m.get_one::<usize>("jobs").copied().unwrap_or(1);
+    let fail_fast = m.get_flag("fail-fast");
+    let text = read_input(path).and_then(|bytes| {
+        String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    });
+    let jobs = text.map_err(|e| e.to_string()).and_then(|text| parse_jobs(&text)).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    let jobs = Arc::new(jobs);
+    let next = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let threads: Vec<_> = (0..workers.min(jobs.len())).map(|_| {
+        let (jobs, next, stop, results) = (jobs.clone(), next.clone(), stop.clone(), results.clone());
+        std::thread::spawn(move || loop {
+            let k = next.fetch_add(1, Ordering::SeqCst);
+            if k >= jobs.len() || stop.load(Ordering::SeqCst) {
+                return;
+            }
+            let start = Instant::now();
+            let (ok, log) = run_job(&jobs[k]);
+            eprint!("{}", log);
+            if !ok && fail_fast {
+                stop.store(true, Ordering::SeqCst);
+            }
+            let status = if ok { JobStatus::Ok } else { JobStatus::Failed };
+            results.lock().unwrap().push((k, status, start.elapsed()));
+        })
+    }).collect();
+    for t in threads {
+        t.join().expect("a batch worker panicked");
+    }
+    let mut results = std::mem::take(&mut *results.lock().unwrap());
+    for k in 0..jobs.len() {
+        if !results.iter().any(|r| r.0 == k) {
+            results.push((k, JobStatus::Skipped, std::time::Duration::ZERO));
         }
     }
+    results.sort_by_key(|r| r.0);
+    let width = jobs.iter().map(|j| j.name.chars().count()).max().unwrap_or(0).max(3);
+    note!("{:<width$}  {:<7}  {:>8}", "job", "status", "seconds", width = width);
+    for (k, status, elapsed) in &results {
+        let status = match status {
+            JobStatus::Ok => "ok",
+            JobStatus::Failed => "failed",
+            JobStatus::Skipped => "skipped",
+        };
+        note!("{:<width$}  {:<7}  {:>8.2}", jobs[*k].name, status, elapsed.as_secs_f64(), width = width);
+    }
+    let failed = results.iter().filter(|r| !matches!(r.1, JobStatus::Ok)).count();
+    note!("{} of {} jobs succeeded", jobs.len() - failed, jobs.len());
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Svg,
+    Png,
+    Pdf,
+    Eps,
+    Tikz,
+    Heatmap,
+    Csv,
+    Json,
+    Npy,
+    SphereObj,
+    SphereJson,
+}
 
-    fn at(&self, l: Letter) -> &T {
-        match l {
-            A => &self.a,
-            B => &self.b,
-            AI => &self.ainv,
-            BI => &self.binv,
+impl Format {
+    /// The default `--size`: pixels for images, points for PDF.
+    fn default_size(self) -> (u32, u32) {
+        match self {
+            Format::Pdf | Format::Eps => (720, 720),
+            _ => (2000, 2000),
         }
     }
 
-    fn at_mut(&mut self, l: Letter) -> &mut T {
-        match l {
-            A => &mut self.a,
-            B => &mut self.b,
-            AI => &mut self.ainv,
-            BI => &mut self.binv,
+    /// Where the output goes without `-o`.
+    fn default_output(self, compress: bool) -> &'static str {
+        match self {
+            Format::Svg if compress => "image.svgz",
+            Format::Svg => "image.svg",
+            Format::Png | Format::Heatmap => "image.png",
+            Format::Pdf => "image.pdf",
+            Format::Eps => "image.eps",
+            Format::Tikz => "image.tex",
+            Format::Csv => "image.csv",
+            Format::Json => "image.json",
+            Format::Npy => "image.npy",
+            Format::SphereObj => "image.obj",
+            Format::SphereJson => "image.json",
         }
     }
 }
 
-// #[derive(Debug)]
-struct Kleinian {
-    mats: Bag<Mat>,
-    data: Option<Data>,
-    ends: Bag<Vec<Complex<f64>>>,
-    last: Complex<f64>,
+/// Logs a warning and keeps it for `--stats-json`.
+fn warn(warnings: &mut Vec<String>, msg: String) {
+    log::warn!("{}", msg);
+    warnings.push(msg);
 }
 
-impl Kleinian {
-    fn new(a: Mat, b: Mat) -> Kleinian {
-        let (ainv, binv) = (a.adj(), b.adj());
-        let bag = Bag::new(a, b, ainv, binv);
-        let vecs = Bag::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
-        Kleinian {
-            mats: bag,
-            data: None,
-            ends: vecs,
-            last: Complex::new(1.0, 0.0),
+/// Saves as PNG, PDF or EPS by the extension, when built with those
+/// features, and as SVG otherwise, gzipped for `.svgz`.
+fn save_tile(path: &str, document: &svg::Document) {
+    #[cfg(feature = "png")]
+    {
+        if path.ends_with(".png") {
+            return or_exit(svg_kleinian::render_png(document, path, 1000), path);
         }
     }
-
-    fn mat(&self, l: Letter) -> &Mat {
-        self.mats.at(l)
+    #[cfg(feature = "pdf")]
+    {
+        if path.ends_with(".pdf") {
+            return save_pdf(path, document, None).unwrap_or_else(|f| f.exit());
+        }
     }
-
-    fn prod(&self, word: Vec<Letter>) -> Mat {
-        word.iter().fold(Mat::id(), |acc, &l| acc * self.mat(l))
+    #[cfg(feature = "eps")]
+    {
+        if path.ends_with(".eps") {
+            return save_eps(path, document, (720, 720)).unwrap_or_else(|f| f.exit());
+        }
     }
+    let level = if path.ends_with(".svgz") { Some(DEFAULT_COMPRESSION) } else { None };
+    or_exit(write_svg(path, document, level), path);
+}
 
-    fn add_end(&mut self, word: Vec<Letter>) {
-        // be careful to add ends in the correct order!
-        if let Some(&l) = word.last() {
-            let z = self.prod(word).fix();
-            self.ends.at_mut(l).push(z);
-        } else {
-            panic!("can't add the fixed point of the identity");
+fn tiles_main(m: &ArgMatches) {
+    let traces = m.get_one::<&Preset>("preset").map_or((Complex::new(2.0, 0.0), Complex::new(2.0, 0.0)), |p| (p.ta, p.tb));
+    let (x, y, w, h) = *m.get_one::<(f64, f64, f64, f64)>("window").expect("required");
+    let size = m.get_one::<(usize, usize)>("grid").copied().unwrap_or((2, 2));
+    let level = m.get_one::<i64>("depth").copied().unwrap_or(50);
+    let pattern = m.get_one::<String>("output").map_or("tile-{row}-{col}.svg", String::as_str);
+    let (cols, rows) = size;
+    let (tw, th) = (w / cols as f64, h / rows as f64);
+    // one epsilon for every tile, fine enough for the tiles' scale, so
+    // neighbours cut the same polyline at their shared edges
+    let scale = tw.max(th) / RenderOptions::default().view_box.2;
+    let mut opts = RenderOptions {
+        epsilon: EPSILON * scale,
+        stroke_width: RenderOptions::default().stroke_width * scale,
+        ..RenderOptions::default()
+    };
+    cli::style_options(m, &mut opts);
+    let opts = &opts;
+    std::thread::scope(|s| {
+        for row in 0..rows {
+            for col in 0..cols {
+                let path = pattern.replace("{row}", &row.to_string()).replace("{col}", &col.to_string());
+                s.spawn(move || {
+                    let mut g = grandma(traces.0, traces.1);
+                    for &l in &[A, B, AI, BI] {
+                        g.add_end(vec![l]);
+                    }
+                    let window = tile_window((x, y, w, h), size, row, col);
+                    save_tile(&path, &tile(&mut g, level, window, opts));
+                });
+            }
         }
-    }
+    });
+}
 
-    fn line(&mut self, z: Complex<f64>) {
-        let data = self.data.take();
-        self.data = match data {
-            Some(d) => Some(d.line_to((z.re, z.im))),
-            None => Some(Data::new().move_to((z.re, z.im))),
-        };
-        self.last = z;
-        // mem::replace(&mut self.data, self.data.line_to((z.re, z.im)));
+fn grid_main(m: &ArgMatches) {
+    let mut opts = RenderOptions {
+        max_nodes: Some(2_000_000),
+        // cells are small, so a coarser trace is enough
+        epsilon: 0.005,
+        ..RenderOptions::default()
+    };
+    cli::style_options(m, &mut opts);
+    let size = m.get_one::<(usize, usize)>("size").copied().unwrap_or((6, 6));
+    let level = m.get_one::<i64>("depth").copied().unwrap_or(30);
+    let output = m.get_one::<String>("output").map_or("grid.svg", String::as_str);
+    if let Some(&epsilon) = m.get_one("epsilon") {
+        opts.epsilon = epsilon;
+    }
+    if let Some(&n) = m.get_one("max-nodes") {
+        opts.max_nodes = Some(n);
     }
+    opts.stroke_width = 0.0015;
+    let document = match m.get_one("mu") {
+        Some(&mu) => grid_mu(mu, size, level, &opts),
+        None => grid(*m.get_one("ta").expect("required"), *m.get_one("tb").expect("required"), size, level, &opts),
+    };
+    save_tile(output, &document);
 }
 
-fn branch(level: i64, l: Letter, t: &Mat, g: &mut Kleinian) {
-
-    let (l1, l2, l3) = match l {
-        A => (B, A, BI),
-        B => (AI, B, A),
-        AI => (BI, AI, B),
-        BI => (A, BI, AI),
+fn animate_main(m: &ArgMatches) {
+    let mut opts = RenderOptions {
+        max_nodes: Some(2_000_000),
+        ..RenderOptions::default()
     };
-    let one = Complex::new(1.0, 0.0);
+    cli::style_options(m, &mut opts);
+    let mut anim = AnimateOptions::default();
+    let range = |id| m.get_one::<(Complex<f64>, Complex<f64>)>(id).copied();
+    let (mut ta, mut tb) = (range("ta"), range("tb"));
+    // the Maskit slice: ta = -iμ with b parabolic
+    if let Some((mu0, mu1)) = range("mu") {
+        let ((ta0, tb0), (ta1, tb1)) = (maskit_traces(mu0), maskit_traces(mu1));
+        ta = Some((ta0, ta1));
+        tb = Some((tb0, tb1));
+    }
+    // a Fuchsian group bent out of the plane: ta turns away from the real
+    // line, by default keeping its modulus, with tb fixed
+    let bend = m.contains_id("bend");
+    if bend {
+        ta = range("bend");
+    }
+    let farey = m.get_one::<Vec<(i64, i64)>>("farey");
+    let cusp_offset = m.get_one::<f64>("cusp-offset").copied().unwrap_or(FAREY_OFFSET);
+    anim.reference_circle = m.get_flag("reference-circle");
+    if let Some(&n) = m.get_one("frames") {
+        anim.frames = n;
+    }
+    if let Some(&depth) = m.get_one("depth") {
+        anim.level = depth;
+    }
+    let interpolation = m.get_one::<Interpolation>("interpolate").copied();
+    if let Some(&n) = m.get_one("max-points") {
+        anim.max_points = n;
+    }
+    if let Some(&fps) = m.get_one::<f64>("fps") {
+        anim.frame_duration = 1.0 / fps;
+    }
+    if let Some(&t) = m.get_one("frame-duration") {
+        anim.frame_duration = t;
+    }
+    let frames_pattern = m.get_one::<String>("out");
+    let resume = m.get_flag("resume");
+    let encoded: Option<Vec<&String>> = m.get_many("format").map(Iterator::collect);
+    let size = m.get_one::<(u32, u32)>("size").copied().unwrap_or((800, 800));
+    let plays = m.get_one::<u16>("loop").copied().unwrap_or(0);
+    let output = m.get_one::<String>("output").map_or("animation.svg", String::as_str);
+    if let Some(fractions) = farey {
+        let path = match farey_path(fractions, cusp_offset, &mut CuspCache::new()) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let stops: Vec<_> = path.into_iter().map(maskit_traces).collect();
+        let (first, last) = (stops[0], stops[stops.len() - 1]);
+        ta = Some((first.0, last.0));
+        tb = Some((first.1, last.1));
+        anim.via = stops[1..stops.len() - 1].to_vec();
+    }
+    if bend {
+        anim.interpolation = Interpolation::Polar;
+        if tb.is_none() {
+            tb = ta.map(|(ta, _)| (ta, ta));
+        }
+    }
+    if let Some(how) = interpolation {
+        anim.interpolation = how;
+    }
+    let (ta, tb) = match (ta, tb) {
+        (Some(ta), Some(tb)) => (ta, tb),
+        _ => unreachable!("clap requires a path for both traces"),
+    };
+    if bend && !grandma(ta.0, tb.0).is_fuchsian() {
+        log::warn!("the bend starts at ta = {}, tb = {}, which is not Fuchsian", fmt_complex(ta.0, COMPLEX_PRECISION), fmt_complex(tb.0, COMPLEX_PRECISION));
+    }
+    if let Some(encoded) = encoded {
+        return save_animation(ta, tb, &anim, &opts, encoded[0], encoded[1], size, plays);
+    }
+    if let Some(pattern) = frames_pattern {
+        for warning in render_frames(ta, tb, &anim, &opts, pattern, resume) {
+            log::warn!("{}", warning);
+        }
+        return;
+    }
+    let document = animate(ta, tb, &anim, &opts);
+    let level = if output.ends_with(".svgz") { Some(DEFAULT_COMPRESSION) } else { None };
+    or_exit(write_svg(output, &document, level), output);
+}
 
-    let t = t * &g.mat(l);
-    let mut z = g.last;
-    let mut end_branch = true;
+fn golden_main(m: &ArgMatches) {
+    let update = m.get_flag("update");
+    let path = svg_kleinian::GOLDEN_PATH;
+    if update {
+        or_exit(svg_kleinian::update_golden(path), path);
+        log::info!("wrote {}", path);
+        return;
+    }
+    match svg_kleinian::check_golden(path) {
+        Ok(true) => note!("{} matches", path),
+        Ok(false) => {
+            eprintln!("the trace no longer matches {}; if that's intended, rerun with --update", path);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
 
-    let mut to_draw = Vec::new();
+/// The arguments with each `--config FILE` replaced by the options in
+/// FILE, or on standard input for `-`: whitespace-separated, as on the
+/// command line, with `#` commenting out the rest of a line. A FILE
+/// ending in `.toml` is a scene file instead, whose options go before all
+/// the others, so the command line overrides them. So do those each
+/// `--from-svg FILE` gives, the parameters FILE was rendered with.
+/// The parameters a render records in its SVG, as `data-` attributes
+/// named like the options that set them.
+const FROM_SVG_KEYS: [&str; 5] = ["recipe", "ta", "tb", "level", "epsilon"];
 
-    for &pt in g.ends.at(l) {
-        let w = t.mob(pt);
-        if level > 0 && (z - w).norm_sqr() > EPSILON * EPSILON {
-            
-            end_branch = false;
-            break;
+fn with_config(mut args: impl Iterator<Item = String>) -> Result<Vec<String>, Failure> {
+    let mut scenes = Vec::new();
+    let mut expanded = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--from-svg" {
+            let path = args.next().ok_or_else(|| Failure::Error("--from-svg needs a FILE".to_string(), 2))?;
+            let recorded: Vec<_> = svg_metadata(&or_fail(read_svg(&path), &path)?).into_iter()
+                .filter(|(key, _)| FROM_SVG_KEYS.contains(&key.as_str()))
+                .collect();
+            if recorded.is_empty() {
+                return Err(Failure::Error(format!("{}: no parameters recorded", path), 2));
+            }
+            scenes.extend(recorded.into_iter().flat_map(|(key, value)| [format!("--{}", key), value]));
+            continue;
+        }
+        if arg != "--config" {
+            expanded.push(arg);
+            continue;
+        }
+        let path = args.next().ok_or_else(|| Failure::Error("--config needs a FILE".to_string(), 2))?;
+        let text = read_input(&path).and_then(|bytes| {
+            String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        });
+        let text = or_fail(text, &path)?;
+        if path.ends_with(".toml") {
+            scenes.extend(or_fail(Scene::from_toml(&text), &path)?.to_args());
+            continue;
+        }
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            expanded.extend(line.split_whitespace().map(String::from));
         }
-        to_draw.push(w);
-        z = w;
     }
+    // an animation scene brings its own `animate`, and `render` goes
+    // before the scene's options
+    match (scenes.first().map(String::as_str), expanded.first().map(String::as_str)) {
+        (Some("animate"), Some("animate")) => {
+            expanded.remove(0);
+        }
+        (Some(_), Some("render")) => {
+            expanded.remove(0);
+            scenes.insert(0, "render".to_string());
+        }
+        _ => {}
+    }
+    scenes.extend(expanded);
+    Ok(scenes)
+}
 
-    if end_branch {
-        for w in to_draw {
-            g.line(w);
+fn main() {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    if raw.iter().any(|a| a == "--watch") {
+        // checked as given, but the file is read afresh for each render
+        parse(raw.clone());
+        return watch_main(raw.into_iter().filter(|a| a != "--watch").collect());
+    }
+    let arguments = with_config(raw.into_iter()).unwrap_or_else(|f| f.exit());
+    let m = parse(arguments.clone());
+    let arguments = report_arguments(arguments);
+    match m.subcommand() {
+        Some(("render", m)) if m.get_flag("dump-config") => {
+            let arguments: Vec<String> = arguments.into_iter().filter(|a| a != "--dump-config").collect();
+            print!("{}", Scene::from_args(&arguments).to_toml());
         }
-        return;
+        Some(("render", m)) => render_main(m, arguments),
+        Some(("info", m)) => info_main(m),
+        Some(("cusp", m)) => cusp_main(m),
+        Some(("animate", m)) => animate_main(m),
+        Some(("grid", m)) => grid_main(m),
+        Some(("tiles", m)) => tiles_main(m),
+        Some(("batch", m)) => batch_main(m),
+        Some(("serve", m)) => serve_main(m),
+        Some(("repl", m)) => repl_main(m),
+        Some(("golden", m)) => golden_main(m),
+        _ => unreachable!("clap requires one of the subcommands"),
     }
+}
 
-    branch(level - 1, l1, &t, g);
-    branch(level - 1, l2, &t, g);
-    branch(level - 1, l3, &t, g);
+// the arguments as a scene or report sees them, without those for logging
+fn report_arguments(args: Vec<String>) -> Vec<String> {
+    args.into_iter()
+        .filter(|a| !matches!(a.as_str(), "-q" | "--quiet" | "-v" | "--verbose" | "-vv" | "-vvv"))
+        .collect()
 }
 
-fn limitset(level: i64, g: &mut Kleinian) {
-    let one = Complex::new(1.0, 0.0);
-    let t = Mat::id();
-    //add the end of each segment as an end
-    g.add_end(vec![BI, AI, B, A]);
-    g.add_end(vec![A, BI, AI, B]);
-    g.add_end(vec![B, A, BI, AI]);
-    g.add_end(vec![AI, B, A, BI]);
+/// Parses the arguments, exiting with clap's message if they don't, and
+/// sets up logging by -q and -v. Options without a subcommand are taken
+/// for `render`'s, with a warning.
+fn parse(args: Vec<String>) -> ArgMatches {
+    let (args, deprecated) = cli::with_subcommand(args);
+    let m = cli::command().get_matches_from(std::iter::once("svg_kleinian".to_string()).chain(args));
+    let (_, sub) = m.subcommand().expect("clap requires a subcommand");
+    init_logging(verbosity_level(sub.get_flag("quiet"), sub.get_count("verbose") as usize));
+    if deprecated {
+        log::warn!("options without a subcommand are deprecated, and will need `svg_kleinian render` in the next release");
+    }
+    m
+}
 
-    g.line(one);
-    branch(level - 1, A, &t, g);
-    branch(level - 1, BI, &t, g);
-    branch(level - 1, AI, &t, g);
-    branch(level - 1, B, &t, g);
+fn render_main(m: &ArgMatches, arguments: Vec<String>) {
+    if let Err(f) = render(m, arguments, None) {
+        f.exit();
+    }
 }
 
-fn main() {
-    // println!("{:?}", Mat::id());
-    let one = Complex::new(1.0,0.0);
-    // let zero = Complex::new(0.0,0.0);
-    // let ma = Mat::new(one,one,zero,one);
-    // let mb = Mat::new(one,zero,one,one);
-    // let mr = &ma * &mb;
-    // let ma_inv = ma.adj();
-    // let mb_inv = mb.adj();
-    // let mr2 = &ma_inv * &mb_inv;
-    // let mr_inv = mr.adj();
-    // let mr2_inv = mr2.adj();
-    // println!("{:?}", (&mr * &mr2 * &mr_inv * &mr2_inv).mob(zero));
-
-    // let mut g = grandma(Complex::new(1.73205080757,1.0), Complex::new(2.0,0.0));
-    let mut g = grandma(Complex::new(2.0, 0.0), Complex::new(2.0, 0.0));
+/// Renders as `m` says, stopping when `cancel` is set, in which case it
+/// writes nothing.
+fn render(m: &ArgMatches, arguments: Vec<String>, cancel: Option<Arc<AtomicBool>>) -> Result<(), Failure> {
+    let mut opts = RenderOptions { cancel, ..RenderOptions::default() };
+    let mut warnings = Vec::new();
+    let mut config = cli::render_config(m, RenderConfig::default());
+    cli::style_options(m, &mut opts);
+    let flag = |id| m.get_flag(id);
+    let html = m.get_one::<String>("html");
+    let dump = flag("dump-generators");
+    let stream = flag("stream-points");
+    let analyze = flag("analyze");
+    let verify = match m.get_one::<f64>("verify-tolerance") {
+        Some(&tol) => Some(Some(tol)),
+        None if flag("verify") => Some(None),
+        None => None,
+    };
+    let model = m.get_one::<Model>("model").copied();
+    // the points are placed after any other post-transform
+    let camera = m.get_one::<Mat>("camera").cloned();
+    let relators: Vec<Word> = m.get_many("relator").map_or_else(Vec::new, |w| w.cloned().collect());
+    let stats_json = m.get_one::<String>("stats-json");
+    let center = m.get_one::<Complex<f64>>("center").copied();
+    let zoom = m.get_one::<f64>("zoom").copied().unwrap_or(1.0);
+    let curves: Vec<Curve> = m.get_many("curve").map_or_else(Vec::new, |c| c.cloned().collect());
+    let format = match m.get_one::<String>("format").map_or("svg", String::as_str) {
+        "svg" => Format::Svg,
+        "png" => Format::Png,
+        "pdf" => Format::Pdf,
+        "eps" => Format::Eps,
+        "tikz" => Format::Tikz,
+        "heatmap" => Format::Heatmap,
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        "npy" => Format::Npy,
+        "sphere-obj" => Format::SphereObj,
+        "sphere-json" => Format::SphereJson,
+        other => unreachable!("clap allows only the formats, not {}", other),
+    };
+    let mut tone = ToneMap::default();
+    if let Some(&gamma) = m.get_one("gamma") {
+        tone.gamma = gamma;
+    }
+    tone.log = flag("log");
+    let mut tikz = TikzOptions { standalone: flag("standalone"), ..TikzOptions::default() };
+    if let Some(&cm) = m.get_one("tikz-width") {
+        tikz.width = cm;
+    }
+    let compress = flag("compress");
+    let compression = m.get_one::<u32>("compression-level").copied();
+    let ndjson = flag("ndjson");
+    let words = flag("words");
+    let with_depth = flag("with-depth");
+    let colormap = m.get_one::<Colormap>("colormap").cloned().unwrap_or(Colormap::Gray);
+    let size = m.get_one::<(u32, u32)>("size").copied();
+    let stroke_px = m.get_one::<f64>("stroke-px").copied();
+    let supersample = m.get_one::<u32>("supersample").copied().unwrap_or(1);
+
+    if flag("color-letters") {
+        opts.letter_colors = Some(RenderOptions::default_letter_colors());
+    }
+    // --overlay and --circles draw in the order given
+    let mut overlays = cli::indexed::<Overlay>(m, "overlay");
+    overlays.extend(cli::indexed::<usize>(m, "circles").into_iter().map(|(k, n)| (k, Overlay::SchottkyCircles(n))));
+    overlays.sort_by_key(|&(k, _)| k);
+    for (_, overlay) in overlays {
+        if let Overlay::Tessellation(n) = overlay {
+            if n > 8 {
+                warn(&mut warnings, format!("tessellation={} draws {} tiles", n, 2 * 3usize.pow(n as u32) - 1));
+            }
+        }
+        opts.overlays.push(overlay);
+    }
+    if let Some(&size) = m.get_one("marker-size") {
+        opts.marker_size = size;
+    }
+    if let Some(color) = m.get_one::<String>("marker-color") {
+        opts.marker_color = color.clone();
+    }
+    opts.chords = flag("chords");
+    opts.smooth = flag("smooth");
+    opts.gasket = flag("gasket");
+    if let Some(&taper) = m.get_one("tree-taper") {
+        opts.tree_taper = taper;
+    }
+    if let Some(&opacity) = m.get_one("domain-opacity") {
+        opts.domain_opacity = opacity;
+    }
+    if let Some(&h) = m.get_one("horoball-height") {
+        opts.horoball_height = h;
+    }
+    if let Some(&factor) = m.get_one("epsilon-decay") {
+        opts.epsilon_decay = Some(factor);
+    }
+    opts.mirror = m.get_one("mirror").copied();
+    opts.mirror_stroke = m.get_one::<String>("mirror-stroke").cloned();
+    opts.post_transform = m.get_one::<Mat>("post-transform").cloned();
+    opts.clip = m.get_one("clip").copied();
+    opts.ribbon_width = m.get_one("ribbon").copied();
+    opts.coordinate_precision = m.get_one("precision").copied();
+    opts.outline = m.get_one("outline").copied();
+    opts.seed_word = m.get_one::<Word>("seed-word").cloned();
+    opts.prefixes = m.get_many("prefix").map_or_else(Vec::new, |w| w.cloned().collect());
+    if let Some(&order) = m.get_one("branch-order") {
+        opts.branch_order = order;
+    }
+    opts.gradient = m.get_one::<String>("gradient").map(|colors| colors.split(',').map(|c| c.trim().to_string()).collect());
+
+    config.validate().map_err(|e| Failure::Error(e.to_string(), 2))?;
+    let (ta, tb) = config.traces();
+    let level = config.level;
+    opts.epsilon = config.epsilon;
+    opts.max_nodes = config.max_nodes;
+    let mut g = config.group().map_err(|e| Failure::Error(e.to_string(), 2))?;
+    // for --from-svg, under the names of FROM_SVG_KEYS
+    opts.metadata.extend([
+        ("recipe", config.recipe.name().to_string()),
+        ("ta", fmt_complex(config.ta, COMPLEX_PRECISION)),
+        ("tb", fmt_complex(config.tb, COMPLEX_PRECISION)),
+        ("level", level.to_string()),
+        ("epsilon", opts.epsilon.to_string()),
+    ].map(|(key, value)| (key.to_string(), value)));
+    for r in relators {
+        g = g.with_relator(r);
+    }
+    if let Some(model) = model {
+        let t = g.disk_transform();
+        g = g.conjugate(&t);
+        let t = match model {
+            Model::Disk => t,
+            Model::HalfPlane => {
+                // ∞ is on the limit set in this model, which the tracer
+                // can't refine towards, so trace in the disk and map the
+                // points over as they are drawn
+                let cayley = disk_to_halfplane();
+                opts.post_transform = Some(match opts.post_transform.take() {
+                    Some(post) => &post * &cayley,
+                    None => cayley.clone(),
+                });
+                &cayley * &t
+            }
+        };
+        let text = [t.a, t.b, t.c, t.d].map(|z| fmt_complex(z, COMPLEX_PRECISION)).join(",");
+        log::info!("{:?} model, z -> (az + b)/(cz + d) with a, b, c, d = {}", model, text.replace(",", ", "));
+        opts.metadata.push(("model".to_string(), format!("{:?}", model).to_lowercase()));
+        opts.metadata.push(("model-transform".to_string(), text));
+    }
+    if let Some(camera) = camera {
+        opts.post_transform = Some(match opts.post_transform.take() {
+            Some(post) => &camera * &post,
+            None => camera,
+        });
+    }
+    // the modes that print rather than draw write to -o, standard output
+    // by default
+    let printed = config.output.clone().unwrap_or_else(|| "-".to_string());
+    if dump {
+        let mut w = or_fail(create_output(&printed), &printed)?;
+        return or_fail(dump_generators(&g, &mut w).and_then(|()| w.flush()), &printed);
+    }
     g.add_end(vec![A]);
     g.add_end(vec![B]);
     g.add_end(vec![AI]);
     g.add_end(vec![BI]);
-    // println!("{:?}", a);
-    // println!("{:?}", b);
-    // println!("{:?}", &a * &b);
-    // println!("{:?}", &a * &b * &a.adj() * &b.adj());
-
-    // let v = &g.b * &g.a * &g.binv * &g.ainv;
-    // println!("{:?}", v);
-    // println!("{:?}", v.mob(one));
+    if analyze {
+        let rows = analyze_depths(&mut g, level, &opts);
+        let mut w = or_fail(create_output(&printed), &printed)?;
+        return or_fail(write_analysis(&rows, &mut w).and_then(|()| w.flush()), &printed);
+    }
+    if stream {
+        let mut w = or_fail(create_output(&printed), &printed)?;
+        return or_fail(g.stream_points_with(level, &opts, &mut w), &printed);
+    }
+    if log::log_enabled!(log::Level::Trace) {
+        log::trace!("a as traced =\n{}", g.mat(A));
+        log::trace!("b as traced =\n{}", g.mat(B));
+        log::trace!("abAB =\n{}", g.word(&"abAB".parse().expect("a valid word")));
+    }
 
+    let mut timings = Vec::new();
+    if opts.gasket && !g.is_gasket() {
+        warn(&mut warnings, "the limit set is not the Apollonian gasket, tracing it instead".to_string());
+        opts.gasket = false;
+    }
+    let start = Instant::now();
+    if !opts.gasket {
+        g.set_record_words(matches!(format, Format::Csv | Format::Json | Format::Npy));
+        limitset_with(level, &mut g, &opts);
+        if g.cancelled() {
+            return Err(Failure::Cancelled);
+        }
+        if g.points().len() < 2 && curves.is_empty() {
+            let e = svg_kleinian::Error::NoPoints { level, epsilon: opts.epsilon };
+            return Err(Failure::Error(format!("error: {}, not writing output", e), 1));
+        }
+    }
+    timings.push(("trace", start.elapsed()));
+    if let Some(tolerance) = verify {
+        let start = Instant::now();
+        if opts.gasket {
+            limitset_with(level, &mut g, &opts);
+        }
+        let tolerance = tolerance.unwrap_or(VERIFY_TOLERANCE * g.epsilon());
+        let check = g.verify_invariance();
+        log::info!("checked {} images of {} points, largest deviation {:.3e} (tolerance {:.3e})",
+            check.checked, g.points().len(), check.deviation, tolerance);
+        if let Some((z, l)) = check.worst {
+            log::info!("worst point {} under {}, moved to {}", fmt_complex(z, COMPLEX_PRECISION), l, fmt_complex(g.mat(l).mob(z), COMPLEX_PRECISION));
+        }
+        if check.deviation > tolerance {
+            let message = "error: the traced points are not invariant under the generators, not writing output";
+            return Err(Failure::Error(message.to_string(), 1));
+        }
+        if let Err(e) = g.verify_endfix(tolerance) {
+            return Err(Failure::Error(format!("error: {}, not writing output", e), 1));
+        }
+        timings.push(("verify", start.elapsed()));
+    }
+    if !opts.prefixes.is_empty() {
+        // frame just the traced pieces, with a small margin
+        let (x0, y0, x1, y1) = g.bounding_box();
+        let pad = 0.05 * (x1 - x0).max(y1 - y0);
+        opts.view_box = (x0 - pad, y0 - pad, x1 - x0 + 2.0 * pad, y1 - y0 + 2.0 * pad);
+    }
+    if center.is_some() || zoom != 1.0 {
+        let (x, y, w, h) = opts.view_box;
+        let c = center.unwrap_or(Complex::new(x + w / 2.0, y + h / 2.0));
+        let (w, h) = (w / zoom, h / zoom);
+        opts.view_box = (c.re - w / 2.0, c.im - h / 2.0, w, h);
+        // the stroke is in viewBox units, so keep it as thick on the page
+        opts.stroke_width /= zoom;
+        // a little beyond the edge, so strokes aren't cut short visibly,
+        // unless --clip asked for a window of its own
+        let pad = 0.05 * w.max(h);
+        opts.clip.get_or_insert((c.re - w / 2.0 - pad, c.im - h / 2.0 - pad, w + 2.0 * pad, h + 2.0 * pad));
+    }
 
+    let size_given = size;
+    let size = size.unwrap_or(format.default_size());
+    let output = config.output.take().unwrap_or_else(|| format.default_output(compress).to_string());
+    log::info!("writing {} as {:?}, {}x{}, viewBox {:?}, stroke width {}",
+        output, format, size.0, size.1, opts.view_box, opts.stroke_width);
+    let start = Instant::now();
+    match format {
+        Format::Heatmap => {
+            let points = match opts.post_transform {
+                Some(ref m) => m.mob_batch(g.points()),
+                None => g.points().to_vec(),
+            };
+            let (w, h) = size;
+            let heatmap = Heatmap::from_points(&points, opts.view_box, w as usize, h as usize);
+            save_heatmap(&output, &heatmap, &tone, colormap)?;
+        }
+        Format::Csv | Format::Json | Format::Npy => {
+            let records = g.point_records().expect("words were recorded");
+            let mut w = or_fail(create_output(&output), &output)?;
+            let params = vec![
+                ("ta".to_string(), fmt_complex(ta, COMPLEX_PRECISION)),
+                ("tb".to_string(), fmt_complex(tb, COMPLEX_PRECISION)),
+                ("level".to_string(), level.to_string()),
+                ("epsilon".to_string(), opts.epsilon.to_string()),
+            ];
+            let written = match format {
+                Format::Npy if output.ends_with(".npz") => {
+                    npz_files(&records, &params, with_depth).and_then(|files| write_npz(&files, &mut w))
+                }
+                Format::Npy => write_npy(&point_columns(&records, with_depth), if with_depth { 3 } else { 2 }, &mut w),
+                Format::Csv => write_csv(&records, words, opts.coordinate_precision, &mut w),
+                _ if ndjson => write_ndjson(&records, &params, words, &mut w),
+                _ => write_json(&records, &params, words, &mut w),
+            }.and_then(|_| w.flush());
+            or_fail(written, &output)?;
+        }
+        Format::SphereObj | Format::SphereJson => {
+            let mut w = or_fail(create_output(&output), &output)?;
+            let written = if format == Format::SphereObj {
+                g.write_sphere_obj(&opts, &mut w)
+            } else {
+                g.write_sphere_json(&opts, &mut w)
+            }.and_then(|_| w.flush());
+            or_fail(written, &output)?;
+        }
+        Format::Tikz => {
+            let mut w = or_fail(create_output(&output), &output)?;
+            or_fail(g.write_tikz(&opts, &tikz, &mut w).and_then(|_| w.flush()), &output)?;
+        }
+        Format::Svg | Format::Png | Format::Pdf | Format::Eps => {
+            if let Some(px) = stroke_px {
+                // the viewBox is fitted into the image, so this is the pixel size
+                let (_, _, w, h) = opts.view_box;
+                let scale = (size.0 as f64 / w).min(size.1 as f64 / h);
+                opts.stroke_width = px / scale;
+            }
 
-    limitset(50, &mut g);
+            if log::log_enabled!(log::Level::Info) && opts.overlays.contains(&Overlay::IsometricCircles) {
+                for &l in &[A, B, AI, BI] {
+                    if g.mat(l).isometric_circle().is_none() {
+                        log::info!("{} fixes infinity, skipping its isometric circle", l);
+                    }
+                }
+                let circles: Vec<_> = [A, B, AI, BI].iter()
+                    .filter_map(|&l| g.mat(l).isometric_circle().map(|c| (l, c)))
+                    .collect();
+                for (n, &(l1, c1)) in circles.iter().enumerate() {
+                    for &(l2, c2) in &circles[n + 1..] {
+                        log::info!("inversive distance {} {}: {}", l1, l2, inversive_distance(c1, c2));
+                    }
+                }
+            }
+            for overlay in &opts.overlays {
+                if let Overlay::Geodesics(fractions) = overlay {
+                    for &(p, q) in fractions {
+                        if g.word(&Word::farey(p, q).unwrap()).is_parabolic() {
+                            warn(&mut warnings, format!("w_{}/{} is parabolic, marking its fixed point instead", p, q));
+                        }
+                    }
+                }
+            }
 
-    let path = Path::new()
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", 0.001)
-        .set("d", g.data.unwrap());
-    
-    let document = Document::new()
-        .set("viewBox", (-1.2, -1.2, 2.4, 2.4))
-        .add(path);
+            let document = if curves.is_empty() {
+                g.document(&opts)
+            } else {
+                svg_kleinian::curves(&curves, level, &opts)
+            };
+            match format {
+                Format::Png => save_png(&output, &document, size, supersample)?,
+                Format::Pdf => save_pdf(&output, &document, size_given)?,
+                Format::Eps => save_eps(&output, &document, size)?,
+                _ => {
+                    if let Some(path) = html {
+                        let caption = format!("ta = {}, tb = {}, level {}", fmt_complex(ta, COMPLEX_PRECISION), fmt_complex(tb, COMPLEX_PRECISION), level);
+                        let mut w = or_fail(create_output(path), path)?;
+                        or_fail(w.write_all(html::page(&document.to_string(), &caption).as_bytes()).and_then(|_| w.flush()), path)?;
+                    }
+                    let level = if compress || output.ends_with(".svgz") {
+                        Some(compression.unwrap_or(DEFAULT_COMPRESSION))
+                    } else {
+                        None
+                    };
+                    or_fail(write_svg(&output, &document, level), &output)?;
+                }
+            }
+        }
+    }
+    timings.push(("write", start.elapsed()));
+    for (phase, t) in &timings {
+        log::debug!("{} took {:.3}s", phase, t.as_secs_f64());
+    }
 
-    svg::save("image.svg", &document).unwrap();
+    if let Some(path) = stats_json {
+        let mut report = RenderReport::new(&g, &opts, level, config.recipe.name(), arguments);
+        report.timings = timings.into_iter().map(|(phase, t)| (phase.to_string(), t.as_secs_f64())).collect();
+        report.warnings = warnings;
+        report.output = match OutputFile::from_path(&output) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("{}: {}", output, e);
+                None
+            }
+        };
+        let mut w = or_fail(create_output(path), path)?;
+        or_fail(report.write_json(&mut w).and_then(|_| w.flush()), path)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,90 @@
+//! The Maskit slice, where b is parabolic with trace 2 and a has trace -iμ,
+//! and its cusps: the μ on the edge of the slice where the Farey word
+//! w_{p/q} becomes parabolic too.
+
+use num::complex::Complex;
+use std::collections::HashMap;
+
+use crate::{fmt_complex, Error, Kleinian, Mat, Word, COMPLEX_PRECISION};
+
+/// Newton's method stops once a step is smaller than this.
+pub const CUSP_TOLERANCE: f64 = 1e-12;
+
+/// Steps Newton's method takes before giving up on a cusp.
+pub const CUSP_ITERATIONS: usize = 100;
+
+/// How far above the cusps `farey_path` passes by default, into the
+/// slice where the groups are discrete.
+pub const FAREY_OFFSET: f64 = 0.05;
+
+/// The traces of a and b at μ, as `grandma` takes them.
+pub fn maskit_traces(mu: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+    (-Complex::<f64>::i() * mu, Complex::new(2.0, 0.0))
+}
+
+/// Maskit's generators for μ, a = [-iμ, -i; -i, 0] and b = [1, 2; 0, 1],
+/// as given in Indra's Pearls.
+pub fn maskit(mu: Complex<f64>) -> Kleinian {
+    let i = Complex::i();
+    let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+    Kleinian::new(Mat::new(-i * mu, -i, -i, zero), Mat::new(one, one * 2.0, zero, one))
+}
+
+/// The cusp for p/q: where w_{p/q} has trace 2, found by Newton's method
+/// from 2p/q + 2i, which lands on the root of the trace polynomial
+/// highest up the slice. Fails when p/q is not in lowest terms or the
+/// iteration does not settle.
+pub fn maskit_cusp(p: i64, q: i64) -> Result<Complex<f64>, Error> {
+    let word = Word::farey(p, q)
+        .ok_or_else(|| Error::Parameters(format!("{}/{} is not a fraction in lowest terms", p, q)))?;
+    let trace = |mu| maskit(mu).word(&word).trace();
+    let mut mu = Complex::new(2.0 * p as f64 / q as f64, 2.0);
+    let h = 1e-7;
+    for k in 0..CUSP_ITERATIONS {
+        let slope = (trace(mu + h) - trace(mu - h)) / (2.0 * h);
+        let step = (trace(mu) - 2.0) / slope;
+        if !step.is_finite() {
+            log::debug!("Newton's method for {}/{} hit a flat spot at μ = {}", p, q, fmt_complex(mu, COMPLEX_PRECISION));
+            break;
+        }
+        mu -= step;
+        log::trace!("Newton's method for {}/{}: μ = {}, step {:.3e}", p, q, fmt_complex(mu, COMPLEX_PRECISION), step.norm());
+        if step.norm() < CUSP_TOLERANCE {
+            log::debug!("the {}/{} cusp settled after {} steps", p, q, k + 1);
+            return Ok(mu);
+        }
+    }
+    Err(Error::NoConvergence(format!("Newton's method found no cusp for {}/{}, stopping near μ = {}", p, q, fmt_complex(mu, COMPLEX_PRECISION))))
+}
+
+/// Cusps already solved for, so a path that comes back to a fraction, or
+/// a run that draws several paths, solves each once.
+#[derive(Debug, Clone, Default)]
+pub struct CuspCache {
+    cusps: HashMap<(i64, i64), Complex<f64>>,
+}
+
+impl CuspCache {
+    pub fn new() -> Self {
+        CuspCache::default()
+    }
+
+    /// `maskit_cusp(p, q)`, solved once; failures are not kept.
+    pub fn cusp(&mut self, p: i64, q: i64) -> Result<Complex<f64>, Error> {
+        if let Some(&mu) = self.cusps.get(&(p, q)) {
+            return Ok(mu);
+        }
+        let mu = maskit_cusp(p, q)?;
+        self.cusps.insert((p, q), mu);
+        Ok(mu)
+    }
+}
+
+/// The μ a walk along the edge of the Maskit slice passes through: the
+/// cusp of each fraction, `offset` above it so the groups there stay
+/// discrete.
+pub fn farey_path(fractions: &[(i64, i64)], offset: f64, cache: &mut CuspCache) -> Result<Vec<Complex<f64>>, Error> {
+    fractions.iter()
+        .map(|&(p, q)| cache.cusp(p, q).map(|mu| mu + Complex::new(0.0, offset)))
+        .collect()
+}
@@ -0,0 +1,169 @@
+use num::complex::Complex;
+use std::ops::Mul;
+
+use crate::{run_after, Bag, Kleinian, Letter, Mat, Word, COMMUTATORS, EPSILON, A, B, AI, BI};
+
+/// A single-precision copy of a `Mat`, for feeding geometry to GPU
+/// pipelines: eight plain `f32`s, the real and imaginary parts of each
+/// entry in turn, laid out as C would so the matrix can be uploaded as it
+/// is. Limit sets traced in f32 lose accuracy much sooner as the depth
+/// grows, so the f64 `Mat` stays the default.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatF32 {
+    pub a_re: f32,
+    pub a_im: f32,
+    pub b_re: f32,
+    pub b_im: f32,
+    pub c_re: f32,
+    pub c_im: f32,
+    pub d_re: f32,
+    pub d_im: f32,
+}
+
+// complex arithmetic on (re, im) pairs
+fn add(z: (f32, f32), w: (f32, f32)) -> (f32, f32) {
+    (z.0 + w.0, z.1 + w.1)
+}
+
+fn mul(z: (f32, f32), w: (f32, f32)) -> (f32, f32) {
+    (z.0 * w.0 - z.1 * w.1, z.0 * w.1 + z.1 * w.0)
+}
+
+fn div(z: (f32, f32), w: (f32, f32)) -> (f32, f32) {
+    let n = w.0 * w.0 + w.1 * w.1;
+    ((z.0 * w.0 + z.1 * w.1) / n, (z.1 * w.0 - z.0 * w.1) / n)
+}
+
+impl MatF32 {
+    pub fn id() -> Self {
+        MatF32::from(&Mat::id())
+    }
+
+    fn a(&self) -> (f32, f32) {
+        (self.a_re, self.a_im)
+    }
+
+    fn b(&self) -> (f32, f32) {
+        (self.b_re, self.b_im)
+    }
+
+    fn c(&self) -> (f32, f32) {
+        (self.c_re, self.c_im)
+    }
+
+    fn d(&self) -> (f32, f32) {
+        (self.d_re, self.d_im)
+    }
+
+    fn from_entries(a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32)) -> Self {
+        MatF32 { a_re: a.0, a_im: a.1, b_re: b.0, b_im: b.1, c_re: c.0, c_im: c.1, d_re: d.0, d_im: d.1 }
+    }
+
+    pub fn mob(&self, z: (f32, f32)) -> (f32, f32) {
+        div(add(mul(self.a(), z), self.b()), add(mul(self.c(), z), self.d()))
+    }
+}
+
+impl From<&Mat> for MatF32 {
+    fn from(m: &Mat) -> Self {
+        let lower = |z: Complex<f64>| (z.re as f32, z.im as f32);
+        MatF32::from_entries(lower(m.a), lower(m.b), lower(m.c), lower(m.d))
+    }
+}
+
+impl Mul<&MatF32> for &MatF32 {
+    type Output = MatF32;
+    fn mul(self, v: &MatF32) -> MatF32 {
+        MatF32::from_entries(
+            add(mul(self.a(), v.a()), mul(self.b(), v.c())),
+            add(mul(self.a(), v.b()), mul(self.b(), v.d())),
+            add(mul(self.c(), v.a()), mul(self.d(), v.c())),
+            add(mul(self.c(), v.b()), mul(self.d(), v.d())),
+        )
+    }
+}
+
+struct Trace32 {
+    mats: Bag<MatF32>,
+    ends: Bag<Vec<(f32, f32)>>,
+    points: Vec<(f32, f32)>,
+    max_runs: Bag<usize>,
+}
+
+fn branch32(level: i64, l: Letter, run: usize, t: &MatF32, g: &mut Trace32) {
+    let eps = EPSILON as f32;
+    let t = t * g.mats.at(l);
+    let mut z = *g.points.last().expect("the trace starts with a point");
+    let mut to_draw = Vec::new();
+    for &pt in g.ends.at(l) {
+        let w = t.mob(pt);
+        if level > 0 && (z.0 - w.0).hypot(z.1 - w.1) > eps {
+            for &next in &l.successors() {
+                if let Some(run) = run_after(&g.max_runs, l, next, run) {
+                    branch32(level - 1, next, run, &t, g);
+                }
+            }
+            return;
+        }
+        to_draw.push(w);
+        z = w;
+    }
+    g.points.extend(to_draw);
+}
+
+impl Kleinian {
+    /// `limitset` traced in single precision, returning the points.
+    pub fn limit_set_f32(&self, level: i64) -> Vec<(f32, f32)> {
+        let mut ends = self.ends.clone();
+        for w in COMMUTATORS.iter() {
+            ends.at_mut(w[3]).push(self.word(&Word(w.to_vec())).fix());
+        }
+        let to32 = |v: &Vec<Complex<f64>>| v.iter().map(|z| (z.re as f32, z.im as f32)).collect();
+        let mut g = Trace32 {
+            mats: Bag::new(MatF32::from(self.mat(A)), MatF32::from(self.mat(B)),
+                MatF32::from(self.mat(AI)), MatF32::from(self.mat(BI))),
+            ends: Bag::new(to32(&ends.a), to32(&ends.b), to32(&ends.ainv), to32(&ends.binv)),
+            points: vec![(1.0, 0.0)],
+            max_runs: self.max_runs.clone(),
+        };
+        for &l in &[A, BI, AI, B] {
+            if *g.max_runs.at(l) == 0 {
+                continue;
+            }
+            branch32(level - 1, l, 1, &MatF32::id(), &mut g);
+        }
+        g.points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset};
+
+    #[test]
+    fn f32_and_f64_agree_at_shallow_depth() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let low = g.limit_set_f32(6);
+        limitset(6, &mut g);
+        assert_eq!(low.len(), g.points().len());
+        for (&(x, y), z) in low.iter().zip(g.points()) {
+            assert!((x as f64 - z.re).hypot(y as f64 - z.im) < 1e-3, "({}, {}) against {}", x, y, z);
+        }
+    }
+
+    #[test]
+    fn products_and_images_match_mat() {
+        let g = grandma(Complex::new(1.91, 0.05), Complex::new(2.0, 0.0));
+        let (a, b) = (g.mat(A), g.mat(B));
+        let m = &MatF32::from(a) * &MatF32::from(b);
+        let z = Complex::new(0.3, -0.2);
+        let w = (a * b).mob(z);
+        let v = m.mob((z.re as f32, z.im as f32));
+        assert!((v.0 as f64 - w.re).hypot(v.1 as f64 - w.im) < 1e-4, "{:?} against {}", v, w);
+    }
+}
@@ -0,0 +1,112 @@
+//! Normalizing the picture so nearly Fuchsian limit sets come out as a
+//! wiggly circle or a wiggly line, comparable across parameters.
+
+use num::complex::Complex;
+use std::str::FromStr;
+
+use crate::{first_cycle, Bag, Kleinian, Mat, Mirror, A, AI, BI};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// Cusps on the unit circle.
+    Disk,
+    /// Cusps on the real line, one of them at infinity.
+    HalfPlane,
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disk" => Ok(Model::Disk),
+            "halfplane" => Ok(Model::HalfPlane),
+            _ => Err(format!("unknown model `{}`, expected disk or halfplane", s)),
+        }
+    }
+}
+
+// sends z to 0, 1, ∞
+fn to_standard([z1, z2, z3]: [Complex<f64>; 3]) -> Mat {
+    Mat::new(z2 - z3, -z1 * (z2 - z3), z2 - z1, -z3 * (z2 - z1))
+}
+
+/// The Möbius map sending p to q, pointwise, scaled to determinant 1.
+pub fn three_point_map(p: [Complex<f64>; 3], q: [Complex<f64>; 3]) -> Mat {
+    let m = &to_standard(q).adj() * &to_standard(p);
+    let k = (m.a * m.d - m.b * m.c).sqrt();
+    Mat::new(m.a / k, m.b / k, m.c / k, m.d / k)
+}
+
+/// z ↦ i(1 + z)/(1 - z), taking the unit disk to the upper half plane and
+/// 1 to ∞.
+pub fn disk_to_halfplane() -> Mat {
+    let i = Complex::<f64>::i();
+    let one = Complex::new(1.0, 0.0);
+    Mat::new(i, i, -one, one)
+}
+
+impl Kleinian {
+    /// The group t g t⁻¹, with its ends moved along. `t` must have
+    /// determinant 1.
+    pub fn conjugate(&self, t: &Mat) -> Kleinian {
+        let inv = t.adj();
+        let conj = |m: &Mat| &(t * m) * &inv;
+        let mut g = Kleinian::new(conj(self.mat(A)), conj(self.mat(crate::B)));
+        let moved = |v: &Vec<Complex<f64>>| v.iter().map(|&z| t.mob(z)).collect();
+        g.ends = Bag::new(moved(&self.ends.a), moved(&self.ends.b), moved(&self.ends.ainv), moved(&self.ends.binv));
+        g.relators = self.relators.clone();
+        g
+    }
+
+    /// The group r g r for the reflection r, with its ends reflected too:
+    /// its limit set is this one's mirror image, traced exactly rather
+    /// than by reflecting the traced points.
+    pub fn mirrored(&self, mirror: Mirror) -> Kleinian {
+        let mut g = Kleinian::new(mirror.mirror_mat(self.mat(A)), mirror.mirror_mat(self.mat(crate::B)));
+        let moved = |v: &Vec<Complex<f64>>| v.iter().map(|&z| mirror.apply(z)).collect();
+        g.ends = Bag::new(moved(&self.ends.a), moved(&self.ends.b), moved(&self.ends.ainv), moved(&self.ends.binv));
+        g.relators = self.relators.clone();
+        g
+    }
+
+    /// The map sending the cusps where the a, B and A branches of the trace
+    /// begin to 1, i and -1, so that the trace still starts at 1 and a
+    /// Fuchsian limit set becomes the unit circle.
+    pub fn disk_transform(&self) -> Mat {
+        let cusp = |l| self.prod(first_cycle(l)).fix();
+        let i = Complex::<f64>::i();
+        let one = Complex::new(1.0, 0.0);
+        three_point_map([cusp(A), cusp(BI), cusp(AI)], [one, i, -one])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, B};
+
+    fn in_disk(ta: Complex<f64>, tb: Complex<f64>) -> Kleinian {
+        let mut g = grandma(ta, tb);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let t = g.disk_transform();
+        let mut g = g.conjugate(&t);
+        limitset(10, &mut g);
+        g
+    }
+
+    #[test]
+    fn real_traces_put_the_limit_set_on_the_unit_circle() {
+        let g = in_disk(Complex::new(3.0, 0.0), Complex::new(3.0, 0.0));
+        assert!(g.is_fuchsian());
+        assert!(g.points().len() > 100);
+        for z in g.points() {
+            assert!((z.norm() - 1.0).abs() < 1e-6, "{} is off the unit circle", z);
+        }
+        let g = in_disk(Complex::new(3.0, 0.05), Complex::new(3.0, 0.0));
+        let off = g.points().iter().map(|z| (z.norm() - 1.0).abs()).fold(0.0, f64::max);
+        assert!(off > 1e-6 && off < 0.2, "{} from the unit circle", off);
+    }
+}
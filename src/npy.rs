@@ -0,0 +1,85 @@
+//! NumPy `.npy` and `.npz` output, written by hand: the formats are simple
+//! and this saves a dependency.
+
+use flate2::Crc;
+use std::io::{self, Write};
+
+/// Writes `data` as a little-endian float64 `.npy` (format version 1.0)
+/// array with `cols` columns, row by row.
+pub fn write_npy<W: Write>(data: &[f64], cols: usize, w: &mut W) -> io::Result<()> {
+    assert!(cols > 0 && data.len().is_multiple_of(cols), "data must fill whole rows");
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}", data.len() / cols, cols);
+    // magic, version and length take 10 bytes; the header is padded with
+    // spaces and ends in a newline so the data starts 64-byte aligned
+    let total = (10 + header.len() + 1).div_ceil(64) * 64;
+    while 10 + header.len() + 1 < total {
+        header.push(' ');
+    }
+    header.push('\n');
+    w.write_all(b"\x93NUMPY\x01\x00")?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    for x in data {
+        w.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes the named files as an uncompressed zip archive, which is what
+/// `np.savez` makes and `np.load` reads as an `.npz`.
+pub fn write_npz<W: Write>(files: &[(&str, Vec<u8>)], w: &mut W) -> io::Result<()> {
+    let mut offset = 0u32;
+    let mut central = Vec::new();
+    for (name, bytes) in files {
+        let mut crc = Crc::new();
+        crc.update(bytes);
+        // version 2.0, no flags, stored, zero time and date, crc, sizes
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0u32.to_le_bytes());
+        fields.extend_from_slice(&crc.sum().to_le_bytes());
+        fields.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+
+        w.write_all(b"PK\x03\x04")?;
+        w.write_all(&fields)?;
+        w.write_all(name.as_bytes())?;
+        w.write_all(bytes)?;
+
+        central.extend_from_slice(b"PK\x01\x02");
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&fields);
+        // comment length, disk, internal and external attributes, offset
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        offset += (30 + name.len() + bytes.len()) as u32;
+    }
+    w.write_all(&central)?;
+    w.write_all(b"PK\x05\x06\0\0\0\0")?;
+    w.write_all(&(files.len() as u16).to_le_bytes())?;
+    w.write_all(&(files.len() as u16).to_le_bytes())?;
+    w.write_all(&(central.len() as u32).to_le_bytes())?;
+    w.write_all(&offset.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_small_array_matches_what_numpy_saves() {
+        // np.save(f, np.array([[1.0, 2.5], [-3.0, 0.125]]))
+        let expected = include_bytes!("../tests/fixtures/tiny.npy");
+        let mut bytes = Vec::new();
+        write_npy(&[1.0, 2.5, -3.0, 0.125], 2, &mut bytes).unwrap();
+        assert_eq!(&bytes[..], &expected[..]);
+        assert_eq!(bytes.len() - 4 * 8, 128);
+        assert!(String::from_utf8_lossy(&bytes[10..128]).contains("'descr': '<f8'"));
+    }
+}
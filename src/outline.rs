@@ -0,0 +1,168 @@
+//! Traced curves as filled bands rather than strokes, for toolchains that
+//! ignore stroke width.
+
+use num::complex::Complex;
+use std::f64::consts::PI;
+use svg::node::element::path::Data;
+
+// joins and caps are approximated by chords subtending at most this angle
+const ARC_STEP: f64 = PI / 8.0;
+
+// points on the circle of radius r about c, from direction `from` (a unit
+// vector) turning through `angle`, excluding the start
+fn arc(out: &mut Vec<Complex<f64>>, c: Complex<f64>, from: Complex<f64>, angle: f64, r: f64) {
+    let steps = (angle.abs() / ARC_STEP).ceil() as usize;
+    for k in 1..=steps {
+        let theta = angle * k as f64 / steps as f64;
+        out.push(c + r * from * Complex::from_polar(&1.0, &theta));
+    }
+}
+
+// the offset by r to the left of travel, with round joins
+fn side(points: &[Complex<f64>], r: f64, out: &mut Vec<Complex<f64>>) {
+    let normal = |p: Complex<f64>, q: Complex<f64>| Complex::<f64>::i() * (q - p) / (q - p).norm();
+    let mut n0 = normal(points[0], points[1]);
+    out.push(points[0] + r * n0);
+    for i in 1..points.len() - 1 {
+        let n1 = normal(points[i], points[i + 1]);
+        out.push(points[i] + r * n0);
+        // the short way round: outside the turn this is the round join,
+        // inside it a small loop that the nonzero fill absorbs
+        arc(out, points[i], n0, (n1 / n0).arg(), r);
+        n0 = n1;
+    }
+    out.push(points[points.len() - 1] + r * n0);
+}
+
+/// Appends to `data` the closed outline of the band of width `2r` around
+/// the polyline, with round joins and caps, to be filled with the nonzero
+/// rule. Every outline point is within r of the polyline.
+pub(crate) fn outline_data(data: Data, points: &[Complex<f64>], r: f64) -> Data {
+    let mut points = points.to_vec();
+    points.dedup();
+    if points.len() < 2 {
+        return data;
+    }
+    let mut out = Vec::with_capacity(4 * points.len());
+    side(&points, r, &mut out);
+    let n = points.len();
+    let end = Complex::<f64>::i() * (points[n - 1] - points[n - 2]) / (points[n - 1] - points[n - 2]).norm();
+    arc(&mut out, points[n - 1], end, -PI, r);
+    points.reverse();
+    side(&points, r, &mut out);
+    let start = Complex::<f64>::i() * (points[n - 1] - points[n - 2]) / (points[n - 1] - points[n - 2]).norm();
+    arc(&mut out, points[n - 1], start, -PI, r);
+
+    let mut data = data.move_to((out[0].re, out[0].im));
+    for z in &out[1..] {
+        data = data.line_to((z.re, z.im));
+    }
+    data.close()
+}
+
+// miters longer than this many half-widths are cut to bevels
+const MITER_LIMIT: f64 = 4.0;
+
+// the offset by r to the left of travel, with mitered joins
+fn mitered_side(points: &[Complex<f64>], r: f64, out: &mut Vec<Complex<f64>>) {
+    let normal = |p: Complex<f64>, q: Complex<f64>| Complex::<f64>::i() * (q - p) / (q - p).norm();
+    let mut n0 = normal(points[0], points[1]);
+    out.push(points[0] + r * n0);
+    for i in 1..points.len() - 1 {
+        let n1 = normal(points[i], points[i + 1]);
+        // n0 + n1 scaled so its projection on either normal is 1
+        let cos = (n0.conj() * n1).re;
+        let miter = (n0 + n1) / (1.0 + cos);
+        if miter.norm() <= MITER_LIMIT {
+            out.push(points[i] + r * miter);
+        } else {
+            out.push(points[i] + r * n0);
+            out.push(points[i] + r * n1);
+        }
+        n0 = n1;
+    }
+    out.push(points[points.len() - 1] + r * n0);
+}
+
+/// Appends to `data` the closed polygon offsetting the polyline by `r` to
+/// either side, with mitered joins and square-cut ends, to be filled with
+/// the even-odd rule so that where the band crosses itself it is cut away
+/// rather than piling up.
+pub(crate) fn ribbon_data(data: Data, points: &[Complex<f64>], r: f64) -> Data {
+    let mut points = points.to_vec();
+    points.dedup();
+    if points.len() < 2 {
+        return data;
+    }
+    let mut out = Vec::with_capacity(2 * points.len() + 2);
+    mitered_side(&points, r, &mut out);
+    points.reverse();
+    mitered_side(&points, r, &mut out);
+
+    let mut data = data.move_to((out[0].re, out[0].im));
+    for z in &out[1..] {
+        data = data.line_to((z.re, z.im));
+    }
+    data.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with, RenderOptions, A, AI, B, BI};
+    use svg::node::element::path::Command;
+
+    fn attribute<'a>(svg: &'a str, name: &str) -> &'a str {
+        let start = svg.find(&format!(" {}=\"", name)).unwrap_or_else(|| panic!("no {} in {}", name, svg));
+        let rest = &svg[start + name.len() + 3..];
+        &rest[..rest.find('"').unwrap()]
+    }
+
+    #[test]
+    fn the_ribbon_is_a_closed_filled_path() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let opts = RenderOptions { ribbon_width: Some(0.02), ..RenderOptions::default() };
+        limitset_with(8, &mut g, &opts);
+        let svg = g.to_svg_string(&opts);
+        let path = &svg[svg.find("<path").expect("a path")..];
+        assert_ne!(attribute(path, "fill"), "none");
+        assert_eq!(attribute(path, "fill-rule"), "evenodd");
+        assert_eq!(attribute(path, "stroke"), "none");
+        let d = attribute(path, "d");
+        let pieces: Vec<_> = d.split('M').skip(1).collect();
+        assert!(!pieces.is_empty());
+        for piece in pieces {
+            assert!(piece.trim_end().ends_with(['z', 'Z']), "an open piece `M{}`", piece);
+        }
+    }
+
+    #[test]
+    fn the_outline_keeps_its_distance_round_a_cusp() {
+        let c = Complex::new;
+        // out along the real axis and sharply back
+        let line = [c(0.0, 0.0), c(1.0, 0.0), c(0.05, 0.1), c(0.5, 0.8)];
+        let r = 0.05;
+        let data = outline_data(Data::new(), &line, r);
+        let points: Vec<Complex<f64>> = data.iter().filter_map(|command| match command {
+            Command::Move(_, p) | Command::Line(_, p) => Some(c(p[0] as f64, p[1] as f64)),
+            _ => None,
+        }).collect();
+        assert!(matches!(data.last(), Some(Command::Close)));
+        let segment = |z: Complex<f64>, p: Complex<f64>, q: Complex<f64>| {
+            let t = (((z - p) * (q - p).conj()).re / (q - p).norm_sqr()).clamp(0.0, 1.0);
+            (p + (q - p) * t - z).norm()
+        };
+        let tolerance = 1e-6;
+        for z in points {
+            let mut distances: Vec<f64> = line.windows(2).map(|s| segment(z, s[0], s[1])).collect();
+            distances.extend(line.iter().map(|p| (z - p).norm()));
+            // within r of the polyline, and r from some segment or vertex of it
+            let nearest = distances.iter().cloned().fold(f64::INFINITY, f64::min);
+            assert!(nearest <= r + tolerance, "{} is {} from the line", z, nearest);
+            assert!(distances.iter().any(|d| (d - r).abs() < tolerance), "{} is off the offset curve", z);
+        }
+    }
+}
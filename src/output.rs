@@ -0,0 +1,29 @@
+//! Files or the standard streams: a path of `-` means standard output when
+//! writing and standard input when reading, so renders can be piped.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+/// The path that stands for standard output or input.
+pub const STDIO: &str = "-";
+
+/// Opens `path` for buffered writing, or locks standard output when it is
+/// `-`. Callers should flush it when done, to see any error.
+pub fn create_output(path: &str) -> io::Result<Box<dyn Write>> {
+    if path == STDIO {
+        Ok(Box::new(BufWriter::new(io::stdout().lock())))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+/// Reads all of `path`, or of standard input when it is `-`.
+pub fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if path == STDIO {
+        io::stdin().lock().read_to_end(&mut bytes)?;
+    } else {
+        File::open(path)?.read_to_end(&mut bytes)?;
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,703 @@
+use svg::node::element::{Circle, Group, Path, Text};
+use svg::node::element::path::Data;
+use svg::node;
+use num::complex::Complex;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::farey::farey_sequence;
+use crate::hull::convex_hull;
+use crate::{Kleinian, Letter, RenderOptions, Mat, Word, COMMUTATORS, EPSILON, A, B, AI, BI};
+
+/// Deepest Cayley tree drawn; each level triples the number of edges.
+pub const MAX_TREE_DEPTH: usize = 10;
+
+pub(crate) const PALETTE: [&str; 8] = [
+    "#d62728", "#1f77b4", "#2ca02c", "#ff7f0e",
+    "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// Extra geometry drawn on top of the limit set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    /// The isometric circles of a, b, a⁻¹ and b⁻¹.
+    IsometricCircles,
+    /// The axis of each word's transformation, drawn as the arc through its
+    /// two fixed points orthogonal to the group's invariant circle, or to
+    /// the unit circle when it has none.
+    Axes(Vec<Word>),
+    /// Markers at the fixed points of the commutators, plus those of any
+    /// extra parabolic words.
+    Cusps(Vec<Word>),
+    /// The ideal quadrilateral spanned by the four cusps.
+    FundamentalDomain,
+    /// Images of the fundamental domain under all reduced words up to the
+    /// given length.
+    Tessellation(usize),
+    /// Edges joining the fixed point of each word to those of its
+    /// one-letter extensions, down to the given depth.
+    CayleyTree(usize),
+    /// Horocircles at the parabolic fixed points of the commutators (and of
+    /// parabolic generators), and their images under words up to the given
+    /// length.
+    Horoballs(usize),
+    /// Images of the four disks bounded by the isometric circles of the
+    /// inverse generators under all reduced words up to the given length,
+    /// nesting down onto the limit set.
+    SchottkyCircles(usize),
+    /// The axes of the Farey words w_{p/q}, the simple closed geodesics
+    /// whose pinching gives the cusp groups.
+    Geodesics(Vec<(i64, i64)>),
+    /// The convex hull of the traced points, as a dashed polygon.
+    ConvexHull,
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.find('=') {
+            Some(n) => (&s[..n], Some(&s[n + 1..])),
+            None => (s, None),
+        };
+        match (name, arg) {
+            ("isometric-circles", None) => Ok(Overlay::IsometricCircles),
+            ("axes", Some(words)) => parse_words(words).map(Overlay::Axes),
+            ("cusps", None) => Ok(Overlay::Cusps(Vec::new())),
+            ("cusps", Some(words)) => parse_words(words).map(Overlay::Cusps),
+            ("fundamental-domain", None) => Ok(Overlay::FundamentalDomain),
+            ("convex-hull", None) => Ok(Overlay::ConvexHull),
+            ("tessellation", Some(n)) => n.parse()
+                .map(Overlay::Tessellation)
+                .map_err(|_| format!("bad word length in `{}`", s)),
+            ("cayley-tree", Some(n)) => n.parse()
+                .map(Overlay::CayleyTree)
+                .map_err(|_| format!("bad depth in `{}`", s)),
+            ("horoballs", Some(n)) => n.parse()
+                .map(Overlay::Horoballs)
+                .map_err(|_| format!("bad word length in `{}`", s)),
+            ("circles", Some(n)) => n.parse()
+                .map(Overlay::SchottkyCircles)
+                .map_err(|_| format!("bad word length in `{}`", s)),
+            ("geodesics", Some(fractions)) => match fractions.strip_prefix("farey:") {
+                Some(n) => n.parse()
+                    .map(|n| Overlay::Geodesics(farey_sequence(n)))
+                    .map_err(|_| format!("bad denominator in `{}`", s)),
+                None => parse_fractions(fractions).map(Overlay::Geodesics),
+            },
+            _ => Err(format!("unknown overlay `{}`", s)),
+        }
+    }
+}
+
+fn parse_words(s: &str) -> Result<Vec<Word>, String> {
+    s.split(',').map(|w| w.parse().map_err(|e: crate::Error| e.to_string())).collect()
+}
+
+fn parse_fractions(s: &str) -> Result<Vec<(i64, i64)>, String> {
+    s.split(',').map(|f| {
+        let (p, q) = match f.find('/') {
+            Some(n) => (f[..n].parse(), f[n + 1..].parse()),
+            None => return Err(format!("expected p/q, got `{}`", f)),
+        };
+        match (p, q) {
+            (Ok(p), Ok(q)) if Word::farey(p, q).is_some() => Ok((p, q)),
+            (Ok(_), Ok(_)) => Err(format!("`{}` is not in lowest terms", f)),
+            _ => Err(format!("bad fraction `{}`", f)),
+        }
+    }).collect()
+}
+
+impl Mat {
+    /// The circle |cz + d| = 1 on which the transformation is a Euclidean
+    /// isometry, as (center, radius); `None` when c ≈ 0 and it fixes ∞.
+    pub fn isometric_circle(&self) -> Option<(Complex<f64>, f64)> {
+        if self.c.norm() < 1e-12 {
+            None
+        } else {
+            Some((-self.d / self.c, 1.0 / self.c.norm()))
+        }
+    }
+}
+
+/// The circle through three points as (center, radius), or `None` when
+/// they are collinear.
+pub fn circle_through(p: Complex<f64>, q: Complex<f64>, r: Complex<f64>) -> Option<(Complex<f64>, f64)> {
+    let (u, v) = (q - p, r - p);
+    let det = 2.0 * (u.conj() * v).im;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let center = p + Complex::<f64>::i() * (u * v.norm_sqr() - v * u.norm_sqr()) / det;
+    Some((center, (center - p).norm()))
+}
+
+/// The inversive distance (d² - r₁² - r₂²) / 2r₁r₂ between two circles,
+/// d being the distance between centers: 1 when they touch from outside,
+/// above 1 when disjoint, in (-1, 1) when they cross at an angle whose
+/// cosine it is, and -(ρ + 1/ρ)/2 for concentric circles with ratio ρ.
+pub fn inversive_distance((c1, r1): (Complex<f64>, f64), (c2, r2): (Complex<f64>, f64)) -> f64 {
+    ((c1 - c2).norm_sqr() - r1 * r1 - r2 * r2) / (2.0 * r1 * r2)
+}
+
+impl Mat {
+    /// The image of the circle (center, radius); `None` when the image is a
+    /// line. The circle is the zero set of the Hermitian form
+    /// [[1, -c], [-c̄, |c|² - r²]], which maps to N* H N with N the inverse.
+    pub fn circle_image(&self, (center, radius): (Complex<f64>, f64)) -> Option<(Complex<f64>, f64)> {
+        let n = self.adj();
+        let (h11, h12) = (Complex::new(1.0, 0.0), -center);
+        let h22 = Complex::new(center.norm_sqr() - radius * radius, 0.0);
+        let (hn11, hn12) = (h11 * n.a + h12 * n.c, h11 * n.b + h12 * n.d);
+        let (hn21, hn22) = (h12.conj() * n.a + h22 * n.c, h12.conj() * n.b + h22 * n.d);
+        let a = (n.a.conj() * hn11 + n.c.conj() * hn21).re;
+        let b = n.a.conj() * hn12 + n.c.conj() * hn22;
+        let d = (n.b.conj() * hn12 + n.d.conj() * hn22).re;
+        if a.abs() < 1e-12 {
+            return None;
+        }
+        let c = -b / a;
+        let r2 = c.norm_sqr() - d / a;
+        Some((c, r2.max(0.0).sqrt()))
+    }
+
+    /// The pair of horocircles at the fixed point of a parabolic, one on
+    /// each side, at height `h` once the parabolic is normalized to z ↦ z+1.
+    /// Writing p for the fixed point, 1/(z - p) is translated by c, so these
+    /// are the images of the lines Im(w/c) = ±h: circles of radius
+    /// 1/(2h|c|) through p. Height 1 gives Ford circles and 1/2 the
+    /// circles of the Apollonian gasket.
+    pub fn horocircles(&self, h: f64) -> Option<[(Complex<f64>, f64); 2]> {
+        let c = self.c;
+        if c.norm() < 1e-12 {
+            return None;
+        }
+        let p = (self.a - self.d) / (2.0 * c);
+        let offset = Complex::<f64>::i() / (2.0 * h * c);
+        let r = 1.0 / (2.0 * h * c.norm());
+        Some([(p - offset, r), (p + offset, r)])
+    }
+}
+
+pub fn draw(overlay: &Overlay, g: &Kleinian, opts: &RenderOptions) -> Group {
+    match *overlay {
+        Overlay::IsometricCircles => isometric_circles(g, opts),
+        Overlay::Axes(ref words) => axes(words, g, opts),
+        Overlay::Cusps(ref words) => cusps(words, g, opts),
+        Overlay::FundamentalDomain => fundamental_domain(g, opts),
+        Overlay::Tessellation(n) => tessellation(n, g, opts),
+        Overlay::CayleyTree(depth) => cayley_tree(depth, g, opts),
+        Overlay::Horoballs(n) => horoballs(n, g, opts),
+        Overlay::SchottkyCircles(n) => schottky_circles(n, g, opts),
+        Overlay::Geodesics(ref fractions) => geodesics(fractions, g, opts),
+        Overlay::ConvexHull => hull(g, opts),
+    }
+}
+
+fn label(z: Complex<f64>, text: String, color: &str, opts: &RenderOptions) -> Text {
+    Text::new()
+        .set("x", z.re)
+        .set("y", z.im)
+        .set("fill", color)
+        .set("font-size", 0.03 * opts.view_box.2)
+        .set("text-anchor", "middle")
+        .add(node::Text::new(text))
+}
+
+fn marker(z: Complex<f64>, color: &str, opts: &RenderOptions) -> Circle {
+    Circle::new()
+        .set("cx", z.re)
+        .set("cy", z.im)
+        .set("r", opts.marker_size * opts.stroke_width)
+        .set("fill", color)
+}
+
+/// The circle or line the overlays' geodesic arcs meet at right angles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Equator {
+    /// A circle, as (center, radius).
+    Circle(Complex<f64>, f64),
+    /// The line through a point in a unit direction.
+    Line(Complex<f64>, Complex<f64>),
+}
+
+/// How far, relative to the equator's size, the fourth cusp of a Fuchsian
+/// group may lie off the circle through the other three.
+pub const EQUATOR_TOLERANCE: f64 = 1e-6;
+
+/// The invariant circle (or line) of a Fuchsian group, through its four
+/// cusps; otherwise the unit circle, which is that of `grandma`'s
+/// Fuchsian groups and so the reference for the groups deformed from them.
+pub(crate) fn equator(g: &Kleinian) -> Equator {
+    let unit = Equator::Circle(Complex::new(0.0, 0.0), 1.0);
+    let cusps = domain_vertices(g);
+    if !g.is_fuchsian() || cusps.len() < 4 || !cusps.iter().all(|z| z.is_finite()) {
+        return unit;
+    }
+    let (p, q, r, s) = (cusps[0], cusps[1], cusps[2], cusps[3]);
+    let size = (q - p).norm().max((r - p).norm()).max((s - p).norm());
+    let u = (q - p) / (q - p).norm();
+    // how far a point is off the line pq, relative to the cusps' spread
+    let off_line = |z: Complex<f64>| (u.conj() * (z - p)).im.abs() / size;
+    let (equator, off) = if off_line(r) < EQUATOR_TOLERANCE {
+        (Equator::Line(p, u), off_line(s))
+    } else {
+        match circle_through(p, q, r) {
+            Some((c, radius)) => (Equator::Circle(c, radius), ((s - c).norm() - radius).abs() / radius),
+            None => return unit,
+        }
+    };
+    if off < EQUATOR_TOLERANCE { equator } else { unit }
+}
+
+/// The circle through `p` and `q` meeting `equator` at right angles, as
+/// (center, radius); `None` when it degenerates to the line through them.
+pub(crate) fn geodesic_circle(p: Complex<f64>, q: Complex<f64>, equator: Equator) -> Option<(Complex<f64>, f64)> {
+    let m = (p + q) / 2.0;
+    let n = (q - p) * Complex::i() / (q - p).norm();
+    // the center is c = m + tn on the bisector of pq
+    let (t, denom) = match equator {
+        // |c - c0|² = R² + |c - p|², where the t² terms cancel
+        Equator::Circle(c0, radius) => {
+            let denom = 2.0 * (n * (p - c0).conj()).re;
+            ((radius * radius + (m - p).norm_sqr() - (m - c0).norm_sqr()) / denom, denom)
+        }
+        // and c on the line
+        Equator::Line(a, u) => {
+            let denom = (n.conj() * u).im;
+            (((a - m).conj() * u).im / denom, denom)
+        }
+    };
+    if denom.abs() < 1e-9 || !t.is_finite() {
+        return None;
+    }
+    let c = m + t * n;
+    Some((c, (c - p).norm()))
+}
+
+/// Extends `data`, whose current point is `p`, along the hyperbolic geodesic
+/// to `q`: the arc of the circle through both points orthogonal to
+/// `equator`, or the segment between them when that circle degenerates to
+/// a line. Also returns the midpoint of the arc.
+pub(crate) fn geodesic(data: Data, p: Complex<f64>, q: Complex<f64>, equator: Equator) -> (Data, Complex<f64>) {
+    let m = (p + q) / 2.0;
+    let (c, r) = match geodesic_circle(p, q, equator) {
+        Some(circle) => circle,
+        None => return (data.line_to((q.re, q.im)), m),
+    };
+    let sweep = ((p - c).conj() * (q - c)).im > 0.0;
+    let mid = c + r * (m - c) / (m - c).norm();
+    let data = data.elliptical_arc_to((r, r, 0, 0, if sweep { 1 } else { 0 }, q.re, q.im));
+    (data, mid)
+}
+
+fn axes(words: &[Word], g: &Kleinian, opts: &RenderOptions) -> Group {
+    let equator = equator(g);
+    let mut group = Group::new().set("class", "axes");
+    for (n, w) in words.iter().enumerate() {
+        let color = PALETTE[n % PALETTE.len()];
+        let m = g.word(w);
+        let (p, q) = (m.fix(), m.adj().fix());
+        if !p.is_finite() || !q.is_finite() {
+            continue;
+        }
+        if m.is_parabolic() {
+            group = group
+                .add(marker(p, color, opts))
+                .add(label(p, w.to_string(), color, opts));
+            continue;
+        }
+        let (data, mid) = geodesic(Data::new().move_to((p.re, p.im)), p, q, equator);
+        group = group
+            .add(Path::new()
+                .set("fill", "none")
+                .set("stroke", color)
+                .set("stroke-width", opts.stroke_width)
+                .set("d", data))
+            .add(label(mid, w.to_string(), color, opts));
+    }
+    group
+}
+
+fn isometric_circles(g: &Kleinian, opts: &RenderOptions) -> Group {
+    let colors = opts.letter_colors.clone()
+        .unwrap_or_else(RenderOptions::default_letter_colors);
+    let mut group = Group::new().set("class", "isometric");
+    for &l in &[A, B, AI, BI] {
+        if let Some((center, radius)) = g.mat(l).isometric_circle() {
+            let color = colors.at(l).as_str();
+            group = group
+                .add(Circle::new()
+                    .set("cx", center.re)
+                    .set("cy", center.im)
+                    .set("r", radius)
+                    .set("fill", "none")
+                    .set("stroke", color)
+                    .set("stroke-width", opts.stroke_width))
+                .add(label(center, l.to_string(), color, opts));
+        }
+    }
+    group
+}
+
+fn cusps(extra: &[Word], g: &Kleinian, opts: &RenderOptions) -> Group {
+    let color = opts.marker_color.as_str();
+    let mut group = Group::new().set("class", "cusps");
+    let words = COMMUTATORS.iter().map(|w| Word(w.to_vec())).chain(extra.iter().cloned());
+    for w in words {
+        let z = g.word(&w).fix();
+        if z.is_finite() {
+            group = group
+                .add(marker(z, color, opts))
+                .add(label(z, w.to_string(), color, opts));
+        }
+    }
+    group
+}
+
+/// The four cusps in the order the traversal passes them: each is the
+/// fixed point of the commutator ending in that top-level letter.
+pub(crate) fn domain_vertices(g: &Kleinian) -> Vec<Complex<f64>> {
+    [A, BI, AI, B].iter()
+        .filter_map(|&l| COMMUTATORS.iter().find(|w| w[3] == l))
+        .map(|w| g.word(&Word(w.to_vec())).fix())
+        .collect()
+}
+
+/// The closed outline of the quadrilateral with the given vertices, with
+/// sides that are geodesic arcs orthogonal to `equator` or, with `chords`,
+/// straight segments.
+pub(crate) fn quadrilateral(vertices: &[Complex<f64>], chords: bool, equator: Equator) -> Data {
+    let first = vertices[0];
+    let mut data = Data::new().move_to((first.re, first.im));
+    for (n, &p) in vertices.iter().enumerate() {
+        let q = vertices[(n + 1) % vertices.len()];
+        data = if chords {
+            data.line_to((q.re, q.im))
+        } else {
+            geodesic(data, p, q, equator).0
+        };
+    }
+    data.close()
+}
+
+fn fundamental_domain(g: &Kleinian, opts: &RenderOptions) -> Group {
+    let vertices = domain_vertices(g);
+    let mut group = Group::new().set("class", "domain");
+    if vertices.iter().all(|z| z.is_finite()) {
+        group = group.add(Path::new()
+            .set("fill", opts.domain_fill.as_str())
+            .set("fill-opacity", opts.domain_opacity)
+            .set("stroke", opts.domain_fill.as_str())
+            .set("stroke-width", opts.stroke_width)
+            .set("d", quadrilateral(&vertices, opts.chords, equator(g))));
+    }
+    group
+}
+
+fn hull(g: &Kleinian, opts: &RenderOptions) -> Group {
+    let points: Vec<_> = g.points().iter().map(|z| (z.re, z.im)).collect();
+    let hull = convex_hull(&points);
+    let mut group = Group::new().set("class", "hull");
+    if let Some(&(x, y)) = hull.first() {
+        let data = hull[1..].iter().fold(Data::new().move_to((x, y)), |data, &p| data.line_to(p)).close();
+        let dash = 8.0 * opts.stroke_width;
+        group = group.add(Path::new()
+            .set("fill", "none")
+            .set("stroke", opts.marker_color.as_str())
+            .set("stroke-width", 2.0 * opts.stroke_width)
+            .set("stroke-dasharray", format!("{} {}", dash, dash / 2.0))
+            .set("d", data));
+    }
+    group
+}
+
+fn tessellation(n: usize, g: &Kleinian, opts: &RenderOptions) -> Group {
+    let vertices = domain_vertices(g);
+    let equator = equator(g);
+    let mut group = Group::new().set("class", "tessellation");
+    for w in Word::reduced_up_to(n) {
+        let m = g.word(&w);
+        let tile: Vec<_> = vertices.iter().map(|&z| m.mob(z)).collect();
+        if !tile.iter().all(|z| z.is_finite()) {
+            continue;
+        }
+        let diameter = tile.iter()
+            .flat_map(|&p| tile.iter().map(move |&q| (p - q).norm()))
+            .fold(0.0, f64::max);
+        if diameter < EPSILON {
+            continue;
+        }
+        group = group.add(Path::new()
+            .set("fill", "none")
+            .set("stroke", "gray")
+            .set("stroke-width", opts.stroke_width / 2.0)
+            .set("data-word", w.to_string())
+            .set("d", quadrilateral(&tile, opts.chords, equator)));
+    }
+    group
+}
+
+fn tree_edges(g: &Kleinian, t: &Mat, l: Letter, parent: Complex<f64>, depth: usize,
+              levels: &mut Vec<Data>) {
+    let t = t * g.mat(l);
+    let z = t.fix();
+    if !z.is_finite() {
+        return;
+    }
+    let data = levels[depth].clone();
+    levels[depth] = data.move_to((parent.re, parent.im)).line_to((z.re, z.im));
+    if depth + 1 < levels.len() {
+        for &next in &l.successors() {
+            tree_edges(g, &t, next, z, depth + 1, levels);
+        }
+    }
+}
+
+// The empty word has no fixed point, so the tree is rooted at the origin.
+fn cayley_tree(depth: usize, g: &Kleinian, opts: &RenderOptions) -> Group {
+    let mut levels = vec![Data::new(); depth.min(MAX_TREE_DEPTH)];
+    if !levels.is_empty() {
+        for &l in &[A, BI, AI, B] {
+            tree_edges(g, &Mat::id(), l, Complex::new(0.0, 0.0), 0, &mut levels);
+        }
+    }
+    let mut group = Group::new().set("class", "cayley-tree");
+    let mut width = opts.stroke_width;
+    for data in levels {
+        group = group.add(Path::new()
+            .set("fill", "none")
+            .set("stroke", "gray")
+            .set("stroke-width", width)
+            .set("d", data));
+        width *= opts.tree_taper;
+    }
+    group
+}
+
+fn horoballs(n: usize, g: &Kleinian, opts: &RenderOptions) -> Group {
+    let mut parabolics: Vec<Mat> = COMMUTATORS.iter()
+        .map(|w| g.word(&Word(w.to_vec())))
+        .collect();
+    parabolics.extend([A, B].iter().map(|&l| g.mat(l).clone()).filter(Mat::is_parabolic));
+    // The horocircles at the parabolic points the words lead to are the
+    // images of those at the base points, not the ones `horocircles` gives
+    // for the conjugates: those are normalized afresh at each point, so
+    // they can reach across the limit set.
+    let base: Vec<_> = parabolics.iter()
+        .filter_map(|p| p.horocircles(opts.horoball_height))
+        .flatten()
+        .collect();
+    let mut seen = HashSet::new();
+    let mut group = Group::new().set("class", "horoballs");
+    for w in Word::reduced_up_to(n) {
+        let m = g.word(&w);
+        for &circle in &base {
+            if let Some((center, r)) = m.circle_image(circle) {
+                let key = ((center.re / 1e-9).round() as i64, (center.im / 1e-9).round() as i64);
+                if r < opts.min_radius || !center.is_finite() || !seen.insert(key) {
+                    continue;
+                }
+                group = group.add(Circle::new()
+                    .set("cx", center.re)
+                    .set("cy", center.im)
+                    .set("r", r)
+                    .set("fill", "none")
+                    .set("stroke", "gray")
+                    .set("stroke-width", opts.stroke_width));
+            }
+        }
+    }
+    group
+}
+
+fn schottky_circles(n: usize, g: &Kleinian, opts: &RenderOptions) -> Group {
+    let mut group = Group::new().set("class", "circles");
+    for w in Word::reduced_up_to(n).iter().skip(1) {
+        let (&l, prefix) = w.0.split_last().expect("the empty word is skipped");
+        // a maps the outside of A's isometric circle into that of a⁻¹
+        let image = g.mat(l.inv()).isometric_circle()
+            .and_then(|circle| g.prod(prefix.to_vec()).circle_image(circle));
+        if let Some((center, r)) = image {
+            if r < opts.min_radius || !center.is_finite() {
+                continue;
+            }
+            group = group.add(Circle::new()
+                .set("cx", center.re)
+                .set("cy", center.im)
+                .set("r", r)
+                .set("fill", "none")
+                .set("stroke", PALETTE[(w.0.len() - 1) % PALETTE.len()])
+                .set("stroke-width", opts.stroke_width));
+        }
+    }
+    group
+}
+
+fn geodesics(fractions: &[(i64, i64)], g: &Kleinian, opts: &RenderOptions) -> Group {
+    let equator = equator(g);
+    let mut group = Group::new().set("class", "geodesics");
+    for &(p, q) in fractions {
+        let w = match Word::farey(p, q) {
+            Some(w) => w,
+            None => continue,
+        };
+        // spread [0, 1] over the hues from red to magenta
+        let hue = if q == 0 { 300.0 } else { (300.0 * p as f64 / q as f64).rem_euclid(360.0) };
+        let color = format!("hsl({:.0}, 70%, 45%)", hue);
+        let text = format!("{}/{}", p, q);
+        let m = g.word(&w);
+        let (z, zz) = (m.fix(), m.adj().fix());
+        if !z.is_finite() || !zz.is_finite() {
+            continue;
+        }
+        if m.is_parabolic() {
+            group = group
+                .add(marker(z, &color, opts))
+                .add(label(z, text, &color, opts));
+            continue;
+        }
+        let (data, mid) = geodesic(Data::new().move_to((z.re, z.im)), z, zz, equator);
+        group = group
+            .add(Path::new()
+                .set("fill", "none")
+                .set("stroke", color.as_str())
+                .set("stroke-width", opts.stroke_width)
+                .set("data-word", w.to_string())
+                .set("d", data))
+            .add(label(mid, text, &color, opts));
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grandma;
+
+    fn c(re: f64, im: f64) -> Complex<f64> {
+        Complex::new(re, im)
+    }
+
+    // the group with both generators conjugated by `h`
+    fn conjugated(g: &Kleinian, h: &Mat) -> Kleinian {
+        let by = |m: &Mat| &(h * m) * &h.adj();
+        Kleinian::new(by(g.mat(A)), by(g.mat(B)))
+    }
+
+    #[test]
+    fn geodesics_meet_the_equator_at_right_angles() {
+        let (p, q) = (c(0.3, -0.2), c(-0.5, 0.6));
+        let (c0, radius) = (c(1.0, 0.5), 2.0);
+        let (center, r) = geodesic_circle(p, q, Equator::Circle(c0, radius)).unwrap();
+        assert!(((center - p).norm() - r).abs() < 1e-12 && ((center - q).norm() - r).abs() < 1e-12);
+        assert!(((center - c0).norm_sqr() - radius * radius - r * r).abs() < 1e-9);
+        // orthogonal to a line is centered on it
+        let u = c(0.6, 0.8);
+        let (center, r) = geodesic_circle(p, q, Equator::Line(c(0.0, -1.0), u)).unwrap();
+        assert!(((center - p).norm() - r).abs() < 1e-12 && ((center - q).norm() - r).abs() < 1e-12);
+        assert!((u.conj() * (center - c(0.0, -1.0))).im.abs() < 1e-12);
+        // through the center of the equator it is a diameter
+        assert_eq!(geodesic_circle(c(-0.5, 0.0), c(0.5, 0.0), Equator::Circle(c(0.0, 0.0), 1.0)), None);
+    }
+
+    #[test]
+    fn a_fuchsian_group_uses_its_own_invariant_circle() {
+        let g = grandma(c(3.0, 0.0), c(3.0, 0.0));
+        match equator(&g) {
+            Equator::Circle(center, radius) => assert!(center.norm() < 1e-6 && (radius - 1.0).abs() < 1e-6),
+            e => panic!("{:?}", e),
+        }
+        // moved by z ↦ 2z + 1
+        let moved = conjugated(&g, &Mat::new(c(2.0, 0.0), c(1.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)));
+        match equator(&moved) {
+            Equator::Circle(center, radius) => assert!((center - 1.0).norm() < 1e-6 && (radius - 2.0).abs() < 1e-6),
+            e => panic!("{:?}", e),
+        }
+        // and by a Cayley map onto the upper half-plane, whose edge is the
+        // real line, sending a point between the cusps to ∞
+        let (z0, i) = (Complex::from_polar(&1.0, &2.0), c(0.0, 1.0));
+        let root = (2.0 * i * z0).sqrt();
+        let cayley = Mat::new(i / root, i * z0 / root, c(-1.0, 0.0) / root, z0 / root);
+        match equator(&conjugated(&g, &cayley)) {
+            Equator::Line(a, u) => assert!(a.im.abs() < 1e-6 && u.im.abs() < 1e-6, "{} {}", a, u),
+            e => panic!("{:?}", e),
+        }
+        // a group that is not Fuchsian falls back to the unit circle
+        let g = grandma(c(2.0, 0.0), c(2.0, 0.0));
+        assert_eq!(equator(&g), Equator::Circle(c(0.0, 0.0), 1.0));
+    }
+
+    #[test]
+    fn the_domain_sides_meet_a_moved_groups_circle_at_right_angles() {
+        let g = conjugated(&grandma(c(3.0, 0.0), c(3.0, 0.0)), &Mat::new(c(2.0, 0.0), c(1.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)));
+        let (c0, radius) = (c(1.0, 0.0), 2.0);
+        let svg = fundamental_domain(&g, &RenderOptions::default()).to_string();
+        let d = svg.split(" d=\"").nth(1).unwrap().split('"').next().unwrap();
+        // each side is an arc, or a line where it is a diameter
+        let sides: Vec<Option<f64>> = d.split(['A', 'L']).skip(1)
+            .map(|side| side.trim().split(',').next().unwrap().parse().ok())
+            .zip(d.matches(['A', 'L']))
+            .map(|(r, command)| if command == "A" { r } else { None })
+            .collect();
+        let vertices = domain_vertices(&g);
+        assert_eq!(sides.len(), vertices.len(), "{}", d);
+        for (n, side) in sides.iter().enumerate() {
+            // a circle through two points of the equator that meets it at
+            // right angles has radius R tan(θ/2), θ the angle they subtend
+            let (p, q) = (vertices[n] - c0, vertices[(n + 1) % vertices.len()] - c0);
+            let theta = (q / p).arg().abs();
+            let r = match *side {
+                Some(r) => r,
+                None => {
+                    assert!((theta - std::f64::consts::PI).abs() < 1e-6, "a straight side {} not through the center", n);
+                    continue;
+                }
+            };
+            assert!((r - radius * (theta / 2.0).tan()).abs() < 1e-6, "{} for side {}", r, n);
+        }
+    }
+
+    // (center, radius) of each <circle> in `svg`
+    fn circles(svg: &str) -> Vec<(Complex<f64>, f64)> {
+        let attr = |e: &str, name: &str| -> f64 {
+            e.split(&format!(" {}=\"", name)).nth(1).unwrap().split('"').next().unwrap().parse().unwrap()
+        };
+        svg.split("<circle").skip(1).map(|e| (c(attr(e, "cx"), attr(e, "cy")), attr(e, "r"))).collect()
+    }
+
+    #[test]
+    fn horoballs_on_the_gasket_sit_in_its_gaps_and_give_its_circles() {
+        let mut g = grandma(c(2.0, 0.0), c(2.0, 0.0));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        crate::limitset_with(30, &mut g, &RenderOptions { epsilon: 0.004, ..RenderOptions::default() });
+        let opts = RenderOptions { min_radius: 0.01, ..RenderOptions::default() };
+        let horoballs = circles(&horoballs(3, &g, &opts).to_string());
+        assert!(horoballs.len() > 10, "{} horoballs", horoballs.len());
+        for &(center, r) in &horoballs {
+            // the limit set stays on one side of each, touching it at most
+            let inside = g.points().iter().filter(|z| (*z - center).norm() < r - 1e-4).count();
+            let outside = g.points().iter().filter(|z| (*z - center).norm() > r + 1e-4).count();
+            assert!(inside == 0 || outside == 0, "({}, {}) crosses the limit set", center, r);
+        }
+        for &(d, s) in g.gasket_circles(0.1).iter() {
+            // written as f32
+            assert!(horoballs.iter().any(|&(center, r)| (d - center).norm() + (s - r).abs() < 1e-5), "({}, {}) is missing", d, s);
+        }
+    }
+
+    #[test]
+    fn inversive_distance_of_tangent_and_concentric_circles() {
+        let d = inversive_distance((c(0.0, 0.0), 1.0), (c(3.0, 4.0), 4.0));
+        assert!((d - 1.0).abs() < 1e-12, "outside tangent: {}", d);
+        let d = inversive_distance((c(0.0, 0.0), 3.0), (c(0.0, 1.0), 2.0));
+        assert!((d + 1.0).abs() < 1e-12, "inside tangent: {}", d);
+        assert!(inversive_distance((c(0.0, 0.0), 1.0), (c(5.0, 0.0), 1.0)) > 1.0);
+        let (r1, r2) = (0.5, 2.0);
+        let rho = r2 / r1;
+        let d = inversive_distance((c(1.0, -1.0), r1), (c(1.0, -1.0), r2));
+        assert!((d + (rho + 1.0 / rho) / 2.0).abs() < 1e-12, "concentric: {}", d);
+    }
+}
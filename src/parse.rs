@@ -0,0 +1,279 @@
+//! Reading complex numbers in the shapes they turn up in: `2`,
+//! `1.91+0.05i` or `1.91+0.05j`, `-0.3i`, `(1.91, 0.05)` and the polar
+//! `2.0@15deg`, with whitespace anywhere between the pieces and exponents
+//! such as `1e-3+2e-2i`.
+
+use num::complex::Complex;
+use std::fmt;
+
+/// Why `parse_complex` rejected its input, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The string as given.
+    pub input: String,
+    /// The offending character, counted in characters from 0; the length
+    /// of the input when it ended too soon.
+    pub position: usize,
+    /// What was expected there.
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.input.chars().nth(self.position) {
+            Some(c) => write!(f, "{} at character {} (`{}`) of `{}`", self.message, self.position + 1, c, self.input),
+            None => write!(f, "{} at the end of `{}`", self.message, self.input),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct Scanner<'a> {
+    s: &'a str,
+    // a byte offset into `s`, always on a character boundary
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, word: &str) -> bool {
+        if self.rest().starts_with(word) {
+            self.pos += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            input: self.s.to_string(),
+            position: self.s[..self.pos].chars().count(),
+            message: message.to_string(),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn end(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    // digits with an optional point and exponent, unsigned; `None`, having
+    // consumed nothing, if there are no digits here
+    fn unsigned(&mut self) -> Option<f64> {
+        let bytes = self.rest().as_bytes();
+        let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let mut n = digits(0);
+        let mut mantissa = n;
+        if bytes.get(n) == Some(&b'.') {
+            let fraction = digits(n + 1);
+            mantissa += fraction;
+            n += 1 + fraction;
+        }
+        if mantissa == 0 {
+            return None;
+        }
+        if let Some(b'e') | Some(b'E') = bytes.get(n) {
+            let sign = match bytes.get(n + 1) {
+                Some(b'+') | Some(b'-') => 1,
+                _ => 0,
+            };
+            let exponent = digits(n + 1 + sign);
+            if exponent > 0 {
+                n += 1 + sign + exponent;
+            }
+        }
+        let x = self.rest()[..n].parse().ok()?;
+        self.pos += n;
+        Some(x)
+    }
+
+    // an optionally signed real number
+    fn real(&mut self) -> Result<f64, ParseError> {
+        self.skip_whitespace();
+        let sign = self.sign();
+        self.skip_whitespace();
+        match self.unsigned() {
+            Some(x) => Ok(sign * x),
+            None => Err(self.error("expected a number")),
+        }
+    }
+
+    fn sign(&mut self) -> f64 {
+        if self.eat('-') {
+            -1.0
+        } else {
+            self.eat('+');
+            1.0
+        }
+    }
+
+    fn imaginary_unit(&mut self) -> bool {
+        self.skip_whitespace();
+        self.eat('i') || self.eat('j')
+    }
+
+    // a number, an imaginary number, or a bare `i`, with the sign given;
+    // the bool is whether it was imaginary
+    fn term(&mut self, sign: f64) -> Result<(f64, bool), ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let x = self.unsigned();
+        let imaginary = self.imaginary_unit();
+        match (x, imaginary) {
+            (Some(x), imaginary) => Ok((sign * x, imaginary)),
+            (None, true) => Ok((sign, true)),
+            (None, false) => {
+                self.pos = start;
+                Err(self.error("expected a number"))
+            }
+        }
+    }
+
+    // `r@θ`, the `r` already read, with θ in degrees when followed by
+    // `deg` or `°` and radians otherwise
+    fn polar(&mut self, r: f64) -> Result<Complex<f64>, ParseError> {
+        let theta = self.real()?;
+        self.skip_whitespace();
+        let theta = if self.eat_str("deg") || self.eat('°') {
+            theta.to_radians()
+        } else {
+            self.eat_str("rad");
+            theta
+        };
+        self.end()?;
+        Ok(Complex::from_polar(&r, &theta))
+    }
+
+    fn complex(&mut self) -> Result<Complex<f64>, ParseError> {
+        self.skip_whitespace();
+        if self.eat('(') {
+            let re = self.real()?;
+            self.skip_whitespace();
+            self.expect(',')?;
+            let im = self.real()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            self.end()?;
+            return Ok(Complex::new(re, im));
+        }
+        let sign = self.sign();
+        let (first, imaginary) = self.term(sign)?;
+        self.skip_whitespace();
+        if imaginary {
+            self.end()?;
+            return Ok(Complex::new(0.0, first));
+        }
+        if self.eat('@') {
+            return self.polar(first);
+        }
+        let sign = match self.peek() {
+            None => return Ok(Complex::new(first, 0.0)),
+            Some('+') | Some('-') => self.sign(),
+            Some(_) => return Err(self.error("expected `+`, `-` or `@`")),
+        };
+        let (second, imaginary) = self.term(sign)?;
+        if !imaginary {
+            return Err(self.error("expected `i` or `j`"));
+        }
+        self.end()?;
+        Ok(Complex::new(first, second))
+    }
+}
+
+/// Parses a complex number written as `x`, `yi`, `x+yi` or `x-yi` (with
+/// `j` for `i` if preferred, and `i` alone for a unit imaginary part), as
+/// the pair `(x, y)`, or in polar form as `r@θdeg`, `r@θ°` or `r@θ` in
+/// radians. Whitespace may separate the pieces.
+pub fn parse_complex(s: &str) -> Result<Complex<f64>, ParseError> {
+    Scanner { s, pos: 0 }.complex()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_and_rejects() {
+        let i = |re, im| Complex::new(re, im);
+        let accepted = [
+            ("2", i(2.0, 0.0)),
+            ("-2", i(-2.0, 0.0)),
+            ("+2.5", i(2.5, 0.0)),
+            (".5", i(0.5, 0.0)),
+            ("1.91+0.05i", i(1.91, 0.05)),
+            ("1.91-0.05i", i(1.91, -0.05)),
+            ("1.91+0.05j", i(1.91, 0.05)),
+            ("  1.91 + 0.05i ", i(1.91, 0.05)),
+            ("-0.3i", i(0.0, -0.3)),
+            ("i", i(0.0, 1.0)),
+            ("-i", i(0.0, -1.0)),
+            ("2+i", i(2.0, 1.0)),
+            ("2-j", i(2.0, -1.0)),
+            ("1e-3+2e-2i", i(1e-3, 2e-2)),
+            ("1E3", i(1e3, 0.0)),
+            ("(1.91, 0.05)", i(1.91, 0.05)),
+            ("( -1 , 2 )", i(-1.0, 2.0)),
+            ("2@0", i(2.0, 0.0)),
+            ("2@90deg", i(0.0, 2.0)),
+            ("2@90°", i(0.0, 2.0)),
+            ("1@3.141592653589793", i(-1.0, 0.0)),
+            ("2@1.5707963267948966rad", i(0.0, 2.0)),
+        ];
+        for &(s, z) in &accepted {
+            match parse_complex(s) {
+                Ok(w) => assert!((w - z).norm() < 1e-12, "`{}` gave {}, not {}", s, w, z),
+                Err(e) => panic!("`{}` was rejected: {}", s, e),
+            }
+        }
+        let rejected = [
+            "", " ", "+", "abc", "2+", "2+3", "2i+3", "2++3i", "1.2.3", "2 3i", "2x", "(1, 2", "(1 2)", "(1, 2) 3",
+            "(1, 2i)", "2@", "2@90grad", "@90deg", "1e", "ii", "2+3ii", "2i3",
+        ];
+        for s in &rejected {
+            assert!(parse_complex(s).is_err(), "`{}` was accepted as {}", s, parse_complex(s).unwrap());
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_culprit() {
+        let e = parse_complex("2+3x").unwrap_err();
+        assert_eq!((e.input.as_str(), e.position), ("2+3x", 3));
+        let e = parse_complex("2+").unwrap_err();
+        assert_eq!(e.position, 2);
+        assert!(e.to_string().contains("at the end of `2+`"), "{}", e);
+    }
+}
@@ -0,0 +1,249 @@
+//! Vector PDF output, behind the `pdf` feature. usvg resolves the SVG into
+//! plain paths with absolute transforms, which are then written out as PDF
+//! path operators directly.
+
+use crate::output::create_output;
+use crate::Error;
+use resvg::tiny_skia::{PathSegment, Transform};
+use resvg::usvg::{self, FillRule, LineCap, LineJoin, Node, Paint};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use svg::Document;
+
+fn parse(doc: &Document) -> Result<usvg::Tree, Error> {
+    usvg::Tree::from_str(&doc.to_string(), &usvg::Options::default())
+        .map_err(|e| Error::Raster(e.to_string()))
+}
+
+/// One page's content stream, with the opacities it uses; `/GSn` in the
+/// stream sets `opacities[n]` for both fill and stroke.
+struct Page {
+    content: String,
+    opacities: Vec<f32>,
+    size: (f32, f32),
+}
+
+impl Page {
+    fn opacity(&mut self, alpha: f32) {
+        let n = match self.opacities.iter().position(|&a| a == alpha) {
+            Some(n) => n,
+            None => {
+                self.opacities.push(alpha);
+                self.opacities.len() - 1
+            }
+        };
+        writeln!(self.content, "/GS{} gs", n).unwrap();
+    }
+
+    fn group(&mut self, group: &usvg::Group, alpha: f32) {
+        // group opacity is folded into its children's, which is exact
+        // unless they overlap
+        let alpha = alpha * group.opacity().get();
+        for node in group.children() {
+            match node {
+                Node::Group(g) => self.group(g, alpha),
+                Node::Path(p) => self.path(p, alpha),
+                Node::Text(t) => self.group(t.flattened(), alpha),
+                Node::Image(_) => {}
+            }
+        }
+    }
+
+    fn path(&mut self, path: &usvg::Path, alpha: f32) {
+        if !path.is_visible() {
+            return;
+        }
+        if let Some(fill) = path.fill() {
+            if let Paint::Color(c) = fill.paint() {
+                self.begin(path.abs_transform(), alpha * fill.opacity().get());
+                writeln!(self.content, "{} {} {} rg", c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0).unwrap();
+                self.segments(path.data());
+                let op = match fill.rule() {
+                    FillRule::NonZero => "f",
+                    FillRule::EvenOdd => "f*",
+                };
+                writeln!(self.content, "{}\nQ", op).unwrap();
+            }
+        }
+        if let Some(stroke) = path.stroke() {
+            if let Paint::Color(c) = stroke.paint() {
+                self.begin(path.abs_transform(), alpha * stroke.opacity().get());
+                writeln!(self.content, "{} {} {} RG", c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0).unwrap();
+                let cap = match stroke.linecap() {
+                    LineCap::Butt => 0,
+                    LineCap::Round => 1,
+                    LineCap::Square => 2,
+                };
+                let join = match stroke.linejoin() {
+                    LineJoin::Miter | LineJoin::MiterClip => 0,
+                    LineJoin::Round => 1,
+                    LineJoin::Bevel => 2,
+                };
+                writeln!(self.content, "{} w {} J {} j", stroke.width().get(), cap, join).unwrap();
+                self.segments(path.data());
+                writeln!(self.content, "S\nQ").unwrap();
+            }
+        }
+    }
+
+    fn begin(&mut self, t: Transform, alpha: f32) {
+        writeln!(self.content, "q\n{} {} {} {} {} {} cm", t.sx, t.ky, t.kx, t.sy, t.tx, t.ty).unwrap();
+        if alpha < 1.0 {
+            self.opacity(alpha);
+        }
+    }
+
+    fn segments(&mut self, data: &resvg::tiny_skia::Path) {
+        let mut last = (0.0, 0.0);
+        for segment in data.segments() {
+            match segment {
+                PathSegment::MoveTo(p) => {
+                    writeln!(self.content, "{} {} m", p.x, p.y).unwrap();
+                    last = (p.x, p.y);
+                }
+                PathSegment::LineTo(p) => {
+                    writeln!(self.content, "{} {} l", p.x, p.y).unwrap();
+                    last = (p.x, p.y);
+                }
+                PathSegment::QuadTo(q, p) => {
+                    // PDF has only cubics; raise the degree
+                    let c1 = (last.0 + 2.0 / 3.0 * (q.x - last.0), last.1 + 2.0 / 3.0 * (q.y - last.1));
+                    let c2 = (p.x + 2.0 / 3.0 * (q.x - p.x), p.y + 2.0 / 3.0 * (q.y - p.y));
+                    writeln!(self.content, "{} {} {} {} {} {} c", c1.0, c1.1, c2.0, c2.1, p.x, p.y).unwrap();
+                    last = (p.x, p.y);
+                }
+                PathSegment::CubicTo(c1, c2, p) => {
+                    writeln!(self.content, "{} {} {} {} {} {} c", c1.x, c1.y, c2.x, c2.y, p.x, p.y).unwrap();
+                    last = (p.x, p.y);
+                }
+                PathSegment::Close => writeln!(self.content, "h").unwrap(),
+            }
+        }
+    }
+}
+
+/// Lays out `tree` on a `width` by `height` point page, fitted inside and
+/// centered like `render_png_sized`.
+fn page(tree: &usvg::Tree, (width, height): (f32, f32)) -> Page {
+    let size = tree.size();
+    let scale = (width / size.width()).min(height / size.height());
+    // PDF's y axis points up
+    let (dx, dy) = ((width - size.width() * scale) / 2.0, (height + size.height() * scale) / 2.0);
+    let mut page = Page {
+        content: format!("{} 0 0 {} {} {} cm\n", scale, -scale, dx, dy),
+        opacities: Vec::new(),
+        size: (width, height),
+    };
+    page.group(tree.root(), 1.0);
+    page
+}
+
+fn write_pdf(pages: &[Page], path: &str) -> Result<(), Error> {
+    // objects 1 and 2 are the catalog and page tree, then a page and its
+    // contents for each page
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|n| format!("{} 0 R", 3 + 2 * n)).collect::<Vec<_>>().join(" "),
+            pages.len()),
+    ];
+    for (n, page) in pages.iter().enumerate() {
+        let states: String = page.opacities.iter().enumerate()
+            .map(|(i, a)| format!("/GS{} << /ca {} /CA {} >> ", i, a, a))
+            .collect();
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /ExtGState << {}>> >> /Contents {} 0 R >>",
+            page.size.0, page.size.1, states, 4 + 2 * n));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", page.content.len(), page.content));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (n, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        write!(out, "{} 0 obj\n{}\nendobj\n", n + 1, object).unwrap();
+    }
+    let xref = out.len();
+    write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(out, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).unwrap();
+    let mut w = create_output(path)?;
+    w.write_all(out.as_bytes())?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Writes `docs` as the pages of one PDF, each `width` by `height` points,
+/// with the picture fitted inside and centered like `render_png_sized`.
+pub fn render_pdf_pages(docs: &[Document], path: &str, size: (f32, f32)) -> Result<(), Error> {
+    let pages = docs.iter()
+        .map(|doc| parse(doc).map(|tree| page(&tree, size)))
+        .collect::<Result<Vec<_>, _>>()?;
+    write_pdf(&pages, path)
+}
+
+/// Writes `doc` as a one-page PDF; see `render_pdf_pages`.
+pub fn render_pdf(doc: &Document, path: &str, size: (f32, f32)) -> Result<(), Error> {
+    render_pdf_pages(std::slice::from_ref(doc), path, size)
+}
+
+/// Width of the page `save_pdf` writes, in points: ten inches.
+pub const PDF_WIDTH: f32 = 720.0;
+
+/// Writes `doc` as a one-page PDF `PDF_WIDTH` points wide, with the page
+/// at the document's aspect ratio.
+pub fn save_pdf(doc: &Document, path: &str) -> Result<(), Error> {
+    let tree = parse(doc)?;
+    let size = tree.size();
+    let page = page(&tree, (PDF_WIDTH, PDF_WIDTH * size.height() / size.width()));
+    write_pdf(&[page], path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, RenderOptions, A, AI, B, BI};
+    use num::complex::Complex;
+
+    #[test]
+    fn the_content_stream_has_a_line_per_segment() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(8, &mut g);
+        let path = std::env::temp_dir().join(format!("svg_kleinian-ops-{}.pdf", std::process::id()));
+        render_pdf(&g.document(&RenderOptions::default()), path.to_str().unwrap(), (200.0, 200.0)).unwrap();
+        let pdf = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let content = pdf.split("stream\n").nth(1).unwrap().split("endstream").next().unwrap();
+        let ops: Vec<&str> = content.lines().filter_map(|line| line.split(' ').next_back()).collect();
+        let count = |op: &str| ops.iter().filter(|&&o| o == op).count();
+        assert_eq!(count("m"), 1);
+        assert_eq!(count("l"), g.points().len() - 1);
+        assert_eq!((count("S"), count("q"), count("Q")), (1, 1, 1));
+        assert_eq!(count("c") + count("f") + count("h"), 0);
+    }
+
+    #[test]
+    fn save_pdf_writes_the_pdf_magic_bytes() {
+        let mut g = grandma(Complex::new(2.0, 0.0), Complex::new(2.0, 0.0));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(6, &mut g);
+        let path = std::env::temp_dir().join(format!("svg_kleinian-magic-{}.pdf", std::process::id()));
+        let opts = RenderOptions { view_box: (-1.2, -0.6, 2.4, 1.2), ..RenderOptions::default() };
+        save_pdf(&g.document(&opts), path.to_str().unwrap()).unwrap();
+        let pdf = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        // one page, at the view's aspect ratio
+        let text = String::from_utf8_lossy(&pdf);
+        assert_eq!(text.matches("/Type /Page ").count(), 1);
+        assert!(text.contains(&format!("/MediaBox [0 0 {} {}]", PDF_WIDTH, PDF_WIDTH / 2.0)), "{}", text);
+    }
+}
@@ -0,0 +1,74 @@
+//! Named trace pairs for `grandma`, mostly from Indra's Pearls, as
+//! starting points that need no trace algebra.
+
+use num::complex::Complex;
+
+use crate::{grandma, Kleinian};
+
+pub struct Preset {
+    pub name: &'static str,
+    pub ta: Complex<f64>,
+    pub tb: Complex<f64>,
+    pub description: &'static str,
+}
+
+const fn c(re: f64, im: f64) -> Complex<f64> {
+    Complex { re, im }
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "apollonian-gasket",
+        ta: c(2.0, 0.0),
+        tb: c(2.0, 0.0),
+        description: "both generators parabolic: the Apollonian gasket (Indra's Pearls, ch. 7)",
+    },
+    Preset {
+        name: "fuchsian",
+        ta: c(3.0, 0.0),
+        tb: c(3.0, 0.0),
+        description: "real traces, so the group preserves a circle, which is its limit set",
+    },
+    Preset {
+        name: "spirals",
+        ta: c(1.91, 0.05),
+        tb: c(3.0, 0.0),
+        description: "a slightly loxodromic a: a Jordan curve wound into spirals (Indra's Pearls, ch. 8)",
+    },
+    Preset {
+        name: "sqrt3-plus-i",
+        ta: c(1.73205080757, 1.0),
+        tb: c(2.0, 0.0),
+        description: "ta = √3 + i with b parabolic: another circle packing",
+    },
+];
+
+/// The preset called `name`.
+pub fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+/// The group of the preset called `name`, without ends added.
+pub fn preset_by_name(name: &str) -> Option<Kleinian> {
+    preset(name).map(|p| grandma(p.ta, p.tb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{limitset, A, AI, B, BI};
+
+    #[test]
+    fn every_preset_renders_a_finite_limit_set() {
+        for p in PRESETS {
+            let mut g = preset_by_name(p.name).unwrap_or_else(|| panic!("no preset {}", p.name));
+            for &l in &[A, B, AI, BI] {
+                g.add_end(vec![l]);
+            }
+            limitset(10, &mut g);
+            assert!(g.points().len() > 1, "{} is empty", p.name);
+            assert!(g.points().iter().all(|z| z.is_finite()), "{} is not finite", p.name);
+        }
+        assert!(preset_by_name("nothing-by-this-name").is_none());
+    }
+}
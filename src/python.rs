@@ -0,0 +1,168 @@
+//! The `svg_kleinian` Python module, for exploring from Jupyter without
+//! going through the command line. Build it with maturin, from
+//! `pyproject.toml`:
+//!
+//! ```python
+//! >>> from svg_kleinian import Kleinian
+//! >>> g = Kleinian.from_mu(2j)
+//! >>> g.traces()
+//! ((2+0j), (2+0j), (2-2j))
+//! >>> svg = g.render_svg(epsilon=0.01, max_depth=40, stroke="navy")
+//! ```
+//!
+//! The crate's errors are raised as subclasses of `KleinianError`, with
+//! their messages, and I/O errors as `OSError`.
+
+use num::complex::Complex;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyOSError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyComplex, PyDict, PyList};
+
+use crate::{maskit_traces, traces, try_grandma, Error, KleinianRenderer, RenderStyle, RendererBuilder, Word};
+
+create_exception!(svg_kleinian, KleinianError, PyException, "An error from the tracer.");
+create_exception!(svg_kleinian, RasterError, KleinianError, "The SVG could not be parsed or rasterized.");
+create_exception!(svg_kleinian, ParametersError, KleinianError, "Parameters outside the range a construction accepts.");
+create_exception!(svg_kleinian, DegenerateError, KleinianError, "Traces at which grandma's recipe breaks down.");
+create_exception!(svg_kleinian, NotDiscreteError, KleinianError, "A group that can't be discrete.");
+create_exception!(svg_kleinian, WordError, KleinianError, "A word with a letter other than a, b, A and B.");
+create_exception!(svg_kleinian, NoConvergenceError, KleinianError, "A solver that stopped without an answer.");
+create_exception!(svg_kleinian, ConfigError, KleinianError, "Render settings that clash or are out of range.");
+create_exception!(svg_kleinian, NoPointsError, KleinianError, "A trace that found no curve to draw.");
+create_exception!(svg_kleinian, TooLargeError, KleinianError, "A trace too large to run without a node budget.");
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> PyErr {
+        let message = e.to_string();
+        match e {
+            Error::Io(_) => PyOSError::new_err(message),
+            Error::Raster(_) => RasterError::new_err(message),
+            Error::Parameters(_) => ParametersError::new_err(message),
+            Error::Degenerate { .. } => DegenerateError::new_err(message),
+            Error::NotDiscrete(_) => NotDiscreteError::new_err(message),
+            Error::Word { .. } => WordError::new_err(message),
+            Error::NoConvergence(_) => NoConvergenceError::new_err(message),
+            Error::Config(_) => ConfigError::new_err(message),
+            Error::NoPoints { .. } => NoPointsError::new_err(message),
+            Error::TooLarge { .. } => TooLargeError::new_err(message),
+        }
+    }
+}
+
+// anything Python's `complex` takes: a number, or a string such as "2+0.05j"
+fn complex(z: &Bound<'_, PyAny>) -> PyResult<Complex<f64>> {
+    let z = z.py().get_type::<PyComplex>().call1((z,))?;
+    let z = z.downcast::<PyComplex>()?;
+    Ok(Complex::new(z.real(), z.imag()))
+}
+
+fn to_py(py: Python<'_>, z: Complex<f64>) -> Bound<'_, PyComplex> {
+    PyComplex::from_doubles(py, z.re, z.im)
+}
+
+/// Grandma's two-generator group with traces ta and tb.
+#[pyclass(module = "svg_kleinian", frozen)]
+pub struct Kleinian {
+    ta: Complex<f64>,
+    tb: Complex<f64>,
+}
+
+impl Kleinian {
+    fn builder(&self, epsilon: f64, max_depth: i64, max_nodes: Option<usize>) -> RendererBuilder {
+        let builder = KleinianRenderer::builder().traces(self.ta, self.tb).epsilon(epsilon).max_depth(max_depth);
+        match max_nodes {
+            Some(n) => builder.max_nodes(n),
+            None => builder,
+        }
+    }
+}
+
+#[pymethods]
+impl Kleinian {
+    /// Raises DegenerateError for traces grandma's recipe can't use.
+    #[new]
+    fn new(ta: &Bound<'_, PyAny>, tb: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let (ta, tb) = (complex(ta)?, complex(tb)?);
+        try_grandma(ta, tb)?;
+        Ok(Kleinian { ta, tb })
+    }
+
+    /// The group at mu in the Maskit slice: ta = -i mu, with b parabolic.
+    #[staticmethod]
+    fn from_mu(mu: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let (ta, tb) = maskit_traces(complex(mu)?);
+        try_grandma(ta, tb)?;
+        Ok(Kleinian { ta, tb })
+    }
+
+    /// The traces of a, b and ab.
+    fn traces<'py>(&self, py: Python<'py>) -> PyResult<(Bound<'py, PyComplex>, Bound<'py, PyComplex>, Bound<'py, PyComplex>)> {
+        let (ta, tb, tab) = traces(&try_grandma(self.ta, self.tb)?);
+        Ok((to_py(py, ta), to_py(py, tb), to_py(py, tab)))
+    }
+
+    /// The matrix of the word w, in a, b and their inverses A and B, as
+    /// rows of complex numbers.
+    fn word<'py>(&self, py: Python<'py>, w: &str) -> PyResult<Vec<Vec<Bound<'py, PyComplex>>>> {
+        let w: Word = w.parse()?;
+        let m = try_grandma(self.ta, self.tb)?.word(&w);
+        Ok(vec![vec![to_py(py, m.a), to_py(py, m.b)], vec![to_py(py, m.c), to_py(py, m.d)]])
+    }
+
+    /// The traced points as complex numbers, in drawing order; a numpy
+    /// array when numpy is installed, a list otherwise. A group not known
+    /// to be discrete needs a max_nodes budget at depth.
+    #[pyo3(signature = (epsilon = 0.001, max_depth = 60, max_nodes = None))]
+    fn limit_points<'py>(&self, py: Python<'py>, epsilon: f64, max_depth: i64, max_nodes: Option<usize>) -> PyResult<Bound<'py, PyAny>> {
+        let output = self.builder(epsilon, max_depth, max_nodes).render()?;
+        let points = PyList::new(py, output.points.iter().map(|&(x, y)| PyComplex::from_doubles(py, x, y)))?;
+        match py.import("numpy") {
+            Ok(numpy) => numpy.call_method1("array", (points,)),
+            Err(_) => Ok(points.into_any()),
+        }
+    }
+
+    /// The SVG text of the limit set. Takes epsilon, max_depth and
+    /// max_nodes as limit_points does, and the style as stroke,
+    /// stroke_width, linejoin, linecap, background and view_box, an
+    /// (x, y, width, height) tuple.
+    #[pyo3(signature = (**options))]
+    fn render_svg(&self, options: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+        let (mut epsilon, mut max_depth, mut max_nodes) = (0.001, 60, None);
+        let mut style = RenderStyle::default();
+        for (key, value) in options.into_iter().flatten() {
+            match key.extract::<String>()?.as_str() {
+                "epsilon" => epsilon = value.extract()?,
+                "max_depth" => max_depth = value.extract()?,
+                "max_nodes" => max_nodes = value.extract()?,
+                "stroke" => style.stroke = value.extract()?,
+                "stroke_width" => style.stroke_width = value.extract()?,
+                "linejoin" => style.linejoin = value.extract()?,
+                "linecap" => style.linecap = value.extract()?,
+                "background" => style.background = value.extract()?,
+                "view_box" => style.view_box = value.extract()?,
+                key => return Err(PyTypeError::new_err(format!("render_svg() got an unexpected keyword argument '{}'", key))),
+            }
+        }
+        let output = self.builder(epsilon, max_depth, max_nodes).style(style).with_document().render()?;
+        Ok(output.document.expect("with_document asks for it").to_string())
+    }
+}
+
+#[pymodule]
+fn svg_kleinian(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_class::<Kleinian>()?;
+    m.add("KleinianError", py.get_type::<KleinianError>())?;
+    m.add("RasterError", py.get_type::<RasterError>())?;
+    m.add("ParametersError", py.get_type::<ParametersError>())?;
+    m.add("DegenerateError", py.get_type::<DegenerateError>())?;
+    m.add("NotDiscreteError", py.get_type::<NotDiscreteError>())?;
+    m.add("WordError", py.get_type::<WordError>())?;
+    m.add("NoConvergenceError", py.get_type::<NoConvergenceError>())?;
+    m.add("ConfigError", py.get_type::<ConfigError>())?;
+    m.add("NoPointsError", py.get_type::<NoPointsError>())?;
+    m.add("TooLargeError", py.get_type::<TooLargeError>())?;
+    Ok(())
+}
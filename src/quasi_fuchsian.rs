@@ -0,0 +1,66 @@
+use num::complex::Complex;
+
+use crate::{Error, Kleinian, Mat};
+
+/// The one-holed torus group with tr a = x, tr b = y and tr ab = z, built
+/// as a = [[x, -1], [1, 0]], b = [[0, s], [-1/s, y]] with s + 1/s = z, then
+/// moved by the Cayley transform so the upper half plane becomes the disk.
+fn fricke(x: Complex<f64>, y: Complex<f64>, z: Complex<f64>) -> Kleinian {
+    let one = Complex::new(1.0, 0.0);
+    let zero = Complex::new(0.0, 0.0);
+    let i = Complex::<f64>::i();
+    let s = (z + (z * z - 4.0).sqrt()) / 2.0;
+    let a = Mat::new(x, -one, one, zero);
+    let b = Mat::new(zero, s, -one / s, y);
+    let k = (2.0 * i).sqrt();
+    let cayley = Mat::new(one / k, -i / k, one / k, i / k);
+    let inv = cayley.adj();
+    Kleinian::new(&cayley * &a * &inv, &cayley * &b * &inv)
+}
+
+/// A quasi-Fuchsian group between two Fuchsian one-holed tori, each given
+/// by its real traces (tr a, tr b), both above 2. The pieces are taken
+/// with perpendicular axes, tr ab = tr a tr b / 2, which makes the
+/// boundary commutator hyperbolic when (tr a² - 4)(tr b² - 4) > 16.
+///
+/// This is only an approximation of Bers simultaneous uniformization: the
+/// traces are complexified as (top + bottom)/2 + i (top - bottom)/2 rather
+/// than solved for, so the two sides of the limit set only roughly carry
+/// the conformal structures asked for. When `top == bottom` the group is
+/// exactly the Fuchsian one.
+pub fn quasi_fuchsian(
+    top: (Complex<f64>, Complex<f64>),
+    bottom: (Complex<f64>, Complex<f64>),
+) -> Result<Kleinian, Error> {
+    for &t in &[top.0, top.1, bottom.0, bottom.1] {
+        if t.im != 0.0 || t.re.is_nan() || t.re <= 2.0 {
+            return Err(Error::Parameters(format!("Fuchsian traces must be real and above 2, got {}", t)));
+        }
+    }
+    let i = Complex::<f64>::i();
+    let mix = |t: f64, b: f64| Complex::new((t + b) / 2.0, 0.0) + i * ((t - b) / 2.0);
+    let x = mix(top.0.re, bottom.0.re);
+    let y = mix(top.1.re, bottom.1.re);
+    Ok(fricke(x, y, x * y / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Word, COMMUTATORS};
+
+    #[test]
+    fn equal_ends_give_a_fuchsian_group() {
+        let end = (Complex::new(3.0, 0.0), Complex::new(3.5, 0.0));
+        let g = quasi_fuchsian(end, end).unwrap();
+        assert!(g.is_fuchsian());
+        let commutator = g.word(&Word(COMMUTATORS[1].to_vec()));
+        assert!(commutator.is_hyperbolic(), "{:?}", commutator.classify());
+        let t = commutator.trace();
+        assert!(t.im.abs() < 1e-9 * t.norm(), "trace {}", t);
+
+        let other = (Complex::new(4.0, 0.0), Complex::new(3.5, 0.0));
+        assert!(!quasi_fuchsian(end, other).unwrap().is_fuchsian());
+        assert!(quasi_fuchsian(end, (Complex::new(1.5, 0.0), end.1)).is_err());
+    }
+}
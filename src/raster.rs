@@ -0,0 +1,210 @@
+//! PNG output through resvg, behind the `png` feature.
+
+use crate::output::create_output;
+use crate::{Colormap, Error, Heatmap, ToneMap};
+use resvg::tiny_skia::{IntSize, Pixmap, PixmapPaint, Transform};
+use resvg::usvg;
+use std::io::Write;
+use std::thread;
+use svg::Document;
+
+fn parse(doc: &Document) -> Result<usvg::Tree, Error> {
+    usvg::Tree::from_str(&doc.to_string(), &usvg::Options::default())
+        .map_err(|e| Error::Raster(e.to_string()))
+}
+
+/// Pixel size of `tree` scaled to `width` pixels across, and the scale used.
+fn raster_size(tree: &usvg::Tree, width: u32) -> Result<(IntSize, f32), Error> {
+    let size = tree.size();
+    let scale = width as f32 / size.width();
+    let height = (size.height() * scale).ceil() as u32;
+    IntSize::from_wh(width, height)
+        .map(|s| (s, scale))
+        .ok_or_else(|| Error::Raster(format!("bad image size {}x{}", width, height)))
+}
+
+fn save(pixmap: &Pixmap, path: &str) -> Result<(), Error> {
+    let png = pixmap.encode_png().map_err(|e| Error::Raster(e.to_string()))?;
+    let mut w = create_output(path)?;
+    w.write_all(&png)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Renders `doc` to a PNG `width` pixels across, keeping the aspect ratio.
+pub fn render_png(doc: &Document, path: &str, width: u32) -> Result<(), Error> {
+    let tree = parse(doc)?;
+    let (size, scale) = raster_size(&tree, width)?;
+    let mut pixmap = Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| Error::Raster("couldn't allocate the image".to_string()))?;
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    save(&pixmap, path)
+}
+
+/// Writes `heatmap` as a PNG with one pixel per bin.
+pub fn save_heatmap(heatmap: &Heatmap, path: &str, tone: &ToneMap, colormap: Colormap) -> Result<(), Error> {
+    let (w, h) = (heatmap.width() as u32, heatmap.height() as u32);
+    let size = IntSize::from_wh(w, h)
+        .ok_or_else(|| Error::Raster(format!("bad image size {}x{}", w, h)))?;
+    // fully opaque, so already premultiplied
+    let pixmap = Pixmap::from_vec(heatmap.rgba(tone, colormap), size)
+        .ok_or_else(|| Error::Raster("couldn't allocate the image".to_string()))?;
+    save(&pixmap, path)
+}
+
+/// Pixel dimensions and antialiasing for `render_png_sized`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PngOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Render at this many times the size and average each block of
+    /// pixels down, for smoother thin strokes.
+    pub supersample: u32,
+}
+
+/// Renders `doc` to a PNG of exactly `opts.width` by `opts.height` pixels,
+/// fitting the picture inside and centering it like SVG's default
+/// `xMidYMid meet`.
+pub fn render_png_sized(doc: &Document, path: &str, opts: &PngOptions) -> Result<(), Error> {
+    save(&rasterize(doc, opts)?, path)
+}
+
+/// The pixels `render_png_sized` writes.
+pub(crate) fn rasterize(doc: &Document, opts: &PngOptions) -> Result<Pixmap, Error> {
+    let tree = parse(doc)?;
+    let k = opts.supersample.max(1);
+    let (w, h) = (opts.width * k, opts.height * k);
+    let mut pixmap = Pixmap::new(w, h)
+        .ok_or_else(|| Error::Raster(format!("bad image size {}x{}", w, h)))?;
+    let size = tree.size();
+    let scale = (w as f32 / size.width()).min(h as f32 / size.height());
+    let transform = Transform::from_scale(scale, scale).post_translate(
+        (w as f32 - size.width() * scale) / 2.0,
+        (h as f32 - size.height() * scale) / 2.0,
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    if k > 1 {
+        pixmap = downsample(&pixmap, k)?;
+    }
+    Ok(pixmap)
+}
+
+/// Averages each `k` by `k` block of `pixmap` into one pixel.
+fn downsample(pixmap: &Pixmap, k: u32) -> Result<Pixmap, Error> {
+    let (w, h) = (pixmap.width() / k, pixmap.height() / k);
+    let mut out = Pixmap::new(w, h)
+        .ok_or_else(|| Error::Raster("couldn't allocate the image".to_string()))?;
+    let src = pixmap.data();
+    let n = k * k;
+    for (i, px) in out.data_mut().chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % w, i as u32 / w);
+        let mut sum = [0u32; 4];
+        for dy in 0..k {
+            for dx in 0..k {
+                let j = (((y * k + dy) * pixmap.width() + x * k + dx) * 4) as usize;
+                for c in 0..4 {
+                    sum[c] += src[j + c] as u32;
+                }
+            }
+        }
+        // the data is premultiplied, so channels average independently
+        for c in 0..4 {
+            px[c] = ((sum[c] + n / 2) / n) as u8;
+        }
+    }
+    Ok(out)
+}
+
+/// Like `render_png`, but cuts the picture into a `tiles` by `tiles` grid
+/// and rasterizes the tiles on separate threads before stitching them, so
+/// poster-sized renders use every core.
+pub fn render_tiled_png(doc: &Document, path: &str, width: u32, tiles: u32) -> Result<(), Error> {
+    let tree = parse(doc)?;
+    let (size, scale) = raster_size(&tree, width)?;
+    let tiles = tiles.max(1);
+    // tile i covers pixels cuts[i]..cuts[i + 1] along each axis
+    let cuts = |len: u32| (0..=tiles).map(|i| (len as u64 * i as u64 / tiles as u64) as u32).collect::<Vec<_>>();
+    let (xs, ys) = (cuts(size.width()), cuts(size.height()));
+
+    let rendered = thread::scope(|s| {
+        let tree = &tree;
+        let handles: Vec<_> = ys.windows(2)
+            .flat_map(|y| xs.windows(2).map(move |x| (x[0], y[0], x[1] - x[0], y[1] - y[0])))
+            .filter(|&(_, _, w, h)| w > 0 && h > 0)
+            .map(|(x, y, w, h)| s.spawn(move || {
+                let mut tile = Pixmap::new(w, h)?;
+                let transform = Transform::from_scale(scale, scale).post_translate(-(x as f32), -(y as f32));
+                resvg::render(tree, transform, &mut tile.as_mut());
+                Some((x, y, tile))
+            }))
+            .collect();
+        handles.into_iter()
+            .map(|h| h.join().expect("tile thread panicked"))
+            .collect::<Option<Vec<_>>>()
+    }).ok_or_else(|| Error::Raster("couldn't allocate a tile".to_string()))?;
+
+    let mut pixmap = Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| Error::Raster("couldn't allocate the image".to_string()))?;
+    for (x, y, tile) in &rendered {
+        pixmap.draw_pixmap(*x as i32, *y as i32, tile.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+    }
+    save(&pixmap, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, RenderOptions, A, AI, B, BI};
+
+    #[test]
+    fn tiles_stitch_to_the_size_of_a_single_render() {
+        let mut g = grandma(num::complex::Complex::new(1.91, 0.05), num::complex::Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(8, &mut g);
+        let opts = RenderOptions { view_box: (-1.2, -1.0, 2.4, 1.7), ..RenderOptions::default() };
+        let doc = g.document(&opts);
+        let dir = std::env::temp_dir().join(format!("svg_kleinian-tiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (single, tiled) = (dir.join("single.png"), dir.join("tiled.png"));
+        render_png(&doc, single.to_str().unwrap(), 101).unwrap();
+        render_tiled_png(&doc, tiled.to_str().unwrap(), 101, 3).unwrap();
+        let (single, tiled) = (Pixmap::load_png(&single).unwrap(), Pixmap::load_png(&tiled).unwrap());
+        assert_eq!((tiled.width(), tiled.height()), (single.width(), single.height()));
+        assert_eq!((tiled.width(), tiled.height()), (101, 72));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_small_png_has_its_size_and_the_curve_in_it() {
+        let two = num::complex::Complex::new(2.0, 0.0);
+        let mut g = grandma(two, two);
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(8, &mut g);
+        let opts = RenderOptions { stroke_width: 0.08, background: Some("white".to_string()), ..RenderOptions::default() };
+        let path = std::env::temp_dir().join(format!("svg_kleinian-small-{}.png", std::process::id()));
+        let png = PngOptions { width: 60, height: 40, supersample: 2 };
+        render_png_sized(&g.document(&opts), path.to_str().unwrap(), &png).unwrap();
+        let pixmap = Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!((pixmap.width(), pixmap.height()), (60, 40));
+        // the square view is 40 pixels across, centered, so the bands to
+        // either side are left transparent
+        let dark = |x: u32, y: u32| {
+            let p = pixmap.pixel(x, y).unwrap();
+            p.alpha() == 255 && p.red() < 128
+        };
+        assert!(pixmap.pixel(0, 0).unwrap().alpha() == 0 && pixmap.pixel(59, 39).unwrap().alpha() == 0);
+        let pixel = |z: num::complex::Complex<f64>| (10 + ((z.re + 1.2) / 2.4 * 40.0) as u32, ((z.im + 1.2) / 2.4 * 40.0) as u32);
+        for &z in g.points().iter().step_by(97) {
+            let (x, y) = pixel(z);
+            let near = (x.saturating_sub(1)..=x + 1).any(|x| (y.saturating_sub(1)..=(y + 1).min(39)).any(|y| dark(x, y)));
+            assert!(near, "nothing drawn near {} at {:?}", z, (x, y));
+        }
+        let drawn = (10..50).flat_map(|x| (0..40).map(move |y| (x, y))).filter(|&(x, y)| dark(x, y)).count();
+        assert!(drawn > 0 && drawn < 800, "{} of 1600 pixels drawn", drawn);
+    }
+}
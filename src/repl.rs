@@ -0,0 +1,148 @@
+//! The commands of `svg_kleinian repl`, one per line, for hunting for
+//! parameters without restarting the program each time:
+//!
+//! ```text
+//! > ta 1.91+0.05i
+//! > level 100
+//! > render out.svg
+//! wrote out.svg: 731065 points over 548296 branches in 0.54s
+//! > cusp 1 3
+//! μ = 0.5812034746+1.6938972023i; now the Maskit slice at it
+//! ```
+//!
+//! `run_command` does the work and returns what to print, so the loop in
+//! `main` only reads lines and prints replies.
+
+use std::io;
+use std::time::Instant;
+
+use crate::{fmt_complex, limitset_with, maskit_cusp, parse_complex, preset, write_svg, Error, Recipe, RenderConfig, RenderOptions, A, AI, B, BI, COMPLEX_PRECISION, DEFAULT_COMPRESSION};
+
+/// The commands `run_command` knows, printed by `help` and after an
+/// unknown one.
+pub const REPL_HELP: &str = "\
+commands:
+  ta Z, tb Z      set a trace
+  eps X           set epsilon
+  level N         set the longest word traced
+  recipe NAME     grandma or maskit
+  preset NAME     take both traces from a preset
+  show            print the current parameters
+  render [FILE]   trace and write an SVG (default image.svg)
+  info            describe the group
+  cusp P Q        find the Maskit cusp for P/Q and switch to it
+  help            print this
+  quit            leave";
+
+/// The parameters a session has set so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub config: RenderConfig,
+}
+
+/// What a command leaves for the loop to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// Print this, if anything, and read the next line.
+    Continue(String),
+    /// Stop reading.
+    Quit,
+}
+
+/// Runs one line of input against `session`. Blank lines do nothing. An
+/// unknown command is an `Error::Config` carrying the help text, and a
+/// bad argument one saying what is wrong with it; either way the session
+/// is left as it was.
+pub fn run_command(session: &mut Session, line: &str) -> Result<Reply, Error> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(Reply::Continue(String::new())),
+    };
+    let rest: Vec<&str> = words.collect();
+    let config = &mut session.config;
+    let text = match (command, &rest[..]) {
+        ("quit", []) | ("exit", []) => return Ok(Reply::Quit),
+        ("help", []) => REPL_HELP.to_string(),
+        // a complex number may be written with spaces, as in `1.91 + 0.05i`
+        ("ta", [_, ..]) => {
+            config.ta = parse_complex(&rest.join(" ")).map_err(|e| Error::Config(e.to_string()))?;
+            String::new()
+        }
+        ("tb", [_, ..]) => {
+            config.tb = parse_complex(&rest.join(" ")).map_err(|e| Error::Config(e.to_string()))?;
+            String::new()
+        }
+        ("eps", [x]) | ("epsilon", [x]) => match x.parse::<f64>() {
+            Ok(epsilon) if epsilon > 0.0 && epsilon.is_finite() => {
+                config.epsilon = epsilon;
+                String::new()
+            }
+            _ => return Err(Error::Config(format!("epsilon must be a positive number, got `{}`", x))),
+        },
+        ("level", [n]) => match n.parse::<i64>() {
+            Ok(level) if level >= 1 => {
+                config.level = level;
+                String::new()
+            }
+            _ => return Err(Error::Config(format!("level must be a whole number at least 1, got `{}`", n))),
+        },
+        ("recipe", [name]) => {
+            config.recipe = name.parse::<Recipe>().map_err(Error::Config)?;
+            String::new()
+        }
+        ("preset", [name]) => match preset(name) {
+            Some(p) => {
+                config.ta = p.ta;
+                config.tb = p.tb;
+                String::new()
+            }
+            None => return Err(Error::Config(format!("unknown preset `{}`", name))),
+        },
+        ("show", []) => describe(config),
+        ("render", []) => render(config, "image.svg")?,
+        ("render", [path]) => render(config, path)?,
+        ("info", []) => {
+            let mut out = Vec::new();
+            config.write_info(&mut out)?;
+            String::from_utf8_lossy(&out).trim_end().to_string()
+        }
+        ("cusp", [p, q]) => match (p.parse::<i64>(), q.parse::<i64>()) {
+            (Ok(p), Ok(q)) if q > 0 => {
+                let mu = maskit_cusp(p, q)?;
+                config.recipe = Recipe::Maskit;
+                config.ta = mu;
+                format!("μ = {}; now the Maskit slice at it", fmt_complex(mu, COMPLEX_PRECISION))
+            }
+            _ => return Err(Error::Config(format!("`cusp` takes a fraction P Q with Q positive, got `{} {}`", p, q))),
+        },
+        _ => return Err(Error::Config(format!("can't make sense of `{}`\n{}", line.trim(), REPL_HELP))),
+    };
+    Ok(Reply::Continue(text))
+}
+
+fn describe(config: &RenderConfig) -> String {
+    format!("recipe {}, ta = {}, tb = {}, level {}, epsilon {}",
+        config.recipe.name(), fmt_complex(config.ta, COMPLEX_PRECISION), fmt_complex(config.tb, COMPLEX_PRECISION),
+        config.level, config.epsilon)
+}
+
+// traces as `RenderConfig::render` does, keeping the group for its counts
+fn render(config: &RenderConfig, path: &str) -> Result<String, Error> {
+    config.validate()?;
+    let mut g = config.group()?;
+    for &l in &[A, B, AI, BI] {
+        g.add_end(vec![l]);
+    }
+    let opts = RenderOptions { epsilon: config.epsilon, max_nodes: config.max_nodes, ..RenderOptions::default() };
+    let start = Instant::now();
+    limitset_with(config.level, &mut g, &opts);
+    if g.points().len() < 2 {
+        return Err(Error::NoPoints { level: config.level, epsilon: config.epsilon });
+    }
+    let level = if path.ends_with(".svgz") { Some(DEFAULT_COMPRESSION) } else { None };
+    write_svg(path, &g.document(&opts), level)
+        .map_err(|e| Error::Io(io::Error::new(e.kind(), format!("{}: {}", path, e))))?;
+    Ok(format!("wrote {}: {} points over {} branches in {:.2}s",
+        path, g.points().len(), g.nodes(), start.elapsed().as_secs_f64()))
+}
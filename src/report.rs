@@ -0,0 +1,244 @@
+//! A machine-readable account of a render, for scripts that would
+//! otherwise scrape the messages on stderr.
+
+use num::complex::Complex;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::{drawn_runs, traces, Error, Kleinian, RenderOptions, Word, COMMUTATORS};
+
+/// Bumped whenever a field of the report changes meaning or is removed;
+/// new fields may appear without a bump.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The SHA-256 digest of `data`, as 64 lowercase hex digits.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The file a render wrote.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub path: String,
+    /// Size and digest, or `None` when written to standard output.
+    pub bytes: Option<u64>,
+    pub sha256: Option<String>,
+}
+
+impl OutputFile {
+    /// Describes the file at `path` as it is now.
+    pub fn from_path(path: &str) -> io::Result<Self> {
+        if path == crate::STDIO {
+            return Ok(OutputFile { path: path.to_string(), bytes: None, sha256: None });
+        }
+        let data = std::fs::read(path)?;
+        Ok(OutputFile { path: path.to_string(), bytes: Some(data.len() as u64), sha256: Some(sha256_hex(&data)) })
+    }
+}
+
+/// The traces of the generators, their product and their commutator, each
+/// written as an `[re, im]` pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportTraces {
+    #[serde(serialize_with = "write_complex", deserialize_with = "read_complex")]
+    pub a: Complex<f64>,
+    #[serde(serialize_with = "write_complex", deserialize_with = "read_complex")]
+    pub b: Complex<f64>,
+    #[serde(serialize_with = "write_complex", deserialize_with = "read_complex")]
+    pub ab: Complex<f64>,
+    #[serde(serialize_with = "write_complex", deserialize_with = "read_complex")]
+    pub commutator: Complex<f64>,
+}
+
+/// What `--stats-json` writes, field for field. Boxes are `[x, y, x, y]`
+/// (bounding box) or `[x, y, width, height]` (viewBox), timings an object
+/// from phase to seconds, and numbers that are not finite `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderReport {
+    pub schema_version: u32,
+    /// The command line as given, after `--config` files are read in.
+    pub arguments: Vec<String>,
+    pub recipe: String,
+    pub traces: ReportTraces,
+    pub points: usize,
+    /// The branches the trace visited; zero in reports from before node
+    /// counts were kept.
+    #[serde(default)]
+    pub nodes: usize,
+    /// The polylines the curve is drawn as, once split at infinity and
+    /// clipped.
+    pub subpaths: usize,
+    #[serde(deserialize_with = "read_quad")]
+    pub bounding_box: (f64, f64, f64, f64),
+    #[serde(deserialize_with = "read_quad")]
+    pub view_box: (f64, f64, f64, f64),
+    #[serde(deserialize_with = "read_number")]
+    pub stroke_width: f64,
+    #[serde(deserialize_with = "read_number")]
+    pub epsilon: f64,
+    pub level: i64,
+    /// Each phase of the run and how long it took, in seconds.
+    #[serde(serialize_with = "write_timings", deserialize_with = "read_timings")]
+    pub timings: Vec<(String, f64)>,
+    pub warnings: Vec<String>,
+    pub output: Option<OutputFile>,
+}
+
+impl RenderReport {
+    /// The report for the last trace of `g`, drawn with `opts`, with
+    /// timings, warnings and output left empty for the caller.
+    pub fn new(g: &Kleinian, opts: &RenderOptions, level: i64, recipe: &str, arguments: Vec<String>) -> Self {
+        let (a, b, ab) = traces(g);
+        RenderReport {
+            schema_version: REPORT_SCHEMA_VERSION,
+            arguments,
+            recipe: recipe.to_string(),
+            traces: ReportTraces { a, b, ab, commutator: g.word(&Word(COMMUTATORS[1].to_vec())).trace() },
+            points: g.points().len(),
+            nodes: g.nodes(),
+            subpaths: drawn_runs(g.points(), opts).len(),
+            bounding_box: g.bounding_box(),
+            view_box: opts.view_box,
+            stroke_width: opts.stroke_width,
+            epsilon: opts.epsilon,
+            level,
+            timings: Vec::new(),
+            warnings: Vec::new(),
+            output: None,
+        }
+    }
+
+    /// Writes the report as one JSON object.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *w, self)?;
+        writeln!(w)
+    }
+
+    /// Reads back a report `write_json` wrote, refusing one of a later
+    /// schema version.
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Version {
+            schema_version: u32,
+        }
+        let invalid = |msg: String| -> Error { io::Error::new(io::ErrorKind::InvalidData, msg).into() };
+        let version = serde_json::from_str::<Version>(text).map_err(|e| invalid(e.to_string()))?.schema_version;
+        if version > REPORT_SCHEMA_VERSION {
+            return Err(invalid(format!("schema version {}, newer than {}", version, REPORT_SCHEMA_VERSION)));
+        }
+        serde_json::from_str(text).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Reads the report at `path`; see `from_json`.
+    pub fn from_path(path: &str) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+// serde_json writes a number that is not finite as `null`, so `null` reads
+// back as NaN
+fn read_number<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(d)?.unwrap_or(f64::NAN))
+}
+
+fn write_complex<S: Serializer>(z: &Complex<f64>, s: S) -> Result<S::Ok, S::Error> {
+    [z.re, z.im].serialize(s)
+}
+
+fn read_complex<'de, D: Deserializer<'de>>(d: D) -> Result<Complex<f64>, D::Error> {
+    let [re, im] = <[Option<f64>; 2]>::deserialize(d)?;
+    Ok(Complex::new(re.unwrap_or(f64::NAN), im.unwrap_or(f64::NAN)))
+}
+
+fn read_quad<'de, D: Deserializer<'de>>(d: D) -> Result<(f64, f64, f64, f64), D::Error> {
+    let [a, b, c, e] = <[Option<f64>; 4]>::deserialize(d)?.map(|x| x.unwrap_or(f64::NAN));
+    Ok((a, b, c, e))
+}
+
+// timings are an object, kept in the order the phases ran
+fn write_timings<S: Serializer>(timings: &[(String, f64)], s: S) -> Result<S::Ok, S::Error> {
+    let mut map = s.serialize_map(Some(timings.len()))?;
+    for (phase, t) in timings {
+        map.serialize_entry(phase, t)?;
+    }
+    map.end()
+}
+
+fn read_timings<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<(String, f64)>, D::Error> {
+    struct Phases;
+
+    impl<'de> Visitor<'de> for Phases {
+        type Value = Vec<(String, f64)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an object from phase to seconds")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+            let mut timings = Vec::new();
+            while let Some((phase, t)) = map.next_entry::<String, Option<f64>>()? {
+                timings.push((phase, t.unwrap_or(f64::NAN)));
+            }
+            Ok(timings)
+        }
+    }
+
+    d.deserialize_map(Phases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with, A, AI, B, BI};
+
+    #[test]
+    fn a_report_reads_back_as_written() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let opts = RenderOptions::default();
+        limitset_with(8, &mut g, &opts);
+        let args = vec!["--level".to_string(), "8".to_string(), "a \"quoted\" \\ arg".to_string()];
+        let mut report = RenderReport::new(&g, &opts, 8, "grandma", args);
+        report.timings = vec![("trace".to_string(), 0.25), ("write".to_string(), 1e-3)];
+        report.warnings = vec!["a warning\nover two lines".to_string()];
+        report.output = Some(OutputFile { path: "out.svg".to_string(), bytes: Some(1234), sha256: Some("ab".repeat(32)) });
+        assert!(report.nodes > 0);
+
+        let path = std::env::temp_dir().join(format!("svg_kleinian-report-{}.json", std::process::id()));
+        let mut w = std::fs::File::create(&path).unwrap();
+        report.write_json(&mut w).unwrap();
+        drop(w);
+        let back = RenderReport::from_path(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((back.level, back.recipe.as_str(), back.nodes), (8, "grandma", report.nodes));
+        assert_eq!(back, report);
+
+        let newer = RenderReport::from_json("{\"schema_version\": 99}");
+        assert!(newer.unwrap_err().to_string().contains("schema version 99"));
+    }
+
+
+    #[test]
+    fn non_finite_numbers_are_written_as_null() {
+        let mut g = grandma(Complex::new(2.0, 0.0), Complex::new(2.0, 0.0));
+        g.add_end(vec![A]);
+        let mut report = RenderReport::new(&g, &RenderOptions::default(), 0, "grandma", Vec::new());
+        report.bounding_box = (f64::INFINITY, 0.0, f64::NEG_INFINITY, 1.0);
+        report.timings = vec![("trace".to_string(), f64::NAN)];
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let text = String::from_utf8(json).unwrap();
+        assert!(text.contains("\"bounding_box\": [\n    null,"));
+        let back = RenderReport::from_json(&text).unwrap();
+        assert!(back.bounding_box.0.is_nan() && back.bounding_box.2.is_nan());
+        assert_eq!(back.timings[0].0, "trace");
+        assert!(back.timings[0].1.is_nan());
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
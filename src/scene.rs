@@ -0,0 +1,868 @@
+//! Scene files: a render written down in TOML, for `--config scene.toml`,
+//! so each figure of a document can be kept as a file and redrawn exactly.
+//!
+//! Each key stands for one command line option, and a scene is read by
+//! turning it into those options, so it can do what the command line does
+//! and no more; the fields of `Scene` and its tables are the keys. The
+//! keys of an `[animation]` table make an `animate` run instead, and may
+//! not be mixed with the others:
+//!
+//! ```toml
+//! ta = "1.91+0.05i"
+//! tb = 2
+//! level = 60
+//!
+//! [style]
+//! background = "white"
+//! overlays = ["cusps"]
+//!
+//! [output]
+//! format = "png"
+//! path = "figure-3.png"
+//! size = "1200x1200"
+//! ```
+
+use serde::de::{Deserializer, Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{parse_complex, Recipe};
+
+/// A number, or a string for what a plain number can't say, such as
+/// `"1.91+0.05i"` or the range `"2..1.9+0.1i"`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SceneValue {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for SceneValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneValue::Number(x) => write!(f, "{}", x),
+            SceneValue::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+impl FromStr for SceneValue {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse().map(SceneValue::Number).unwrap_or_else(|_| SceneValue::Text(s.to_string())))
+    }
+}
+
+impl<'de> Deserialize<'de> for SceneValue {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct Value;
+
+        impl<'de> Visitor<'de> for Value {
+            type Value = SceneValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number or a string such as \"1.91+0.05i\"")
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<SceneValue, E> {
+                Ok(SceneValue::Number(n as f64))
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<SceneValue, E> {
+                Ok(SceneValue::Number(n as f64))
+            }
+
+            fn visit_f64<E>(self, x: f64) -> Result<SceneValue, E> {
+                Ok(SceneValue::Number(x))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<SceneValue, E> {
+                Ok(SceneValue::Text(s.to_string()))
+            }
+        }
+
+        d.deserialize_any(Value)
+    }
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+// a recipe the command line knows
+fn recipe<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    let name = String::deserialize(d)?;
+    Recipe::from_str(&name).map_err(D::Error::custom)?;
+    Ok(Some(name))
+}
+
+// a number, or a string that reads as a complex number
+fn complex<'de, D: Deserializer<'de>>(d: D) -> Result<Option<SceneValue>, D::Error> {
+    let value = SceneValue::deserialize(d)?;
+    if let SceneValue::Text(ref s) = value {
+        parse_complex(s).map_err(D::Error::custom)?;
+    }
+    Ok(Some(value))
+}
+
+// a number, or a string `Z..Z` or `Z`
+fn range<'de, D: Deserializer<'de>>(d: D) -> Result<Option<SceneValue>, D::Error> {
+    let value = SceneValue::deserialize(d)?;
+    if let SceneValue::Text(ref s) = value {
+        let ends = match s.find("..") {
+            Some(n) => vec![&s[..n], &s[n + 2..]],
+            None => vec![s.as_str()],
+        };
+        for z in ends {
+            parse_complex(z).map_err(D::Error::custom)?;
+        }
+    }
+    Ok(Some(value))
+}
+
+// a string, or an array of strings joined with commas
+fn joined<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    struct Joined;
+
+    impl<'de> Visitor<'de> for Joined {
+        type Value = String;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string or an array of strings")
+        }
+
+        fn visit_str<E>(self, s: &str) -> Result<String, E> {
+            Ok(s.to_string())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut items: A) -> Result<String, A::Error> {
+            let mut all = Vec::new();
+            while let Some(item) = items.next_element::<String>()? {
+                all.push(item);
+            }
+            Ok(all.join(","))
+        }
+    }
+
+    d.deserialize_any(Joined).map(Some)
+}
+
+type Args<'a, 'b> = &'a mut dyn Iterator<Item = &'b String>;
+
+// the next argument as the value of an option, if it reads as one
+fn value<T: FromStr>(field: &mut Option<T>, args: Args) {
+    if let Some(v) = args.next().and_then(|a| a.parse().ok()) {
+        *field = Some(v);
+    }
+}
+
+fn push<T: ToString>(args: &mut Vec<String>, flag: &str, value: &Option<T>) {
+    if let Some(v) = value {
+        args.push(flag.to_string());
+        args.push(v.to_string());
+    }
+}
+
+fn push_flag(args: &mut Vec<String>, flag: &str, on: bool) {
+    if on {
+        args.push(flag.to_string());
+    }
+}
+
+// the flag given once for each item
+fn push_list(args: &mut Vec<String>, flag: &str, items: &[String]) {
+    for item in items {
+        args.push(flag.to_string());
+        args.push(item.clone());
+    }
+}
+
+/// A render as a scene file describes it. Each field is a key of the file,
+/// standing for the option of the same name, with `-` for `_`, except
+/// where noted; a flag set to false is the same as leaving it out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    #[serde(deserialize_with = "recipe")]
+    pub recipe: Option<String>,
+    pub preset: Option<String>,
+    #[serde(deserialize_with = "complex")]
+    pub ta: Option<SceneValue>,
+    #[serde(deserialize_with = "complex")]
+    pub tb: Option<SceneValue>,
+    pub level: Option<i64>,
+    pub epsilon: Option<f64>,
+    pub epsilon_decay: Option<f64>,
+    pub max_nodes: Option<i64>,
+    pub node_ceiling: Option<i64>,
+    /// `--relator`, once for each.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relators: Vec<String>,
+    /// `--prefix`, once for each.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefixes: Vec<String>,
+    pub branch_order: Option<String>,
+    pub seed_word: Option<String>,
+    /// `--curve`, once for each.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub curves: Vec<String>,
+    #[serde(skip_serializing_if = "SceneStyle::is_empty")]
+    pub style: SceneStyle,
+    #[serde(skip_serializing_if = "SceneView::is_empty")]
+    pub view: SceneView,
+    #[serde(skip_serializing_if = "SceneOutput::is_empty")]
+    pub output: SceneOutput,
+    /// Present for an `animate` run.
+    pub animation: Option<SceneAnimation>,
+}
+
+/// The `[style]` table.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneStyle {
+    pub stroke: Option<String>,
+    pub stroke_px: Option<f64>,
+    pub linejoin: Option<String>,
+    pub linecap: Option<String>,
+    pub background: Option<String>,
+    #[serde(deserialize_with = "joined")]
+    pub gradient: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub color_letters: bool,
+    pub outline: Option<f64>,
+    pub ribbon: Option<f64>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub smooth: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub gasket: bool,
+    pub circles: Option<i64>,
+    pub mirror: Option<String>,
+    pub mirror_stroke: Option<String>,
+    /// `--overlay`, once for each.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<String>,
+    pub marker_size: Option<f64>,
+    pub marker_color: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub chords: bool,
+    pub tree_taper: Option<f64>,
+    pub domain_opacity: Option<f64>,
+    pub horoball_height: Option<f64>,
+}
+
+/// The `[view]` table.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneView {
+    #[serde(deserialize_with = "joined")]
+    pub post_transform: Option<String>,
+    pub camera: Option<String>,
+    pub model: Option<String>,
+    #[serde(deserialize_with = "complex")]
+    pub center: Option<SceneValue>,
+    pub zoom: Option<f64>,
+    #[serde(deserialize_with = "joined")]
+    pub clip: Option<String>,
+}
+
+/// The `[output]` table.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneOutput {
+    pub format: Option<String>,
+    /// `--output`.
+    pub path: Option<String>,
+    pub size: Option<String>,
+    pub supersample: Option<i64>,
+    pub precision: Option<i64>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub compress: bool,
+    pub compression_level: Option<i64>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub standalone: bool,
+    pub tikz_width: Option<f64>,
+    pub gamma: Option<f64>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub log: bool,
+    pub colormap: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub ndjson: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub words: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub with_depth: bool,
+    pub html: Option<String>,
+    pub stats_json: Option<String>,
+}
+
+/// The `[animation]` table, the options of `animate`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneAnimation {
+    #[serde(deserialize_with = "range")]
+    pub ta: Option<SceneValue>,
+    #[serde(deserialize_with = "range")]
+    pub tb: Option<SceneValue>,
+    #[serde(deserialize_with = "range")]
+    pub mu: Option<SceneValue>,
+    #[serde(deserialize_with = "range")]
+    pub bend: Option<SceneValue>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub reference_circle: bool,
+    #[serde(deserialize_with = "joined")]
+    pub farey: Option<String>,
+    pub cusp_offset: Option<f64>,
+    pub frames: Option<i64>,
+    pub depth: Option<i64>,
+    pub interpolate: Option<String>,
+    pub max_points: Option<i64>,
+    pub fps: Option<f64>,
+    pub frame_duration: Option<f64>,
+    /// `-o`.
+    pub path: Option<String>,
+    /// `--out`.
+    pub frames_pattern: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub resume: bool,
+    /// `--format`'s two values.
+    pub encode: Option<[String; 2]>,
+    pub size: Option<String>,
+    pub r#loop: Option<i64>,
+}
+
+impl SceneStyle {
+    fn is_empty(&self) -> bool {
+        *self == SceneStyle::default()
+    }
+
+    // takes `flag` and its value from `args`, if it is one of the table's
+    fn set(&mut self, flag: &str, args: Args) -> bool {
+        match flag {
+            "--stroke" => value(&mut self.stroke, args),
+            "--stroke-px" => value(&mut self.stroke_px, args),
+            "--linejoin" => value(&mut self.linejoin, args),
+            "--linecap" => value(&mut self.linecap, args),
+            "--background" => value(&mut self.background, args),
+            "--gradient" => value(&mut self.gradient, args),
+            "--color-letters" => self.color_letters = true,
+            "--outline" => value(&mut self.outline, args),
+            "--ribbon" => value(&mut self.ribbon, args),
+            "--smooth" => self.smooth = true,
+            "--gasket" => self.gasket = true,
+            "--circles" => value(&mut self.circles, args),
+            "--mirror" => value(&mut self.mirror, args),
+            "--mirror-stroke" => value(&mut self.mirror_stroke, args),
+            "--overlay" => self.overlays.extend(args.next().cloned()),
+            "--marker-size" => value(&mut self.marker_size, args),
+            "--marker-color" => value(&mut self.marker_color, args),
+            "--chords" => self.chords = true,
+            "--tree-taper" => value(&mut self.tree_taper, args),
+            "--domain-opacity" => value(&mut self.domain_opacity, args),
+            "--horoball-height" => value(&mut self.horoball_height, args),
+            _ => return false,
+        }
+        true
+    }
+
+    fn push_args(&self, args: &mut Vec<String>) {
+        push(args, "--stroke", &self.stroke);
+        push(args, "--stroke-px", &self.stroke_px);
+        push(args, "--linejoin", &self.linejoin);
+        push(args, "--linecap", &self.linecap);
+        push(args, "--background", &self.background);
+        push(args, "--gradient", &self.gradient);
+        push_flag(args, "--color-letters", self.color_letters);
+        push(args, "--outline", &self.outline);
+        push(args, "--ribbon", &self.ribbon);
+        push_flag(args, "--smooth", self.smooth);
+        push_flag(args, "--gasket", self.gasket);
+        push(args, "--circles", &self.circles);
+        push(args, "--mirror", &self.mirror);
+        push(args, "--mirror-stroke", &self.mirror_stroke);
+        push_list(args, "--overlay", &self.overlays);
+        push(args, "--marker-size", &self.marker_size);
+        push(args, "--marker-color", &self.marker_color);
+        push_flag(args, "--chords", self.chords);
+        push(args, "--tree-taper", &self.tree_taper);
+        push(args, "--domain-opacity", &self.domain_opacity);
+        push(args, "--horoball-height", &self.horoball_height);
+    }
+}
+
+impl SceneView {
+    fn is_empty(&self) -> bool {
+        *self == SceneView::default()
+    }
+
+    fn set(&mut self, flag: &str, args: Args) -> bool {
+        match flag {
+            "--post-transform" => value(&mut self.post_transform, args),
+            "--camera" => value(&mut self.camera, args),
+            "--model" => value(&mut self.model, args),
+            "--center" => value(&mut self.center, args),
+            "--zoom" => value(&mut self.zoom, args),
+            "--clip" => value(&mut self.clip, args),
+            _ => return false,
+        }
+        true
+    }
+
+    fn push_args(&self, args: &mut Vec<String>) {
+        push(args, "--post-transform", &self.post_transform);
+        push(args, "--camera", &self.camera);
+        push(args, "--model", &self.model);
+        push(args, "--center", &self.center);
+        push(args, "--zoom", &self.zoom);
+        push(args, "--clip", &self.clip);
+    }
+}
+
+impl SceneOutput {
+    fn is_empty(&self) -> bool {
+        *self == SceneOutput::default()
+    }
+
+    fn set(&mut self, flag: &str, args: Args) -> bool {
+        match flag {
+            "--format" => value(&mut self.format, args),
+            "--output" | "-o" => value(&mut self.path, args),
+            "--size" => value(&mut self.size, args),
+            "--supersample" => value(&mut self.supersample, args),
+            "--precision" => value(&mut self.precision, args),
+            "--compress" => self.compress = true,
+            "--compression-level" => value(&mut self.compression_level, args),
+            "--standalone" => self.standalone = true,
+            "--tikz-width" => value(&mut self.tikz_width, args),
+            "--gamma" => value(&mut self.gamma, args),
+            "--log" => self.log = true,
+            "--colormap" => value(&mut self.colormap, args),
+            "--ndjson" => self.ndjson = true,
+            "--words" => self.words = true,
+            "--with-depth" => self.with_depth = true,
+            "--html" => value(&mut self.html, args),
+            "--stats-json" => value(&mut self.stats_json, args),
+            _ => return false,
+        }
+        true
+    }
+
+    fn push_args(&self, args: &mut Vec<String>) {
+        push(args, "--format", &self.format);
+        push(args, "--output", &self.path);
+        push(args, "--size", &self.size);
+        push(args, "--supersample", &self.supersample);
+        push(args, "--precision", &self.precision);
+        push_flag(args, "--compress", self.compress);
+        push(args, "--compression-level", &self.compression_level);
+        push_flag(args, "--standalone", self.standalone);
+        push(args, "--tikz-width", &self.tikz_width);
+        push(args, "--gamma", &self.gamma);
+        push_flag(args, "--log", self.log);
+        push(args, "--colormap", &self.colormap);
+        push_flag(args, "--ndjson", self.ndjson);
+        push_flag(args, "--words", self.words);
+        push_flag(args, "--with-depth", self.with_depth);
+        push(args, "--html", &self.html);
+        push(args, "--stats-json", &self.stats_json);
+    }
+}
+
+impl SceneAnimation {
+    fn set(&mut self, flag: &str, args: Args) {
+        match flag {
+            "--ta" => value(&mut self.ta, args),
+            "--tb" => value(&mut self.tb, args),
+            "--mu" => value(&mut self.mu, args),
+            "--bend" => value(&mut self.bend, args),
+            "--reference-circle" => self.reference_circle = true,
+            "--farey" => value(&mut self.farey, args),
+            "--cusp-offset" => value(&mut self.cusp_offset, args),
+            "--frames" => value(&mut self.frames, args),
+            "--depth" => value(&mut self.depth, args),
+            "--interpolate" => value(&mut self.interpolate, args),
+            "--max-points" => value(&mut self.max_points, args),
+            "--fps" => value(&mut self.fps, args),
+            "--frame-duration" => value(&mut self.frame_duration, args),
+            "-o" => value(&mut self.path, args),
+            "--out" => value(&mut self.frames_pattern, args),
+            "--resume" => self.resume = true,
+            "--format" => {
+                let pair: Vec<String> = args.take(2).cloned().collect();
+                if let [format, path] = &pair[..] {
+                    self.encode = Some([format.clone(), path.clone()]);
+                }
+            }
+            "--size" => value(&mut self.size, args),
+            "--loop" => value(&mut self.r#loop, args),
+            _ => {}
+        }
+    }
+
+    fn push_args(&self, args: &mut Vec<String>) {
+        push(args, "--ta", &self.ta);
+        push(args, "--tb", &self.tb);
+        push(args, "--mu", &self.mu);
+        push(args, "--bend", &self.bend);
+        push_flag(args, "--reference-circle", self.reference_circle);
+        push(args, "--farey", &self.farey);
+        push(args, "--cusp-offset", &self.cusp_offset);
+        push(args, "--frames", &self.frames);
+        push(args, "--depth", &self.depth);
+        push(args, "--interpolate", &self.interpolate);
+        push(args, "--max-points", &self.max_points);
+        push(args, "--fps", &self.fps);
+        push(args, "--frame-duration", &self.frame_duration);
+        push(args, "-o", &self.path);
+        push(args, "--out", &self.frames_pattern);
+        push_flag(args, "--resume", self.resume);
+        if let Some(pair) = &self.encode {
+            args.push("--format".to_string());
+            args.extend(pair.iter().cloned());
+        }
+        push(args, "--size", &self.size);
+        push(args, "--loop", &self.r#loop);
+    }
+}
+
+impl Scene {
+    /// Reads a scene file. An error names the line, and the key when it is
+    /// the value that's wrong.
+    pub fn from_toml(text: &str) -> Result<Scene, String> {
+        let scene: Scene = serde_path_to_error::deserialize(toml::Deserializer::new(text)).map_err(|e| {
+            let line = e.inner().span().map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+            match e.path().to_string().as_str() {
+                "." => format!("line {}: {}", line, e.inner().message()),
+                path => format!("line {}: `{}`: {}", line, path, e.inner().message()),
+            }
+        })?;
+        if scene.animation.is_some() {
+            let rest = Scene { animation: None, ..scene.clone() };
+            if let Some(other) = rest.first_key() {
+                return Err(format!("`{}` has no effect in a scene with an [animation] table; set the keys of [animation] only", other));
+            }
+        }
+        Ok(scene)
+    }
+
+    // the first key set, as `table.key` or `key`
+    fn first_key(&self) -> Option<String> {
+        let table = match toml::Value::try_from(self).ok()? {
+            toml::Value::Table(table) => table,
+            _ => return None,
+        };
+        let (key, value) = table.into_iter().next()?;
+        match value {
+            toml::Value::Table(inner) => inner.keys().next().map(|k| format!("{}.{}", key, k)),
+            _ => Some(key),
+        }
+    }
+
+    /// Picks out of command line arguments the options a scene has keys
+    /// for, later ones overriding earlier ones, as on the command line;
+    /// `animate` first makes it an animation scene. Options without a key
+    /// are skipped.
+    pub fn from_args(args: &[String]) -> Scene {
+        let mut scene = Scene::default();
+        let mut args = args.iter().peekable();
+        if args.peek().map(|a| a.as_str()) == Some("animate") {
+            args.next();
+            scene.animation = Some(SceneAnimation::default());
+        }
+        while let Some(flag) = args.next() {
+            let args: Args = &mut args;
+            if let Some(animation) = &mut scene.animation {
+                animation.set(flag, args);
+                continue;
+            }
+            match flag.as_str() {
+                "--recipe" => value(&mut scene.recipe, args),
+                "--preset" => value(&mut scene.preset, args),
+                "--ta" => value(&mut scene.ta, args),
+                "--tb" => value(&mut scene.tb, args),
+                "--level" => value(&mut scene.level, args),
+                "--epsilon" => value(&mut scene.epsilon, args),
+                "--epsilon-decay" => value(&mut scene.epsilon_decay, args),
+                "--max-nodes" => value(&mut scene.max_nodes, args),
+                "--node-ceiling" => value(&mut scene.node_ceiling, args),
+                "--relator" => scene.relators.extend(args.next().cloned()),
+                "--prefix" => scene.prefixes.extend(args.next().cloned()),
+                "--branch-order" => value(&mut scene.branch_order, args),
+                "--seed-word" => value(&mut scene.seed_word, args),
+                "--curve" => scene.curves.extend(args.next().cloned()),
+                flag => {
+                    if !scene.style.set(flag, args) && !scene.view.set(flag, args) {
+                        scene.output.set(flag, args);
+                    }
+                }
+            }
+        }
+        scene
+    }
+
+    /// The command line arguments the scene stands for, starting with
+    /// `animate` for an animation.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(animation) = &self.animation {
+            args.push("animate".to_string());
+            animation.push_args(&mut args);
+            return args;
+        }
+        push(&mut args, "--recipe", &self.recipe);
+        push(&mut args, "--preset", &self.preset);
+        push(&mut args, "--ta", &self.ta);
+        push(&mut args, "--tb", &self.tb);
+        push(&mut args, "--level", &self.level);
+        push(&mut args, "--epsilon", &self.epsilon);
+        push(&mut args, "--epsilon-decay", &self.epsilon_decay);
+        push(&mut args, "--max-nodes", &self.max_nodes);
+        push(&mut args, "--node-ceiling", &self.node_ceiling);
+        push_list(&mut args, "--relator", &self.relators);
+        push_list(&mut args, "--prefix", &self.prefixes);
+        push(&mut args, "--branch-order", &self.branch_order);
+        push(&mut args, "--seed-word", &self.seed_word);
+        push_list(&mut args, "--curve", &self.curves);
+        self.style.push_args(&mut args);
+        self.view.push_args(&mut args);
+        self.output.push_args(&mut args);
+        args
+    }
+
+    /// The scene as a file `from_toml` reads back to the same scene.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("a scene is always a TOML table")
+    }
+}
+
+/// One render of a job file: its name, and its scene, or why the scene
+/// could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub name: String,
+    pub scene: Result<Scene, String>,
+}
+
+/// Reads a job file: scenes one after another, each starting with a
+/// `[[job]]` line, with its tables written `[job.style]` and so on, and
+/// an optional `name` key first (`job-N` otherwise). Every job must set
+/// where its output goes. A job whose scene is wrong is returned with the
+/// error, so the others can still run; an error in the file as a whole,
+/// such as keys before the first job, fails it all.
+pub fn parse_jobs(text: &str) -> Result<Vec<Job>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let starts: Vec<usize> = (0..lines.len()).filter(|&n| lines[n].trim() == "[[job]]").collect();
+    let first = starts.first().copied().unwrap_or(lines.len());
+    if let Some(n) = (0..first).find(|&n| !matches!(lines[n].trim().chars().next(), None | Some('#'))) {
+        return Err(format!("line {}: expected `[[job]]` before any keys", n + 1));
+    }
+    let mut jobs: Vec<Job> = Vec::new();
+    for (k, &start) in starts.iter().enumerate() {
+        let end = starts.get(k + 1).copied().unwrap_or(lines.len());
+        let mut name = None;
+        let mut in_table = false;
+        // the job's own lines, the rest blanked so line numbers still fit
+        let mut scene = vec![""; lines.len()];
+        let mut tables = Vec::new();
+        for n in start + 1..end {
+            let line = lines[n].trim();
+            if let Some(table) = line.strip_prefix("[job.").and_then(|t| t.strip_suffix(']')) {
+                tables.push((n, format!("[{}]", table)));
+                in_table = true;
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(format!("line {}: expected `[[job]]` or `[job.TABLE]`, got `{}`", n + 1, line));
+            }
+            match line.split_once('=') {
+                Some((key, value)) if !in_table && key.trim() == "name" => {
+                    let value = value.split('#').next().unwrap_or("").trim();
+                    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                        Some(v) => name = Some(v.to_string()),
+                        None => return Err(format!("line {}: `name` must be a string, got `{}`", n + 1, value)),
+                    }
+                }
+                _ => scene[n] = lines[n],
+            }
+        }
+        let name = name.unwrap_or_else(|| format!("job-{}", k + 1));
+        if jobs.iter().any(|j| j.name == name) {
+            return Err(format!("line {}: there are two jobs named `{}`", start + 1, name));
+        }
+        let mut scene: Vec<String> = scene.into_iter().map(String::from).collect();
+        for (n, table) in tables {
+            scene[n] = table;
+        }
+        let scene = Scene::from_toml(&scene.join("\n")).and_then(|s| {
+            let animated = s.animation.as_ref()
+                .is_some_and(|a| a.path.is_some() || a.frames_pattern.is_some() || a.encode.is_some());
+            if s.output.path.is_some() || animated {
+                Ok(s)
+            } else {
+                Err("no output path; set `output.path`, or every job writes the same file".to_string())
+            }
+        });
+        jobs.push(Job { name, scene });
+    }
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    // a scene setting every key outside [animation]
+    fn every_key() -> Scene {
+        let text = Some("a \"quoted\" \\ value".to_string());
+        Scene {
+            recipe: Some("maskit".to_string()),
+            preset: text.clone(),
+            ta: Some(SceneValue::Text("1.91+0.05i".to_string())),
+            tb: Some(SceneValue::Number(2.0)),
+            level: Some(3),
+            epsilon: Some(0.25),
+            epsilon_decay: Some(0.5),
+            max_nodes: Some(1000),
+            node_ceiling: Some(2000),
+            relators: strings(&["abAB", "x"]),
+            prefixes: strings(&["a"]),
+            branch_order: text.clone(),
+            seed_word: text.clone(),
+            curves: strings(&["ab", "aB"]),
+            style: SceneStyle {
+                stroke: text.clone(),
+                stroke_px: Some(1.5),
+                linejoin: text.clone(),
+                linecap: text.clone(),
+                background: text.clone(),
+                gradient: Some("a,b".to_string()),
+                color_letters: true,
+                outline: Some(0.25),
+                ribbon: Some(0.25),
+                smooth: true,
+                gasket: true,
+                circles: Some(3),
+                mirror: text.clone(),
+                mirror_stroke: text.clone(),
+                overlays: strings(&["cusps", "axes"]),
+                marker_size: Some(0.25),
+                marker_color: text.clone(),
+                chords: true,
+                tree_taper: Some(0.25),
+                domain_opacity: Some(0.25),
+                horoball_height: Some(0.25),
+            },
+            view: SceneView {
+                post_transform: Some("a,b".to_string()),
+                camera: text.clone(),
+                model: text.clone(),
+                center: Some(SceneValue::Text("0.5-i".to_string())),
+                zoom: Some(2.5),
+                clip: Some("a,b".to_string()),
+            },
+            output: SceneOutput {
+                format: text.clone(),
+                path: text.clone(),
+                size: text.clone(),
+                supersample: Some(3),
+                precision: Some(3),
+                compress: true,
+                compression_level: Some(3),
+                standalone: true,
+                tikz_width: Some(0.25),
+                gamma: Some(0.25),
+                log: true,
+                colormap: text.clone(),
+                ndjson: true,
+                words: true,
+                with_depth: true,
+                html: text.clone(),
+                stats_json: text,
+            },
+            animation: None,
+        }
+    }
+
+    fn every_animation_key() -> Scene {
+        let text = Some("a \"quoted\" \\ value".to_string());
+        let range = Some(SceneValue::Text("2..1.9+0.1i".to_string()));
+        Scene {
+            animation: Some(SceneAnimation {
+                ta: range.clone(),
+                tb: Some(SceneValue::Number(2.0)),
+                mu: range.clone(),
+                bend: range,
+                reference_circle: true,
+                farey: Some("a,b".to_string()),
+                cusp_offset: Some(0.25),
+                frames: Some(3),
+                depth: Some(3),
+                interpolate: text.clone(),
+                max_points: Some(3),
+                fps: Some(0.25),
+                frame_duration: Some(0.25),
+                path: text.clone(),
+                frames_pattern: text.clone(),
+                resume: true,
+                encode: Some(["gif".to_string(), "out.gif".to_string()]),
+                size: text,
+                r#loop: Some(3),
+            }),
+            ..Scene::default()
+        }
+    }
+
+    #[test]
+    fn every_key_reads_back_as_written() {
+        for scene in [every_key(), every_animation_key()] {
+            let text = scene.to_toml();
+            assert_eq!(Scene::from_toml(&text), Ok(scene.clone()), "{}", text);
+            assert_eq!(Scene::from_args(&scene.to_args()), scene);
+        }
+    }
+
+    #[test]
+    fn strings_join_and_false_flags_drop_out() {
+        let scene = Scene::from_toml("[style]\ngradient = [\"red\", \"blue\"]\nsmooth = false\n").unwrap();
+        assert_eq!(scene.style.gradient.as_deref(), Some("red,blue"));
+        assert_eq!(scene.to_args(), strings(&["--gradient", "red,blue"]));
+    }
+
+    #[test]
+    fn a_bad_line_is_refused_by_number() {
+        let fails = |text: &str, line: usize, what: &str| {
+            let e = Scene::from_toml(text).unwrap_err();
+            assert!(e.starts_with(&format!("line {}: ", line)) && e.contains(what), "{}", e);
+        };
+        fails("level = 3\nnonsense\n", 2, "expected `.`, `=`");
+        fails("level = 3\n\n[style]\nlevel = 4\n", 4, "`style.level`: unknown field `level`");
+        fails("[colors]\n", 1, "`colors`: unknown field `colors`");
+        fails("level = \"deep\"\n", 1, "`level`: invalid type: string \"deep\"");
+        fails("level = 3 4\n", 1, "expected newline");
+        fails("level = 3\nlevel = 4\n", 2, "duplicate key `level`");
+        fails("recipe = \"riley\"\n", 1, "`recipe`: unknown recipe");
+        fails("[view]\ncenter = \"1+\"\n", 2, "`view.center`");
+        let mixed = Scene::from_toml("level = 3\n[animation]\nframes = 2\n").unwrap_err();
+        assert!(mixed.starts_with("`level` has no effect"), "{}", mixed);
+    }
+
+    #[test]
+    fn two_jobs_read_as_two_scenes() {
+        let text = "[[job]]\nname = \"first\"\nlevel = 20\n[job.output]\npath = \"a.svg\"\n\n\
+                    [[job]]\nta = 2\n[job.style]\nstroke = \"red\"\n";
+        let jobs = parse_jobs(text).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].name, "first");
+        let first = jobs[0].scene.as_ref().unwrap();
+        assert_eq!(first.level, Some(20));
+        assert_eq!(first.output.path.as_deref(), Some("a.svg"));
+        // the second is named by its place, and sets no output
+        assert_eq!(jobs[1].name, "job-2");
+        assert!(jobs[1].scene.as_ref().unwrap_err().contains("no output path"));
+    }
+}
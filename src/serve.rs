@@ -0,0 +1,277 @@
+//! A small HTTP server for exploring groups from a browser, say over an
+//! SSH port forward to a headless machine, behind the `serve` feature.
+//! `GET /render.svg?ta=2+0.05i&tb=2&eps=0.0005` answers with the limit
+//! set, with an `X-Cache: hit` or `miss` header saying whether it was
+//! drawn afresh, and `GET /` with a page of boxes for the parameters. It speaks
+//! just enough HTTP/1.1 for that, written by hand on `std::net`, which
+//! saves a dependency for something this simple.
+
+use num::complex::Complex;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::{grandma, limitset_with, parse_complex, Error, RenderOptions, A, AI, B, BI, EPSILON};
+
+/// How `serve` runs and bounds the renders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServeOptions {
+    /// Requests handled at once; more wait their turn.
+    pub workers: usize,
+    /// The most branches one render may visit.
+    pub max_nodes: usize,
+    /// How long a request waits for its render before giving up on it.
+    pub timeout: Duration,
+    /// Renders kept to answer repeated requests, least recently used
+    /// dropped first.
+    pub cache_size: usize,
+    /// The depth a request gets when it doesn't give one, and the most it
+    /// may ask for.
+    pub depth: i64,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        ServeOptions {
+            workers: 4,
+            max_nodes: 2_000_000,
+            timeout: Duration::from_secs(30),
+            cache_size: 64,
+            depth: 50,
+        }
+    }
+}
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>svg_kleinian</title>
+<style>
+body { margin: 0; font-family: sans-serif; }
+form { height: 2.5em; line-height: 2.5em; text-align: center; }
+img { display: block; width: 100vw; height: calc(100vh - 2.5em); object-fit: contain; }
+</style>
+</head>
+<body>
+<form id="params">
+ta <input name="ta" value="2">
+tb <input name="tb" value="2">
+eps <input name="eps" value="0.001" size="8">
+depth <input name="depth" value="50" size="4">
+<button>render</button>
+</form>
+<img id="render" src="render.svg">
+<script>
+document.getElementById("params").addEventListener("submit", function (e) {
+  e.preventDefault();
+  var query = Array.prototype.map.call(this.elements, function (input) {
+    return input.name ? input.name + "=" + encodeURIComponent(input.value) : null;
+  }).filter(Boolean).join("&");
+  document.getElementById("render").src = "render.svg?" + query;
+});
+</script>
+</body>
+</html>
+"#;
+
+// the renders already made, by their parameters, most recent last
+struct Cache {
+    capacity: usize,
+    svgs: HashMap<String, Arc<String>>,
+    order: VecDeque<String>,
+}
+
+impl Cache {
+    fn get(&mut self, key: &str) -> Option<Arc<String>> {
+        let svg = self.svgs.get(key)?.clone();
+        if let Some(n) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(n).unwrap();
+            self.order.push_back(key);
+        }
+        Some(svg)
+    }
+
+    fn insert(&mut self, key: String, svg: Arc<String>) {
+        if self.capacity == 0 || self.svgs.contains_key(&key) {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.svgs.remove(&old);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.svgs.insert(key, svg);
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Arc<String>,
+    // for a render, whether the cache had it: `hit` or `miss`
+    cache: Option<&'static str>,
+}
+
+impl Response {
+    fn text(status: &'static str, body: String) -> Self {
+        Response { status, content_type: "text/plain; charset=utf-8", body: Arc::new(body), cache: None }
+    }
+}
+
+// `%XX` escapes decoded; `+` is left alone, as it is the sign in `2+0.05i`
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut n = 0;
+    while n < bytes.len() {
+        let hex = bytes.get(n + 1..n + 3).and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[n], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                n += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                n += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// the traces, epsilon and depth a query asks for
+fn parse_query(query: &str, opts: &ServeOptions) -> Result<(Complex<f64>, Complex<f64>, f64, i64), String> {
+    let (mut ta, mut tb) = (Complex::new(2.0, 0.0), Complex::new(2.0, 0.0));
+    let (mut eps, mut depth) = (EPSILON, opts.depth);
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        let bad = || format!("bad value `{}` for `{}`", value, name);
+        match name {
+            "ta" => ta = parse_complex(&value).map_err(|e| format!("bad value for `ta`: {}", e))?,
+            "tb" => tb = parse_complex(&value).map_err(|e| format!("bad value for `tb`: {}", e))?,
+            "eps" => eps = value.parse().ok().filter(|&e: &f64| e > 0.0).ok_or_else(bad)?,
+            "depth" => depth = value.parse().ok().filter(|&d| d > 0 && d <= opts.depth).ok_or_else(bad)?,
+            _ => return Err(format!("unknown parameter `{}`, expected ta, tb, eps or depth", name)),
+        }
+    }
+    Ok((ta, tb, eps, depth))
+}
+
+fn render(ta: Complex<f64>, tb: Complex<f64>, epsilon: f64, depth: i64, max_nodes: usize, cancel: Arc<AtomicBool>) -> String {
+    let mut g = grandma(ta, tb);
+    for &l in &[A, B, AI, BI] {
+        g.add_end(vec![l]);
+    }
+    let opts = RenderOptions { epsilon, max_nodes: Some(max_nodes), cancel: Some(cancel), ..RenderOptions::default() };
+    limitset_with(depth, &mut g, &opts);
+    g.document(&opts).to_string()
+}
+
+fn render_response(query: &str, opts: &ServeOptions, cache: &Mutex<Cache>) -> Response {
+    let (ta, tb, eps, depth) = match parse_query(query, opts) {
+        Ok(params) => params,
+        Err(e) => return Response::text("400 Bad Request", e),
+    };
+    let key = format!("{} {} {} {}", ta, tb, eps, depth);
+    if let Some(svg) = cache.lock().unwrap().get(&key) {
+        return Response { status: "200 OK", content_type: "image/svg+xml", body: svg, cache: Some("hit") };
+    }
+    // the render runs on this worker, so no more run at once than there
+    // are workers; a watchdog cancels one that outlasts the timeout, and
+    // what it drew by then is dropped
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (done, finished) = mpsc::channel::<()>();
+    let rendered = thread::scope(|s| {
+        let watched = &cancel;
+        s.spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(opts.timeout) {
+                watched.store(true, Ordering::Relaxed);
+            }
+        });
+        let flag = cancel.clone();
+        let rendered = panic::catch_unwind(AssertUnwindSafe(|| render(ta, tb, eps, depth, opts.max_nodes, flag)));
+        drop(done);
+        rendered
+    });
+    match rendered {
+        Ok(_) if cancel.load(Ordering::Relaxed) => {
+            Response::text("503 Service Unavailable", format!("the render took longer than {:?}", opts.timeout))
+        }
+        Ok(svg) => {
+            let svg = Arc::new(svg);
+            cache.lock().unwrap().insert(key, svg.clone());
+            Response { status: "200 OK", content_type: "image/svg+xml", body: svg, cache: Some("miss") }
+        }
+        Err(_) => Response::text("500 Internal Server Error", "the render panicked".to_string()),
+    }
+}
+
+fn handle(stream: TcpStream, opts: &ServeOptions, cache: &Mutex<Cache>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers say nothing a GET here needs
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+    let mut parts = request.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => {
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            match path {
+                "/" => Response { status: "200 OK", content_type: "text/html; charset=utf-8", body: Arc::new(PAGE.to_string()), cache: None },
+                "/render.svg" => render_response(query, opts, cache),
+                _ => Response::text("404 Not Found", format!("no such page `{}`", path)),
+            }
+        }
+        (Some(_), Some(_)) => Response::text("405 Method Not Allowed", "only GET is served".to_string()),
+        _ => Response::text("400 Bad Request", "malformed request line".to_string()),
+    };
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n", response.status, response.content_type, response.body.len())?;
+    if let Some(cache) = response.cache {
+        write!(stream, "X-Cache: {}\r\n", cache)?;
+    }
+    write!(stream, "Connection: close\r\n\r\n")?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+/// Answers requests on `listener` until it fails, with `opts.workers`
+/// threads taking connections in turn and each rendering its own. A request's own failures, such as
+/// bad parameters or a render past the timeout, are answered with an
+/// error status and don't stop the server.
+pub fn serve(listener: TcpListener, opts: &ServeOptions) -> Result<(), Error> {
+    let cache = Arc::new(Mutex::new(Cache { capacity: opts.cache_size, svgs: HashMap::new(), order: VecDeque::new() }));
+    let (send, receive) = mpsc::channel::<TcpStream>();
+    let receive = Arc::new(Mutex::new(receive));
+    for _ in 0..opts.workers.max(1) {
+        let (receive, cache, opts) = (receive.clone(), cache.clone(), opts.clone());
+        thread::spawn(move || loop {
+            let stream = match receive.lock().unwrap().recv() {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            // a client that hangs up early is its own problem
+            let _ = handle(stream, &opts, &cache);
+        });
+    }
+    for stream in listener.incoming() {
+        if send.send(stream?).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
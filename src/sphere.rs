@@ -0,0 +1,104 @@
+//! The trace on the Riemann sphere, where ∞ is a point like any other, as
+//! 3D geometry for Blender, three.js and the like.
+
+use num::complex::Complex;
+use std::io::{self, Write};
+
+use crate::export::json_number;
+use crate::{Kleinian, RenderOptions, A, AI, B, BI};
+
+/// Inverse stereographic projection onto the unit sphere from its north
+/// pole: 0 goes to the south pole (0, 0, -1), the unit circle to the
+/// equator, and ∞, or any non-finite point, to the north pole (0, 0, 1).
+pub fn to_sphere(z: Complex<f64>) -> [f64; 3] {
+    let r2 = z.norm_sqr();
+    if !r2.is_finite() {
+        return [0.0, 0.0, 1.0];
+    }
+    let d = r2 + 1.0;
+    [2.0 * z.re / d, 2.0 * z.im / d, (r2 - 1.0) / d]
+}
+
+/// Stereographic projection back to the plane, with the north pole going
+/// to infinity.
+pub fn from_sphere([x, y, z]: [f64; 3]) -> Complex<f64> {
+    let d = 1.0 - z;
+    if d == 0.0 {
+        return Complex::new(f64::INFINITY, f64::INFINITY);
+    }
+    Complex::new(x / d, y / d)
+}
+
+impl Kleinian {
+    // the points of the last trace on the sphere, after any post-transform,
+    // and the range of them drawn by each top-level branch or prefix; each
+    // range starts at the end of the one before, so they join up
+    fn sphere_subpaths(&self, opts: &RenderOptions) -> (Vec<[f64; 3]>, Vec<(usize, usize)>) {
+        let points = match opts.post_transform {
+            Some(ref m) => self.points.iter().map(|&z| m.mob(z)).collect(),
+            None => self.points.clone(),
+        };
+        let spans = if self.prefix_spans.is_empty() {
+            [A, BI, AI, B].iter()
+                .map(|&l| {
+                    let (start, end) = *self.spans.at(l);
+                    (start.saturating_sub(1), end)
+                })
+                .collect()
+        } else {
+            self.prefix_spans.clone()
+        };
+        (points.into_iter().map(to_sphere).collect(), spans)
+    }
+
+    /// Writes the last trace on the sphere as a Wavefront OBJ: a vertex per
+    /// point and a line element per top-level branch, or per prefix when
+    /// tracing prefixes.
+    pub fn write_sphere_obj<W: Write>(&self, opts: &RenderOptions, w: &mut W) -> io::Result<()> {
+        let (points, spans) = self.sphere_subpaths(opts);
+        writeln!(w, "# the limit set on the unit sphere, infinity at the north pole (0, 0, 1)")?;
+        for [x, y, z] in &points {
+            writeln!(w, "v {} {} {}", x, y, z)?;
+        }
+        for (start, end) in spans {
+            if end - start < 2 {
+                continue;
+            }
+            // OBJ indices count from 1
+            let indices: Vec<_> = (start + 1..=end).map(|n| n.to_string()).collect();
+            writeln!(w, "l {}", indices.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the points of the last trace on the sphere as a JSON array
+    /// of `[x, y, z]` triples, in tracing order.
+    pub fn write_sphere_json<W: Write>(&self, opts: &RenderOptions, w: &mut W) -> io::Result<()> {
+        let (points, _) = self.sphere_subpaths(opts);
+        writeln!(w, "[")?;
+        for (n, [x, y, z]) in points.iter().enumerate() {
+            let comma = if n + 1 < points.len() { "," } else { "" };
+            writeln!(w, "  [{}, {}, {}]{}", json_number(*x), json_number(*y), json_number(*z), comma)?;
+        }
+        writeln!(w, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_projection_puts_zero_south_and_the_unit_circle_on_the_equator() {
+        assert_eq!(to_sphere(Complex::new(0.0, 0.0)), [0.0, 0.0, -1.0]);
+        assert_eq!(to_sphere(Complex::new(1.0, 0.0)), [1.0, 0.0, 0.0]);
+        assert_eq!(to_sphere(Complex::new(f64::INFINITY, 0.0)), [0.0, 0.0, 1.0]);
+        assert!(!from_sphere([0.0, 0.0, 1.0]).is_finite());
+        for &(re, im) in &[(0.3, -0.2), (0.0, 1.0), (-4.0, 2.5), (100.0, 0.01)] {
+            let z = Complex::new(re, im);
+            let p = to_sphere(z);
+            assert!((p.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12, "{:?} is off the sphere", p);
+            assert!((from_sphere(p) - z).norm() < 1e-9 * z.norm().max(1.0), "{} comes back as {}", z, from_sphere(p));
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use num::complex::Complex;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::{
+    branch_epsilon, check_branch_order, limitset_with, reverses, run_after, successors, trace_ends, trace_start, Bag,
+    Kleinian, Letter, Mat, RenderOptions,
+};
+
+// a branch still to visit: the word of the first `depth` letters of the
+// path, then `l`, whose transform up to `l` is `t`
+struct Task {
+    level: i64,
+    l: Letter,
+    run: usize,
+    t: Mat,
+    depth: usize,
+}
+
+// the traversal of `branch`, with its stack in a Vec instead of on the
+// call stack, so it can stop after any branch
+struct LimitSetIter<'a> {
+    g: &'a Kleinian,
+    ends: Bag<Vec<Complex<f64>>>,
+    epsilon: f64,
+    epsilon_decay: Option<f64>,
+    reversed: bool,
+    // the branches visited so far
+    nodes: usize,
+    last: Complex<f64>,
+    path: Vec<Letter>,
+    stack: Vec<Task>,
+    // the points of the last branch drawn, not yet yielded
+    points: VecDeque<Complex<f64>>,
+}
+
+impl LimitSetIter<'_> {
+    fn visit(&mut self, Task { level, l, run, t, depth }: Task) {
+        self.nodes += 1;
+        self.path.truncate(depth);
+        self.path.push(l);
+        if self.g.relators.iter().any(|r| self.path.ends_with(r)) {
+            return;
+        }
+        let t = t * self.g.mat(l);
+        let mut z = self.last;
+        let mut to_draw = Vec::new();
+        let epsilon = branch_epsilon(self.epsilon, self.epsilon_decay, self.path.len());
+        for &pt in self.ends.at(l) {
+            let w = t.mob(pt);
+            if level > 0 && (z - w).norm_sqr() > epsilon * epsilon {
+                // pushed last first, so they come off in order
+                for &next in successors(l, self.reversed).iter().rev() {
+                    if let Some(run) = run_after(&self.g.max_runs, l, next, run) {
+                        self.stack.push(Task { level: level - 1, l: next, run, t: t.clone(), depth: depth + 1 });
+                    }
+                }
+                return;
+            }
+            to_draw.push(w);
+            z = w;
+        }
+        self.last = z;
+        self.points.extend(to_draw);
+    }
+}
+
+impl Iterator for LimitSetIter<'_> {
+    type Item = (f64, f64);
+
+    fn next(&mut self) -> Option<(f64, f64)> {
+        loop {
+            if let Some(z) = self.points.pop_front() {
+                return Some((z.re, z.im));
+            }
+            let task = self.stack.pop()?;
+            self.visit(task);
+        }
+    }
+}
+
+impl Kleinian {
+    /// Traces the same points as `limitset`, writing each to `w` as a line
+    /// `x y`, so memory stays bounded by `STREAM_CHUNK` rather than the
+    /// number of points.
+    pub fn stream_points<W: Write>(&mut self, level: i64, w: &mut W) -> io::Result<()> {
+        self.stream_points_with(level, &RenderOptions::default(), w)
+    }
+
+    /// Traces the same points as `limitset_with` under `opts`, writing them
+    /// to `w` as `stream_points` does. The trace runs on a thread of its
+    /// own and hands its points over every `STREAM_CHUNK`; if writing
+    /// fails it stops there. Afterwards the group holds no points.
+    ///
+    /// # Panics
+    ///
+    /// As `limitset_with` does.
+    pub fn stream_points_with<W: Write>(&mut self, level: i64, opts: &RenderOptions, w: &mut W)
+        -> io::Result<()>
+    {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<Complex<f64>>>(2);
+        self.sink = Some(sender);
+        let written = thread::scope(|scope| {
+            let g = &mut *self;
+            let trace = scope.spawn(move || {
+                limitset_with(level, g, opts);
+                g.flush_sink();
+                g.sink = None;
+            });
+            let mut written = Ok(());
+            for chunk in receiver.iter() {
+                written = chunk.iter().try_for_each(|z| writeln!(w, "{} {}", z.re, z.im));
+                if written.is_err() {
+                    break;
+                }
+            }
+            // dropping the receiver makes the trace's next send fail
+            drop(receiver);
+            if let Err(e) = trace.join() {
+                std::panic::resume_unwind(e);
+            }
+            written
+        });
+        self.sink = None;
+        self.spans = Bag::new((0, 0), (0, 0), (0, 0), (0, 0));
+        self.prefix_spans.clear();
+        written?;
+        w.flush()
+    }
+
+    /// The points `limitset` traces, found as they are asked for: only the
+    /// branches needed for the points taken so far are visited, and memory
+    /// is bounded by the depth, so `take` and the like stop the traversal
+    /// early.
+    pub fn iter_limit_set(&self, level: i64) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.iter_limit_set_with(level, &RenderOptions::default())
+    }
+
+    /// The points `limitset_with` traces under `opts`, found as they are
+    /// asked for as `iter_limit_set` finds them. Prefixes, `max_nodes` and
+    /// `cancel` are left to the caller, who can stop taking points instead.
+    ///
+    /// # Panics
+    ///
+    /// As `limitset_with` does.
+    pub fn iter_limit_set_with(&self, level: i64, opts: &RenderOptions) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.limit_set_iter(level, opts)
+    }
+
+    fn limit_set_iter(&self, level: i64, opts: &RenderOptions) -> LimitSetIter<'_> {
+        if let Err(e) = check_branch_order(&opts.branch_order) {
+            panic!("{}", e);
+        }
+        let reversed = reverses(&opts.branch_order);
+        let seed = opts.seed_word.as_ref().map_or_else(Mat::id, |w| self.word(w));
+        let start = seed.mob(trace_start(self, &opts.branch_order));
+        let stack = opts.branch_order.iter().rev()
+            .filter(|&&l| *self.max_runs.at(l) > 0)
+            .map(|&l| Task { level: level - 1, l, run: 1, t: seed.clone(), depth: 0 })
+            .collect();
+        LimitSetIter {
+            g: self,
+            ends: trace_ends(self, reversed),
+            epsilon: opts.epsilon,
+            epsilon_decay: opts.epsilon_decay,
+            reversed,
+            nodes: 0,
+            last: start,
+            path: Vec::new(),
+            stack,
+            points: VecDeque::from(vec![start]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, read_points, STREAM_CHUNK, A, AI, B, BI};
+
+    fn group() -> Kleinian {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05))
+            .with_relator("aaa".parse().unwrap());
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g
+    }
+
+    // the points `stream_points_with` writes, read back
+    fn streamed(g: &mut Kleinian, level: i64, opts: &RenderOptions) -> Vec<(f64, f64)> {
+        let mut out = Vec::new();
+        g.stream_points_with(level, opts, &mut out).unwrap();
+        read_points(&out[..]).unwrap()
+    }
+
+    fn traced(g: &mut Kleinian, level: i64, opts: &RenderOptions) -> Vec<(f64, f64)> {
+        limitset_with(level, g, opts);
+        g.points().iter().map(|z| (z.re, z.im)).collect()
+    }
+
+    #[test]
+    fn streams_what_limitset_with_traces() {
+        let runs = vec![
+            RenderOptions { epsilon: 0.02, ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.01, max_nodes: Some(5000), ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.01, prefixes: vec!["ab".parse().unwrap(), "B".parse().unwrap()], ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.01, seed_word: Some("b".parse().unwrap()), branch_order: [B, A, BI, AI], ..RenderOptions::default() },
+        ];
+        for opts in &runs {
+            let mut g = group();
+            let expected = traced(&mut g, 20, opts);
+            assert!(expected.len() > STREAM_CHUNK, "{} points is too few to span chunks", expected.len());
+            let points = streamed(&mut g, 20, opts);
+            assert_eq!(points, expected);
+            assert!(g.points().is_empty());
+        }
+    }
+
+    #[test]
+    fn a_failed_write_stops_the_trace() {
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut g = group();
+        let opts = RenderOptions { epsilon: 0.001, ..RenderOptions::default() };
+        assert!(g.stream_points_with(20, &opts, &mut Full).is_err());
+        assert!(g.cancelled);
+    }
+
+    #[test]
+    fn the_iterator_gives_the_traced_points() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        let points: Vec<_> = g.iter_limit_set(8).collect();
+        limitset(8, &mut g);
+        assert_eq!(points, g.points().iter().map(|z| (z.re, z.im)).collect::<Vec<_>>());
+        let runs = vec![
+            RenderOptions { epsilon: 0.01, ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.02, epsilon_decay: Some(0.9), ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.01, branch_order: [B, AI, BI, A], ..RenderOptions::default() },
+            RenderOptions { epsilon: 0.01, seed_word: Some("b".parse().unwrap()), branch_order: [B, A, BI, AI], ..RenderOptions::default() },
+        ];
+        for opts in &runs {
+            let mut g = group();
+            let points: Vec<_> = g.iter_limit_set_with(20, opts).collect();
+            assert_eq!(points, traced(&mut g, 20, opts), "{:?}", opts.branch_order);
+        }
+    }
+
+    #[test]
+    fn taking_a_few_points_visits_a_few_branches() {
+        let g = group();
+        let opts = RenderOptions { epsilon: 1e-6, ..RenderOptions::default() };
+        let mut points = g.limit_set_iter(1000, &opts);
+        assert_eq!(points.by_ref().take(100).count(), 100);
+        assert!(points.nodes < 5000, "{} branches for 100 points", points.nodes);
+        assert!(points.stack.len() < 5000, "{} branches queued", points.stack.len());
+    }
+
+    #[test]
+    fn a_reserved_trace_matches_one_grown_point_by_point() {
+        let mut g = group();
+        limitset(8, &mut g);
+        // every branch of 4·3^7 could draw a point, well under the cap
+        assert!(g.points.capacity() >= 4 * 3usize.pow(7));
+        let mut grown = Vec::new();
+        for p in g.iter_limit_set(8) {
+            grown.push(p);
+        }
+        assert_eq!(grown, g.points().iter().map(|z| (z.re, z.im)).collect::<Vec<_>>());
+    }
+}
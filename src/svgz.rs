@@ -0,0 +1,106 @@
+//! Writing and reading SVG gzipped as `.svgz`, which deep renders shrink to
+//! a fraction of their size in and browsers open directly.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use svg::Document;
+
+use crate::output::{create_output, read_input};
+
+/// The gzip level used when none is given, zlib's usual trade-off.
+pub const DEFAULT_COMPRESSION: u32 = 6;
+
+/// Writes `doc` to `path`, or standard output for `-`, gzipped at `level`
+/// (0 to 9) when given. The SVG text is compressed as it is written, so it
+/// is never held whole.
+pub fn write_svg(path: &str, doc: &Document, level: Option<u32>) -> io::Result<()> {
+    log::debug!("writing {}{}", path, level.map_or(String::new(), |l| format!(", gzipped at level {}", l)));
+    let file = create_output(path)?;
+    match level {
+        Some(level) => {
+            let mut gz = GzEncoder::new(file, Compression::new(level.min(9)));
+            svg::write(&mut gz, doc)?;
+            gz.finish()?.flush()
+        }
+        None => {
+            let mut file = file;
+            svg::write(&mut file, doc)?;
+            file.flush()
+        }
+    }
+}
+
+/// Reads the SVG text at `path`, or on standard input for `-`,
+/// decompressing it first if it is gzipped, whatever the file is called.
+pub fn read_svg(path: &str) -> io::Result<String> {
+    let bytes = read_input(path)?;
+    let mut text = String::new();
+    // the gzip magic number
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(&bytes[..]).read_to_string(&mut text)?;
+    } else {
+        text = String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+    Ok(text)
+}
+
+/// The `data-` attributes of the root `svg` element in `text`, without the
+/// prefix, in the order written; a render records its parameters there.
+pub fn svg_metadata(text: &str) -> Vec<(String, String)> {
+    let tag = match text.find("<svg") {
+        Some(start) => &text[start + 4..],
+        None => return Vec::new(),
+    };
+    let mut rest = &tag[..tag.find('>').unwrap_or(tag.len())];
+    let mut metadata = Vec::new();
+    while let Some(eq) = rest.find("=\"") {
+        let name = rest[..eq].split_whitespace().last().unwrap_or("");
+        let value = &rest[eq + 2..];
+        let end = match value.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+        if let Some(name) = name.strip_prefix("data-") {
+            metadata.push((name.to_string(), value[..end].to_string()));
+        }
+        rest = &value[end + 1..];
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset, RenderOptions, A, AI, B, BI};
+    use num::complex::Complex;
+
+    fn path_data(svg: &str) -> &str {
+        svg.split(" d=\"").nth(1).expect("a path").split('"').next().unwrap()
+    }
+
+    #[test]
+    fn a_gzipped_render_reads_back_as_the_plain_one() {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset(8, &mut g);
+        let opts = RenderOptions { metadata: vec![("ta".to_string(), "1.91+0.05i".to_string())], ..RenderOptions::default() };
+        let doc = g.document(&opts);
+        let dir = std::env::temp_dir().join(format!("svg_kleinian-svgz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (plain, gzipped) = (dir.join("plain.svg"), dir.join("gzipped.svgz"));
+        write_svg(plain.to_str().unwrap(), &doc, None).unwrap();
+        write_svg(gzipped.to_str().unwrap(), &doc, Some(9)).unwrap();
+        let bytes = std::fs::read(&gzipped).unwrap();
+        assert!(bytes.starts_with(&[0x1f, 0x8b]));
+        let (plain, gzipped) = (read_svg(plain.to_str().unwrap()).unwrap(), read_svg(gzipped.to_str().unwrap()).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(bytes.len() < plain.len());
+        assert!(path_data(&plain).len() > 100);
+        assert_eq!(path_data(&gzipped), path_data(&plain));
+        assert_eq!(svg_metadata(&gzipped), vec![("ta".to_string(), "1.91+0.05i".to_string())]);
+    }
+}
@@ -0,0 +1,172 @@
+//! TikZ output for LaTeX documents: the traced curve as `\draw` commands,
+//! with its colors defined up front so LaTeX controls the rest.
+
+use num::complex::Complex;
+use std::io::{self, Write};
+
+use crate::{drawn_runs, gradient_pieces, hex_rgb, overlay, Kleinian, RenderOptions, A, AI, B, BI};
+
+/// The most points in one `\draw`; longer runs are split into several
+/// that share their end points, as TikZ slows to a crawl and can run out
+/// of memory on very long paths.
+pub const MAX_TIKZ_POINTS: usize = 1000;
+
+/// How the picture is laid out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TikzOptions {
+    /// Width of the viewBox in centimetres.
+    pub width: f64,
+    /// Wrap the picture in a complete `standalone` document.
+    pub standalone: bool,
+}
+
+impl Default for TikzOptions {
+    fn default() -> Self {
+        TikzOptions { width: 10.0, standalone: false }
+    }
+}
+
+/// The color TikZ knows `color` by: hex colors are defined as
+/// `kleinianN`, anything else is passed through as a color name.
+fn tikz_color(color: &str, defined: &mut Vec<(String, [f64; 3])>) -> String {
+    match hex_rgb(color) {
+        Some(rgb) => {
+            let n = match defined.iter().position(|(_, c)| *c == rgb) {
+                Some(n) => n,
+                None => {
+                    defined.push((format!("kleinian{}", defined.len()), rgb));
+                    defined.len() - 1
+                }
+            };
+            defined[n].0.clone()
+        }
+        None => color.to_string(),
+    }
+}
+
+/// The pieces of the trace and their strokes, split the way `document`
+/// splits them.
+fn strokes<'a>(g: &'a Kleinian, opts: &RenderOptions) -> Vec<(&'a [Complex<f64>], String)> {
+    let points = &g.points[..];
+    match opts.letter_colors {
+        _ if opts.gradient.as_ref().is_some_and(|c| !c.is_empty()) => {
+            gradient_pieces(points, opts.gradient.as_ref().expect("checked above"))
+        }
+        _ if g.prefix_spans.len() > 1 => g.prefix_spans.iter().enumerate()
+            .map(|(n, &(start, end))| (&points[start..end], overlay::PALETTE[n % overlay::PALETTE.len()].to_string()))
+            .collect(),
+        Some(ref colors) => [A, BI, AI, B].iter()
+            .map(|&l| {
+                let (start, end) = *g.spans.at(l);
+                (&points[start.saturating_sub(1)..end], colors.at(l).clone())
+            })
+            .collect(),
+        None => vec![(points, opts.stroke.clone())],
+    }
+}
+
+impl Kleinian {
+    /// Writes the traced curve as a `tikzpicture`, scaled so the viewBox
+    /// is `tikz.width` centimetres across, with y pointing up as TikZ has
+    /// it. Coordinates get `coordinate_precision` decimal places, in
+    /// centimetres, or 4 when that is unset; overlays, outlines and
+    /// smoothing are SVG only.
+    pub fn write_tikz<W: Write>(&self, opts: &RenderOptions, tikz: &TikzOptions, w: &mut W) -> io::Result<()> {
+        let (x0, y0, width, height) = opts.view_box;
+        let scale = tikz.width / width;
+        let digits = opts.coordinate_precision.unwrap_or(4);
+        let coord = |z: &Complex<f64>| format!("({:.*},{:.*})", digits, (z.re - x0) * scale, digits, (y0 + height - z.im) * scale);
+
+        let mut defined = Vec::new();
+        let mut body = Vec::new();
+        for (points, stroke) in strokes(self, opts) {
+            let color = tikz_color(&stroke, &mut defined);
+            for run in drawn_runs(points, opts) {
+                // chunks overlap by a point so the pieces join up
+                let mut start = 0;
+                while start + 1 < run.len() {
+                    let end = (start + MAX_TIKZ_POINTS).min(run.len());
+                    let path: Vec<_> = run[start..end].iter().map(coord).collect();
+                    body.push(format!("\\draw[{}] {};", color, path.join(" -- ")));
+                    start = end - 1;
+                }
+            }
+        }
+
+        if tikz.standalone {
+            writeln!(w, "\\documentclass[tikz]{{standalone}}")?;
+        }
+        for (name, [r, g, b]) in &defined {
+            writeln!(w, "\\definecolor{{{}}}{{RGB}}{{{},{},{}}}", name, r, g, b)?;
+        }
+        if tikz.standalone {
+            writeln!(w, "\\begin{{document}}")?;
+        }
+        writeln!(w, "\\begin{{tikzpicture}}[line width={:.*}cm, line join=round, line cap=round]",
+            digits.max(4), opts.stroke_width * scale)?;
+        writeln!(w, "\\useasboundingbox (0,0) rectangle ({:.*},{:.*});", digits, tikz.width, digits, height * scale)?;
+        for line in body {
+            writeln!(w, "{}", line)?;
+        }
+        writeln!(w, "\\end{{tikzpicture}}")?;
+        if tikz.standalone {
+            writeln!(w, "\\end{{document}}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grandma, limitset_with};
+
+    fn traced(opts: &RenderOptions) -> Kleinian {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        limitset_with(9, &mut g, opts);
+        g
+    }
+
+    fn tikz(g: &Kleinian, opts: &RenderOptions, tikz: &TikzOptions) -> String {
+        let mut out = Vec::new();
+        g.write_tikz(opts, tikz, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn a_long_path_is_drawn_in_pieces_of_at_most_max_points() {
+        let opts = RenderOptions::default();
+        let g = traced(&opts);
+        let n = g.points().len();
+        assert!(n > MAX_TIKZ_POINTS);
+        let out = tikz(&g, &opts, &TikzOptions::default());
+        assert_eq!(out.matches("\\begin{tikzpicture}").count(), 1);
+        assert!(out.trim_end().ends_with("\\end{tikzpicture}"));
+        assert!(!out.contains("documentclass"));
+        let draws: Vec<&str> = out.lines().filter(|l| l.starts_with("\\draw")).collect();
+        assert_eq!(draws.len(), (n - 1).div_ceil(MAX_TIKZ_POINTS - 1));
+        // the pieces share their ends, so they hold every point once more each
+        let points: usize = draws.iter().map(|d| d.matches(" -- ").count() + 1).sum();
+        assert_eq!(points, n + draws.len() - 1);
+        assert!(draws.iter().all(|d| d.starts_with("\\draw[black] (") && d.ends_with(");")));
+    }
+
+    #[test]
+    fn letter_colors_are_defined_and_each_drawn() {
+        let opts = RenderOptions { letter_colors: Some(RenderOptions::default_letter_colors()), ..RenderOptions::default() };
+        let g = traced(&opts);
+        let out = tikz(&g, &opts, &TikzOptions { standalone: true, ..TikzOptions::default() });
+        assert!(out.starts_with("\\documentclass[tikz]{standalone}"));
+        assert!(out.trim_end().ends_with("\\end{document}"));
+        let begin = out.find("\\begin{tikzpicture}").unwrap();
+        assert!(out.find("\\begin{document}").unwrap() < begin);
+        assert!(begin < out.find("\\end{tikzpicture}").unwrap());
+        assert_eq!(out.matches("\\definecolor").count(), 4);
+        for n in 0..4 {
+            assert!(out.contains(&format!("\\draw[kleinian{}] (", n)), "no path in kleinian{}", n);
+        }
+    }
+}
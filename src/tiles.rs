@@ -0,0 +1,113 @@
+//! A window of the limit set cut into a grid of tiles, each traced only
+//! where it shows, which assemble without seams.
+
+use svg::Document;
+
+use crate::{limitset_with, Kleinian, RenderOptions};
+
+/// The window of the tile in row `row` and column `col` when `window` is
+/// cut into `cols` by `rows` tiles, given like `RenderOptions::view_box`.
+pub fn tile_window(
+    (x, y, w, h): (f64, f64, f64, f64),
+    (cols, rows): (usize, usize),
+    row: usize,
+    col: usize,
+) -> (f64, f64, f64, f64) {
+    let (tw, th) = (w / cols as f64, h / rows as f64);
+    (x + col as f64 * tw, y + row as f64 * th, tw, th)
+}
+
+/// Traces `g` to `level` for just `window`, skipping the branches off it,
+/// and draws it clipped to the window. The tiles of one picture should
+/// share `opts.epsilon`, so that each cuts the same polyline at its edges,
+/// and the half-open clip leaves each seam to one of them.
+pub fn tile(g: &mut Kleinian, level: i64, window: (f64, f64, f64, f64), opts: &RenderOptions) -> Document {
+    let opts = RenderOptions {
+        view_box: window,
+        clip: Some(window),
+        cull: Some(window),
+        ..opts.clone()
+    };
+    limitset_with(level, g, &opts);
+    g.document(&opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex;
+    use crate::{drawn_runs, grandma, A, AI, B, BI};
+
+    fn group() -> Kleinian {
+        let mut g = grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        g
+    }
+
+    fn length(runs: &[Vec<Complex<f64>>]) -> f64 {
+        runs.iter().flat_map(|run| run.windows(2)).map(|p| (p[1] - p[0]).norm()).sum()
+    }
+
+    // the ends of the runs lying on the line x = 0 (or y = 0, when
+    // `vertical` is false), sorted, other than `start`, where the trace
+    // begins and ends rather than being cut
+    fn on_seam(runs: &[Vec<Complex<f64>>], vertical: bool, start: Complex<f64>) -> Vec<(f64, f64)> {
+        let mut seam: Vec<(f64, f64)> = runs.iter()
+            .flat_map(|run| [run[0], run[run.len() - 1]])
+            .filter(|&z| z != start && if vertical { z.re == 0.0 } else { z.im == 0.0 })
+            .map(|z| (z.re, z.im))
+            .collect();
+        seam.sort_by(|p, q| p.partial_cmp(q).unwrap());
+        seam
+    }
+
+    #[test]
+    fn two_by_two_tiles_meet_without_gaps_or_overlaps() {
+        let window = (-1.25, -1.25, 2.5, 2.5);
+        let (level, opts) = (40, RenderOptions { epsilon: 0.005, ..RenderOptions::default() });
+        let mut whole = group();
+        limitset_with(level, &mut whole, &opts);
+        let whole_runs = drawn_runs(whole.points(), &RenderOptions { clip: Some(window), ..opts.clone() });
+
+        let mut tiles = vec![vec![Vec::new(); 2]; 2];
+        for (row, tiles) in tiles.iter_mut().enumerate() {
+            for (col, runs) in tiles.iter_mut().enumerate() {
+                let w = tile_window(window, (2, 2), row, col);
+                let mut g = group();
+                tile(&mut g, level, w, &opts);
+                assert!(g.nodes() < whole.nodes(), "tile {} {} traced everything", row, col);
+                *runs = drawn_runs(g.points(), &RenderOptions { clip: Some(w), ..opts.clone() });
+                assert!(!runs.is_empty());
+            }
+        }
+
+        // together they draw what the whole window does, once
+        let total: f64 = tiles.iter().flatten().map(|runs| length(runs)).sum();
+        assert!((total - length(&whole_runs)).abs() < 1e-9 * total, "{} against {}", total, length(&whole_runs));
+        let mut segments: Vec<[u64; 4]> = tiles.iter().flatten().flatten()
+            .flat_map(|run| run.windows(2))
+            .map(|p| [p[0].re.to_bits(), p[0].im.to_bits(), p[1].re.to_bits(), p[1].im.to_bits()])
+            .collect();
+        let n = segments.len();
+        segments.sort();
+        segments.dedup();
+        assert_eq!(segments.len(), n, "a segment is drawn twice");
+
+        // where the curve crosses a seam, both sides cut it at the same point
+        let start = whole.points()[0];
+        let mut crossings = 0;
+        for (row, tiles) in tiles.iter().enumerate() {
+            let left = on_seam(&tiles[0], true, start);
+            assert_eq!(left, on_seam(&tiles[1], true, start), "row {}", row);
+            crossings += left.len();
+        }
+        for (col, (top, bottom)) in tiles[0].iter().zip(&tiles[1]).enumerate() {
+            let top = on_seam(top, false, start);
+            assert_eq!(top, on_seam(bottom, false, start), "column {}", col);
+            crossings += top.len();
+        }
+        assert!(crossings > 0);
+    }
+}
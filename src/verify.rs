@@ -0,0 +1,212 @@
+//! A self-check of a trace: each generator carries the limit set onto
+//! itself, so it should carry the traced points onto (points near) other
+//! traced points.
+
+use num::complex::Complex;
+use std::collections::HashMap;
+
+use crate::{Error, Kleinian, Letter, Mat, Word, COMMUTATORS, A, AI, B, BI};
+
+/// The default tolerance of `--verify`, as a multiple of the trace's
+/// epsilon: a point's image lies on the limit set between two traced
+/// points, which are about epsilon apart.
+pub const VERIFY_TOLERANCE: f64 = 10.0;
+
+/// What `verify_invariance` found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invariance {
+    /// The largest distance from an image of a traced point to the nearest
+    /// traced point.
+    pub deviation: f64,
+    /// The traced point whose image was furthest off, and the generator
+    /// that moved it there.
+    pub worst: Option<(Complex<f64>, Letter)>,
+    /// How many images were checked.
+    pub checked: usize,
+}
+
+// the traced points bucketed into square cells, for nearest-point queries
+struct Grid<'a> {
+    points: &'a [Complex<f64>],
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl<'a> Grid<'a> {
+    fn new(points: &'a [Complex<f64>], bbox: (f64, f64, f64, f64)) -> Self {
+        let (x0, y0, x1, y1) = bbox;
+        // about as many cells along the longer side as points along a side
+        // of a square, which keeps the buckets small for curves
+        let cell = ((x1 - x0).max(y1 - y0) / (points.len() as f64).sqrt()).max(f64::MIN_POSITIVE);
+        let mut grid = Grid { points, cell, cells: HashMap::new() };
+        for (n, z) in points.iter().enumerate() {
+            grid.cells.entry(grid.key(*z)).or_default().push(n);
+        }
+        grid
+    }
+
+    fn key(&self, z: Complex<f64>) -> (i64, i64) {
+        ((z.re / self.cell).floor() as i64, (z.im / self.cell).floor() as i64)
+    }
+
+    // the distance from `z` to the nearest point, searching outward ring
+    // by ring until no nearer point can be further out
+    fn nearest(&self, z: Complex<f64>) -> f64 {
+        let (i, j) = self.key(z);
+        let mut best = f64::INFINITY;
+        let mut r = 0i64;
+        while (r - 1) as f64 * self.cell < best {
+            for di in -r..=r {
+                for dj in -r..=r {
+                    if di.abs() != r && dj.abs() != r {
+                        continue;
+                    }
+                    if let Some(cell) = self.cells.get(&(i + di, j + dj)) {
+                        for &n in cell {
+                            best = best.min((self.points[n] - z).norm());
+                        }
+                    }
+                }
+            }
+            r += 1;
+        }
+        best
+    }
+}
+
+/// Applies each of `generators` to each finite point of `points` and
+/// measures how far the image is from the nearest point. Images outside
+/// the points' bounding box, grown by its size on every side, are not
+/// checked: they are near the point at infinity, where the traced points
+/// are too sparse to compare against.
+pub fn verify_invariance(points: &[Complex<f64>], generators: &[(Letter, Mat)]) -> Invariance {
+    let finite: Vec<_> = points.iter().copied().filter(|z| z.is_finite()).collect();
+    let inf = f64::INFINITY;
+    let (x0, y0, x1, y1) = finite.iter()
+        .fold((inf, inf, -inf, -inf), |(x0, y0, x1, y1), z| {
+            (x0.min(z.re), y0.min(z.im), x1.max(z.re), y1.max(z.im))
+        });
+    let mut result = Invariance { deviation: 0.0, worst: None, checked: 0 };
+    if finite.is_empty() {
+        return result;
+    }
+    let grid = Grid::new(&finite, (x0, y0, x1, y1));
+    let (w, h) = (x1 - x0, y1 - y0);
+    for &(l, ref m) in generators {
+        for &z in &finite {
+            let image = m.mob(z);
+            if !image.is_finite() || image.re < x0 - w || image.re > x1 + w
+                || image.im < y0 - h || image.im > y1 + h {
+                continue;
+            }
+            result.checked += 1;
+            let d = grid.nearest(image);
+            if d > result.deviation {
+                result.deviation = d;
+                result.worst = Some((z, l));
+            }
+        }
+    }
+    result
+}
+
+impl Kleinian {
+    /// Checks the last trace against the generators; see
+    /// `verify_invariance`.
+    pub fn verify_invariance(&self) -> Invariance {
+        let generators: Vec<_> = [A, B, AI, BI].iter().map(|&l| (l, self.mats.at(l).clone())).collect();
+        verify_invariance(&self.points, &generators)
+    }
+
+    /// The fixed points of the four cyclic conjugates of the commutator, in
+    /// the order of `COMMUTATORS`, which the traversal adds as the last end
+    /// of the letter each conjugate ends in. Each is a cusp of the limit
+    /// set, the fixed point of a parabolic, and together they are where its
+    /// four top-level branches meet: the branch under a letter ends at the
+    /// image under that letter of its last end, the fixed point of the
+    /// conjugate beginning with it. For `grandma`, whose normalization puts
+    /// the fixed point of baBA at 1, the usual order a, B, A, b starts
+    /// there and runs through those of aBAb (at -1), BAba and AbaB before
+    /// closing at 1 again.
+    pub fn endfix_all(&self) -> [Complex<f64>; 4] {
+        COMMUTATORS.map(|c| self.word(&Word(c.to_vec())).fix())
+    }
+
+    /// Checks what the traversal assumes of `endfix_all`: that the points
+    /// are finite, pairwise more than `tolerance` apart, and each within
+    /// `tolerance` of a point of the last trace, which must have been made.
+    pub fn verify_endfix(&self, tolerance: f64) -> Result<(), Error> {
+        let ends = self.endfix_all();
+        let names = COMMUTATORS.map(|c| Word(c.to_vec()).to_string());
+        for (k, z) in ends.iter().enumerate() {
+            if !z.is_finite() {
+                return Err(Error::NotDiscrete(format!("the fixed point of {} is not finite", names[k])));
+            }
+            for j in 0..k {
+                if (z - ends[j]).norm() <= tolerance {
+                    return Err(Error::NotDiscrete(format!("the fixed points of {} and {} coincide", names[j], names[k])));
+                }
+            }
+        }
+        let finite: Vec<_> = self.points.iter().copied().filter(|z| z.is_finite()).collect();
+        if finite.is_empty() {
+            return Err(Error::Parameters("there are no traced points to check against".to_string()));
+        }
+        let grid = Grid::new(&finite, crate::extent(&finite));
+        for (k, &z) in ends.iter().enumerate() {
+            let d = grid.nearest(z);
+            if d > tolerance {
+                return Err(Error::NotDiscrete(format!("the fixed point of {} is {:.3e} from the traced curve", names[k], d)));
+            }
+        }
+        Ok(())
+    }
+
+    /// The epsilon of the last trace.
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn an_invariant_set_passes_and_a_corrupted_one_fails() {
+        // the 64th roots of unity, carried onto themselves by a turn of 1/64
+        let n = 64;
+        let mut points: Vec<_> = (0..n).map(|k| Complex::from_polar(&1.0, &(2.0 * PI * k as f64 / n as f64))).collect();
+        let turn = 2.0 * PI / n as f64;
+        let generators = [(A, Mat::rotation(turn)), (AI, Mat::rotation(-turn))];
+        let good = verify_invariance(&points, &generators);
+        assert_eq!(good.checked, 2 * n);
+        assert!(good.deviation < 1e-12, "{}", good.deviation);
+
+        // one point knocked off the circle
+        points[10] *= 1.5;
+        let bad = verify_invariance(&points, &generators);
+        assert!(bad.deviation > 0.1, "{}", bad.deviation);
+        assert_eq!(bad.worst.map(|(z, _)| z), Some(points[10]));
+    }
+
+    #[test]
+    fn the_four_cusps_are_distinct_and_on_the_trace() {
+        let mut g = crate::grandma(Complex::new(1.91, 0.05), Complex::new(1.91, 0.05));
+        for &l in &[A, B, AI, BI] {
+            g.add_end(vec![l]);
+        }
+        crate::limitset_with(10, &mut g, &crate::RenderOptions::default());
+        let ends = g.endfix_all();
+        for (k, z) in ends.iter().enumerate() {
+            assert!(z.is_finite(), "{}", z);
+            for w in &ends[..k] {
+                assert!((z - w).norm() > 0.1, "{} and {}", z, w);
+            }
+            let nearest = g.points().iter().map(|p| (p - z).norm()).fold(f64::INFINITY, f64::min);
+            assert!(nearest <= g.epsilon(), "{} is {} from the trace", z, nearest);
+        }
+        g.verify_endfix(g.epsilon()).unwrap();
+    }
+}
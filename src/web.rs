@@ -0,0 +1,88 @@
+//! Entry points shaped for a web page with sliders: plain numbers in, a
+//! string or a flat array out, touching no files and starting no threads.
+//! The `wasm` feature exports them to JavaScript through `wasm-bindgen`.
+
+use num::complex::Complex;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{grandma, limitset_with, RenderOptions, A, AI, B, BI};
+
+/// Branches a trace for the page may visit before giving up, so that a
+/// slider dragged off the discrete groups doesn't hang the tab.
+pub const WEB_MAX_NODES: usize = 2_000_000;
+
+// runs `f`, turning a panic into its message
+fn catching<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "the trace panicked".to_string())
+    })
+}
+
+// the points of grandma's group for these traces
+fn trace(ta_re: f64, ta_im: f64, tb_re: f64, tb_im: f64, epsilon: f64, max_depth: u32) -> (crate::Kleinian, RenderOptions) {
+    let mut g = grandma(Complex::new(ta_re, ta_im), Complex::new(tb_re, tb_im));
+    for &l in &[A, B, AI, BI] {
+        g.add_end(vec![l]);
+    }
+    let opts = RenderOptions { epsilon, max_nodes: Some(WEB_MAX_NODES), ..RenderOptions::default() };
+    limitset_with(i64::from(max_depth), &mut g, &opts);
+    (g, opts)
+}
+
+/// The SVG text of the limit set of grandma's group with traces
+/// ta = `ta_re` + `ta_im` i and tb = `tb_re` + `tb_im` i, traced to
+/// `epsilon` but no deeper than `max_depth`, drawn with the default
+/// options. A panic along the way comes back as its message.
+pub fn render_svg(ta_re: f64, ta_im: f64, tb_re: f64, tb_im: f64, epsilon: f64, max_depth: u32) -> Result<String, String> {
+    catching(|| {
+        let (g, opts) = trace(ta_re, ta_im, tb_re, tb_im, epsilon, max_depth);
+        g.document(&opts).to_string()
+    })
+}
+
+/// The points `render_svg` would draw, as x and y interleaved, for
+/// callers drawing on a canvas themselves.
+pub fn limit_points(ta_re: f64, ta_im: f64, tb_re: f64, tb_im: f64, epsilon: f64, max_depth: u32) -> Result<Vec<f64>, String> {
+    catching(|| {
+        let (g, _) = trace(ta_re, ta_im, tb_re, tb_im, epsilon, max_depth);
+        g.points().iter().flat_map(|z| [z.re, z.im]).collect()
+    })
+}
+
+/// The exports of the `wasm` feature, as `renderSvg` and `limitPoints`.
+/// Errors, and panics caught where the target unwinds, come back as a
+/// thrown `Error` carrying the message; wasm32 builds abort on a panic,
+/// which reaches JavaScript as a `RuntimeError` instead.
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use js_sys::Float64Array;
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen(js_name = renderSvg)]
+    pub fn render_svg(ta_re: f64, ta_im: f64, tb_re: f64, tb_im: f64, epsilon: f64, max_depth: u32) -> Result<String, JsError> {
+        super::render_svg(ta_re, ta_im, tb_re, tb_im, epsilon, max_depth).map_err(|e| JsError::new(&e))
+    }
+
+    /// The points as a `Float64Array` of x and y interleaved.
+    #[wasm_bindgen(js_name = limitPoints)]
+    pub fn limit_points(ta_re: f64, ta_im: f64, tb_re: f64, tb_im: f64, epsilon: f64, max_depth: u32) -> Result<Float64Array, JsError> {
+        let points = super::limit_points(ta_re, ta_im, tb_re, tb_im, epsilon, max_depth).map_err(|e| JsError::new(&e))?;
+        Ok(Float64Array::from(&points[..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_gasket_renders_as_an_svg_path() {
+        let svg = render_svg(2.0, 0.0, 2.0, 0.0, 1e-3, 50).unwrap();
+        assert!(svg.starts_with("<svg"), "{}", &svg[..svg.len().min(100)]);
+        assert!(svg.contains("<path"));
+        let points = limit_points(2.0, 0.0, 2.0, 0.0, 1e-2, 20).unwrap();
+        assert!(points.len() > 2 && points.len().is_multiple_of(2));
+    }
+}
@@ -0,0 +1,267 @@
+//! Each subcommand run as the binary, checking by its output that it did
+//! the work of its own library entry point.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_svg_kleinian")).args(args).output().expect("the binary runs")
+}
+
+fn stdout(out: &Output) -> String {
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+// a directory of this test's own, emptied
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("svg_kleinian-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn svg_at(path: &PathBuf) -> String {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    assert!(text.starts_with("<svg"), "{} is not an SVG", path.display());
+    text
+}
+
+#[test]
+fn render_writes_an_svg() {
+    let path = scratch("render").join("out.svg");
+    let out = run(&["render", "--level", "6", "--stroke", "blue", "-o", path.to_str().unwrap()]);
+    stdout(&out);
+    assert!(svg_at(&path).contains("stroke=\"blue\""));
+}
+
+#[test]
+fn stats_json_reads_back_as_a_report() {
+    let dir = scratch("stats");
+    let (svg, json) = (dir.join("out.svg"), dir.join("report.json"));
+    let out = run(&["render", "--level", "6", "-o", svg.to_str().unwrap(), "--stats-json", json.to_str().unwrap()]);
+    stdout(&out);
+    let report = svg_kleinian::RenderReport::from_path(json.to_str().unwrap()).unwrap();
+    assert_eq!((report.level, report.recipe.as_str()), (6, "grandma"));
+    assert!(report.nodes > 0 && report.points > 0);
+    let output = report.output.expect("the output file");
+    assert_eq!(output.bytes, Some(std::fs::metadata(&svg).unwrap().len()));
+}
+
+#[test]
+fn bare_options_render_with_a_warning() {
+    let path = scratch("bare").join("out.svg");
+    let out = run(&["--level", "6", "-o", path.to_str().unwrap()]);
+    stdout(&out);
+    svg_at(&path);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("deprecated"));
+}
+
+#[test]
+fn render_streams_points() {
+    let text = stdout(&run(&["render", "--level", "6", "--epsilon", "0.05", "--stream-points"]));
+    assert!(text.lines().count() > 10);
+    assert!(text.lines().all(|l| l.split(' ').count() == 2));
+    let path = scratch("stream").join("points.txt");
+    let printed = stdout(&run(&["render", "--level", "6", "--epsilon", "0.05", "--stream-points", "-o", path.to_str().unwrap()]));
+    assert!(printed.is_empty(), "{}", printed);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+}
+
+#[test]
+fn zoom_crops_to_a_little_beyond_the_window() {
+    let path = scratch("zoom").join("out.svg");
+    stdout(&run(&["render", "--level", "12", "--center", "0.3+0.2i", "--zoom", "4", "-o", path.to_str().unwrap()]));
+    let text = svg_at(&path);
+    // the default square is 2.4 across, so the window is 0.6 and the
+    // clip a twentieth of that beyond, computed as the binary does
+    let (cx, cy, w) = (0.3, 0.2, 2.4 / 4.0);
+    let pad = 0.05 * w;
+    let (x0, y0) = (cx - w / 2.0 - pad, cy - w / 2.0 - pad);
+    // the path is written in f32
+    let (x0, y0, x1, y1) = (x0 as f32, y0 as f32, (x0 + w + 2.0 * pad) as f32, (y0 + w + 2.0 * pad) as f32);
+    assert!(text.contains(&format!("stroke-width=\"{}\"", 0.001 / 4.0)), "{}", text);
+    let d = text.split(" d=\"").nth(1).expect("a path").split('"').next().unwrap();
+    let numbers: Vec<f32> = d.split(|c: char| c.is_ascii_alphabetic() || c == ',' || c == ' ')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().unwrap())
+        .collect();
+    assert!(numbers.len() > 20, "{}", d);
+    let mut on_edge = 0;
+    for xy in numbers.chunks(2) {
+        let (x, y) = (xy[0], xy[1]);
+        assert!(x0 <= x && x <= x1 && y0 <= y && y <= y1, "({}, {}) is outside the clip", x, y);
+        if x == x0 || x == x1 || y == y0 || y == y1 {
+            on_edge += 1;
+        }
+    }
+    // the curve runs off the edge, cut exactly there
+    assert!(on_edge >= 2, "{} points on the edge", on_edge);
+}
+
+#[test]
+fn generators_go_to_the_output() {
+    let path = scratch("generators").join("gens.txt");
+    let printed = stdout(&run(&["render", "--dump-generators", "-o", path.to_str().unwrap()]));
+    assert!(printed.is_empty(), "{}", printed);
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("a = ") && text.contains("\nB = "), "{}", text);
+    assert_eq!(stdout(&run(&["render", "--dump-generators"])), text);
+}
+
+#[test]
+fn analysis_goes_to_the_output() {
+    let path = scratch("analyze").join("depths.txt");
+    let printed = stdout(&run(&["render", "--analyze", "--level", "6", "-o", path.to_str().unwrap()]));
+    assert!(printed.is_empty(), "{}", printed);
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("depth"), "{}", text);
+    assert_eq!(stdout(&run(&["render", "--analyze", "--level", "6"])), text);
+}
+
+#[test]
+fn info_describes_the_group() {
+    let text = stdout(&run(&["info", "--ta", "1.9", "--level", "5"]));
+    assert!(text.contains("ta = 1.9"), "{}", text);
+}
+
+#[test]
+fn cusp_finds_mu_and_renders() {
+    let path = scratch("cusp").join("cusp.svg");
+    let out = run(&["cusp", "1", "3", "--level", "10", "-o", path.to_str().unwrap()]);
+    assert!(stdout(&out).is_empty());
+    let said = String::from_utf8_lossy(&out.stderr);
+    assert!(said.starts_with("μ = 0.58"), "{}", said);
+    svg_at(&path);
+    // μ goes to stderr, leaving standard output to the SVG
+    let text = stdout(&run(&["cusp", "1", "3", "--level", "10", "-o", "-"]));
+    assert!(text.starts_with("<svg") && text.trim_end().ends_with("</svg>"), "{}", &text[..text.len().min(200)]);
+    // without a render, μ is the whole answer
+    let text = stdout(&run(&["cusp", "1", "3"]));
+    assert!(text.starts_with("μ = 0.58"), "{}", text);
+}
+
+#[test]
+fn from_svg_reads_a_gzipped_render() {
+    let dir = scratch("from-svg");
+    let (first, again, direct) = (dir.join("first.svgz"), dir.join("again.svg"), dir.join("direct.svg"));
+    stdout(&run(&["render", "--ta", "1.91+0.05i", "--level", "8", "--epsilon", "0.01", "-o", first.to_str().unwrap()]));
+    assert!(std::fs::read(&first).unwrap().starts_with(&[0x1f, 0x8b]));
+    stdout(&run(&["render", "--from-svg", first.to_str().unwrap(), "-o", again.to_str().unwrap()]));
+    stdout(&run(&["render", "--ta", "1.91+0.05i", "--level", "8", "--epsilon", "0.01", "-o", direct.to_str().unwrap()]));
+    let (again, direct) = (svg_at(&again), svg_at(&direct));
+    assert!(again.contains("data-ta=\"1.91+0.05i\"") && again.contains("data-level=\"8\""), "{}", again);
+    assert_eq!(again, direct);
+    // the command line still wins
+    let out = stdout(&run(&["render", "--from-svg", first.to_str().unwrap(), "--level", "5", "--dump-config"]));
+    assert!(out.contains("ta = \"1.91+0.05i\"") && out.contains("level = 5\n"), "{}", out);
+    assert!(!run(&["render", "--from-svg", dir.join("missing.svgz").to_str().unwrap()]).status.success());
+}
+
+#[test]
+fn grid_draws_a_grid() {
+    let path = scratch("grid").join("grid.svg");
+    stdout(&run(&["grid", "--ta", "1.9..2.1", "--tb", "2", "--size", "2x2", "--depth", "8", "-o", path.to_str().unwrap()]));
+    svg_at(&path);
+}
+
+#[test]
+fn grid_spans_a_rectangle_of_mu() {
+    let path = scratch("grid-mu").join("grid.svg");
+    stdout(&run(&["grid", "--mu", "-0.5+2.2i..0.5+2.6i", "--size", "2x2", "--depth", "8", "-o", path.to_str().unwrap()]));
+    let text = svg_at(&path);
+    assert_eq!(text.matches("class=\"cell\"").count(), 4);
+    for mu in ["-0.5+2.2i", "0.5+2.2i", "-0.5+2.6i", "0.5+2.6i"] {
+        assert!(text.contains(&format!("\nμ = {}\n", mu)), "no cell for μ = {}", mu);
+    }
+}
+
+#[test]
+fn tiles_draws_each_tile() {
+    let dir = scratch("tiles");
+    let pattern = dir.join("t-{row}-{col}.svg");
+    stdout(&run(&["tiles", "--window", "-1,-1,2,2", "--grid", "2x1", "--depth", "8", "-o", pattern.to_str().unwrap()]));
+    svg_at(&dir.join("t-0-0.svg"));
+    svg_at(&dir.join("t-0-1.svg"));
+}
+
+#[test]
+fn animate_draws_an_animation() {
+    let path = scratch("animate").join("a.svg");
+    stdout(&run(&["animate", "--mu", "2i..1.95i", "--frames", "2", "--depth", "6", "-o", path.to_str().unwrap()]));
+    assert!(svg_at(&path).contains("<animate"));
+}
+
+#[test]
+fn animate_walks_through_two_cusps() {
+    let path = scratch("farey").join("a.svg");
+    stdout(&run(&["animate", "--farey", "0/1,1/2", "--frames", "6", "--depth", "6", "-o", path.to_str().unwrap()]));
+    assert_eq!(svg_at(&path).matches("class=\"frame\"").count(), 6);
+    let out = run(&["animate", "--farey", "0/1,2/4", "--frames", "6", "-o", path.to_str().unwrap()]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("2/4"));
+}
+
+#[test]
+fn batch_runs_its_jobs() {
+    let dir = scratch("batch");
+    let jobs = dir.join("jobs.toml");
+    let out = dir.join("one.svg");
+    std::fs::write(&jobs, format!("[[job]]\nname = \"one\"\nlevel = 5\n[job.output]\npath = {:?}\n", out)).unwrap();
+    let done = run(&["batch", jobs.to_str().unwrap()]);
+    assert!(stdout(&done).is_empty());
+    let text = String::from_utf8_lossy(&done.stderr);
+    assert!(text.contains("1 of 1 jobs succeeded"), "{}", text);
+    svg_at(&out);
+}
+
+#[test]
+fn repl_answers_commands() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_svg_kleinian"))
+        .args(["repl", "--ta", "1.9"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"level 7\nshow\nquit\n").unwrap();
+    let text = stdout(&child.wait_with_output().unwrap());
+    assert!(text.contains("ta = 1.9, tb = 2, level 7"), "{}", text);
+}
+
+#[test]
+fn golden_checks_the_saved_trace() {
+    let out = run(&["golden"]);
+    stdout(&out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("matches"));
+}
+
+#[test]
+fn quiet_leaves_stderr_empty() {
+    let dir = scratch("quiet");
+    let svg = dir.join("q.svg");
+    let svg = svg.to_str().unwrap();
+    let runs: Vec<Vec<&str>> = vec![
+        vec!["--level", "6", "-o", svg],
+        vec!["render", "-vv", "--level", "6", "-o", svg],
+        vec!["cusp", "1", "2", "-v", "--level", "6", "-o", svg],
+        vec!["golden"],
+    ];
+    for args in runs {
+        let loud = run(&args);
+        stdout(&loud);
+        assert!(!loud.stderr.is_empty(), "{:?} said nothing to quieten", args);
+        let mut quiet = args.clone();
+        quiet.insert(if args[0].starts_with('-') { 0 } else { 1 }, "-q");
+        let out = run(&quiet);
+        stdout(&out);
+        assert!(out.stderr.is_empty(), "{:?}: {}", quiet, String::from_utf8_lossy(&out.stderr));
+    }
+}
+
+#[test]
+fn bad_options_fail_with_usage() {
+    let out = run(&["render", "--epsilon", "0"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("positive"));
+}
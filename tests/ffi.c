@@ -0,0 +1,81 @@
+/* The C side of tests/ffi.rs: each check calls the library as a C caller
+ * would and returns 0, or the line of the first thing that went wrong. */
+
+#include <math.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "svg_kleinian.h"
+
+#define CHECK(cond) do { if (!(cond)) return __LINE__; } while (0)
+
+static KleinianParams gasket(void) {
+    KleinianParams p = {KLEINIAN_PARAMS_VERSION, 20, 2.0, 0.0, 2.0, 0.0, 1e-2};
+    return p;
+}
+
+/* a null buffer gets the length, a short one an error, and one that
+ * size the SVG */
+int ffi_two_call_sizing(void) {
+    KleinianParams p = gasket();
+    size_t len = 0;
+    CHECK(kleinian_render_svg(&p, NULL, &len) == KLEINIAN_OK);
+    CHECK(len > 1);
+    uint8_t *buf = malloc(len);
+    CHECK(buf != NULL);
+    size_t short_len = len - 1;
+    int short_code = kleinian_render_svg(&p, buf, &short_len);
+    size_t full_len = len;
+    int code = kleinian_render_svg(&p, buf, &full_len);
+    int ok = short_code == KLEINIAN_ERR_BUFFER_TOO_SMALL && short_len == len
+        && code == KLEINIAN_OK && full_len == len
+        && buf[len - 1] == '\0' && strncmp((const char *)buf, "<svg", 4) == 0
+        && strstr((const char *)buf, "<path") != NULL;
+    free(buf);
+    CHECK(ok);
+
+    size_t count = 0;
+    CHECK(kleinian_limit_points(&p, NULL, &count) == KLEINIAN_OK);
+    CHECK(count > 2 && count % 2 == 0);
+    double *points = malloc(count * sizeof *points);
+    CHECK(points != NULL);
+    code = kleinian_limit_points(&p, points, &count);
+    ok = code == KLEINIAN_OK && isfinite(points[0]) && isfinite(points[count - 1]);
+    free(points);
+    CHECK(ok);
+    return 0;
+}
+
+int ffi_null_params(void) {
+    size_t len = 0;
+    double out[8];
+    CHECK(kleinian_render_svg(NULL, NULL, &len) == KLEINIAN_ERR_NULL);
+    CHECK(kleinian_limit_points(NULL, NULL, &len) == KLEINIAN_ERR_NULL);
+    CHECK(kleinian_traces(NULL, out) == KLEINIAN_ERR_NULL);
+    CHECK(kleinian_word(NULL, "ab", out) == KLEINIAN_ERR_NULL);
+    return 0;
+}
+
+int ffi_wrong_version(void) {
+    KleinianParams p = gasket();
+    p.version = KLEINIAN_PARAMS_VERSION + 1;
+    size_t len = 0;
+    double out[6];
+    CHECK(kleinian_render_svg(&p, NULL, &len) == KLEINIAN_ERR_VERSION);
+    CHECK(kleinian_traces(&p, out) == KLEINIAN_ERR_VERSION);
+    return 0;
+}
+
+/* each failure leaves a message naming what went wrong */
+int ffi_error_message(void) {
+    KleinianParams p = gasket();
+    double out[8];
+    CHECK(kleinian_word(&p, "abx", out) == KLEINIAN_ERR_WORD);
+    const char *message = kleinian_last_error_message();
+    CHECK(message != NULL && strchr(message, 'x') != NULL);
+    p.version = 0;
+    CHECK(kleinian_word(&p, "ab", out) == KLEINIAN_ERR_VERSION);
+    message = kleinian_last_error_message();
+    CHECK(message != NULL && strstr(message, "version") != NULL);
+    return 0;
+}
@@ -0,0 +1,41 @@
+//! The C interface called from C: `build.rs` compiles `tests/ffi.c`
+//! against `include/svg_kleinian.h`, and each test runs one of its checks,
+//! which returns the line it failed at, or 0.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+// linked for the C functions, which nothing here names in Rust
+extern crate svg_kleinian;
+
+use std::os::raw::c_int;
+
+extern "C" {
+    fn ffi_two_call_sizing() -> c_int;
+    fn ffi_null_params() -> c_int;
+    fn ffi_wrong_version() -> c_int;
+    fn ffi_error_message() -> c_int;
+}
+
+fn check(name: &str, line: c_int) {
+    assert_eq!(line, 0, "{} failed at tests/ffi.c:{}", name, line);
+}
+
+#[test]
+fn a_null_buffer_asks_for_the_length() {
+    check("ffi_two_call_sizing", unsafe { ffi_two_call_sizing() });
+}
+
+#[test]
+fn null_params_are_an_error() {
+    check("ffi_null_params", unsafe { ffi_null_params() });
+}
+
+#[test]
+fn a_wrong_version_is_an_error() {
+    check("ffi_wrong_version", unsafe { ffi_wrong_version() });
+}
+
+#[test]
+fn a_failure_leaves_a_message() {
+    check("ffi_error_message", unsafe { ffi_error_message() });
+}
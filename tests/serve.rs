@@ -0,0 +1,57 @@
+//! The server on an ephemeral port, asked for renders over plain TCP.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use svg_kleinian::{serve, ServeOptions};
+
+fn start(opts: ServeOptions) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, &opts));
+    addr
+}
+
+// the status line, the headers and the body of the answer to `GET target`
+fn get(addr: SocketAddr, target: &str) -> (String, Vec<String>, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").expect("a blank line after the headers");
+    let mut lines = head.split("\r\n").map(str::to_string);
+    let status = lines.next().unwrap();
+    (status, lines.collect(), body.to_string())
+}
+
+#[test]
+fn a_render_is_answered_then_cached() {
+    let addr = start(ServeOptions::default());
+    let target = "/render.svg?ta=1.91%2B0.05i&tb=2&eps=0.01&depth=20";
+    let (status, headers, body) = get(addr, target);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"Content-Type: image/svg+xml".to_string()), "{:?}", headers);
+    assert!(headers.contains(&"X-Cache: miss".to_string()), "{:?}", headers);
+    assert!(body.starts_with("<svg") && body.contains("<path"), "{}", body);
+    let (status, headers, again) = get(addr, target);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"X-Cache: hit".to_string()), "{:?}", headers);
+    assert_eq!(again, body);
+}
+
+#[test]
+fn a_slow_render_times_out() {
+    let addr = start(ServeOptions { timeout: Duration::from_millis(1), max_nodes: usize::MAX, ..ServeOptions::default() });
+    let (status, _, body) = get(addr, "/render.svg?eps=0.000001");
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable", "{}", body);
+}
+
+#[test]
+fn bad_parameters_are_refused() {
+    let addr = start(ServeOptions::default());
+    let (status, _, body) = get(addr, "/render.svg?eps=0");
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    assert!(body.contains("eps"), "{}", body);
+}
@@ -0,0 +1,20 @@
+//! The `wasm` feature's exports, run in a JavaScript engine by
+//! `wasm-pack test --node -- --features wasm`.
+
+#![cfg(target_arch = "wasm32")]
+
+use svg_kleinian::wasm::{limit_points, render_svg};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn render_svg_draws_a_path() {
+    let svg = render_svg(2.0, 0.0, 2.0, 0.0, 1e-3, 50).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<path"));
+}
+
+#[wasm_bindgen_test]
+fn limit_points_are_pairs() {
+    let points = limit_points(2.0, 0.0, 2.0, 0.0, 1e-2, 20).unwrap();
+    assert!(points.length() > 2 && points.length().is_multiple_of(2));
+}